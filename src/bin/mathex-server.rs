@@ -0,0 +1,12 @@
+use std::net::TcpListener;
+
+/// Serves the JSON API of `mathex::math::server` on the address given as the first argument,
+/// `127.0.0.1:3000` by default.
+fn main() -> std::io::Result<()> {
+    let address = std::env::args()
+        .nth(1)
+        .unwrap_or_else(|| "127.0.0.1:3000".into());
+    let listener = TcpListener::bind(&address)?;
+    eprintln!("listening on {}", listener.local_addr()?);
+    mathex::math::server::serve(listener)
+}
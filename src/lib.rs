@@ -3,7 +3,46 @@ pub mod math;
 #[cfg(test)]
 mod test {
 
+    use super::math::analysis::*;
+    use super::math::approximate::*;
+    use super::math::arena::*;
+    use super::math::big::{self, BigRational};
+    use super::math::bits::*;
+    use super::math::cancel::*;
+    use super::math::combinatorics::*;
+    use super::math::completion::*;
+    use super::math::constants;
+    use super::math::correction::*;
+    use super::math::curves::*;
+    use super::math::derivative::*;
+    use super::math::diagnostics::*;
+    use super::math::equations::*;
+    use super::math::escape::*;
+    use super::math::evaluator::*;
+    use super::math::exact::*;
+    use super::math::export::*;
+    use super::math::finite_field::*;
+    use super::math::format::*;
+    use super::math::functions::{self, Function};
+    use super::math::incremental::*;
+    use super::math::interval::*;
+    use super::math::lint::*;
+    use super::math::matrix::*;
+    use super::math::nt::{self, Sequence};
+    use super::math::numeric;
+    use super::math::parser::*;
+    use super::math::path::*;
+    use super::math::polynomial::*;
+    use super::math::pool::*;
+    use super::math::proportion::*;
+    use super::math::rational_function::*;
+    use super::math::shared::*;
+    use super::math::surface::*;
+    use super::math::template::*;
     use super::math::terms::*;
+    use super::math::transforms::*;
+    use super::math::verify::*;
+    use super::term;
 
     use Term::*;
     #[test]
@@ -14,4 +53,3682 @@ mod test {
 
         assert_eq!(value, Number::Rational(Fraction::new(170, 100)))
     }
+
+    #[test]
+    fn test_pade_exp() {
+        let taylor = Polynomial::new(
+            "x",
+            vec![
+                Fraction::new(1, 1),
+                Fraction::new(1, 1),
+                Fraction::new(1, 2),
+                Fraction::new(1, 6),
+                Fraction::new(1, 24),
+            ],
+        );
+
+        let (numerator, denominator) = taylor.pade_parts(2, 2).unwrap();
+
+        assert_eq!(
+            numerator.coefficients(),
+            &[
+                Fraction::new(1, 1),
+                Fraction::new(1, 2),
+                Fraction::new(1, 12)
+            ]
+        );
+        assert_eq!(
+            denominator.coefficients(),
+            &[
+                Fraction::new(1, 1),
+                Fraction::new(-1, 2),
+                Fraction::new(1, 12)
+            ]
+        );
+
+        let value = taylor
+            .pade(2, 2)
+            .unwrap()
+            .substitute("x", Number::Rational(Fraction::new(1, 1)))
+            .get_value()
+            .unwrap();
+
+        assert_eq!(value, Number::Rational(Fraction::new(19, 7)))
+    }
+
+    #[test]
+    fn test_fourier_coefficients() {
+        let period = 2.0 * std::f64::consts::PI;
+        let series = numeric::fourier_coefficients(&Variable("x".into()), "x", period, 3).unwrap();
+
+        assert!(series.a0.abs() < 1e-9);
+        for (k, (a, b)) in series.a.iter().zip(&series.b).enumerate() {
+            let k = (k + 1) as f64;
+            let expected = 2.0 * (-1f64).powf(k + 1.0) / k;
+
+            assert!(a.abs() < 1e-9);
+            assert!((b - expected).abs() < 1e-6);
+        }
+    }
+
+    #[test]
+    fn test_laplace_table() {
+        // 3t^2 + 1
+        let term = Addition(
+            Box::new(Multiplication(
+                Box::new(Value((3.0).into())),
+                Box::new(Exponentation(
+                    Box::new(Variable("t".into())),
+                    Box::new(Value((2.0).into())),
+                )),
+            )),
+            Box::new(Value((1.0).into())),
+        );
+
+        let transformed = laplace(&term, "t", "s").unwrap();
+        let value = transformed
+            .substitute("s", (2.0).into())
+            .get_value()
+            .unwrap();
+        assert_eq!(value, Number::Rational(Fraction::new(5, 4)));
+
+        let inverse = inverse_laplace(&laplace(&term, "t", "s").unwrap(), "s", "t").unwrap();
+        let value = inverse.substitute("t", (3.0).into()).get_value().unwrap();
+        assert_eq!(value, Number::Rational(Fraction::new(28, 1)));
+
+        let sine = StandardForm::Sine(Fraction::new(3, 1))
+            .laplace("s")
+            .unwrap();
+        assert_eq!(
+            recognize_laplace(&sine, "s").unwrap(),
+            vec![(Fraction::new(1, 1), StandardForm::Sine(Fraction::new(3, 1)))]
+        );
+
+        // 25! and the square of a large rate overflow instead of panicking
+        assert_eq!(laplace(&Term::parse("t^25").unwrap(), "t", "s"), None);
+        assert!(laplace(&Term::parse("t^20").unwrap(), "t", "s").is_some());
+        assert_eq!(
+            laplace(&Term::parse("sin(4000000000*t)").unwrap(), "t", "s"),
+            None
+        );
+        for input in ["s^-30", "(s^2 + 9223372036854775807)^-1"] {
+            let transform = Term::parse(input).unwrap();
+            assert_eq!(inverse_laplace(&transform, "s", "t"), None, "{input}");
+        }
+        assert_eq!(recognize(&Term::parse("t^4294967296").unwrap(), "t"), None);
+    }
+
+    #[test]
+    fn test_symbolic_matrix() {
+        let var = |name: &str| Variable(name.into());
+        let matrix = Matrix::from_rows(vec![
+            vec![Value((2.0).into()), var("x")],
+            vec![Value((1.0).into()), var("y")],
+        ])
+        .unwrap();
+
+        let determinant = matrix
+            .determinant()
+            .unwrap()
+            .substitute("x", (3.0).into())
+            .substitute("y", (4.0).into());
+        assert_eq!(determinant.get_value().unwrap(), Number::Rational(5.into()));
+
+        let inverse = matrix.inverse().unwrap();
+        let entry = |row, col| {
+            Term::clone(inverse.get(row, col))
+                .substitute("x", (3.0).into())
+                .substitute("y", (4.0).into())
+                .get_value()
+                .unwrap()
+        };
+
+        assert_eq!(entry(0, 0), Number::Rational(Fraction::new(4, 5)));
+        assert_eq!(entry(0, 1), Number::Rational(Fraction::new(-3, 5)));
+        assert_eq!(entry(1, 0), Number::Rational(Fraction::new(-1, 5)));
+        assert_eq!(entry(1, 1), Number::Rational(Fraction::new(2, 5)));
+    }
+
+    #[test]
+    fn test_eigenvalues() {
+        let frac = |n| Fraction::new(n, 1);
+
+        let symmetric =
+            Matrix::from_rows(vec![vec![frac(2), frac(1)], vec![frac(1), frac(2)]]).unwrap();
+        let polynomial = symmetric.characteristic_polynomial("x").unwrap();
+        assert_eq!(polynomial.coefficients(), &[frac(3), frac(-4), frac(1)]);
+        assert_eq!(
+            symmetric.eigenvalues().unwrap(),
+            vec![Number::Rational(frac(1)), Number::Rational(frac(3))]
+        );
+
+        let fibonacci =
+            Matrix::from_rows(vec![vec![frac(1), frac(1)], vec![frac(1), frac(0)]]).unwrap();
+        let golden = (1.0 + 5f64.sqrt()) / 2.0;
+        let eigenvalues: Vec<f64> = fibonacci
+            .eigenvalues()
+            .unwrap()
+            .into_iter()
+            .map(f64::from)
+            .collect();
+        assert!((eigenvalues[0] + 1.0 / golden).abs() < 1e-12);
+        assert!((eigenvalues[1] - golden).abs() < 1e-12);
+
+        let diagonal = Matrix::from_rows(
+            (0..4)
+                .map(|row| {
+                    (0..4)
+                        .map(|col| if row == col { frac(row + 1) } else { frac(0) })
+                        .collect()
+                })
+                .collect(),
+        )
+        .unwrap();
+        let mut eigenvalues: Vec<f64> = diagonal
+            .eigenvalues()
+            .unwrap()
+            .into_iter()
+            .map(f64::from)
+            .collect();
+        eigenvalues.sort_by(f64::total_cmp);
+        assert_eq!(eigenvalues, vec![1.0, 2.0, 3.0, 4.0]);
+
+        // The QR algorithm leaves a swap and a rotation as 2x2 blocks: the swap's eigenvalues
+        // are -1 and 1, the rotation's are complex and left out
+        let blocks = |block: [[i64; 2]; 2]| {
+            Matrix::from_rows(
+                (0..4)
+                    .map(|row| {
+                        (0..4)
+                            .map(|col| match (row, col) {
+                                (0..=1, 0..=1) => frac(block[row][col]),
+                                (2, 2) => frac(2),
+                                (3, 3) => frac(3),
+                                _ => frac(0),
+                            })
+                            .collect()
+                    })
+                    .collect(),
+            )
+            .unwrap()
+        };
+        let mut eigenvalues: Vec<f64> = blocks([[0, 1], [1, 0]])
+            .eigenvalues()
+            .unwrap()
+            .into_iter()
+            .map(f64::from)
+            .collect();
+        eigenvalues.sort_by(f64::total_cmp);
+        assert_eq!(eigenvalues, vec![-1.0, 1.0, 2.0, 3.0]);
+        let mut eigenvalues: Vec<f64> = blocks([[0, -1], [1, 0]])
+            .eigenvalues()
+            .unwrap()
+            .into_iter()
+            .map(f64::from)
+            .collect();
+        eigenvalues.sort_by(f64::total_cmp);
+        assert_eq!(eigenvalues, vec![2.0, 3.0]);
+
+        // Divisors of a large constant term are only searched up to its square root
+        let large = Polynomial::new("x", vec![Fraction::from(-1_000_000_000_000), frac(1)]);
+        assert_eq!(
+            large.rational_roots(),
+            vec![Fraction::from(1_000_000_000_000)]
+        );
+    }
+
+    #[test]
+    fn test_vector_operations() {
+        let vector = |values: [f64; 3]| Vector(values.iter().map(|v| Value((*v).into())).collect());
+        let (a, b) = (vector([1.0, 2.0, 3.0]), vector([4.0, 5.0, 6.0]));
+
+        assert_eq!(
+            a.dot(&b).unwrap().get_value().unwrap(),
+            Number::Rational(32.into())
+        );
+        assert_eq!(
+            a.cross(&b).unwrap().get_vector_value().unwrap(),
+            vec![
+                Number::Rational((-3).into()),
+                Number::Rational(6.into()),
+                Number::Rational((-3).into())
+            ]
+        );
+        assert_eq!(
+            vector([3.0, 4.0, 0.0]).norm().unwrap().get_value().unwrap(),
+            Number::Rational(5.into())
+        );
+
+        let scaled = Vector(vec![Variable("x".into()), Value((1.0).into())])
+            .scale(&Value((2.0).into()))
+            .unwrap()
+            .substitute("x", (3.0).into());
+        assert_eq!(
+            scaled.get_vector_value().unwrap(),
+            vec![Number::Rational(6.into()), Number::Rational(2.into())]
+        );
+        assert!(!scaled.has_value());
+    }
+
+    #[test]
+    fn test_collections() {
+        let values = |values: &[f64]| values.iter().map(|v| Value((*v).into())).collect();
+        let list = List(values(&[3.0, 1.0, 2.0, 1.0]));
+        let set = Set(values(&[3.0, 1.0, 2.0, 1.0]));
+
+        assert_eq!(list.len(), Some(4));
+        assert_eq!(set.len(), Some(3));
+        assert_eq!(set.contains((2.0).into()), Some(true));
+        assert_eq!(set.contains((5.0).into()), Some(false));
+
+        let index = Index(Box::new(list.clone()), Box::new(Variable("i".into())));
+        assert!(!index.has_value());
+        assert_eq!(
+            index.clone().substitute("i", (2.0).into()).get_value(),
+            Some(Number::Rational(2.into()))
+        );
+        assert_eq!(index.substitute("i", (7.0).into()).get_value(), None);
+
+        let squares = list
+            .map_elements(
+                "x",
+                &Exponentation(
+                    Box::new(Variable("x".into())),
+                    Box::new(Value((2.0).into())),
+                ),
+            )
+            .unwrap();
+        assert_eq!(
+            squares.get_collection_value(),
+            Some(
+                vec![9.into(), 1.into(), 4.into(), 1.into()]
+                    .into_iter()
+                    .map(Number::Rational)
+                    .collect()
+            )
+        );
+
+        let large = set.filter(|value| f64::from(*value) > 1.5).unwrap();
+        assert_eq!(
+            large.get_collection_value(),
+            Some(vec![Number::Rational(3.into()), Number::Rational(2.into())])
+        );
+    }
+
+    #[test]
+    fn test_ranges() {
+        let n = |v: i64| Number::Rational(v.into());
+        let a = Range::new(n(0), n(2), Closedness::ClosedOpen);
+        let b = Range::closed(n(2), n(5));
+
+        assert!(a.contains(n(0)) && !a.contains(n(2)));
+        assert!(a.intersection(&b).is_none());
+        assert_eq!(
+            a.union(&b),
+            vec![Range::new(n(0), n(5), Closedness::Closed)]
+        );
+        assert_eq!(
+            Range::open(n(0), n(1))
+                .union(&Range::open(n(1), n(2)))
+                .len(),
+            2
+        );
+        assert_eq!(
+            b.intersection(&Range::open(n(3), n(10))),
+            Some(Range::new(n(3), n(5), Closedness::OpenClosed))
+        );
+
+        assert_eq!(a + b, Range::new(n(2), n(7), Closedness::ClosedOpen));
+        assert_eq!(
+            Range::closed(n(-1), n(2)) * Range::closed(n(3), n(4)),
+            Range::closed(n(-4), n(8))
+        );
+        assert_eq!(-a, Range::new(n(-2), n(0), Closedness::OpenClosed));
+
+        // 0 times an unbounded end is 0 rather than NaN
+        assert_eq!(
+            Range::closed(n(0), n(1)) * Range::real_line(),
+            Range::real_line()
+        );
+        let positive = Range::new(
+            n(0),
+            Number::Irrational(f64::INFINITY),
+            Closedness::ClosedOpen,
+        );
+        assert_eq!(Range::closed(n(0), n(1)) * positive, positive);
+        assert_eq!(
+            Range::closed(n(0), n(0)) * Range::real_line(),
+            Range::closed(n(0), n(0))
+        );
+    }
+
+    #[test]
+    fn test_number_canonical_keys() {
+        use std::collections::{BTreeSet, HashMap};
+
+        assert_eq!(
+            Number::Irrational(0.5),
+            Number::Rational(Fraction::new(1, 2))
+        );
+        assert_eq!(
+            Number::Irrational(0.1).canonical(),
+            Number::Rational(Fraction::new(3602879701896397, 36028797018963968))
+        );
+        assert_ne!(
+            Number::Irrational(0.1),
+            Number::Rational(Fraction::new(1, 10))
+        );
+        assert_eq!(Number::Irrational(f64::NAN), Number::Irrational(f64::NAN));
+
+        let mut cache = HashMap::new();
+        cache.insert(Number::Irrational(0.75), "three quarters");
+        assert_eq!(
+            cache.get(&Number::Rational(Fraction::new(3, 4))),
+            Some(&"three quarters")
+        );
+
+        let ordered: Vec<Number> = [
+            Number::Irrational(f64::INFINITY),
+            Number::Rational(2.into()),
+            Number::Irrational(-1.5),
+            Number::Irrational(f64::NEG_INFINITY),
+        ]
+        .into_iter()
+        .collect::<BTreeSet<_>>()
+        .into_iter()
+        .collect();
+        assert_eq!(
+            ordered,
+            vec![
+                Number::Rational(Fraction::new(-3, 2)),
+                Number::Rational(2.into()),
+                Number::Irrational(f64::NEG_INFINITY),
+                Number::Irrational(f64::INFINITY),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_number_round_trip() {
+        assert_eq!(Number::Rational(Fraction::new(-3, 4)).to_string(), "-3/4");
+        assert_eq!(Number::Rational(7.into()).to_string(), "7");
+        assert_eq!(Number::Irrational(1.0).to_string(), "1.0");
+        assert_eq!(Number::Irrational(0.1).to_string(), "0.1");
+
+        for value in [0.1, 1.0 / 3.0, 1e300, 5e-324, -0.0, -2.5e-8, f64::INFINITY] {
+            let text = Number::Irrational(value).to_string();
+            match text.parse::<Number>().unwrap() {
+                Number::Irrational(parsed) => assert_eq!(parsed.to_bits(), value.to_bits()),
+                Number::Rational(_) => panic!("{} re-parsed as a rational", text),
+            }
+        }
+
+        assert_eq!("12/18".parse(), Ok(Number::Rational(Fraction::new(2, 3))));
+        assert!("1/0".parse::<Number>().is_err());
+        assert!("x".parse::<Number>().is_err());
+    }
+
+    #[test]
+    fn test_fallback_recording() {
+        // 2 * 9^(1/2) + sqrt(2)
+        let term = Addition(
+            Box::new(Multiplication(
+                Box::new(Value((2.0).into())),
+                Box::new(Exponentation(
+                    Box::new(Value((9.0).into())),
+                    Box::new(Value((0.5).into())),
+                )),
+            )),
+            Box::new(RootExtraction(
+                Box::new(Value((2.0).into())),
+                Box::new(Value((2.0).into())),
+            )),
+        );
+
+        let (_, events) = record_fallbacks(|| term.get_value());
+        let reasons: Vec<FallbackReason> = events.iter().map(|event| event.reason).collect();
+        assert_eq!(
+            reasons,
+            vec![
+                FallbackReason::NonIntegerPower,
+                FallbackReason::NonIntegerPower
+            ]
+        );
+        assert!(matches!(events[1].subterm, RootExtraction(_, _)));
+
+        let exact = Multiplication(Box::new(Value((2.0).into())), Box::new(Value((3.0).into())));
+        let (_, events) = record_fallbacks(|| exact.get_value());
+        assert!(events.is_empty());
+
+        let (_, events) = record_fallbacks(|| Value(Number::Irrational(f64::INFINITY)).get_value());
+        assert_eq!(events[0].reason, FallbackReason::IrrationalLiteral);
+    }
+
+    #[test]
+    fn test_evaluate_exact() {
+        let value = |v: f64| Box::new(Value(v.into()));
+
+        // (8 / 27)^(2/3) = 4/9
+        let root = Exponentation(
+            Box::new(Value(Number::Rational(Fraction::new(8, 27)))),
+            Box::new(Value(Number::Rational(Fraction::new(2, 3)))),
+        );
+        assert_eq!(root.evaluate_exact(), Ok(Fraction::new(4, 9)));
+
+        let irrational = Addition(value(1.0), Box::new(RootExtraction(value(2.0), value(2.0))));
+        assert_eq!(
+            irrational.evaluate_exact(),
+            Err(ExactnessViolation {
+                at: TermPath(vec![1]),
+                reason: ExactnessReason::IrrationalPower,
+            })
+        );
+        assert!(irrational.get_value().is_some());
+
+        let overflow = Exponentation(value(10.0), value(40.0));
+        assert_eq!(
+            overflow.evaluate_exact().unwrap_err().reason,
+            ExactnessReason::Overflow
+        );
+
+        let division = Exponentation(value(0.0), value(-1.0));
+        assert_eq!(
+            division.evaluate_exact().unwrap_err().reason,
+            ExactnessReason::DivisionByZero
+        );
+    }
+
+    #[test]
+    fn test_cancellation() {
+        let term = Exponentation(
+            Box::new(Variable("x".into())),
+            Box::new(Value((2.0).into())),
+        );
+        let token = CancellationToken::new();
+
+        let area = numeric::integrate_cancellable(&term, "x", 0.0, 3.0, &token).unwrap();
+        assert!((area.unwrap() - 9.0).abs() < 1e-9);
+        let symbolic = Term::parse("2*x + x*3").unwrap();
+        let equation = Equation::new(symbolic.clone(), Term::parse("10").unwrap());
+        assert_eq!(
+            symbolic.simplify_cancellable(&token),
+            Ok(symbolic.simplify())
+        );
+        assert_eq!(
+            symbolic.derivative_cancellable("x", &token),
+            Ok(symbolic.derivative("x"))
+        );
+        assert_eq!(
+            equation.isolate_cancellable("x", &token),
+            Ok(equation.isolate("x"))
+        );
+
+        token.clone().cancel();
+        assert_eq!(
+            numeric::integrate_cancellable(&term, "x", 0.0, 3.0, &token),
+            Err(Cancelled)
+        );
+        assert_eq!(
+            numeric::fourier_coefficients_cancellable(&term, "x", 2.0, 3, &token),
+            Err(Cancelled)
+        );
+        assert_eq!(
+            Matrix::new(1, 1, vec![1.0])
+                .unwrap()
+                .eigenvalues_cancellable(10, &token),
+            Err(Cancelled)
+        );
+        assert_eq!(symbolic.simplify_cancellable(&token), Err(Cancelled));
+        assert_eq!(symbolic.derivative_cancellable("x", &token), Err(Cancelled));
+        assert_eq!(equation.isolate_cancellable("x", &token), Err(Cancelled));
+    }
+
+    #[test]
+    fn test_budget_report() {
+        let symmetric = Matrix::new(2, 2, vec![2.0, 1.0, 1.0, 2.0]).unwrap();
+
+        let (_, budget) = symmetric.eigenvalues_with_budget(500);
+        assert!(budget.converged && !budget.exhausted());
+        assert!(budget.used > 0 && budget.used < 500);
+
+        let (_, budget) = symmetric.eigenvalues_with_budget(2);
+        assert!(budget.exhausted());
+        assert_eq!(budget.used, 2);
+
+        // Symbolic operations count the subterms they visit
+        let term = Term::parse("2*x*3 + sin(x)^2").unwrap();
+        let (simplified, budget) = term.simplify_with_budget(100);
+        assert_eq!(simplified, term.simplify());
+        assert!(budget.converged && budget.used == term.iter_subterms().count());
+        let (partial, budget) = term.simplify_with_budget(3);
+        assert!(budget.exhausted());
+        let context = EvalContext::new().with("x", Number::Rational(2.into()));
+        assert_eq!(
+            partial.evaluate_in(&context),
+            simplified.evaluate_in(&context)
+        );
+        assert_eq!(
+            term.derivative_with_budget("x", 100).0,
+            term.derivative("x")
+        );
+        let (derivative, budget) = term.derivative_with_budget("x", 3);
+        assert!(derivative.is_none() && budget.exhausted());
+
+        let approximate = |input: &str, digits, limit| {
+            Term::parse(input)
+                .unwrap()
+                .approximate_with_budget(&EvalContext::new(), digits, limit)
+        };
+        let (digits, budget) = approximate("pi", 5, 6);
+        assert_eq!((digits, budget.used), (Ok("3.14159".into()), 1));
+        // Exactly between two roundings, so no precision decides it
+        let (digits, budget) = approximate("sin(pi) + 1/2", 0, 2);
+        assert_eq!(digits, Err(ApproximationError::Undecided));
+        assert!(budget.exhausted());
+    }
+
+    #[test]
+    fn test_verify_transformation() {
+        let var = |name: &str| Box::new(Variable(name.into()));
+        let two = || Box::new(Value((2.0).into()));
+
+        // (x + y)^2 against x^2 + 2xy + y^2
+        let original = Exponentation(Box::new(Addition(var("x"), var("y"))), two());
+        let expanded = Addition(
+            Box::new(Addition(
+                Box::new(Exponentation(var("x"), two())),
+                Box::new(Multiplication(
+                    two(),
+                    Box::new(Multiplication(var("x"), var("y"))),
+                )),
+            )),
+            Box::new(Exponentation(var("y"), two())),
+        );
+        assert_eq!(verify_transformation(&original, &expanded, 50), Ok(()));
+
+        // x^2 + y^2 is not a valid rewrite
+        let wrong = Addition(
+            Box::new(Exponentation(var("x"), two())),
+            Box::new(Exponentation(var("y"), two())),
+        );
+        let counterexample = verify_transformation(&original, &wrong, 50).unwrap_err();
+        assert_eq!(counterexample.assignment.len(), 2);
+        assert_ne!(counterexample.original, counterexample.transformed);
+    }
+
+    #[test]
+    fn test_finite_field_polynomials() {
+        let gf2 = |coefficients: &[i64]| {
+            Polynomial::new("x", coefficients.iter().map(|c| Gf::<2>::new(*c)).collect())
+        };
+
+        // x^2 + 1 = (x + 1)^2 over GF(2)
+        let factorization = gf2(&[1, 0, 1]).factor().unwrap();
+        assert_eq!(factorization.factors, vec![(gf2(&[1, 1]), 2)]);
+        assert!(gf2(&[1, 1, 0, 0, 1]).is_irreducible());
+
+        // CRC-style remainder of x^5 + x^3 + 1 modulo x^3 + x + 1
+        let (_, remainder) = gf2(&[1, 0, 0, 1, 0, 1])
+            .div_rem(&gf2(&[1, 1, 0, 1]))
+            .unwrap();
+        assert_eq!(remainder, gf2(&[1, 0, 1]));
+
+        // 2x^2 + 3 = 2 (x + 1)(x + 4) over GF(5)
+        let gf5 = |coefficients: &[i64]| {
+            Polynomial::new("x", coefficients.iter().map(|c| Gf::<5>::new(*c)).collect())
+        };
+        let factorization = gf5(&[3, 0, 2]).factor().unwrap();
+        assert_eq!(factorization.unit, Gf::new(2));
+        assert_eq!(
+            factorization.factors,
+            vec![(gf5(&[1, 1]), 1), (gf5(&[4, 1]), 1)]
+        );
+
+        let product = gf5(&[1, 1]).mul(&gf5(&[4, 1])).scale(Gf::new(2));
+        assert_eq!(product, gf5(&[3, 0, 2]));
+
+        let token = CancellationToken::new();
+        token.cancel();
+        assert_eq!(gf5(&[3, 0, 2]).factor_cancellable(&token), Err(Cancelled));
+        assert_eq!(
+            gf5(&[3, 2])
+                .factor_cancellable(&token)
+                .unwrap()
+                .unwrap()
+                .factors
+                .len(),
+            1
+        );
+    }
+
+    #[test]
+    fn test_rational_functions() {
+        let q = |coefficients: &[i64]| {
+            Polynomial::new(
+                "x",
+                coefficients.iter().map(|c| Fraction::from(*c)).collect(),
+            )
+        };
+
+        // (x^2 - 1) / (2x - 2) = (x + 1) / 2
+        let f = RationalFunction::new(q(&[-1, 0, 1]), q(&[-2, 2])).unwrap();
+        assert_eq!(
+            f.to_polynomial(),
+            Some(q(&[1, 1]).scale(Fraction::new(1, 2)))
+        );
+        assert!(RationalFunction::new(q(&[1]), q(&[])).is_none());
+
+        let g = RationalFunction::new(q(&[1]), q(&[-2, 1])).unwrap();
+        assert_eq!(g.evaluate(Fraction::from(2)), None);
+        assert_eq!(g.evaluate(Fraction::from(3)), Some(Fraction::from(1)));
+
+        // 1/(x - 2) - 1/(x - 2) cancels completely
+        assert!(g.sub(&g).numerator().is_zero());
+        assert_eq!(g.div(&g).unwrap().to_polynomial(), Some(q(&[1])));
+
+        // x * (x + 1)^-1 round-trips through Term
+        let term = Multiplication(
+            Box::new(Variable("x".into())),
+            Box::new(Exponentation(
+                Box::new(Addition(
+                    Box::new(Variable("x".into())),
+                    Box::new(Value(Number::Rational(Fraction::from(1)))),
+                )),
+                Box::new(Value(Number::Rational(Fraction::from(-1)))),
+            )),
+        );
+        let h = RationalFunction::from_term(&term, "x").unwrap();
+        assert_eq!(h.numerator(), &q(&[0, 1]));
+        assert_eq!(h.denominator(), &q(&[1, 1]));
+        assert_eq!(RationalFunction::from_term(&h.to_term(), "x"), Some(h));
+
+        assert_eq!(Polynomial::from_term(&term, "x"), None);
+        assert!(RationalFunction::from_term(&Variable("y".into()), "x").is_none());
+    }
+
+    #[test]
+    fn test_asymptotes() {
+        let q = |coefficients: &[i64]| {
+            Polynomial::new(
+                "x",
+                coefficients.iter().map(|c| Fraction::from(*c)).collect(),
+            )
+        };
+
+        // (x^2 + 1) / (x - 1) = x + 1 + 2 / (x - 1)
+        let f = RationalFunction::new(q(&[1, 0, 1]), q(&[-1, 1])).unwrap();
+        assert_eq!(
+            f.to_term().asymptotes("x"),
+            Some(vec![
+                Asymptote::Vertical(Number::Rational(Fraction::from(1))),
+                Asymptote::Oblique {
+                    slope: Fraction::from(1),
+                    intercept: Fraction::from(1)
+                },
+            ])
+        );
+
+        // 3x^2 / (x^2 - 2) has irrational poles at +-sqrt(2)
+        let g = RationalFunction::new(q(&[0, 0, 3]), q(&[-2, 0, 1])).unwrap();
+        let asymptotes = g.asymptotes().unwrap();
+        assert_eq!(asymptotes.len(), 3);
+        match asymptotes[1] {
+            Asymptote::Vertical(pole) => assert!((f64::from(pole) - 2f64.sqrt()).abs() < 1e-12),
+            ref other => panic!("expected a pole, got {other:?}"),
+        }
+        assert_eq!(asymptotes[2], Asymptote::Horizontal(Fraction::from(3)));
+
+        // The singularity of (x^2 - 1) / (x - 1) is removable
+        let h = RationalFunction::new(q(&[-1, 0, 1]), q(&[-1, 1])).unwrap();
+        assert_eq!(h.asymptotes(), Some(vec![]));
+    }
+
+    #[test]
+    fn test_critical_points() {
+        let q = |coefficients: &[i64]| {
+            Polynomial::new(
+                "x",
+                coefficients.iter().map(|c| Fraction::from(*c)).collect(),
+            )
+        };
+        let at = |value: i64| Number::Rational(Fraction::from(value));
+
+        // x^3 - 3x has a maximum at -1 and a minimum at 1
+        let cubic = q(&[0, -3, 0, 1]).to_term();
+        assert_eq!(
+            cubic.critical_points("x", &Range::real_line()),
+            Some(vec![
+                CriticalPoint {
+                    at: at(-1),
+                    kind: CriticalKind::Maximum
+                },
+                CriticalPoint {
+                    at: at(1),
+                    kind: CriticalKind::Minimum
+                },
+            ])
+        );
+        assert_eq!(
+            cubic
+                .critical_points("x", &Range::closed(at(0), at(2)))
+                .unwrap()
+                .len(),
+            1
+        );
+
+        // x^3 is stationary at 0 but keeps rising
+        let points = q(&[0, 0, 0, 1])
+            .to_term()
+            .critical_points("x", &Range::real_line())
+            .unwrap();
+        assert_eq!(points[0].kind, CriticalKind::Inflection);
+
+        // x^4 - 8x has its minimum at the cube root of 2, found numerically
+        let points = q(&[0, -8, 0, 0, 1])
+            .to_term()
+            .critical_points("x", &Range::real_line())
+            .unwrap();
+        assert_eq!(points.len(), 1);
+        assert!((f64::from(points[0].at) - 2f64.cbrt()).abs() < 1e-9);
+        assert_eq!(points[0].kind, CriticalKind::Minimum);
+
+        // sin(x) is not rational, so its peaks on [0, 6] are found from cos(x)
+        let sine = Function(Function::Sin, Box::new(Variable("x".into())));
+        let points = sine
+            .critical_points("x", &Range::closed(at(0), at(6)))
+            .unwrap();
+        assert_eq!(points.len(), 2);
+        assert!((f64::from(points[0].at) - std::f64::consts::FRAC_PI_2).abs() < 1e-9);
+        assert_eq!(points[0].kind, CriticalKind::Maximum);
+        assert!((f64::from(points[1].at) - 3.0 * std::f64::consts::FRAC_PI_2).abs() < 1e-9);
+        assert_eq!(points[1].kind, CriticalKind::Minimum);
+        assert_eq!(sine.critical_points("x", &Range::real_line()), None);
+    }
+
+    #[test]
+    fn test_exact_integral() {
+        let rational = |n: i64, d: i64| Number::Rational(Fraction::new(n, d));
+
+        // The integral of x^2 + 1/2 over [0, 1] is exactly 5/6
+        let term = Addition(
+            Box::new(Exponentation(
+                Box::new(Variable("x".into())),
+                Box::new(Value(rational(2, 1))),
+            )),
+            Box::new(Value(rational(1, 2))),
+        );
+        assert_eq!(
+            term.definite_integral("x", rational(0, 1), rational(1, 1)),
+            Some(rational(5, 6))
+        );
+
+        // x^70 over [0, 2] overflows exactly, so it falls back to quadrature as well
+        let x70 = Polynomial::monomial("x", Fraction::from(1), 70);
+        assert_eq!(
+            x70.definite_integral(Fraction::from(0), Fraction::from(2)),
+            None
+        );
+        assert_eq!(x70.checked_evaluate(Fraction::from(2)), None);
+        match x70
+            .to_term()
+            .definite_integral("x", rational(0, 1), rational(2, 1))
+        {
+            Some(Number::Irrational(area)) => {
+                let exact = 2f64.powi(71) / 71.0;
+                assert!(((area - exact) / exact).abs() < 1e-6)
+            }
+            other => panic!("expected a numeric result, got {other:?}"),
+        }
+
+        // An irrational bound falls back to quadrature
+        match term.definite_integral("x", rational(0, 1), Number::Irrational(2f64.sqrt())) {
+            Some(Number::Irrational(area)) => {
+                assert!((area - (2f64.sqrt().powi(3) / 3.0 + 2f64.sqrt() / 2.0)).abs() < 1e-9)
+            }
+            other => panic!("expected a numeric result, got {other:?}"),
+        }
+    }
+
+    #[test]
+    fn test_solids_of_revolution() {
+        use std::f64::consts::PI;
+
+        let (zero, one) = (
+            Number::Rational(Fraction::from(0)),
+            Number::Rational(Fraction::from(1)),
+        );
+        let x = Variable("x".into());
+
+        // y = x over [0, 1] is a segment of length sqrt(2) sweeping a cone
+        let length = f64::from(arc_length(&x, "x", zero, one).unwrap());
+        assert!((length - 2f64.sqrt()).abs() < 1e-9);
+
+        let volume = f64::from(volume_of_revolution(&x, "x", zero, one).unwrap());
+        assert!((volume - PI / 3.0).abs() < 1e-12);
+
+        let surface = f64::from(surface_of_revolution(&x, "x", zero, one).unwrap());
+        assert!((surface - PI * 2f64.sqrt()).abs() < 1e-9);
+
+        // Constants in other variables have a flat graph
+        let length = f64::from(arc_length(&Variable("y".into()), "x", zero, one).unwrap());
+        assert!((length - 1.0).abs() < 1e-9);
+
+        // One arch of sin(x) sweeps a spindle of area 2 pi (sqrt(2) + ln(1 + sqrt(2)))
+        let sine = Function(Function::Sin, Box::new(x.clone()));
+        let pi = Number::Irrational(PI);
+        let length = f64::from(arc_length(&sine, "x", zero, pi).unwrap());
+        assert!((length - 3.820197789027712).abs() < 1e-9);
+        let surface = f64::from(surface_of_revolution(&sine, "x", zero, pi).unwrap());
+        let expected = 2.0 * PI * (2f64.sqrt() + (1.0 + 2f64.sqrt()).ln());
+        assert!((surface - expected).abs() < 1e-9);
+
+        // sqrt(x) over [1, 4] sweeps a paraboloid, but its tangent is vertical at 0
+        let root = RootExtraction(
+            Box::new(x.clone()),
+            Box::new(Value(Number::Rational(Fraction::from(2)))),
+        );
+        let four = Number::Rational(Fraction::from(4));
+        let surface = f64::from(surface_of_revolution(&root, "x", one, four).unwrap());
+        let expected = 4.0 * PI / 3.0 * (4.25f64.powf(1.5) - 1.25f64.powf(1.5));
+        assert!((surface - expected).abs() < 1e-9);
+        assert!(arc_length(&root, "x", zero, one).is_none());
+    }
+
+    #[test]
+    fn test_parametric_curves() {
+        let power = |exponent: i64| {
+            Exponentation(
+                Box::new(Variable("t".into())),
+                Box::new(Value(Number::Rational(Fraction::from(exponent)))),
+            )
+        };
+
+        // (t^2, t^3) has slope 3t/2
+        let curve = ParametricCurve::new(power(2), power(3), "t");
+        assert_eq!(curve.point(2.0), Some((4.0, 8.0)));
+        let slope = curve.slope().unwrap();
+        assert_eq!(numeric::evaluate(&slope, "t", 2.0), Some(3.0));
+
+        let graph = ParametricCurve::from_graph(power(2), "t");
+        assert_eq!(
+            graph.sample(-1.0, 1.0, 3),
+            vec![(-1.0, 1.0), (0.0, 0.0), (1.0, 1.0)]
+        );
+        assert!(graph.slope().is_some());
+
+        // r = 2 is a circle
+        let circle = PolarCurve::new(Value(Number::Rational(Fraction::from(2))), "theta");
+        for (x, y) in circle.sample(0.0, std::f64::consts::PI, 5) {
+            assert!((x.hypot(y) - 2.0).abs() < 1e-12);
+        }
+        let spiral = PolarCurve::new(Term::parse("theta/2").unwrap(), "theta");
+        let parametric = spiral.to_parametric();
+        assert_eq!(parametric.x.to_string(), "theta/2*cos(theta)");
+        assert_eq!(parametric.y.to_string(), "theta/2*sin(theta)");
+        for theta in [0.5, 2.0, 4.0] {
+            let ((x, y), (px, py)) = (
+                spiral.point(theta).unwrap(),
+                parametric.point(theta).unwrap(),
+            );
+            assert!((x - px).abs() < 1e-12 && (y - py).abs() < 1e-12);
+        }
+        // dy/dx = (sin + theta cos) / (cos - theta sin) for r = theta/2
+        let slope = parametric.slope().unwrap();
+        let (sin, cos) = 1f64.sin_cos();
+        let expected = (sin + cos) / (cos - sin);
+        assert!((numeric::evaluate(&slope, "theta", 1.0).unwrap() - expected).abs() < 1e-12);
+
+        // The unit circle (cos t, sin t) has slope -cot(t)
+        let unit = ParametricCurve::new(
+            Term::parse("cos(t)").unwrap(),
+            Term::parse("sin(t)").unwrap(),
+            "t",
+        );
+        let slope = unit.slope().unwrap();
+        let at = numeric::evaluate(&slope, "t", std::f64::consts::FRAC_PI_4).unwrap();
+        assert!((at + 1.0).abs() < 1e-12);
+        assert_eq!(numeric::evaluate(&slope, "t", 0.0), None);
+
+        let (r, theta) = cartesian_to_polar(0.0, 2.0);
+        assert_eq!(r, 2.0);
+        assert!((theta - std::f64::consts::FRAC_PI_2).abs() < 1e-12);
+    }
+
+    #[test]
+    fn test_sample_grid() {
+        // sqrt(x * y) is undefined where the product is negative
+        let term = RootExtraction(
+            Box::new(Multiplication(
+                Box::new(Variable("x".into())),
+                Box::new(Variable("y".into())),
+            )),
+            Box::new(Value(Number::Rational(Fraction::from(2)))),
+        );
+
+        let grid = term.sample_grid("x", "y", -1.0..=1.0, 0.0..=4.0, 3);
+        assert_eq!(grid.xs, vec![-1.0, 0.0, 1.0]);
+        assert_eq!(grid.ys, vec![0.0, 2.0, 4.0]);
+        assert_eq!(*grid.values.get(2, 2), Ok(2.0));
+        assert_eq!(*grid.values.get(1, 0), Err(numeric::DomainError::NotFinite));
+
+        let parallel = term.sample_grid_parallel("x", "y", -1.0..=1.0, 0.0..=4.0, 3, 2);
+        assert_eq!(parallel.values, grid.values);
+
+        let unbound = Variable("z".into()).sample_grid("x", "y", 0.0..=1.0, 0.0..=1.0, 2);
+        assert!(unbound
+            .values
+            .entries()
+            .iter()
+            .all(|cell| *cell == Err(numeric::DomainError::UnboundVariable)));
+
+        // Indexing by a variable does not compile, so those cells are evaluated one by one
+        let indexed = Term::parse("[10, 20, 30][x] + y").unwrap();
+        let grid = indexed.sample_grid("x", "y", 0.0..=2.0, 0.0..=1.0, 3);
+        assert_eq!(*grid.values.get(0, 1), Ok(20.0));
+        assert_eq!(*grid.values.get(2, 2), Ok(31.0));
+    }
+
+    #[test]
+    fn test_level_set() {
+        let square = |var: &str| {
+            Exponentation(
+                Box::new(Variable(var.into())),
+                Box::new(Value(Number::Rational(Fraction::from(2)))),
+            )
+        };
+        let circle = Addition(Box::new(square("x")), Box::new(square("y")));
+
+        let curves = circle.level_set("x", "y", 25.0, -6.0..=6.0, -6.0..=6.0, 49);
+        assert_eq!(curves.len(), 1);
+        let curve = &curves[0];
+        assert_eq!(curve.first(), curve.last());
+        assert!(curve.iter().all(|(x, y)| (x.hypot(*y) - 5.0).abs() < 0.02));
+
+        // Clipping the region leaves an open arc in the first quadrant
+        let arcs = circle.level_set("x", "y", 25.0, 0.0..=6.0, 0.0..=6.0, 25);
+        assert_eq!(arcs.len(), 1);
+        assert_ne!(arcs[0].first(), arcs[0].last());
+    }
+
+    #[test]
+    fn test_surface_export() {
+        let plane = Addition(
+            Box::new(Variable("x".into())),
+            Box::new(Variable("y".into())),
+        );
+
+        let mesh = plane.sample_surface("x", "y", 0.0..=1.0, 0.0..=1.0, 2);
+        assert_eq!(mesh.vertices.len(), 4);
+        assert_eq!(mesh.faces, vec![[0, 1, 3], [0, 3, 2]]);
+
+        let mut obj = Vec::new();
+        mesh.write_obj(&mut obj).unwrap();
+        let obj = String::from_utf8(obj).unwrap();
+        assert!(obj.starts_with("v 0 0 0\nv 1 0 1\n"));
+        assert!(obj.ends_with("f 1 2 4\nf 1 4 3\n"));
+
+        // 1/x is undefined along x = 0, which drops the triangles touching it
+        let reciprocal = Exponentation(
+            Box::new(Variable("x".into())),
+            Box::new(Value(Number::Rational(Fraction::from(-1)))),
+        );
+        let grid = reciprocal.sample_grid("x", "y", 0.0..=1.0, 0.0..=1.0, 2);
+        assert!(Mesh::from_grid(&grid).faces.is_empty());
+
+        let mut csv = Vec::new();
+        grid.write_csv(&mut csv).unwrap();
+        assert_eq!(
+            String::from_utf8(csv).unwrap(),
+            "x,y,z\n0,0,\n1,0,1\n0,1,\n1,1,1\n"
+        );
+    }
+
+    #[cfg(feature = "plotting")]
+    #[test]
+    fn test_heat_map() {
+        use super::math::plot::*;
+
+        assert_eq!(Colormap::Grayscale.color(0.5), [128, 128, 128]);
+        assert_eq!(Colormap::Viridis.color(2.0), [253, 231, 37]);
+
+        let term = Multiplication(
+            Box::new(Variable("x".into())),
+            Box::new(Variable("y".into())),
+        );
+        let grid = term.sample_grid("x", "y", -1.0..=1.0, -1.0..=1.0, 8);
+        assert_eq!(grid.value_range(), Some((-1.0, 1.0)));
+
+        let mut png = Vec::new();
+        grid.write_png(&mut png, Colormap::Diverging).unwrap();
+        assert!(png.starts_with(b"\x89PNG"));
+    }
+
+    #[test]
+    fn test_csv_export() {
+        let reciprocal = Exponentation(
+            Box::new(Variable("x".into())),
+            Box::new(Value(Number::Rational(Fraction::from(-1)))),
+        );
+
+        let mut out = Vec::new();
+        reciprocal
+            .write_samples_csv("x", [0.0, 2.0], &mut out)
+            .unwrap();
+        assert_eq!(String::from_utf8(out).unwrap(), "x,value\n0,\n2,0.5\n");
+
+        let mut writer = CsvWriter::new(Vec::new(), &["a,b", "c"]).unwrap();
+        assert!(writer.write_row(&[Ok(1.0)]).is_err());
+        writer.write_row(&[Ok(1.0), Ok(2.0)]).unwrap();
+        assert_eq!(
+            String::from_utf8(writer.into_inner()).unwrap(),
+            "\"a,b\",c\n1,2\n"
+        );
+    }
+
+    #[cfg(feature = "parquet")]
+    #[test]
+    fn test_parquet_export() {
+        use super::math::columnar::*;
+        use arrow_array::{Array, Float64Array};
+        use parquet::arrow::arrow_reader::ParquetRecordBatchReaderBuilder;
+        use std::fs::{self, File};
+
+        let path = std::env::temp_dir().join(format!("mathex-{}.parquet", std::process::id()));
+        let read = || ParquetRecordBatchReaderBuilder::try_new(File::open(&path).unwrap()).unwrap();
+
+        let reciprocal = Term::parse("1/x").unwrap();
+        reciprocal
+            .write_samples_parquet("x", [0.0, 2.0], File::create(&path).unwrap())
+            .unwrap();
+        let batches: Vec<_> = read().build().unwrap().map(Result::unwrap).collect();
+        assert_eq!(batches.len(), 1);
+        let values = batches[0].column_by_name("value").unwrap();
+        assert_eq!(
+            values.as_any().downcast_ref::<Float64Array>(),
+            Some(&Float64Array::from(vec![None, Some(0.5)]))
+        );
+
+        let mut writer = ParquetWriter::new(File::create(&path).unwrap(), &["a", "b"]).unwrap();
+        assert!(writer.write_row(&[Ok(1.0)]).is_err());
+        for i in 0..=ROW_GROUP_ROWS {
+            writer
+                .write_row(&[Ok(i as f64), Err(numeric::DomainError::NotFinite)])
+                .unwrap();
+        }
+        writer.into_inner().unwrap();
+        let metadata = read().metadata().clone();
+        assert_eq!(metadata.num_row_groups(), 2);
+        assert_eq!(
+            metadata.file_metadata().num_rows(),
+            ROW_GROUP_ROWS as i64 + 1
+        );
+        fs::remove_file(&path).unwrap();
+
+        let batch = results_batch(
+            &["x", "y"],
+            &[&[Ok(1.0)], &[Err(numeric::DomainError::NotFinite)]],
+        )
+        .unwrap();
+        assert_eq!((batch.num_rows(), batch.column(1).null_count()), (1, 1));
+        assert!(results_batch(&["x"], &[]).is_err());
+    }
+
+    #[cfg(feature = "arrow")]
+    #[test]
+    fn test_evaluate_arrays() {
+        use super::math::columnar::*;
+        use arrow_array::Float64Array;
+
+        let term = Term::parse("price * quantity / 2").unwrap();
+        let price = Float64Array::from(vec![Some(1.5), None, Some(4.0), Some(1.0)]);
+        let quantity = Float64Array::from(vec![2.0, 0.5, 3.0, f64::INFINITY]);
+        assert_eq!(
+            evaluate_arrays(&term, &[("price", &price), ("quantity", &quantity)]),
+            Some(Float64Array::from(vec![Some(1.5), None, Some(6.0), None]))
+        );
+        assert_eq!(
+            evaluate_arrays(&term, &[("price", &price), ("quantity", &vec![1.0].into())]),
+            None
+        );
+    }
+
+    #[test]
+    fn test_evaluate_columns() {
+        let term = Multiplication(
+            Box::new(Variable("price".into())),
+            Box::new(Variable("quantity".into())),
+        );
+        let (price, quantity) = ([1.5, 2.0, 4.0], [2.0, 0.5, 3.0]);
+
+        assert_eq!(
+            numeric::evaluate_columns(&term, &[("price", &price), ("quantity", &quantity)]),
+            Some(vec![Ok(3.0), Ok(1.0), Ok(12.0)])
+        );
+        assert_eq!(
+            numeric::evaluate_columns(&term, &[("price", &price), ("quantity", &[1.0])]),
+            None
+        );
+        assert_eq!(
+            numeric::evaluate_columns(&term, &[("price", &price[..1])]),
+            Some(vec![Err(numeric::DomainError::UnboundVariable)])
+        );
+    }
+
+    #[test]
+    fn test_templates() {
+        use std::collections::HashMap;
+
+        let rational = |value: i64| Value(Number::Rational(Fraction::from(value)));
+
+        // principal * (1 + rate) ^ years
+        let template = Template::new(
+            Multiplication(
+                Box::new(Variable("principal".into())),
+                Box::new(Exponentation(
+                    Box::new(Addition(
+                        Box::new(rational(1)),
+                        Box::new(Variable("rate".into())),
+                    )),
+                    Box::new(Variable("years".into())),
+                )),
+            ),
+            vec![
+                Placeholder::new("principal", Kind::Scalar),
+                Placeholder::new("rate", Kind::Scalar).assuming(Assumption::NonNegative),
+                Placeholder::new("years", Kind::Scalar)
+                    .assuming(Assumption::Constant)
+                    .assuming(Assumption::Integer),
+            ],
+        );
+
+        let mut arguments = HashMap::from([
+            ("principal", Variable("p".into())),
+            ("rate", Value(Number::Rational(Fraction::new(1, 10)))),
+            ("years", rational(2)),
+        ]);
+        let instance = template.instantiate(&arguments).unwrap();
+        let value = instance.substitute("p", Number::Rational(Fraction::from(100)));
+        assert_eq!(
+            value.get_value(),
+            Some(Number::Rational(Fraction::from(121)))
+        );
+
+        arguments.insert("years", Value(Number::Rational(Fraction::new(1, 2))));
+        assert_eq!(
+            template.instantiate(&arguments).unwrap_err(),
+            TemplateError::Violated {
+                name: "years".into(),
+                assumption: Assumption::Integer
+            }
+        );
+
+        arguments.insert("years", rational(2));
+        arguments.insert("rate", Variable("r".into()));
+        assert!(matches!(
+            template.instantiate(&arguments),
+            Err(TemplateError::Unverifiable { .. })
+        ));
+
+        arguments.insert("rate", Vector(vec![rational(1)]));
+        assert!(matches!(
+            template.instantiate(&arguments),
+            Err(TemplateError::WrongKind { .. })
+        ));
+
+        arguments.remove("rate");
+        assert_eq!(
+            template.instantiate(&arguments).unwrap_err(),
+            TemplateError::Missing("rate".into())
+        );
+
+        arguments.insert("rate", rational(0));
+        arguments.insert("fees", rational(0));
+        assert_eq!(
+            template.instantiate(&arguments).unwrap_err(),
+            TemplateError::Unexpected("fees".into())
+        );
+    }
+
+    #[test]
+    fn test_ieee_semantics() {
+        let rational = |n: i64, d: i64| Value(Number::Rational(Fraction::new(n, d)));
+        let sum = Addition(Box::new(rational(1, 10)), Box::new(rational(2, 10)));
+
+        assert_eq!(
+            sum.evaluate_with(numeric::Semantics::ExactPreferred),
+            Some(Number::Rational(Fraction::new(3, 10)))
+        );
+        match sum.evaluate_with(numeric::Semantics::Ieee) {
+            Some(Number::Irrational(value)) => {
+                assert_eq!(value.to_bits(), (0.1f64 + 0.2).to_bits())
+            }
+            other => panic!("expected a float, got {other:?}"),
+        }
+
+        // Exact mode keeps sqrt(2) irrational, so its square is a float too
+        let square = Exponentation(
+            Box::new(RootExtraction(
+                Box::new(rational(2, 1)),
+                Box::new(rational(2, 1)),
+            )),
+            Box::new(rational(2, 1)),
+        );
+        assert_eq!(
+            square.evaluate_with(numeric::Semantics::ExactPreferred),
+            Some(Number::Irrational(2.0000000000000004))
+        );
+        assert_eq!(
+            square.evaluate_with(numeric::Semantics::Ieee),
+            Some(Number::Irrational(2f64.powf(0.5).powf(2.0)))
+        );
+    }
+
+    #[test]
+    fn test_lint() {
+        let rational = |value: i64| Value(Number::Rational(Fraction::from(value)));
+        let x = || Box::new(Variable("x".into()));
+
+        // x - x
+        let difference = Addition(x(), Box::new(Multiplication(Box::new(rational(-1)), x())));
+        assert_eq!(
+            difference.lint(),
+            vec![Lint {
+                at: TermPath::root(),
+                kind: LintKind::SelfCancellation
+            }]
+        );
+
+        // 1 / (x + 1) + (2 - 2)^-1
+        let term = Addition(
+            Box::new(Exponentation(
+                Box::new(Addition(x(), Box::new(rational(1)))),
+                Box::new(rational(-1)),
+            )),
+            Box::new(Exponentation(
+                Box::new(Addition(Box::new(rational(2)), Box::new(rational(-2)))),
+                Box::new(rational(-1)),
+            )),
+        );
+        let kinds: Vec<(Vec<usize>, LintKind)> = term
+            .lint()
+            .into_iter()
+            .map(|lint| (lint.at.0, lint.kind))
+            .collect();
+        assert_eq!(
+            kinds,
+            vec![
+                (vec![0], LintKind::PossibleDivisionByZero),
+                (vec![1], LintKind::DivisionByZero),
+            ]
+        );
+
+        let root = RootExtraction(Box::new(rational(-4)), Box::new(rational(2)));
+        assert_eq!(root.lint()[0].kind, LintKind::EvenRootOfNegative);
+        assert!(rational(2).lint().is_empty());
+
+        // 1/0 is flagged where it is written, and dividing by its infinite value separately
+        let kinds: Vec<LintKind> = (Term::parse("x / (1/0)").unwrap().lint())
+            .into_iter()
+            .map(|lint| lint.kind)
+            .collect();
+        assert_eq!(
+            kinds,
+            vec![LintKind::NonFiniteValue, LintKind::DivisionByZero]
+        );
+
+        let source = |input: &str| -> Vec<(usize, LintKind)> {
+            (lint_source(input).into_iter())
+                .map(|lint| (lint.at, lint.kind))
+                .collect()
+        };
+        assert_eq!(
+            source("2*(x) + ((y - 1))*sin(x)"),
+            vec![
+                (2, LintKind::RedundantParentheses),
+                (9, LintKind::RedundantParentheses)
+            ]
+        );
+        assert!(source("(x + 1)*2 - (x - 1) + (2^3)^x").is_empty());
+        assert_eq!(
+            source("2x (x + 1)"),
+            vec![
+                (1, LintKind::ImplicitMultiplication),
+                (3, LintKind::ImplicitMultiplication)
+            ]
+        );
+    }
+
+    #[test]
+    fn test_digit_separators() {
+        let million = Number::Rational(Fraction::from(1_000_000));
+
+        assert_eq!("1_000_000".parse::<Number>(), Ok(million));
+        assert!("1__000".parse::<Number>().is_err());
+        assert!("_1".parse::<Number>().is_err());
+
+        assert_eq!(
+            Number::parse_with("1,000,000", &NumberFormat::ENGLISH),
+            Ok(million)
+        );
+        assert_eq!(
+            Number::parse_with("-1,234.5", &NumberFormat::ENGLISH),
+            Ok(Number::Irrational(-1234.5))
+        );
+        assert_eq!(
+            Number::parse_with("1.234,5", &NumberFormat::GERMAN),
+            Ok(Number::Irrational(1234.5))
+        );
+        assert!(Number::parse_with("1,5", &NumberFormat::ENGLISH).is_err());
+        assert!(Number::parse_with("1,0000", &NumberFormat::ENGLISH).is_err());
+        assert!(Number::parse_with("1.000,000,0", &NumberFormat::GERMAN).is_err());
+
+        assert_eq!(million.format_with(&NumberFormat::ENGLISH), "1,000,000");
+        assert_eq!(
+            Number::Rational(Fraction::new(-12345, 1000)).format_with(&NumberFormat::ENGLISH),
+            "-2,469/200"
+        );
+        assert_eq!(
+            Number::Irrational(-1234567.25).format_with(&NumberFormat::GERMAN),
+            "-1.234.567,25"
+        );
+        assert_eq!(
+            million.format_with(&NumberFormat::PLAIN),
+            million.to_string()
+        );
+    }
+
+    #[test]
+    fn test_aggregations() {
+        let list = List(
+            [3, -1, 4, -1, 5]
+                .iter()
+                .map(|value| Value(Number::Rational(Fraction::from(*value))))
+                .collect(),
+        );
+        let positive = |value: &Number| f64::from(*value) > 0.0;
+
+        assert_eq!(list.count_if(positive), Some(3));
+        assert_eq!(
+            list.sum_if(positive),
+            Some(Number::Rational(Fraction::from(12)))
+        );
+        assert_eq!(list.any(|value| f64::from(*value) > 4.0), Some(true));
+        assert_eq!(list.all(positive), Some(false));
+
+        // Sets aggregate each distinct value once
+        let set = Set(match &list {
+            List(elements) => elements.clone(),
+            _ => unreachable!(),
+        });
+        assert_eq!(set.count_if(|_| true), Some(4));
+        assert_eq!(
+            set.sum_if(|_| true),
+            Some(Number::Rational(Fraction::from(11)))
+        );
+
+        assert_eq!(List(vec![]).all(positive), Some(true));
+        assert_eq!(Variable("x".into()).any(positive), None);
+    }
+
+    #[test]
+    fn test_bit_operations() {
+        let n = |value: i64| Number::Rational(Fraction::from(value));
+
+        assert_eq!(n(0b1100).bit_and(n(0b1010)), Ok(n(0b1000)));
+        assert_eq!(n(0b1100).bit_or(n(0b1010)), Ok(n(0b1110)));
+        assert_eq!(n(0b1100).bit_xor(n(0b1010)), Ok(n(0b0110)));
+        assert_eq!(n(3).shift_left(n(4)), Ok(n(48)));
+        assert_eq!(n(-7).shift_right(n(1)), Ok(n(-4)));
+        assert_eq!(n(255).popcount(), Ok(n(8)));
+
+        let half = Number::Rational(Fraction::new(1, 2));
+        assert_eq!(half.bit_and(n(1)), Err(BitError::NotAnInteger(half)));
+        assert_eq!(
+            Number::Irrational(2.0).popcount(),
+            Err(BitError::NotAnInteger(Number::Irrational(2.0)))
+        );
+        assert_eq!(n(1).shift_left(n(-1)), Err(BitError::Negative(n(-1))));
+        assert_eq!(n(1).shift_left(n(64)), Err(BitError::Overflow));
+        assert_eq!(n(i64::MAX).shift_left(n(1)), Err(BitError::Overflow));
+        assert_eq!(n(-1).popcount(), Err(BitError::Negative(n(-1))));
+
+        let value = |input: &str| Term::parse(input).unwrap().evaluate();
+        assert_eq!(value("bitand(12, 10) + bitor(12, 10)"), Ok(n(22)));
+        assert_eq!(value("xor(12, 10)"), Ok(n(6)));
+        assert_eq!(value("shl(3, 4) - shr(-7, 1)"), Ok(n(52)));
+        assert_eq!(value("popcount(255)"), Ok(n(8)));
+        assert_eq!(value("shl(1, 64)"), Err(EvalError::Overflow));
+        assert_eq!(value("bitand(1/2, 1)"), Err(EvalError::InvalidDomain));
+        assert_eq!(value("popcount(-1)"), Err(EvalError::InvalidDomain));
+        // Floats holding integers work too, as in compiled code
+        let compiled = Term::parse("xor(x, 3)").unwrap().compile(&["x"]).unwrap();
+        assert_eq!(compiled.eval(&[5.0]), 6.0);
+    }
+
+    #[test]
+    fn test_ilog() {
+        let q = |n: i64, d: i64| Fraction::new(n, d);
+
+        assert_eq!(ilog(q(2, 1), q(1024, 1)), Some(10));
+        assert_eq!(ilog(q(10, 1), q(1, 1)), Some(0));
+        assert_eq!(ilog(q(2, 1), q(1, 8)), Some(-3));
+        assert_eq!(ilog(q(2, 3), q(16, 81)), Some(4));
+        assert_eq!(ilog(q(3, 1), q(3i64.pow(39), 1)), Some(39));
+
+        assert_eq!(ilog(q(2, 1), q(1000, 1)), None);
+        assert_eq!(ilog(q(1, 1), q(1, 1)), None);
+        assert_eq!(ilog(q(-2, 1), q(4, 1)), None);
+        assert_eq!(ilog(q(2, 1), q(0, 1)), None);
+    }
+
+    #[test]
+    fn test_special_numbers() {
+        assert_eq!(harmonic(0), Some(Fraction::from(0)));
+        assert_eq!(harmonic(4), Some(Fraction::new(25, 12)));
+        assert_eq!(harmonic(1000), None);
+
+        let bernoullis: Vec<Fraction> = (0..=8).map(|n| bernoulli(n).unwrap()).collect();
+        assert_eq!(
+            bernoullis,
+            vec![
+                Fraction::from(1),
+                Fraction::new(-1, 2),
+                Fraction::new(1, 6),
+                Fraction::from(0),
+                Fraction::new(-1, 30),
+                Fraction::from(0),
+                Fraction::new(1, 42),
+                Fraction::from(0),
+                Fraction::new(-1, 30),
+            ]
+        );
+        assert_eq!(bernoulli(12), Some(Fraction::new(-691, 2730)));
+
+        assert_eq!(stirling_first(4, 2), Some(11));
+        assert_eq!(stirling_first(5, 5), Some(1));
+        assert_eq!(stirling_second(5, 2), Some(15));
+        assert_eq!(stirling_second(4, 0), Some(0));
+        assert_eq!(stirling_second(0, 0), Some(1));
+        assert_eq!(stirling_second(2, 3), Some(0));
+        assert_eq!(stirling_first(30, 1), None);
+        // Near the diagonal the triangle is skipped, so n can be large
+        assert_eq!(stirling_first(30, 29), Some(435));
+        assert_eq!(stirling_second(100, 99), Some(4950));
+        assert_eq!(stirling_first(100, 98), Some(12087075));
+        assert_eq!(stirling_second(100, 98), Some(11925375));
+        assert_eq!(stirling_second(100, 60), None);
+
+        let value = |input: &str| Term::parse(input).unwrap().evaluate();
+        let rational = |n: i64, d: i64| Ok(Number::Rational(Fraction::new(n, d)));
+        assert_eq!(value("harmonic(4)"), rational(25, 12));
+        assert_eq!(value("bernoulli(12)"), rational(-691, 2730));
+        assert_eq!(value("stirling1(4, 2) + stirling2(5, 2)"), rational(26, 1));
+        assert_eq!(value("harmonic(1000)"), Err(EvalError::Overflow));
+        assert_eq!(value("stirling2(3, 1/2)"), Err(EvalError::InvalidDomain));
+        // Large arguments stop at the first overflow instead of filling huge tables
+        assert_eq!(value("stirling2(4000000000, 1)"), rational(1, 1));
+        assert_eq!(value("stirling2(4000000000, 3)"), Err(EvalError::Overflow));
+        assert_eq!(
+            value("stirling1(4000000000, 3999999999)"),
+            rational(7999999998000000000, 1)
+        );
+        assert_eq!(
+            value("stirling1(4000000000, 3999999998)"),
+            Err(EvalError::Overflow)
+        );
+        assert_eq!(value("bernoulli(4000000000)"), Err(EvalError::Overflow));
+    }
+
+    #[test]
+    fn test_evaluate_partial() {
+        let rational = |value: i64| Value(Number::Rational(Fraction::from(value)));
+        let var = |name: &str| Box::new(Variable(name.into()));
+
+        // (a * b) + (c + (2 * 3))
+        let term = Addition(
+            Box::new(Multiplication(var("a"), var("b"))),
+            Box::new(Addition(
+                var("c"),
+                Box::new(Multiplication(Box::new(rational(2)), Box::new(rational(3)))),
+            )),
+        );
+
+        let context = EvalContext::new().with("a", Number::Rational(Fraction::from(4)));
+        let partial = term.evaluate_partial(&context);
+        assert_eq!(
+            partial.term,
+            Addition(
+                Box::new(Multiplication(Box::new(rational(4)), var("b"))),
+                Box::new(Addition(var("c"), Box::new(rational(6)))),
+            )
+        );
+        assert_eq!(partial.unbound, vec!["b".into(), "c".into()]);
+
+        let context = context
+            .with("b", Number::Rational(Fraction::from(5)))
+            .with("c", Number::Rational(Fraction::from(-1)));
+        let partial = term.evaluate_partial(&context);
+        assert_eq!(partial.term, rational(25));
+        assert!(partial.unbound.is_empty());
+
+        // A division by zero stays symbolic instead of failing
+        let reciprocal = Exponentation(var("x"), Box::new(rational(-1)));
+        let zero = EvalContext::new().with("x", Number::Rational(Fraction::from(0)));
+        assert_eq!(
+            reciprocal.evaluate_partial(&zero).term,
+            Exponentation(Box::new(rational(0)), Box::new(rational(-1)))
+        );
+    }
+
+    #[test]
+    fn test_fingerprint() {
+        let rational = |n: i64, d: i64| Value(Number::Rational(Fraction::new(n, d)));
+        let term = Addition(Box::new(Variable("x".into())), Box::new(rational(1, 2)));
+
+        // Stable across runs and platforms, not just within one process
+        assert_eq!(
+            term.fingerprint(),
+            0xd90f_a26f_2ff6_3494_6082_8cc9_2c54_060a
+        );
+
+        let float = Addition(
+            Box::new(Variable("x".into())),
+            Box::new(Value(Number::Irrational(0.5))),
+        );
+        assert_eq!(term.fingerprint(), float.fingerprint());
+
+        let swapped = Addition(Box::new(rational(1, 2)), Box::new(Variable("x".into())));
+        assert_ne!(term.fingerprint(), swapped.fingerprint());
+
+        let set = Set(vec![rational(1, 1), rational(2, 1)]);
+        let reordered = Set(vec![rational(2, 1), rational(1, 1), rational(2, 1)]);
+        assert_eq!(set.fingerprint(), reordered.fingerprint());
+        assert_ne!(
+            List(vec![rational(1, 1), rational(2, 1)]).fingerprint(),
+            List(vec![rational(2, 1), rational(1, 1)]).fingerprint()
+        );
+
+        assert!(term.depends_on("x"));
+        assert!(!term.depends_on("y"));
+        assert!(!set.depends_on("x"));
+    }
+
+    #[test]
+    fn test_parse() {
+        let rational = |n: i64, d: i64| Box::new(Value(Number::Rational(Fraction::new(n, d))));
+        let var = |name: &str| Box::new(Variable(name.into()));
+
+        assert_eq!(
+            Term::parse("2 * (x + 3)^2"),
+            Ok(Multiplication(
+                rational(2, 1),
+                Box::new(Exponentation(
+                    Box::new(Addition(var("x"), rational(3, 1))),
+                    rational(2, 1)
+                )),
+            ))
+        );
+
+        // Negation desugars into a product with -1
+        assert_eq!(
+            "a - b / 2".parse(),
+            Ok(Subtraction(
+                var("a"),
+                Box::new(Division(var("b"), rational(2, 1))),
+            ))
+        );
+        assert_eq!(
+            Term::parse("-x^2"),
+            Ok(Multiplication(
+                rational(-1, 1),
+                Box::new(Exponentation(var("x"), rational(2, 1)))
+            ))
+        );
+        assert_eq!(
+            Term::parse("2^3^2").unwrap().get_value(),
+            Some(Number::Rational(Fraction::from(512)))
+        );
+        assert_eq!(Term::parse("0.25"), Ok(*rational(1, 4)));
+
+        assert_eq!(
+            Term::parse("[1, 2, 3][0] + sqrt(4)").unwrap().get_value(),
+            Some(Number::Rational(Fraction::from(3)))
+        );
+        assert_eq!(
+            Term::parse("{1, 2}"),
+            Ok(Set(vec![*rational(1, 1), *rational(2, 1)]))
+        );
+        assert_eq!(Term::parse("(x,)"), Ok(List(vec![*var("x")])));
+        assert_eq!(
+            Term::parse("root(8, 3)"),
+            Ok(RootExtraction(rational(8, 1), rational(3, 1)))
+        );
+
+        let error = |input: &str| Term::parse(input).unwrap_err();
+        assert_eq!(
+            error("2 * (x + 1"),
+            ParseError {
+                kind: ParseErrorKind::Unclosed('('),
+                position: 4
+            }
+        );
+        assert_eq!(error("2 +").kind, ParseErrorKind::UnexpectedEnd);
+        assert_eq!(
+            error("2 # 3").kind,
+            ParseErrorKind::UnexpectedCharacter('#')
+        );
+        assert_eq!(error("2 x").position, 2);
+        assert_eq!(
+            error("f(x)").kind,
+            ParseErrorKind::UnknownFunction {
+                name: "f".into(),
+                suggestions: Vec::new()
+            }
+        );
+        assert_eq!(error("1.2.3").kind, ParseErrorKind::InvalidNumber);
+    }
+
+    #[test]
+    fn test_tangents_and_curvature() {
+        let parabola = Term::parse("x^2").unwrap();
+        let q = |n: i64| Fraction::from(n);
+        let at = |term: &Term, x: f64| numeric::evaluate(term, "x", x).unwrap();
+
+        // The tangent to x^2 at 3 is 6x - 9
+        let tangent = tangent_line_at(&parabola, "x", q(3)).unwrap();
+        assert_eq!(
+            Polynomial::from_term(&tangent, "x"),
+            Some(Polynomial::new("x", vec![q(-9), q(6)]))
+        );
+
+        // The normal there is -x/6 + 19/2
+        let normal = normal_line_at(&parabola, "x", q(3)).unwrap();
+        assert_eq!(
+            Polynomial::from_term(&normal, "x"),
+            Some(Polynomial::new(
+                "x",
+                vec![Fraction::new(19, 2), Fraction::new(-1, 6)]
+            ))
+        );
+        assert!(normal_line_at(&parabola, "x", q(0)).is_none());
+
+        // x^2 has curvature 2 at its vertex and 2 / 5^(3/2) at 1
+        let kappa = curvature(&parabola, "x").unwrap();
+        assert!((at(&kappa, 0.0) - 2.0).abs() < 1e-12);
+        assert!((at(&kappa, 1.0) - 2.0 / 5f64.powf(1.5)).abs() < 1e-12);
+
+        let reciprocal = Term::parse("1 / x").unwrap();
+        assert!(tangent_line_at(&reciprocal, "x", q(0)).is_none());
+
+        // Terms that are not rational functions go through the general derivative
+        let sine = Term::parse("sin(x)").unwrap();
+        let tangent = tangent_line_at(&sine, "x", q(1)).unwrap();
+        assert!((at(&tangent, 1.0) - 1f64.sin()).abs() < 1e-12);
+        assert!((at(&tangent, 2.0) - 1f64.sin() - 1f64.cos()).abs() < 1e-12);
+        let kappa = curvature(&sine, "x").unwrap();
+        assert!((at(&kappa, std::f64::consts::FRAC_PI_2) - 1.0).abs() < 1e-12);
+        assert!((at(&kappa, -std::f64::consts::FRAC_PI_2) - 1.0).abs() < 1e-12);
+    }
+
+    #[test]
+    fn test_display() {
+        let var = |name: &str| Box::new(Variable(name.into()));
+        let product = Multiplication(var("a"), var("b"));
+        assert_eq!(Addition(Box::new(product), var("c")).to_string(), "a*b + c");
+
+        for input in [
+            "a*b + c",
+            "a*(b + c)",
+            "a - (b - c)",
+            "a - 3",
+            "a/(b*c)",
+            "-(a*b)",
+            "-x^2",
+            "(-2)^x",
+            "(1/2)^x",
+            "(a^b)^c",
+            "a^b^c",
+            "a^-(b + 1)",
+            "sqrt(x + 1)",
+            "root(x, 3)",
+            "[1, x][0]",
+            "{x, y}",
+            "(x,)",
+            "()",
+        ] {
+            let term = Term::parse(input).unwrap();
+            assert_eq!(term.to_string(), input);
+            assert_eq!(Term::parse(&term.to_string()), Ok(term));
+        }
+
+        // Floats in exponent notation and variables named like constants read back too
+        let context = EvalContext::new()
+            .with("e", Number::Rational(2.into()))
+            .with("pi", Number::Rational(3.into()));
+        for term in [
+            Value(Number::Irrational(1e30)),
+            Value(Number::Irrational(-1e-7)),
+            Value(Number::Irrational(2.5e300)),
+            Multiplication(var("e"), Box::new(Constant(constants::Constant::E))),
+            Addition(var("pi"), Box::new(Constant(constants::Constant::Pi))),
+        ] {
+            let read = Term::parse(&term.to_string()).unwrap();
+            assert_eq!(
+                read.evaluate_in(&context).map(f64::from),
+                term.evaluate_in(&context).map(f64::from),
+                "{term}"
+            );
+        }
+        assert_eq!(Variable("e".into()).to_string(), "`e`");
+        assert_eq!(Term::parse("`e`"), Ok(Variable("e".into())));
+        assert_eq!(
+            Term::parse("1e-7"),
+            Ok(Value(Number::Rational(Fraction::new(1, 10_000_000))))
+        );
+        assert_eq!(Term::parse("1e30"), Ok(Value(Number::Irrational(1e30))));
+        assert_eq!(Value(Number::Irrational(f64::NAN)).to_string(), "NaN");
+    }
+
+    #[test]
+    fn test_isolation() {
+        let equation = |lhs: &str, rhs: &str| {
+            Equation::new(Term::parse(lhs).unwrap(), Term::parse(rhs).unwrap())
+        };
+        let show = |steps: Vec<Step>| {
+            steps
+                .iter()
+                .map(|step| format!("{}: {}", step.operation, step.equation))
+                .collect::<Vec<_>>()
+        };
+
+        assert_eq!(
+            show(equation("2*x + 3", "7").isolate("x").unwrap()),
+            [
+                "subtract 3 from both sides: 2*x = 4",
+                "divide both sides by 2: x = 2",
+            ]
+        );
+        assert_eq!(
+            show(equation("7", "x/2 - 1").isolate("x").unwrap()),
+            [
+                "swap sides: x/2 - 1 = 7",
+                "add 1 to both sides: x/2 = 8",
+                "multiply both sides by 2: x = 16",
+            ]
+        );
+
+        // Literal equations keep the other variables symbolic
+        let steps = equation("a*x + b", "c").isolate("x").unwrap();
+        assert_eq!(steps.last().unwrap().equation.to_string(), "x = (c - b)/a");
+        let steps = equation("sqrt(x)", "3").isolate("x").unwrap();
+        assert_eq!(steps.last().unwrap().equation.to_string(), "x = 9");
+
+        assert_eq!(
+            equation("x + x", "1").isolate("x"),
+            Err(IsolationError::Repeated)
+        );
+        assert_eq!(
+            equation("y", "1").isolate("x"),
+            Err(IsolationError::NotFound)
+        );
+        assert_eq!(
+            equation("0*x", "1").isolate("x"),
+            Err(IsolationError::DivisionByZero)
+        );
+        assert!(matches!(
+            equation("2^x", "8").isolate("x"),
+            Err(IsolationError::Unsupported(_))
+        ));
+    }
+
+    #[test]
+    fn test_subtraction_division() {
+        let rational = |n: i64, d: i64| Box::new(Value(Number::Rational(Fraction::new(n, d))));
+        let var = |name: &str| Box::new(Variable(name.into()));
+
+        let term = Division(
+            Box::new(Subtraction(var("x"), rational(1, 1))),
+            rational(4, 1),
+        );
+        assert!(!term.has_value());
+        let at_three = term
+            .clone()
+            .substitute("x", Number::Rational(Fraction::from(3)));
+        assert!(at_three.has_value());
+        assert_eq!(
+            at_three.get_value(),
+            Some(Number::Rational(Fraction::new(1, 2)))
+        );
+        assert_eq!(at_three.evaluate_exact(), Ok(Fraction::new(1, 2)));
+        assert_eq!(numeric::evaluate(&term, "x", 5.0), Some(1.0));
+
+        // Dividing by zero has no value instead of panicking
+        let by_zero = Division(rational(1, 1), rational(0, 1));
+        assert_eq!(by_zero.get_value(), None);
+        assert_eq!(
+            by_zero
+                .evaluate_exact()
+                .map_err(|violation| violation.reason),
+            Err(ExactnessReason::DivisionByZero)
+        );
+        assert_eq!(by_zero.lint()[0].kind, LintKind::DivisionByZero);
+
+        let difference = Subtraction(
+            Box::new(Vector(vec![*rational(3, 1), *rational(5, 1)])),
+            Box::new(Vector(vec![*rational(1, 1), *rational(1, 1)])),
+        );
+        assert_eq!(
+            Division(Box::new(difference), rational(2, 1)).get_vector_value(),
+            Some(vec![
+                Number::Rational(Fraction::from(1)),
+                Number::Rational(Fraction::from(2))
+            ])
+        );
+
+        assert_eq!(
+            RationalFunction::from_term(&term, "x"),
+            RationalFunction::new(
+                Polynomial::new("x", vec![Fraction::new(-1, 4), Fraction::new(1, 4)]),
+                Polynomial::new("x", vec![Fraction::from(1)]),
+            )
+        );
+    }
+
+    #[test]
+    fn test_evaluate() {
+        let eval = |input: &str| Term::parse(input).unwrap().evaluate();
+        let rational = |n: i64, d: i64| Ok(Number::Rational(Fraction::new(n, d)));
+
+        assert_eq!(eval("(1 + 2) / 4 - 1"), rational(-1, 4));
+        assert_eq!(eval("(2/3)^-2"), rational(9, 4));
+        assert_eq!(eval("root(-8, 3)"), rational(-2, 1));
+        assert_eq!(eval("[1, 2, 3][2]"), rational(3, 1));
+
+        assert_eq!(
+            eval("x + 1"),
+            Err(EvalError::UnboundVariable {
+                name: "x".into(),
+                suggestions: Vec::new()
+            })
+        );
+        assert_eq!(eval("1 / (2 - 2)"), Err(EvalError::DivisionByZero));
+        assert_eq!(eval("0^-1"), Err(EvalError::DivisionByZero));
+        assert_eq!(eval("root(4, 0)"), Err(EvalError::DivisionByZero));
+        assert_eq!(eval("sqrt(-4)"), Err(EvalError::InvalidDomain));
+        // Fractions that overflow are evaluated again with integers of any size
+        assert_eq!(eval("(2/3)^100*(3/2)^100"), rational(1, 1));
+        assert_eq!(eval("2^100/2^99 + 1"), rational(3, 1));
+        let (value, events) = record_fallbacks(|| eval("2^100"));
+        assert_eq!(value, Ok(Number::Irrational(2f64.powi(100))));
+        assert_eq!(events[0].reason, FallbackReason::Overflow);
+        assert_eq!(eval("2^100 + pi"), Err(EvalError::Overflow));
+        assert_eq!(eval("2^10000000000"), Err(EvalError::Overflow));
+        assert_eq!(eval("[1, 2]"), Err(EvalError::NotAScalar));
+        assert_eq!(eval("[1, 2][2]"), Err(EvalError::InvalidIndex));
+        assert_eq!(eval("{1, 2}[0]"), Err(EvalError::InvalidIndex));
+
+        // Exact roots stay rational, anything touching an irrational stays irrational
+        assert_eq!(eval("sqrt(9/4)"), rational(3, 2));
+        let irrational = |input: &str| matches!(eval(input), Ok(Number::Irrational(_)));
+        assert!(irrational("sqrt(2) + sqrt(3) + sqrt(5) + sqrt(7)"));
+        assert!(irrational("sqrt(2) * sqrt(3)"));
+        assert!(irrational("2^(1/2) - 1"));
+        let pi_x = Term::parse("pi * x")
+            .unwrap()
+            .substitute("x", Number::Rational(3.into()));
+        assert!(matches!(pi_x.evaluate(), Ok(Number::Irrational(_))));
+
+        // The infallible power falls back to floats instead of panicking
+        let two = Number::Rational(Fraction::from(2));
+        assert_eq!(
+            two.pow(&Number::Rational(Fraction::from(100))),
+            Number::Irrational(2f64.powi(100))
+        );
+        assert_eq!(Term::parse("x").unwrap().get_value(), None);
+    }
+
+    #[test]
+    fn test_rearrange() {
+        let formula = |input: &str| {
+            let (lhs, rhs) = input.split_once('=').unwrap();
+            Equation::new(Term::parse(lhs).unwrap(), Term::parse(rhs).unwrap())
+        };
+        let solve =
+            |input: &str, symbol: &str| formula(input).rearrange_for(symbol).unwrap().to_string();
+
+        assert_eq!(solve("v = u + a*t", "a"), "(v - u)/t");
+        assert_eq!(solve("v = u + a*t", "u"), "v - a*t");
+        assert_eq!(solve("v = u + a*t", "v"), "u + a*t");
+        assert_eq!(solve("E = m*c^2", "c"), "sqrt(E/m)");
+        assert_eq!(solve("s = u*t + a*t^2/2", "a"), "(s - u*t)*2/t^2");
+        assert_eq!(solve("F = G*m*M/r^2", "r"), "sqrt(G*m*M/F)");
+
+        let result = formula("v = u + a*t").rearrange_for("a").unwrap();
+        let at = |term: &Term| {
+            numeric::try_evaluate(term, &[("v", 10.0), ("u", 4.0), ("t", 3.0)]).unwrap()
+        };
+        assert_eq!(at(&result), 2.0);
+
+        assert_eq!(
+            formula("s = u*t + a*t^2/2").rearrange_for("t"),
+            Err(IsolationError::Repeated)
+        );
+    }
+
+    #[test]
+    fn test_eval_context() {
+        let q = |n: i64| Number::Rational(Fraction::from(n));
+        let distance = Term::parse("u*t + a*t^2/2").unwrap();
+
+        let mut context = EvalContext::new().with("u", q(1)).with("a", q(2));
+        assert_eq!(
+            distance.evaluate_in(&context),
+            Err(EvalError::UnboundVariable {
+                name: "t".into(),
+                suggestions: Vec::new()
+            })
+        );
+
+        let distances: Vec<_> = (0..4)
+            .map(|t| {
+                context.set("t", q(t));
+                distance.evaluate_in(&context).unwrap()
+            })
+            .collect();
+        assert_eq!(distances, [q(0), q(2), q(6), q(12)]);
+
+        // The term itself is left untouched
+        assert_eq!(distance, Term::parse("u*t + a*t^2/2").unwrap());
+        assert_eq!(context.set("t", q(5)), Some(q(3)));
+        assert_eq!(context.remove("a"), Some(q(2)));
+        assert_eq!(context.get("a"), None);
+    }
+
+    #[test]
+    fn test_rename() {
+        use std::collections::HashMap;
+
+        let term = Term::parse("x^2 + 2*x*y - [y, z][0]").unwrap();
+
+        assert_eq!(
+            term.rename_variable("x", "t").to_string(),
+            "t^2 + 2*t*y - [y, z][0]"
+        );
+
+        // Renaming is simultaneous, so x and y swap instead of collapsing
+        let swapped = term.rename_variables(&HashMap::from([("x", "y"), ("y", "x")]));
+        assert_eq!(swapped.to_string(), "y^2 + 2*y*x - [x, z][0]");
+        assert_eq!(
+            swapped.rename_variables(&HashMap::from([("x", "y"), ("y", "x")])),
+            term
+        );
+    }
+
+    #[test]
+    fn test_derivative() {
+        let d = |input: &str| {
+            Term::parse(input)
+                .unwrap()
+                .derivative("x")
+                .map(|term| term.to_string())
+        };
+
+        assert_eq!(d("x^3 + 2*x - 5").as_deref(), Some("3*x^2 + 2"));
+        assert_eq!(d("a*x").as_deref(), Some("a"));
+        assert_eq!(d("x*y").as_deref(), Some("y"));
+        assert_eq!(d("1/x").as_deref(), Some("-1/x^2"));
+        assert_eq!(d("sqrt(x)").as_deref(), Some("1/(2*sqrt(x))"));
+        assert_eq!(d("[x^2, 3]").as_deref(), Some("[2*x, 0]"));
+        assert_eq!(d("a^x").as_deref(), Some("a^x*ln(a)"));
+        assert_eq!(d("sin(x)").as_deref(), Some("cos(x)"));
+        assert_eq!(d("ln(x)").as_deref(), Some("1/x"));
+
+        let simplified = |input: &str| {
+            Term::parse(input)
+                .unwrap()
+                .derivative_simplified("x")
+                .map(|term| term.to_string())
+        };
+        assert_eq!(simplified("(x + 1)*(x + 2)").as_deref(), Some("x + x + 3"));
+        assert_eq!(simplified("exp(2*x)").as_deref(), Some("2*exp(2*x)"));
+        assert_eq!(
+            simplified("sin(x)*cos(x)").as_deref(),
+            Some("cos(x)*cos(x) - sin(x)*sin(x)")
+        );
+        assert_eq!(simplified("[1, 2][x]"), None);
+
+        let nth = |input: &str, n| {
+            Term::parse(input)
+                .unwrap()
+                .nth_derivative("x", n)
+                .map(|term| term.to_string())
+        };
+        assert_eq!(nth("x^4", 0).as_deref(), Some("x^4"));
+        assert_eq!(nth("x^4", 3).as_deref(), Some("24*x"));
+        assert_eq!(nth("x^4", 5).as_deref(), Some("0"));
+        assert_eq!(nth("exp(2*x)", 4).as_deref(), Some("16*exp(2*x)"));
+
+        // Orders are computed once and kept, and a missing order ends the sequence
+        let mut derivatives = Derivatives::new(Term::parse("sin(x)").unwrap(), "x");
+        assert_eq!(derivatives.nth(4), Some(&Term::parse("sin(x)").unwrap()));
+        assert_eq!(derivatives.cached().len(), 5);
+        assert_eq!(derivatives.nth(2).unwrap().to_string(), "-sin(x)");
+        assert_eq!(derivatives.cached().len(), 5);
+        let mut derivatives = Derivatives::new(Term::parse("x*prime(x)").unwrap(), "x");
+        assert_eq!(derivatives.nth(1), None);
+        assert_eq!(derivatives.cached().len(), 1);
+
+        // Check the chain and quotient rules against central differences
+        let h = 1e-6;
+        for input in [
+            "(x^2 + 1)^3",
+            "sqrt(x^2 + 1)",
+            "x/(x + 1)",
+            "2^(3*x)",
+            "root(x, 3)*x",
+            "x^x",
+            "sin(x^2)*exp(-x)",
+            "tan(x)/ln(x + 3)",
+        ] {
+            let term = Term::parse(input).unwrap();
+            let derivative = term.derivative("x").unwrap();
+            let at = |term: &Term, x: f64| numeric::evaluate(term, "x", x).unwrap();
+            let expected = (at(&term, 1.5 + h) - at(&term, 1.5 - h)) / (2.0 * h);
+            assert!((at(&derivative, 1.5) - expected).abs() < 1e-5, "{input}");
+        }
+    }
+
+    #[test]
+    fn test_compose() {
+        let parse = |input: &str| Term::parse(input).unwrap();
+
+        assert_eq!(
+            parse("k*x + x_1").prefix_variables("spring::").to_string(),
+            "spring::k*spring::x + spring::x_1"
+        );
+
+        // x and x_1 are both taken, so the second x becomes x_2
+        let (sum, renaming) = parse("k*x + x_1").compose(&parse("m*x + k"), Term::Addition);
+        assert_eq!(sum.to_string(), "k*x + x_1 + (m*x_2 + k_1)");
+        assert_eq!(renaming.len(), 2);
+        assert_eq!(renaming.get("x").map(|name| &**name), Some("x_2"));
+        assert_eq!(renaming.get("k").map(|name| &**name), Some("k_1"));
+    }
+
+    #[test]
+    fn test_simplify() {
+        let simplify = |input: &str| Term::parse(input).unwrap().simplify().to_string();
+
+        assert_eq!(simplify("2 * 3 + 4"), "10");
+        assert_eq!(simplify("sqrt(16) + x"), "x + 4");
+        assert_eq!(simplify("x + 0"), "x");
+        assert_eq!(simplify("1 * x^1 * 1"), "x");
+        assert_eq!(simplify("x * 0 + y"), "y");
+        assert_eq!(simplify("x^0 + root(y, 1)"), "y + 1");
+        assert_eq!(simplify("(x + 1) + (y + 2) - 5"), "x + y - 2");
+        assert_eq!(simplify("2 * (x * (3 * y))"), "6*x*y");
+        assert_eq!(simplify("3 * x / (2 * y)"), "3*x/(2*y)");
+        assert_eq!(simplify("x / 4 * 2"), "x/2");
+        assert_eq!(simplify("x - 2*y"), "x - 2*y");
+        assert_eq!(simplify("x + -1*y + (-3)*z"), "x - y - 3*z");
+        assert_eq!(simplify("x - y*(2*z)"), "x - 2*y*z");
+        assert_eq!(simplify("-(x - 1)"), "-(x - 1)");
+        assert_eq!(simplify("[1 + 1, x * 1]"), "[2, x]");
+        assert_eq!(simplify("sqrt(2) * 1"), "sqrt(2)");
+        // Undefined terms stay undefined
+        for input in ["0/0", "0*(1/0)", "0*ln(0)", "1^(1/0)", "ln(0)^0", "0*x/0"] {
+            let simplified = Term::parse(input).unwrap().simplify();
+            assert!(
+                simplified.evaluate().is_err(),
+                "{input} became {simplified}"
+            );
+        }
+        assert_eq!(simplify("0*ln(0)"), "0*ln(0)");
+        assert_eq!(simplify("0*(x + 1)*y"), "0");
+
+        // Derivatives come out readable
+        let derivative = Term::parse("(x^2 + 1)*x").unwrap().derivative("x").unwrap();
+        assert_eq!(derivative.simplify().to_string(), "2*x*x + x^2 + 1");
+    }
+
+    #[test]
+    fn test_functions() {
+        let term = Term::parse("sin(x^2) + ln(1)").unwrap();
+        assert_eq!(
+            term,
+            Addition(
+                Box::new(Function(
+                    functions::Function::Sin,
+                    Box::new(Exponentation(
+                        Box::new(Variable("x".into())),
+                        Box::new(Value((2.0).into())),
+                    )),
+                )),
+                Box::new(Function(
+                    functions::Function::Ln,
+                    Box::new(Value((1.0).into()))
+                )),
+            )
+        );
+        assert_eq!(Term::parse(&term.to_string()).unwrap(), term);
+
+        let exact = |input: &str| Term::parse(input).unwrap().evaluate_exact();
+        assert_eq!(exact("ln(1)"), Ok(Fraction::new(0, 1)));
+        assert_eq!(exact("cos(0) + exp(0)"), Ok(Fraction::new(2, 1)));
+        assert_eq!(exact("floor(7/2) + ceil(-1/3)"), Ok(Fraction::new(3, 1)));
+        assert_eq!(exact("abs(-2/3)"), Ok(Fraction::new(2, 3)));
+        assert!(exact("sin(1)").is_err());
+
+        let value = Term::parse("sin(1)").unwrap().evaluate().unwrap();
+        assert!((f64::from(value) - 1f64.sin()).abs() < 1e-12);
+        assert!(matches!(
+            Term::parse("sin(1) * exp(1)").unwrap().evaluate(),
+            Ok(Number::Irrational(_))
+        ));
+        assert_eq!(
+            Term::parse("ln(0)").unwrap().evaluate(),
+            Err(EvalError::InvalidDomain)
+        );
+
+        // Pseudo-random samples are exact, reproducible and independent of each other
+        let sample = |seed: i64, i: i64| {
+            let term = Term::parse(&format!("rand({seed}, {i})")).unwrap();
+            match term.evaluate() {
+                Ok(Number::Rational(value)) => value,
+                other => panic!("expected a rational, got {other:?}"),
+            }
+        };
+        assert_eq!(sample(7, 3), sample(7, 3));
+        assert_ne!(sample(7, 3), sample(7, 4));
+        assert_ne!(sample(7, 3), sample(8, 3));
+        for i in 0..100 {
+            let value = sample(0, i);
+            assert!(value >= Fraction::from(0) && value < Fraction::from(1));
+        }
+        assert_eq!(
+            Term::parse("rand(1, -1)").unwrap().evaluate(),
+            Err(EvalError::InvalidDomain)
+        );
+
+        let series = numeric::fourier_coefficients(&Variable("x".into()), "x", 2.0, 3).unwrap();
+        let term = series.to_term("x");
+        for x in [-0.7, 0.2, 0.9] {
+            let value = numeric::evaluate(&term, "x", x).unwrap();
+            assert!((value - series.evaluate(x)).abs() < 1e-9);
+        }
+
+        let exp = Term::parse("3*exp(-2*t) + sin(5*t)").unwrap();
+        let transformed = laplace(&exp, "t", "s").unwrap();
+        let inverse = inverse_laplace(&transformed, "s", "t").unwrap();
+        let value = inverse.substitute("t", (1.0).into()).evaluate().unwrap();
+        assert!((f64::from(value) - (3.0 * (-2f64).exp() + 5f64.sin())).abs() < 1e-9);
+    }
+
+    #[test]
+    fn test_rational_fast_path() {
+        let x = Number::Rational(Fraction::new(3, 2));
+        let context = EvalContext::new().with("x", x);
+        for input in [
+            "x*x + 2*x - 5",
+            "abs(x - 7)/3 - floor(x/2) + ceil(-x)",
+            "(x - 1)^-3*x^4",
+            "x*sqrt(4)",
+        ] {
+            let term = Term::parse(input).unwrap();
+            let exact = term.clone().substitute("x", x).evaluate_exact().unwrap();
+            assert_eq!(term.evaluate_in(&context), Ok(Number::Rational(exact)));
+
+            // An irrational binding takes the general path and agrees numerically
+            let float = EvalContext::new().with("x", Number::Irrational(1.5));
+            let value = f64::from(term.evaluate_in(&float).unwrap());
+            assert!((value - f64::from(Number::Rational(exact))).abs() < 1e-9);
+        }
+
+        let error = |input: &str| Term::parse(input).unwrap().evaluate_in(&context);
+        assert_eq!(error("1/(x - x)"), Err(EvalError::DivisionByZero));
+        assert_eq!(error("(x - x)^-1"), Err(EvalError::DivisionByZero));
+        assert_eq!(
+            error("(1000*x)^40/(1000*x)^39"),
+            Ok(Number::Rational(Fraction::from(1500)))
+        );
+        assert_eq!(
+            error("x + y"),
+            Err(EvalError::UnboundVariable {
+                name: "y".into(),
+                suggestions: Vec::new()
+            })
+        );
+    }
+
+    #[test]
+    fn test_constants() {
+        let area = Term::parse("pi*r^2").unwrap();
+        assert_eq!(
+            area,
+            Multiplication(
+                Box::new(Constant(constants::Constant::Pi)),
+                Box::new(Exponentation(
+                    Box::new(Variable("r".into())),
+                    Box::new(Value((2.0).into())),
+                )),
+            )
+        );
+        assert_eq!(Term::parse("π*r^2").unwrap(), area);
+        assert_eq!(area.to_string(), "pi*r^2");
+
+        // Constants survive simplification and exact evaluation refuses them
+        let term = Term::parse("2*pi*3 + 0*e").unwrap();
+        assert_eq!(term.simplify().to_string(), "6*pi");
+        assert_eq!(
+            term.evaluate_exact().unwrap_err().reason,
+            ExactnessReason::IrrationalConstant
+        );
+        let derivative = area.derivative("r").unwrap().simplify();
+        assert_eq!(derivative.to_string(), "2*pi*r");
+
+        let value = |input: &str| f64::from(Term::parse(input).unwrap().evaluate().unwrap());
+        assert!((value("2*pi") - std::f64::consts::TAU).abs() < 1e-12);
+        assert!((value("ln(e)") - 1.0).abs() < 1e-12);
+        assert!(value("phi^2 - phi - 1").abs() < 1e-12);
+
+        let (_, events) = record_fallbacks(|| Term::parse("e*2").unwrap().evaluate());
+        assert_eq!(events.len(), 1);
+        assert_eq!(events[0].reason, FallbackReason::Constant);
+    }
+
+    #[test]
+    fn test_iterators() {
+        let term = Term::parse("x*y + sin(x)^2 - [pi, 3]").unwrap();
+
+        let subterms: Vec<_> = term.iter_subterms().map(Term::to_string).collect();
+        assert_eq!(subterms.len(), 12);
+        assert_eq!(subterms[0], term.to_string());
+        assert_eq!(subterms[1], "x*y + sin(x)^2");
+
+        let leaves: Vec<_> = term.iter_leaves().map(Term::to_string).collect();
+        assert_eq!(leaves, ["x", "y", "x", "2", "pi", "3"]);
+
+        let variables: Vec<_> = term.iter_variables().collect();
+        assert_eq!(variables, ["x", "y", "x"]);
+
+        let deep = (0..10_000).fold(Variable("x".into()), |acc, _| {
+            Addition(Box::new(acc), Box::new(Value((1.0).into())))
+        });
+        assert_eq!(deep.iter_leaves().count(), 10_001);
+    }
+
+    #[test]
+    fn test_latex() {
+        let latex = |input: &str| Term::parse(input).unwrap().to_latex();
+
+        assert_eq!(
+            latex("x^2/2 + root(y, 3)"),
+            r"\frac{x^{2}}{2} + \sqrt[3]{y}"
+        );
+        assert_eq!(latex("2*x*y - 3/4"), r"2x \cdot y - \frac{3}{4}");
+        assert_eq!(latex("(a + b)^(n - 1)"), r"\left(a + b\right)^{n - 1}");
+        assert_eq!(
+            latex("(1/2)^x*pi"),
+            r"\left(\frac{1}{2}\right)^{x} \cdot \pi"
+        );
+        assert_eq!(
+            latex("-sqrt(x_1)*sin(theta)"),
+            r"-\sqrt{x_{1}} \cdot \sin\left(\theta\right)"
+        );
+        assert_eq!(
+            latex("abs(x) + floor(y)"),
+            r"\left|x\right| + \left\lfloor y \right\rfloor"
+        );
+        assert_eq!(latex("a*(b - c)"), r"a \cdot \left(b - c\right)");
+        assert_eq!(
+            latex("[1, x][0] + {2}"),
+            r"\left[1, x\right]_{0} + \left\{2\right\}"
+        );
+    }
+
+    #[test]
+    fn test_try_map() {
+        let term = Term::parse("x*2 + sin(y)").unwrap();
+
+        // Children are visited before their parents
+        let mut visited = Vec::new();
+        let same = term.try_map(|t| {
+            visited.push(t.to_string());
+            Ok::<_, ()>(t)
+        });
+        assert_eq!(same, Ok(term.clone()));
+        assert_eq!(visited, ["x", "2", "x*2", "y", "sin(y)", "x*2 + sin(y)"]);
+
+        let bind = |t: Term| match &t {
+            Variable(name) if &**name == "x" => Ok(Value((3.0).into())),
+            Variable(name) => Err(name.clone()),
+            _ => Ok(t),
+        };
+        assert_eq!(
+            Term::parse("x*x - 1").unwrap().try_map(bind),
+            Ok(Term::parse("3*3 - 1").unwrap())
+        );
+
+        let mut calls = 0;
+        let result = Term::parse("y + x*x").unwrap().try_map(|t| {
+            calls += 1;
+            bind(t)
+        });
+        assert_eq!(result, Err("y".into()));
+        assert_eq!(calls, 1);
+    }
+
+    #[test]
+    fn test_from_latex() {
+        let parse = |input: &str| Term::from_latex(input).map(|term| term.to_string());
+
+        assert_eq!(
+            parse(r"\frac{x^2}{2} + 3\sqrt[3]{y}").as_deref(),
+            Ok("x^2/2 + 3*root(y, 3)")
+        );
+        assert_eq!(
+            parse(r"2\pi r \cdot \sin\left(\theta\right)").as_deref(),
+            Ok("2*pi*r*sin(theta)")
+        );
+        assert_eq!(
+            parse(r"\sin^2 x + \cos^{2}x").as_deref(),
+            Ok("sin(x)^2 + cos(x)^2")
+        );
+        assert_eq!(
+            parse(r"e^{-x_1} \times \mathrm{speed}").as_deref(),
+            Ok("e^-x_1*speed")
+        );
+        assert_eq!(
+            parse(r"\left|a - b\right| + \lfloor \frac{n}{2} \rfloor").as_deref(),
+            Ok("abs(a - b) + floor(n/2)")
+        );
+        assert_eq!(
+            parse(r"\left[1, 2\right]_{0} + \{3\}").as_deref(),
+            Ok("[1, 2][0] + {3}")
+        );
+
+        assert_eq!(
+            Term::from_latex(r"\foo{x}").unwrap_err(),
+            ParseError {
+                kind: ParseErrorKind::UnknownCommand("foo".into()),
+                position: 0,
+            }
+        );
+        assert_eq!(
+            Term::from_latex(r"\frac{1}{2").unwrap_err().kind,
+            ParseErrorKind::Unclosed('{')
+        );
+
+        // Rendered terms read back to the same value
+        for input in [
+            "x^2/2 + root(y, 3)",
+            "-(a + b)^(n - 1)*3/4",
+            "2*x*y - sin(theta)/ln(z_2)",
+            "abs(x - 1) + ceil(y)*pi",
+        ] {
+            let term = Term::parse(input).unwrap();
+            let read = Term::from_latex(&term.to_latex()).unwrap();
+            let bindings = [
+                ("x", 1.5),
+                ("y", 2.0),
+                ("a", 0.5),
+                ("b", 1.0),
+                ("n", 3.0),
+                ("theta", 0.3),
+                ("z_2", 2.5),
+            ];
+            assert_eq!(
+                numeric::evaluate_ieee(&read, &bindings),
+                numeric::evaluate_ieee(&term, &bindings),
+                "{input}"
+            );
+        }
+
+        // Parenthesized arguments end at the closing parenthesis, and variables named like
+        // constants or functions are escaped
+        assert_eq!(
+            parse(r"\sin(x)^2 + \sin x^2").as_deref(),
+            Ok("sin(x)^2 + sin(x^2)")
+        );
+        let variable = |name: &str| Variable(name.into());
+        for term in [
+            Term::parse("sin(x)^2").unwrap(),
+            Term::parse("nCr(n, 2)^2").unwrap(),
+            variable("e"),
+            variable("sin"),
+            variable("sin_1") * variable("e_2"),
+        ] {
+            assert_eq!(
+                Term::from_latex(&term.to_latex()),
+                Ok(term.clone()),
+                "{term:?}"
+            );
+        }
+        assert_eq!(variable("e").to_latex(), r"\mathit{e}");
+    }
+
+    #[test]
+    fn test_canonicalize() {
+        let canonical = |input: &str| Term::parse(input).unwrap().canonicalize();
+
+        let term = canonical("y*x + 2 + sin(b*a)");
+        assert_eq!(term.to_string(), "2 + sin(a*b) + x*y");
+        assert_eq!(term, canonical("sin(a*b) + (x*y + 2)"));
+        assert_eq!(
+            term.fingerprint(),
+            canonical("2 + sin(a*b) + y*x").fingerprint()
+        );
+
+        // Only commutative children move
+        assert_eq!(canonical("b - a").to_string(), "b - a");
+        assert_eq!(canonical("(d*c)/(b + a)").to_string(), "c*d/(a + b)");
+        assert_eq!(canonical("{y, 3, x^2, x}").to_string(), "{3, x, y, x^2}");
+        assert_eq!(canonical("pi*x*2*e").to_string(), "2*pi*e*x");
+    }
+
+    #[test]
+    fn test_explain() {
+        let q = |n: i64| Number::Rational(Fraction::from(n));
+        let context = EvalContext::new().with("x", q(2));
+        let term = Term::parse("5*x^3 + 2").unwrap();
+
+        let steps = term.evaluation_steps(&context);
+        let shown: Vec<_> = steps.iter().map(|step| step.term.to_string()).collect();
+        assert_eq!(shown, ["2^3", "5*8", "40 + 2"]);
+        assert_eq!(steps[2].value, Ok(q(42)));
+
+        assert_eq!(
+            term.explain(&context),
+            "First we evaluated the power 2^3 = 8, then the product 5*8 = 40, and finally the sum \
+             40 + 2 = 42."
+        );
+        assert_eq!(
+            Term::parse("sqrt(x + 7)").unwrap().explain_latex(&context),
+            r"First we evaluated the sum $2 + 7$ = $9$, and then the root $\sqrt{9}$ = $3$."
+        );
+        assert_eq!(
+            Term::parse("1/(x - 2)").unwrap().explain(&context),
+            "First we evaluated the difference 2 - 2 = 0, and then the quotient 1/0, which fails: \
+             division by zero."
+        );
+        assert_eq!(
+            Term::parse("x*3 + y").unwrap().explain(&context),
+            "We evaluated the product 2*3 = 6, but then failed: variable `y` has no value."
+        );
+        assert_eq!(
+            Term::parse("x").unwrap().explain(&context),
+            "The value is 2."
+        );
+    }
+
+    #[test]
+    fn test_variables() {
+        let term = Term::parse("y*x + sin(x)^n - [pi, z][0]").unwrap();
+        assert_eq!(Vec::from_iter(term.variables()), ["n", "x", "y", "z"]);
+        assert!(Term::parse("2*pi + e").unwrap().variables().is_empty());
+    }
+
+    #[test]
+    fn test_evaluate_located() {
+        let context = EvalContext::new().with("x", Number::Rational(Fraction::from(2)));
+        let locate = |input: &str| Term::parse(input).unwrap().evaluate_located(&context);
+
+        let error = locate("3 + x/(x - 2)").unwrap_err();
+        assert_eq!(error.error, EvalError::DivisionByZero);
+        assert_eq!(error.at, TermPath(vec![1]));
+        assert_eq!(error.subterm, "x/(x - 2)");
+        assert_eq!(error.to_string(), "division by zero in `x/(x - 2)`");
+
+        let error = locate("x*2 + sin(y)^2").unwrap_err();
+        assert_eq!(
+            error.error,
+            EvalError::UnboundVariable {
+                name: "y".into(),
+                suggestions: Vec::new()
+            }
+        );
+        assert_eq!(error.at, TermPath(vec![1, 0, 0]));
+
+        let error = locate("[1, 1/0][x - 1]").unwrap_err();
+        assert_eq!(error.at, TermPath(vec![0, 1]));
+        assert_eq!(error.subterm, "1/0");
+
+        assert_eq!(locate("[1, 2][5]").unwrap_err().at, TermPath::root());
+        assert_eq!(locate("x^2"), Ok(Number::Rational(Fraction::from(4))));
+    }
+
+    #[test]
+    fn test_substitute_term() {
+        let square = Term::parse("x^2 + x").unwrap();
+        let shifted = square.substitute_term("x", &Term::parse("y + 1").unwrap());
+        assert_eq!(shifted.to_string(), "(y + 1)^2 + (y + 1)");
+
+        // The replacement may mention the variable itself
+        let nested = square.substitute_term("x", &Term::parse("2*x").unwrap());
+        assert_eq!(nested.to_string(), "(2*x)^2 + 2*x");
+
+        let value = shifted.substitute("y", (2.0).into()).get_value();
+        assert_eq!(value, Some(Number::Rational(Fraction::from(12))));
+    }
+
+    #[test]
+    fn test_escaping() {
+        let hostile = Multiplication(
+            Box::new(Variable(r"}\input{/etc/passwd".into())),
+            Box::new(Variable("a_$b".into())),
+        );
+        assert_eq!(
+            hostile.to_latex(),
+            r"\mathrm{\}\backslash input\{/etc/passwd} \cdot \mathrm{a\_\$b}"
+        );
+        assert_eq!(Variable("x_1".into()).to_latex(), "x_{1}");
+
+        assert_eq!(
+            escape_xml(r#"<script>alert("x & y")</script>"#),
+            "&lt;script&gt;alert(&quot;x &amp; y&quot;)&lt;/script&gt;"
+        );
+        assert_eq!(escape_latex("50% ~ 2^3"), r"50\% \sim  2\wedge 3");
+    }
+
+    #[test]
+    fn test_substitute_all() {
+        use std::collections::HashMap;
+
+        let term = Term::parse("x^2 + x*y").unwrap();
+        let replacements = HashMap::from([
+            ("x", Term::parse("y + 1").unwrap()),
+            ("y", Term::parse("2*x").unwrap()),
+        ]);
+        assert_eq!(
+            term.substitute_all(&replacements).to_string(),
+            "(y + 1)^2 + (y + 1)*(2*x)"
+        );
+        assert_eq!(term.substitute_all(&HashMap::new()), term);
+    }
+
+    #[test]
+    fn test_deterministic_evaluation() {
+        let eval = |input: &str, x: f64| {
+            numeric::evaluate_deterministic(&Term::parse(input).unwrap(), &[("x", x)])
+        };
+
+        assert_eq!(eval("x^10", 1.5), Ok(57.6650390625));
+        assert_eq!(eval("x^(-2)", 4.0), Ok(0.0625));
+        assert_eq!(eval("sqrt(x)", 2.0), Ok(2f64.sqrt()));
+        assert_eq!(eval("abs(x) + floor(x)", -1.5), Ok(-0.5));
+        assert_eq!(
+            eval("sin(x)", 1.0),
+            Err(numeric::DomainError::Nondeterministic)
+        );
+        assert_eq!(
+            eval("x^0.5", 2.0),
+            Err(numeric::DomainError::Nondeterministic)
+        );
+        assert_eq!(
+            eval("1 / (x - 1)", 1.0),
+            Err(numeric::DomainError::NotFinite)
+        );
+    }
+
+    #[test]
+    fn test_builder() {
+        let x = || Term::var("x");
+
+        assert_eq!(x() * 2.0 + 1.0, Term::parse("x*2 + 1").unwrap());
+        assert_eq!(
+            2 * x().pow(2) - x() / 3,
+            Term::parse("2*x^2 - x/3").unwrap()
+        );
+        assert_eq!((x() + "y").root(3), Term::parse("root(x + y, 3)").unwrap());
+        assert_eq!(-x() * 0.5, Term::parse("-x * 0.5").unwrap());
+        assert_eq!(
+            Term::apply(functions::Function::Sin, x()) + constants::Constant::Pi,
+            Term::parse("sin(x) + pi").unwrap()
+        );
+    }
+
+    #[test]
+    fn test_term_macro() {
+        let parse = |input| Term::parse(input).unwrap();
+
+        assert_eq!(term!(3 * x ^ 2 + 1 / 2), parse("3 * x^2 + 1/2"));
+        assert_eq!(term!(2 ^ 3 ^ 2), parse("2^3^2"));
+        assert_eq!(term!(-x ^ 2 - -y), parse("-x^2 - -y"));
+        assert_eq!(term!(x ^ -2 * (a - b) / c), parse("x^-2 * (a - b) / c"));
+        assert_eq!(
+            term!(sin(2 * pi * t) + root(x + 1, 3)),
+            parse("sin(2*pi*t) + root(x + 1, 3)")
+        );
+        assert_eq!(term!(sqrt(x) ^ 2.5), parse("sqrt(x)^2.5"));
+    }
+
+    #[test]
+    fn test_expr_pool() {
+        let formulas = [
+            "x*y + 2^10",
+            "y*x - 1",
+            "sqrt(y*x) / (2^10)",
+            "z + 1",
+            "[x, y][1]",
+        ];
+        let terms: Vec<Term> = formulas.iter().map(|f| Term::parse(f).unwrap()).collect();
+
+        let mut pool = ExprPool::new();
+        let handles: Vec<_> = terms.iter().map(|term| pool.insert(term)).collect();
+        // x, y, x*y, 2, 10, 1024 and 1 are shared, and 2^10 is folded to 1024
+        assert_eq!(pool.node_count(), 14);
+
+        let context = EvalContext::new()
+            .with("x", Number::Rational(2.into()))
+            .with("y", Number::Rational(8.into()));
+        let values = pool.evaluate_all(&context);
+        for (term, handle) in terms.iter().zip(&handles) {
+            assert_eq!(values[handle.index()], term.evaluate_in(&context));
+            assert_eq!(pool.evaluate(*handle, &context), term.evaluate_in(&context));
+        }
+        assert_eq!(
+            values[3],
+            Err(EvalError::UnboundVariable {
+                name: "z".into(),
+                suggestions: Vec::new()
+            })
+        );
+    }
+
+    #[test]
+    #[cfg(feature = "serde")]
+    fn test_persist() {
+        use super::math::persist::*;
+
+        let term = Term::parse("sin(pi * x) / 3 + [0.1, 2/3][1] - sqrt(y)").unwrap();
+        let json = term.to_json();
+        assert!(json.starts_with(r#"{"version":2,"term":[{"constant":"pi"}"#));
+        assert_eq!(Term::from_json(&json).unwrap(), term);
+
+        // Terms far deeper than the call stack allows round-trip
+        let mut deep = Variable("x".into());
+        for i in 0..100_000 {
+            deep = Addition(Box::new(deep), Box::new(Value(Number::Rational(i.into()))));
+        }
+        assert_eq!(Term::from_json(&deep.to_json()).unwrap(), deep);
+        let invalid = r#"{"version":2,"term":[{"addition":[0,1]}]}"#;
+        assert!(matches!(Term::from_json(invalid), Err(LoadError::Json(_))));
+
+        // Version 1 nested its nodes, and migrates without registering anything
+        let nested = r#"{"version":1,"term":{"function":["sin",{"list":[{"variable":"x"}]}]}}"#;
+        assert_eq!(
+            Term::from_json(nested).unwrap(),
+            Function(Function::Sin, Box::new(List(vec![Variable("x".into())])))
+        );
+
+        let inf = Term::Value(Number::Irrational(f64::INFINITY));
+        assert_eq!(Term::from_json(&inf.to_json()).unwrap(), inf);
+
+        // A hypothetical version 0 that called variables `var`
+        let old = r#"{"version":0,"term":{"addition":[{"var":"x"},{"value":"1"}]}}"#;
+        assert!(matches!(
+            Term::from_json(old),
+            Err(LoadError::MissingMigration(0))
+        ));
+        let migrations = Migrations::new().register(0, |mut term| {
+            if let Some(name) = term["addition"][0].get_mut("var").map(|name| name.take()) {
+                term["addition"][0] = serde_json::json!({ "variable": name });
+            }
+            term
+        });
+        assert_eq!(
+            Term::from_json_versioned(old, &migrations).unwrap(),
+            Term::parse("x + 1").unwrap()
+        );
+
+        let newer = r#"{"version":3,"term":[{"value":"1"}]}"#;
+        assert!(matches!(
+            Term::from_json(newer),
+            Err(LoadError::UnsupportedVersion(3))
+        ));
+    }
+
+    #[test]
+    #[cfg(feature = "differential")]
+    fn test_differential() {
+        use super::math::differential::*;
+
+        for agreeing in ["(x^3 - y) / (x + 1/3) - abs(x - y)^2", "sin(x) * exp(y)"] {
+            let agreeing = Term::parse(agreeing).unwrap();
+            assert_eq!(differential_test(&agreeing, 200, 1e-9), Vec::new());
+        }
+
+        // 1e17 + 1 - 1e17 is 1 exactly, but 0 in f64
+        let cancelling = Term::parse("x * 10^17 + 1 - x * 10^17").unwrap();
+        let discrepancies = differential_test(&cancelling, 20, 1e-9);
+        assert!(!discrepancies.is_empty());
+        assert!(discrepancies
+            .iter()
+            .all(|d| d.exact == Ok(Number::Rational(1.into())) && d.reference != Ok(1.0)));
+    }
+
+    #[test]
+    fn test_sexpr() {
+        let term = Term::parse("2*x + x^2").unwrap();
+        assert_eq!(term.to_sexpr(), "(+ (* 2 x) (^ x 2))");
+
+        for input in [
+            "sin(pi*x) / root(y, 3) - 1/2",
+            "[1, 2.5, -3][k]",
+            "{(a, b), }",
+            "e^-x",
+        ] {
+            let term = Term::parse(input).unwrap();
+            assert_eq!(Term::from_sexpr(&term.to_sexpr()), Ok(term));
+        }
+        for name in ["pi", "1x", "a b", "q\"(", "inf"] {
+            let term = Term::Variable(name.into());
+            assert_eq!(Term::from_sexpr(&term.to_sexpr()), Ok(term));
+        }
+
+        assert_eq!(
+            Term::from_sexpr(" (* a b\n  c) "),
+            Ok(Term::parse("a*b*c").unwrap())
+        );
+        let kind = |input| Term::from_sexpr(input).unwrap_err().kind;
+        assert_eq!(
+            kind("(- 1)"),
+            ParseErrorKind::WrongArity {
+                function: "-".into(),
+                expected: 2
+            }
+        );
+        assert_eq!(
+            kind("(foo 1)"),
+            ParseErrorKind::UnknownFunction {
+                name: "foo".into(),
+                suggestions: Vec::new()
+            }
+        );
+        assert_eq!(kind("(+ 1 2"), ParseErrorKind::Unclosed('('));
+        assert_eq!(kind("x y"), ParseErrorKind::UnexpectedToken);
+    }
+
+    #[test]
+    fn test_mathml() {
+        let mathml = |input| Term::parse(input).unwrap().to_mathml();
+
+        assert_eq!(
+            mathml("2*x^2 - 1/3"),
+            "<math xmlns=\"http://www.w3.org/1998/Math/MathML\"><mrow><mrow><mn>2</mn>\
+             <mo>&#x2062;</mo><msup><mrow><mi>x</mi></mrow><mrow><mn>2</mn></mrow></msup>\
+             </mrow><mo>&#x2212;</mo><mfrac><mrow><mn>1</mn></mrow><mrow><mn>3</mn></mrow>\
+             </mfrac></mrow></math>"
+        );
+        assert!(mathml("root(x_1, 3) * sin(pi)").contains(
+            "<mroot><mrow><msub><mi>x</mi><mi>1</mi></msub></mrow><mrow><mn>3</mn></mrow></mroot>\
+             <mo>&#x22C5;</mo><mrow><mi>sin</mi><mo>&#x2061;</mo><mrow><mo>(</mo><mi>π</mi>"
+        ));
+        assert!(mathml("(a + b)^2").contains("<msup><mrow><mrow><mo>(</mo><mrow><mi>a</mi>"));
+
+        let hostile = Term::Variable("<script>".into()).to_mathml();
+        assert!(hostile.contains("<mi>&lt;script&gt;</mi>"));
+    }
+
+    #[test]
+    fn test_approximate() {
+        let approximate = |input: &str, digits| Term::parse(input).unwrap().approximate(digits);
+
+        assert_eq!(
+            approximate("pi", 50).unwrap(),
+            "3.14159265358979323846264338327950288419716939937511"
+        );
+        assert_eq!(
+            approximate("sqrt(2)", 30).unwrap(),
+            "1.414213562373095048801688724210"
+        );
+        assert_eq!(
+            approximate("e", 30).unwrap(),
+            "2.718281828459045235360287471353"
+        );
+        assert_eq!(approximate("phi", 20).unwrap(), "1.61803398874989484820");
+        assert_eq!(
+            approximate("ln(10)", 25).unwrap(),
+            "2.3025850929940456840179915"
+        );
+        assert_eq!(
+            approximate("sin(1) + cos(100)", 20).unwrap(),
+            "1.70378985709558044075"
+        );
+        assert_eq!(
+            approximate("2^(1/3) - root(-8, 3)", 15).unwrap(),
+            "3.259921049894873"
+        );
+        assert_eq!(approximate("-2/3", 4).unwrap(), "-0.6667");
+        assert_eq!(approximate("1/8", 2).unwrap(), "0.13");
+        assert_eq!(approximate("10^20 + 1 - 10^20", 0).unwrap(), "1");
+
+        assert_eq!(
+            approximate("x + 1", 5),
+            Err(ApproximationError::Eval(EvalError::UnboundVariable {
+                name: "x".into(),
+                suggestions: Vec::new()
+            }))
+        );
+        assert_eq!(
+            approximate("ln(-1)", 5),
+            Err(ApproximationError::Eval(EvalError::InvalidDomain))
+        );
+        assert_eq!(
+            approximate("1 / (sqrt(2)^2 - 2)", 5),
+            Err(ApproximationError::Undecided)
+        );
+    }
+
+    #[test]
+    fn test_deep_terms() {
+        let depth = 100_000;
+        let mut chain = Variable("x".into());
+        for _ in 0..depth {
+            chain = Addition(Box::new(chain), Box::new(Value(Number::Rational(1.into()))));
+        }
+
+        assert!(!chain.has_value());
+        assert_eq!(chain.get_value(), None);
+
+        let mut context = EvalContext::new();
+        context.set("x", Number::Rational(2.into()));
+        let expected = Number::Rational((depth + 2).into());
+        assert_eq!(chain.evaluate_in(&context), Ok(expected));
+
+        let substituted = chain.clone().substitute("x", Number::Rational(2.into()));
+        assert!(substituted.has_value());
+        assert_eq!(substituted.get_value(), Some(expected));
+
+        // Cloning, comparing, hashing and dropping don't recurse either
+        let copy = substituted.clone();
+        assert_eq!(copy, substituted);
+        assert_ne!(copy, chain);
+        let terms = std::collections::HashSet::from([&copy, &substituted, &chain]);
+        assert_eq!(terms.len(), 2);
+    }
+
+    #[test]
+    fn test_relative_error() {
+        let rational = |numer, denom| Number::Rational(Fraction::new(numer, denom));
+
+        let measured = rational(981, 100);
+        assert_eq!(
+            measured.relative_error(rational(98, 10)),
+            Ok(rational(1, 980))
+        );
+        assert_eq!(
+            rational(3, 2).percent_error(rational(2, 1)),
+            Ok(rational(25, 1))
+        );
+        assert_eq!(
+            measured.relative_error(rational(0, 1)),
+            Err(EvalError::DivisionByZero)
+        );
+
+        assert_eq!(
+            rational(3, 1).relative_difference(rational(4, 1)),
+            Ok(rational(1, 4))
+        );
+        assert_eq!(
+            rational(4, 1).relative_difference(rational(3, 1)),
+            Ok(rational(1, 4))
+        );
+        assert_eq!(
+            rational(0, 1).relative_difference(rational(0, 1)),
+            Ok(rational(0, 1))
+        );
+
+        let context = EvalContext::new().with("g", measured);
+        let (answer, expected) = (Term::parse("g").unwrap(), Term::parse("9.8").unwrap());
+        assert_eq!(
+            answer.relative_error(&expected, &context),
+            Ok(rational(1, 980))
+        );
+        assert_eq!(
+            answer.within_tolerance(&expected, rational(1, 100), &context),
+            Ok(true)
+        );
+        assert_eq!(
+            answer.within_tolerance(&expected, rational(1, 1000), &context),
+            Ok(false)
+        );
+
+        let zero = Term::parse("1 - 1").unwrap();
+        assert_eq!(
+            Term::parse("1/1000")
+                .unwrap()
+                .within_tolerance(&zero, rational(1, 100), &context),
+            Ok(true)
+        );
+    }
+
+    #[test]
+    fn test_proportion() {
+        let proportion = Proportion::parse("3 : 4 = 1/2 : x").unwrap();
+        assert_eq!(proportion.to_string(), "3 : 4 = 1/2 : x");
+        assert_eq!(proportion.solve(), Ok(("x".into(), Fraction::new(2, 3))));
+
+        assert_eq!(
+            Proportion::parse("k : 2.5 = 6 : 5").unwrap().solve(),
+            Ok(("k".into(), Fraction::new(3, 1)))
+        );
+        assert_eq!(
+            Proportion::parse("0 : 2 = 3 : x").unwrap().solve(),
+            Err(ProportionError::DivisionByZero)
+        );
+        assert_eq!(
+            Proportion::parse("a : 2 = 3 : b").unwrap().solve(),
+            Err(ProportionError::Unknowns(2))
+        );
+        assert!(matches!(
+            Proportion::parse("pi : 2 = 3 : x").unwrap().solve(),
+            Err(ProportionError::Inexact(_))
+        ));
+
+        let (two, three) = (Term::parse("2").unwrap(), Term::parse("3").unwrap());
+        assert_eq!(
+            solve_proportion(&two, &Term::var("y"), &three, &Term::parse("9").unwrap()),
+            Ok(("y".into(), Fraction::new(6, 1)))
+        );
+
+        let error = |input| Proportion::parse(input).unwrap_err();
+        assert_eq!(error("1 : 2 = 3").kind, ParseErrorKind::UnexpectedEnd);
+        assert_eq!(error("1 = 2 : 3 : x").position, 2);
+        assert_eq!(error("1 : 2 = 3 : x : y").position, 14);
+        assert_eq!(error("1 : 2 = 3 : )").position, 12);
+    }
+
+    #[test]
+    fn test_single_pass_evaluation() {
+        // Evaluation stops at the unbound variable, before reaching the constant
+        let term = Term::parse("y * 2 + pi").unwrap();
+        let (value, events) = record_fallbacks(|| term.evaluate());
+        assert_eq!(
+            value,
+            Err(EvalError::UnboundVariable {
+                name: "y".into(),
+                suggestions: Vec::new()
+            })
+        );
+        assert!(events.is_empty());
+        assert_eq!(term.get_value(), None);
+
+        let term = Term::parse("1/(1 - 1) + y").unwrap();
+        assert_eq!(term.evaluate(), Err(EvalError::DivisionByZero));
+
+        let context = EvalContext::new().with("y", Number::Rational(Fraction::new(1, 3)));
+        let term = Term::parse("3*y + floor(y^-2)/2").unwrap();
+        assert_eq!(
+            term.evaluate_in(&context),
+            Ok(Number::Rational(Fraction::new(11, 2)))
+        );
+
+        // Rational terms take the fast path, the rest hands over to the general evaluator
+        let (value, events) = record_fallbacks(|| term.evaluate_in(&context));
+        assert_eq!(value, Ok(Number::Rational(Fraction::new(11, 2))));
+        assert!(events.is_empty());
+        let context = context.with("y", Number::Irrational(0.5));
+        assert_eq!(
+            term.evaluate_in(&context),
+            Ok(Number::Irrational(1.5 + 2.0))
+        );
+        assert_eq!(
+            Term::parse("(9/4)^(1/2) - 1").unwrap().evaluate(),
+            Ok(Number::Rational(Fraction::new(1, 2)))
+        );
+    }
+
+    #[test]
+    fn test_term_arena() {
+        let mut arena = TermArena::new();
+        let context = EvalContext::new().with("x", Number::Rational(Fraction::new(1, 2)));
+        for input in [
+            "3*x^2 - sqrt(x + 2)",
+            "[1, x, 3][1] / abs(-x)",
+            "sin(pi*x) + e",
+        ] {
+            let term = Term::parse(input).unwrap();
+            let id = arena.insert(&term);
+            assert_eq!(arena.to_term(id), term);
+            assert_eq!(arena.evaluate(id, &context), term.evaluate_in(&context));
+        }
+        let id = arena.insert(&Term::parse("[1, 2][5] + y").unwrap());
+        assert_eq!(arena.evaluate(id, &context), Err(EvalError::InvalidIndex));
+
+        // Shared subterms are stored and evaluated once, so this doubles x sixty times
+        let mut doubled = arena.variable("x");
+        for _ in 0..60 {
+            doubled = arena.add(doubled, doubled);
+        }
+        assert_eq!(
+            arena.evaluate(doubled, &context),
+            Ok(Number::Rational(Fraction::from(1 << 59)))
+        );
+
+        let mut arena = TermArena::with_capacity(400_001);
+        let mut chain = arena.variable("x");
+        for _ in 0..200_000 {
+            let one = arena.integer(1);
+            chain = arena.add(chain, one);
+        }
+        assert_eq!(arena.len(), 400_001);
+        assert_eq!(
+            arena.evaluate(chain, &context),
+            Ok(Number::Rational(Fraction::new(400_001, 2)))
+        );
+    }
+
+    #[test]
+    fn test_sequences() {
+        let integers = |values: &[i64]| -> Vec<Number> {
+            values
+                .iter()
+                .map(|&value| Number::Rational(value.into()))
+                .collect()
+        };
+        assert_eq!(
+            nt::primes().take(8).collect::<Vec<_>>(),
+            integers(&[2, 3, 5, 7, 11, 13, 17, 19])
+        );
+        assert_eq!(
+            nt::fibonacci().take(8).collect::<Vec<_>>(),
+            integers(&[0, 1, 1, 2, 3, 5, 8, 13])
+        );
+        assert_eq!(
+            nt::triangular().skip(3).take(3).collect::<Vec<_>>(),
+            integers(&[6, 10, 15])
+        );
+        // F(92) is the last Fibonacci number that fits
+        assert_eq!(nt::fibonacci().count(), 93);
+        assert_eq!(nt::primes().at(100), Some(Number::Rational(541.into())));
+        assert_eq!(nt::primes().at(0), None);
+        assert_eq!(
+            nt::primes().at(nt::MAX_PRIME_INDEX),
+            Some(Number::Rational(1299709.into()))
+        );
+        assert_eq!(nt::primes().at(nt::MAX_PRIME_INDEX + 1), None);
+        assert_eq!(
+            nt::triangular().at(u32::MAX.into()),
+            Some(Number::Rational(9223372034707292160.into()))
+        );
+
+        let evaluate = |input: &str| Term::parse(input).unwrap().evaluate();
+        assert_eq!(
+            evaluate("prime(10) + fibonacci(10)"),
+            Ok(Number::Rational(84.into()))
+        );
+        assert_eq!(
+            evaluate("triangular(floor(pi))"),
+            Ok(Number::Rational(6.into()))
+        );
+        assert_eq!(evaluate("prime(0)"), Err(EvalError::InvalidDomain));
+        assert_eq!(evaluate("prime(100000000)"), Err(EvalError::Overflow));
+        assert_eq!(evaluate("fibonacci(1/2)"), Err(EvalError::InvalidDomain));
+        assert_eq!(evaluate("fibonacci(100)"), Err(EvalError::Overflow));
+
+        let term = Term::parse("prime(n)").unwrap();
+        assert_eq!(Term::from_latex(&term.to_latex()), Ok(term.clone()));
+        assert_eq!(term.derivative("n"), None);
+        assert_eq!(term.derivative("x"), Some(Term::parse("0").unwrap()));
+        assert_eq!(
+            Term::parse("fibonacci(30)")
+                .unwrap()
+                .approximate(2)
+                .unwrap(),
+            "832040.00"
+        );
+    }
+
+    #[test]
+    fn test_selection_notation() {
+        let value = |input: &str| Term::parse(input).unwrap().evaluate();
+        let integer = |value: i64| Ok(Number::Rational(value.into()));
+        assert_eq!(value("5P2"), integer(20));
+        assert_eq!(value("10C3 + 1"), integer(121));
+        assert_eq!(value("nCr(10, 3)"), integer(120));
+        assert_eq!(value("nPr(6, 0)"), integer(1));
+        assert_eq!(value("C(52, 5)"), integer(2_598_960));
+        assert_eq!(value("C(60, 30)"), integer(118_264_581_564_861_424));
+
+        let binomial = Term::parse("C(n, 2)").unwrap();
+        assert_eq!(binomial.to_string(), "nCr(n, 2)");
+        let context = EvalContext::new().with("n", Number::Rational(7.into()));
+        assert_eq!(binomial.evaluate_in(&context), integer(21));
+        assert_eq!(Term::from_latex(&binomial.to_latex()), Ok(binomial.clone()));
+        assert_eq!(Term::from_sexpr(&binomial.to_sexpr()), Ok(binomial.clone()));
+        assert_eq!(binomial.derivative("n"), None);
+        assert_eq!(
+            binomial.derivative("x"),
+            Some(Value(Number::Rational(0.into())))
+        );
+
+        // `k` is an ordinary argument, and large ones take no more work than small ones
+        assert_eq!(
+            value("C(5, k)"),
+            Err(EvalError::UnboundVariable {
+                name: "k".into(),
+                suggestions: Vec::new()
+            })
+        );
+        assert_eq!(value("nPr(5, 1/2)"), Err(EvalError::InvalidDomain));
+        assert_eq!(value("C(5, -1)"), Err(EvalError::InvalidDomain));
+        assert_eq!(value("nCr(1, 3000000)"), integer(0));
+        assert_eq!(value("nPr(3000000, 3000000)"), Err(EvalError::Overflow));
+        assert_eq!(value("nCr(3000000, 1500000)"), Err(EvalError::Overflow));
+        assert_eq!(value("nCr(3000000, 2999999)"), integer(3_000_000));
+        assert!(matches!(
+            Term::parse("nCr(5)").unwrap_err().kind,
+            ParseErrorKind::WrongArity { expected: 2, .. }
+        ));
+    }
+
+    #[test]
+    fn test_shared_terms() {
+        let mut interner = Interner::new();
+        let term = Term::parse("sin(x^2) * sin(x^2) + x^2").unwrap();
+        let shared = interner.intern(&term);
+        assert_eq!(shared.to_term(), term);
+        // x, 2, x^2, sin(x^2), the product and the sum
+        assert_eq!(interner.len(), 6);
+        let SharedNode::Addition(_, square) = shared.node() else {
+            unreachable!("the term is a sum")
+        };
+        assert_eq!(&interner.intern(&Term::parse("x^2").unwrap()), square);
+
+        for input in [
+            "x^3 * ln(x) / (x + 1)",
+            "2^x + x^x - root(x, 3)",
+            "[sin(x), abs(x), e^(2*x)][1] - tan(3)",
+            "cos(x)*cos(x) - sqrt(x^2 + 1)",
+        ] {
+            let term = Term::parse(input).unwrap();
+            let shared = interner.intern(&term);
+            assert_eq!(
+                interner.derivative(&shared, "x").map(|d| d.to_term()),
+                term.derivative("x")
+            );
+        }
+        let prime = interner.intern(&Term::parse("prime(x)").unwrap());
+        assert_eq!(interner.derivative(&prime, "x"), None);
+
+        // A product of x with itself, doubled forty times, has 2^40 leaves as a boxed term but
+        // only a few nodes when shared, and so does its derivative
+        let mut interner = Interner::new();
+        let mut power = interner.intern(&Term::var("x"));
+        for _ in 0..40 {
+            power = interner.share(SharedNode::Multiplication(power.clone(), power));
+        }
+        let before = interner.len();
+        let derivative = interner.derivative(&power, "x").unwrap();
+        assert!(interner.len() - before < 200);
+        assert!(matches!(derivative.node(), SharedNode::Addition(lhs, rhs) if lhs != rhs));
+    }
+
+    #[test]
+    fn test_borrowing_substitution() {
+        let formula = Term::parse("v*t + a*t^2/2").unwrap();
+        let at = |t: i64| {
+            formula
+                .substituted("v", Number::Rational(3.into()))
+                .substituted("a", Number::Rational((-2).into()))
+                .substituted("t", Number::Rational(t.into()))
+                .get_value()
+        };
+        assert_eq!(at(1), Some(Number::Rational(2.into())));
+        assert_eq!(at(3), Some(Number::Rational(0.into())));
+        assert!(formula.depends_on("t"));
+
+        let mut term = formula.clone();
+        term.substitute_in_place("t", Number::Rational(2.into()));
+        assert_eq!(term, Term::parse("v*2 + a*2^2/2").unwrap());
+        assert_eq!(
+            term,
+            formula.clone().substitute("t", Number::Rational(2.into()))
+        );
+    }
+
+    #[test]
+    #[cfg(feature = "server")]
+    fn test_server() {
+        use super::math::server::*;
+        use serde_json::json;
+        use std::io::{Read, Write};
+
+        let post =
+            |path, body: serde_json::Value| respond("POST", path, body.to_string().as_bytes());
+
+        let parsed = post("/parse", json!({ "input": "x^2 + 1" }));
+        assert_eq!(parsed.status, 200);
+        let term = parsed.body["term"].clone();
+        assert_eq!(
+            serde_json::from_value::<Term>(term.clone()).unwrap(),
+            Term::parse("x^2 + 1").unwrap()
+        );
+
+        let evaluated = post(
+            "/evaluate",
+            json!({ "term": term, "bindings": { "x": "1/2" } }),
+        );
+        assert_eq!(evaluated.body, json!({ "value": "5/4" }));
+        let derived = post("/derive", json!({ "input": "x^3", "var": "x" }));
+        assert_eq!(
+            serde_json::from_value::<Term>(derived.body["term"].clone()).unwrap(),
+            Term::parse("x^3").unwrap().derivative("x").unwrap()
+        );
+        let simplified = post("/simplify", json!({ "input": "2*x*3" }));
+        assert_eq!(
+            serde_json::from_value::<Term>(simplified.body["term"].clone()).unwrap(),
+            Term::parse("2*x*3").unwrap().simplify()
+        );
+        assert_eq!(simplified.body["budget"]["converged"], true);
+
+        let error = post("/parse", json!({ "input": "1 + " }));
+        assert_eq!(error.status, 400);
+        assert_eq!(error.body["error"]["kind"], "parse");
+        assert_eq!(error.body["error"]["position"], 4);
+        let unbound = post("/evaluate", json!({ "input": "y" }));
+        assert_eq!(
+            (unbound.status, &unbound.body["error"]["kind"]),
+            (422, &json!("eval"))
+        );
+        assert_eq!(post("/derive", json!({ "input": "x" })).status, 400);
+        assert_eq!(
+            post("/evaluate", json!({ "input": "1", "extra": 1 })).status,
+            400
+        );
+        assert_eq!(respond("GET", "/parse", b"").status, 405);
+        assert_eq!(respond("POST", "/solve", b"{}").status, 404);
+        assert_eq!(
+            respond("POST", "/parse", b"{").body["error"]["kind"],
+            "invalid_json"
+        );
+        let large = vec!["x"; MAX_NODES].join("+");
+        assert_eq!(post("/simplify", json!({ "input": large })).status, 413);
+        // Evaluation needs no deadline, since every function bounds its arguments
+        let prime = post("/evaluate", json!({ "input": "prime(100000000)" }));
+        assert_eq!(prime.status, 422);
+        let cancelled = CancellationToken::new();
+        cancelled.cancel();
+        for path in ["/simplify", "/derive"] {
+            let body = json!({ "input": "x^2", "var": "x" }).to_string();
+            let timeout = respond_cancellable("POST", path, body.as_bytes(), &cancelled);
+            assert_eq!(
+                (timeout.status, &timeout.body["error"]["kind"]),
+                (503, &json!("timeout"))
+            );
+        }
+
+        let listener = std::net::TcpListener::bind("127.0.0.1:0").unwrap();
+        let address = listener.local_addr().unwrap();
+        std::thread::spawn(move || serve(listener));
+        let body = r#"{"input": "1/3 + 1/6"}"#;
+        let mut stream = std::net::TcpStream::connect(address).unwrap();
+        write!(
+            stream,
+            "POST /evaluate HTTP/1.1\r\nHost: localhost\r\nContent-Length: {}\r\n\r\n{body}",
+            body.len()
+        )
+        .unwrap();
+        let mut response = String::new();
+        stream.read_to_string(&mut response).unwrap();
+        assert!(response.starts_with("HTTP/1.1 200 OK\r\n"));
+        assert!(response.ends_with(r#"{"value":"1/2"}"#));
+
+        // Connections are served with enough stack for the most deeply nested inputs
+        let depth = (MAX_INPUT - 1) / 2;
+        let body = json!({ "input": format!("{}x{}", "(".repeat(depth), ")".repeat(depth)) });
+        let body = body.to_string();
+        let mut stream = std::net::TcpStream::connect(address).unwrap();
+        write!(
+            stream,
+            "POST /simplify HTTP/1.1\r\nContent-Length: {}\r\n\r\n{body}",
+            body.len()
+        )
+        .unwrap();
+        let mut response = String::new();
+        stream.read_to_string(&mut response).unwrap();
+        assert!(
+            response.ends_with(r#""term":[{"variable":"x"}]}"#),
+            "{response}"
+        );
+    }
+
+    #[test]
+    fn test_compile() {
+        let term = Term::parse("x^2 + sin(y)*pi - 3/4 + [1, x][1]").unwrap();
+        let compiled = term.compile(&["x", "y"]).unwrap();
+        assert_eq!(compiled.arity(), 2);
+        for (x, y) in [(0.0, 0.0), (1.5, -2.0), (-3.25, 10.0)] {
+            let expected = numeric::evaluate_ieee(&term, &[("x", x), ("y", y)]).unwrap();
+            assert_eq!(compiled.eval(&[x, y]), expected);
+        }
+
+        // Constant subterms are computed once, with the same rounding as when evaluated later
+        let constant = Term::parse("sqrt(2)^2 + x")
+            .unwrap()
+            .compile(&["x"])
+            .unwrap();
+        assert_eq!(constant.eval(&[0.0]), 2f64.sqrt().powf(2.0));
+        assert!(Term::parse("1/0 - x")
+            .unwrap()
+            .compile(&["x"])
+            .unwrap()
+            .eval(&[1.0])
+            .is_infinite());
+
+        let compile = |input: &str| Term::parse(input).unwrap().compile(&["x"]);
+        assert_eq!(compile("x + y"), Err(numeric::DomainError::UnboundVariable));
+        assert_eq!(compile("[x, 1]"), Err(numeric::DomainError::NotAScalar));
+        assert_eq!(
+            compile("[1, 2][x]"),
+            Err(numeric::DomainError::InvalidIndex)
+        );
+        assert_eq!(
+            compile("[1, 2][2]"),
+            Err(numeric::DomainError::InvalidIndex)
+        );
+    }
+
+    #[test]
+    fn test_evaluator() {
+        let term = Term::parse("x^2 + sin(y)*pi - 3/4").unwrap();
+        let mut evaluator = Evaluator::new(term.clone(), &["x", "y"]);
+        for call in 0..COMPILE_CALLS * 2 {
+            let (x, y) = (call as f64 / 4.0, 1.0 - call as f64);
+            let expected = numeric::evaluate_ieee(&term, &[("x", x), ("y", y)]);
+            assert_eq!(evaluator.eval(&[x, y]), expected);
+            let tier = if call < COMPILE_CALLS {
+                Tier::Interpreted
+            } else {
+                Tier::Compiled
+            };
+            assert_eq!(evaluator.tier(), tier);
+        }
+
+        // Large terms are compiled right away
+        let sum = (0..COMPILE_SIZE).fold(Term::parse("x").unwrap(), |acc, i| {
+            Addition(
+                Box::new(acc),
+                Box::new(Value(Number::Rational(i64::try_from(i).unwrap().into()))),
+            )
+        });
+        let mut evaluator = Evaluator::new(sum, &["x"]);
+        assert_eq!(evaluator.eval(&[1.0]), Ok(2017.0));
+        assert_eq!(evaluator.tier(), Tier::Compiled);
+
+        // Terms that can't be compiled keep being interpreted
+        let mut evaluator = Evaluator::new(Term::parse("[1, 2][x]").unwrap(), &["x"]);
+        for _ in 0..=COMPILE_CALLS {
+            assert_eq!(evaluator.eval(&[1.0]), Ok(2.0));
+        }
+        assert_eq!(evaluator.tier(), Tier::Interpreted);
+    }
+    #[test]
+    fn test_incremental_reparse() {
+        let mut document = Document::new("2 * (x + 1) + sin(y)");
+        assert_eq!(document.edit(9..10, "20"), Reparse::Region(4..12));
+        assert_eq!(document.source(), "2 * (x + 20) + sin(y)");
+        assert_eq!(document.term(), Term::parse(document.source()).as_ref());
+
+        // Units after an edit have moved along with it
+        assert_eq!(document.edit(19..20, "y^2"), Reparse::Region(15..23));
+        assert_eq!(
+            document.term(),
+            Term::parse("2 * (x + 20) + sin(y^2)").as_ref()
+        );
+
+        // Nested units are found again after their parent was reparsed
+        let mut document = Document::new("[1, (x)]");
+        assert_eq!(document.edit(5..6, "x - (y)"), Reparse::Region(4..13));
+        assert_eq!(document.edit(10..11, "z"), Reparse::Region(9..12));
+        assert_eq!(document.term(), Term::parse("[1, (x - (z))]").as_ref());
+
+        // Contents that do not parse on their own, and units changed by what surrounds them, fall
+        // back to parsing everything
+        let mut document = Document::new("(x + 1) * 2");
+        assert_eq!(document.edit(2..3, ")"), Reparse::Full);
+        assert!(document.term().is_err());
+        assert_eq!(document.edit(2..3, "+"), Reparse::Full);
+        assert_eq!(document.term(), Term::parse("(x + 1) * 2").as_ref());
+        let mut document = Document::new("-(x)");
+        assert_eq!(document.edit(2..3, "3"), Reparse::Full);
+        assert_eq!(document.term(), Ok(&Value(Number::Rational((-3).into()))));
+
+        // The arguments of functions of several arguments are found in their list
+        let mut document = Document::new("nCr(x, 2)");
+        assert_eq!(document.edit(4..5, "(y)"), Reparse::Region(0..11));
+        assert_eq!(document.edit(5..6, "z"), Reparse::Region(4..7));
+        assert_eq!(document.term(), Term::parse("nCr((z), 2)").as_ref());
+    }
+
+    #[test]
+    fn test_evaluate_batch() {
+        let term = Term::parse("1 / (x - 2) + x^2").unwrap();
+        let inputs = [0, 1, 2, 3].map(|x| Number::Rational(x.into()));
+        assert_eq!(
+            term.evaluate_batch("x", &inputs),
+            vec![
+                Ok(Number::Rational((-1, 2).into())),
+                Ok(Number::Rational(0.into())),
+                Err(EvalError::DivisionByZero),
+                Ok(Number::Rational(10.into())),
+            ]
+        );
+
+        // More inputs than fit in one block, with the same results as one at a time
+        let inputs: Vec<f64> = (0..1000).map(|i| i as f64 / 100.0 - 3.0).collect();
+        let batch = term.evaluate_batch_f64("x", &inputs).unwrap();
+        assert_eq!(batch.len(), inputs.len());
+        for (x, value) in inputs.iter().zip(&batch) {
+            let expected = numeric::evaluate_ieee(&term, &[("x", *x)]).unwrap();
+            assert!(*value == expected || value.is_nan() && expected.is_nan());
+        }
+        assert_eq!(
+            term.evaluate_batch_f64("x", &[2.0]).unwrap(),
+            [f64::INFINITY]
+        );
+        assert_eq!(
+            term.evaluate_batch_f64("y", &[1.0]),
+            Err(numeric::DomainError::UnboundVariable)
+        );
+    }
+
+    #[test]
+    fn test_completion() {
+        let context = EvalContext::new()
+            .with("phase", Number::Rational((1, 2).into()))
+            .with("q", Number::Rational(0.into()));
+        let labels = |prefix| -> Vec<_> {
+            complete(prefix, &context)
+                .into_iter()
+                .map(|completion| (completion.label, completion.kind))
+                .collect()
+        };
+        assert_eq!(
+            labels("p"),
+            [
+                ("phase".into(), CompletionKind::Variable),
+                ("popcount".into(), CompletionKind::Function),
+                ("prime".into(), CompletionKind::Function),
+                ("phi".into(), CompletionKind::Constant),
+                ("pi".into(), CompletionKind::Constant),
+            ]
+        );
+        assert_eq!(labels("nC"), [("nCr".into(), CompletionKind::Function)]);
+        assert!(labels("z").is_empty());
+        assert_eq!(complete("", &context).len(), 2 + 24 + 2 + 3);
+
+        let details: Vec<_> = complete("r", &context)
+            .into_iter()
+            .map(|completion| completion.detail)
+            .collect();
+        assert_eq!(details, ["rand(seed, i)".into(), "root(x, n)".into()]);
+        assert_eq!(complete("phase", &context)[0].detail, "phase = 1/2".into());
+    }
+
+    #[test]
+    fn test_corrections() {
+        let best = |input: &str| {
+            let corrections = suggest_corrections(input);
+            let best = corrections.first().unwrap();
+            assert_eq!(best.term, Term::parse(&best.input).unwrap());
+            (best.input.clone(), best.fixes.clone())
+        };
+
+        assert_eq!(
+            best("2x + 1"),
+            ("2*x + 1".into(), vec![Fix::ImplicitMultiplication])
+        );
+        assert_eq!(
+            best("(x + 1)(x - 1)"),
+            ("(x + 1)*(x - 1)".into(), vec![Fix::ImplicitMultiplication])
+        );
+        assert_eq!(
+            best("(x + [1"),
+            ("(x + [1])".into(), vec![Fix::BalanceBrackets])
+        );
+        assert_eq!(best("x + 1)"), ("x + 1".into(), vec![Fix::BalanceBrackets]));
+        assert_eq!(best("x**2"), ("x^2".into(), vec![Fix::PowerOperator]));
+        assert_eq!(best("sin x"), ("sin(x)".into(), vec![Fix::CallParentheses]));
+        assert_eq!(
+            best("2x**2 + sin 3x"),
+            (
+                "2*x^2 + sin(3*x)".into(),
+                vec![
+                    Fix::PowerOperator,
+                    Fix::CallParentheses,
+                    Fix::ImplicitMultiplication
+                ]
+            )
+        );
+
+        // Inputs that parse need no correction, and some mistakes have none
+        assert!(suggest_corrections("2*x + 1").is_empty());
+        assert!(suggest_corrections("x + * 1").is_empty());
+        assert!(suggest_corrections("x # 1").is_empty());
+    }
+
+    #[test]
+    fn test_did_you_mean() {
+        let names = ["sin", "sinh", "sign", "cos"];
+        assert_eq!(
+            similar_names("sine", names),
+            vec!["sin".into(), "sinh".into()]
+        );
+        assert_eq!(similar_names("cso", names), vec!["cos".into()]);
+        assert!(similar_names("x", ["y"]).is_empty());
+
+        let error = Term::parse("2*sqtr(x)").unwrap_err();
+        assert_eq!(
+            error.kind,
+            ParseErrorKind::UnknownFunction {
+                name: "sqtr".into(),
+                suggestions: vec!["sqrt".into()]
+            }
+        );
+        assert_eq!(
+            error.to_string(),
+            "unknown function `sqtr` (did you mean `sqrt`?) at position 2"
+        );
+
+        let context = EvalContext::new()
+            .with("radius", Number::Rational(1.into()))
+            .with("radii", Number::Rational(2.into()));
+        let error = Term::parse("2*pii*raduis")
+            .unwrap()
+            .evaluate_in(&context.clone().with("pii", Number::Rational(3.into())))
+            .unwrap_err();
+        assert_eq!(
+            error,
+            EvalError::UnboundVariable {
+                name: "raduis".into(),
+                suggestions: vec!["radius".into(), "radii".into()]
+            }
+        );
+        assert_eq!(
+            error.to_string(),
+            "variable `raduis` has no value (did you mean `radius` or `radii`?)"
+        );
+        assert_eq!(
+            Term::parse("pih").unwrap().evaluate_in(&context),
+            Err(EvalError::UnboundVariable {
+                name: "pih".into(),
+                suggestions: vec!["phi".into(), "pi".into()]
+            })
+        );
+    }
+
+    #[test]
+    fn test_restricted_functions() {
+        let context = EvalContext::new()
+            .with("x", Number::Rational(3.into()))
+            .restrict_functions(&[Function::Sin, Function::Abs]);
+        assert!(context.allows(Function::Sin) && !context.allows(Function::Fibonacci));
+
+        let term = Term::parse("abs(-x) + fibonacci(x)").unwrap();
+        let error = EvalError::RestrictedFunction(Function::Fibonacci);
+        assert_eq!(term.evaluate_in(&context), Err(error.clone()));
+        assert_eq!(error.to_string(), "function `fibonacci` is not allowed");
+        assert_eq!(
+            Term::parse("abs(-x) + sin(0)")
+                .unwrap()
+                .evaluate_in(&context),
+            Ok(Number::Rational(3.into()))
+        );
+        // Overflowing the fast path does not get around the restriction either
+        assert_eq!(
+            Term::parse("exp(x)*2^100").unwrap().evaluate_in(&context),
+            Err(EvalError::RestrictedFunction(Function::Exp))
+        );
+
+        let mut arena = TermArena::new();
+        let id = arena.insert(&term);
+        assert_eq!(arena.evaluate(id, &context), term.evaluate_in(&context));
+        let mut pool = ExprPool::new();
+        let handle = pool.insert(&Term::parse("fibonacci(3)").unwrap());
+        assert_eq!(pool.evaluate(handle, &context), term.evaluate_in(&context));
+        assert_eq!(
+            term.approximate_in(&context, 10),
+            Err(ApproximationError::Eval(EvalError::RestrictedFunction(
+                Function::Fibonacci
+            )))
+        );
+        assert_eq!(
+            term.evaluate_partial(&context).term.to_string(),
+            "3 + fibonacci(3)"
+        );
+    }
+
+    #[test]
+    fn test_big_rationals() {
+        let big = |input: &str| Term::parse(input).unwrap().evaluate_big();
+        let two_to_100: BigRational = "1267650600228229401496703205376".parse().unwrap();
+        assert_eq!(big("2^100"), Ok(two_to_100));
+        assert_eq!(
+            Term::parse("2^100")
+                .unwrap()
+                .evaluate_exact()
+                .unwrap_err()
+                .reason,
+            ExactnessReason::Overflow
+        );
+
+        // Intermediate results may be large as long as the result fits in a `Fraction` again
+        let value = big("(2^100 + 1) - 2^100 + (1/3)^50 * 3^50").unwrap();
+        assert_eq!(big::to_number(&value), Number::Rational(2.into()));
+        assert_eq!(big("(2^120)^(1/4)"), big("2^30"));
+        assert_eq!(
+            big("floor(2^70 / 3) - 2^70 / 3"),
+            Ok(BigRational::new((-1).into(), 3.into()))
+        );
+        assert_eq!(big("(-8)^(1/3)"), big("-2"));
+        assert_eq!(big("1^(2^31)"), big("1"));
+
+        let reason = |input| big(input).unwrap_err().reason;
+        assert_eq!(reason("2^(2^40)"), ExactnessReason::Overflow);
+        assert_eq!(reason("(-4)^(1/2)"), ExactnessReason::IrrationalPower);
+        assert_eq!(reason("2^100 / 0"), ExactnessReason::DivisionByZero);
+        assert_eq!(reason("sin(2^100)"), ExactnessReason::Transcendental);
+    }
 }
@@ -21,4 +21,4 @@ fn App() -> Html {
 
 fn main() {
     yew::Renderer::<App>::new().render();
-}
\ No newline at end of file
+}
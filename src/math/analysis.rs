@@ -0,0 +1,364 @@
+use std::f64::consts::PI;
+
+use fraction::{One, Signed, ToPrimitive};
+
+use super::derivative::Derivatives;
+use super::functions::Function;
+use super::interval::Range;
+use super::numeric;
+use super::polynomial::Polynomial;
+use super::rational_function::RationalFunction;
+use super::terms::{EvalContext, Fraction, Number, Term};
+use super::values::integer;
+
+const SAMPLES: usize = 1000;
+const BISECTIONS: usize = 100;
+const TOLERANCE: f64 = 1e-9;
+/// How small the derivative must be at a numerically found root, to tell roots from poles.
+const ROOT_TOLERANCE: f64 = 1e-6;
+/// The highest derivative tried when classifying a critical point found numerically.
+const MAX_ORDER: usize = 8;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum CriticalKind {
+    Minimum,
+    Maximum,
+    /// A stationary point where the function keeps rising or falling.
+    Inflection,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct CriticalPoint {
+    pub at: Number,
+    pub kind: CriticalKind,
+}
+
+impl RationalFunction {
+    /// The derivative by the quotient rule.
+    pub fn derivative(&self) -> RationalFunction {
+        let (num, den) = (self.numerator(), self.denominator());
+        RationalFunction::new(
+            num.derivative().mul(den).sub(&num.mul(&den.derivative())),
+            den.mul(den),
+        )
+        .unwrap()
+    }
+
+    /// The stationary points inside `interval`, in ascending order, classified by the first
+    /// non-vanishing higher derivative. Constant functions have no isolated critical points.
+    ///
+    /// Roots of the derivative are exact where possible; otherwise they are located numerically
+    /// from sign changes, which misses roots of even multiplicity.
+    pub fn critical_points(&self, interval: &Range) -> Vec<CriticalPoint> {
+        let first = self.derivative();
+        if first.numerator().is_zero() {
+            return Vec::new();
+        }
+
+        let mut points = first
+            .numerator()
+            .real_roots()
+            .unwrap_or_else(|| approximate_roots(first.numerator(), interval));
+        points.retain(|point| interval.contains(*point));
+        points.sort_by(|a, b| f64::from(*a).total_cmp(&f64::from(*b)));
+        points.dedup();
+
+        let derivatives: Vec<RationalFunction> = (0..first.numerator().degree().unwrap_or(0) + 1)
+            .scan(first, |derivative, _| {
+                *derivative = derivative.derivative();
+                Some(derivative.clone())
+            })
+            .collect();
+
+        points
+            .into_iter()
+            .map(|at| CriticalPoint {
+                at,
+                kind: classify(&derivatives, at),
+            })
+            .collect()
+    }
+}
+
+impl Term {
+    /// Integrates over `[a, b]` with respect to `var`. Polynomials with rational bounds are
+    /// integrated exactly unless that overflows; anything else falls back to
+    /// `numeric::integrate`.
+    pub fn definite_integral(&self, var: &str, a: Number, b: Number) -> Option<Number> {
+        if let (Some(polynomial), Number::Rational(a), Number::Rational(b)) =
+            (Polynomial::from_term(self, var), a, b)
+        {
+            if let Some(area) = polynomial.definite_integral(a, b) {
+                return Some(Number::Rational(area));
+            }
+        }
+
+        numeric::integrate(self, var, f64::from(a), f64::from(b)).map(Number::Irrational)
+    }
+
+    /// The critical points of this term in `var` inside `interval`, in ascending order.
+    ///
+    /// Rational functions are handled exactly by `RationalFunction::critical_points`. Other terms
+    /// are differentiated with `Term::derivative`, and the roots of the derivative located and
+    /// classified numerically, which needs a bounded `interval`. Returns `None` if the interval
+    /// is unbounded or the term has no derivative.
+    pub fn critical_points(&self, var: &str, interval: &Range) -> Option<Vec<CriticalPoint>> {
+        if let Some(function) = RationalFunction::from_term(self, var) {
+            return Some(function.critical_points(interval));
+        }
+
+        let (lo, hi) = (f64::from(interval.lo), f64::from(interval.hi));
+        if !lo.is_finite() || !hi.is_finite() {
+            return None;
+        }
+        let mut derivatives = Derivatives::new(self.clone(), var);
+        let first = derivatives.nth(1)?.clone();
+        if !first.depends_on(var) {
+            return Some(Vec::new());
+        }
+
+        let slope = |x| numeric::evaluate(&first, var, x);
+        let mut points: Vec<f64> = bisect_roots(slope, lo, hi)
+            .into_iter()
+            .filter(|&x| slope(x).is_some_and(|value| value.abs() < ROOT_TOLERANCE))
+            .filter(|&x| interval.contains(Number::Irrational(x)))
+            .collect();
+        points.dedup_by(|a, b| (*a - *b).abs() < TOLERANCE);
+
+        Some(
+            points
+                .into_iter()
+                .map(|at| CriticalPoint {
+                    at: Number::Irrational(at),
+                    kind: classify_numerically(&mut derivatives, at),
+                })
+                .collect(),
+        )
+    }
+}
+
+/// The length of the graph of `term` over `[a, b]`, integrating `sqrt(1 + f'^2)` with
+/// `numeric::integrate`. Returns `None` if `term` has no derivative in `var` or the integrand is
+/// undefined somewhere on `[a, b]`, like at a vertical tangent.
+pub fn arc_length(term: &Term, var: &str, a: Number, b: Number) -> Option<Number> {
+    let integrand = arc_element(term, var)?;
+    numeric::integrate(&integrand, var, f64::from(a), f64::from(b)).map(Number::Irrational)
+}
+
+/// The volume swept by rotating the graph of `term` over `[a, b]` around the `var` axis, by the
+/// disk method `pi * integral of f^2`.
+pub fn volume_of_revolution(term: &Term, var: &str, a: Number, b: Number) -> Option<Number> {
+    let area = square(term.clone()).definite_integral(var, a, b)?;
+    Some(Number::Irrational(PI) * area)
+}
+
+/// The area of the surface swept by rotating the graph of `term` over `[a, b]` around the `var`
+/// axis, `2 pi * integral of |f| sqrt(1 + f'^2)`. Returns `None` where `arc_length` does.
+pub fn surface_of_revolution(term: &Term, var: &str, a: Number, b: Number) -> Option<Number> {
+    let radius = Term::Function(Function::Abs, Box::new(term.clone()));
+    let integrand = Term::Multiplication(Box::new(radius), Box::new(arc_element(term, var)?));
+    let area = numeric::integrate(&integrand, var, f64::from(a), f64::from(b))?;
+    Some(Number::Irrational(2.0 * PI * area))
+}
+
+/// `sqrt(1 + f'^2)`, the length of the graph of `term` per unit of `var`.
+fn arc_element(term: &Term, var: &str) -> Option<Term> {
+    let slope = term.derivative_simplified(var)?;
+    Some(Term::RootExtraction(
+        Box::new(Term::Addition(
+            Box::new(integer(1)),
+            Box::new(square(slope)),
+        )),
+        Box::new(integer(2)),
+    ))
+}
+
+/// The tangent `f(x0) + f'(x0) (var - x0)` to the graph of `term` at `x0`, using
+/// `Term::derivative`. The coefficients are exact where the value and slope are rational.
+/// Returns `None` if `term` has no derivative in `var` or either fails to evaluate at `x0`, like
+/// at a pole.
+pub fn tangent_line_at(term: &Term, var: &str, x0: Fraction) -> Option<Term> {
+    let (value, slope) = value_and_slope(term, var, x0)?;
+    line(var, x0, value, slope)
+}
+
+/// The normal `f(x0) - (var - x0) / f'(x0)` to the graph of `term` at `x0`. Returns `None` where
+/// `tangent_line_at` does, and also where the tangent is horizontal, since the normal is then
+/// the vertical line `var = x0`.
+pub fn normal_line_at(term: &Term, var: &str, x0: Fraction) -> Option<Term> {
+    let (value, slope) = value_and_slope(term, var, x0)?;
+    if f64::from(slope) == 0.0 {
+        return None;
+    }
+    let slope = Number::Rational(-Fraction::one()).checked_div(slope).ok()?;
+    line(var, x0, value, slope)
+}
+
+/// The curvature `|f''| / (1 + f'^2)^(3/2)` of the graph of `term`, as a term in `var`. Returns
+/// `None` if `term` has no derivative in `var`.
+pub fn curvature(term: &Term, var: &str) -> Option<Term> {
+    let first = term.derivative_simplified(var)?;
+    let second = first.derivative_simplified(var)?;
+
+    let magnitude = Term::Function(Function::Abs, Box::new(second));
+    let scale = Term::Exponentation(
+        Box::new(Term::Addition(
+            Box::new(integer(1)),
+            Box::new(square(first)),
+        )),
+        Box::new(Term::Value(Number::Rational(Fraction::new(-3, 2)))),
+    );
+    Some(Term::Multiplication(Box::new(magnitude), Box::new(scale)))
+}
+
+fn value_and_slope(term: &Term, var: &str, x0: Fraction) -> Option<(Number, Number)> {
+    let slope = term.derivative_simplified(var)?;
+    let context = EvalContext::new().with(var, Number::Rational(x0));
+    Some((
+        term.evaluate_in(&context).ok()?,
+        slope.evaluate_in(&context).ok()?,
+    ))
+}
+
+/// The line through `(x0, value)` with `slope`, written as a polynomial in `var` if both are
+/// rational.
+fn line(var: &str, x0: Fraction, value: Number, slope: Number) -> Option<Term> {
+    let intercept = value
+        .checked_sub(slope.checked_mul(Number::Rational(x0)).ok()?)
+        .ok()?;
+    Some(match (intercept, slope) {
+        (Number::Rational(intercept), Number::Rational(slope)) => {
+            Polynomial::new(var, vec![intercept, slope]).to_term()
+        }
+        _ => Term::Addition(
+            Box::new(Term::Multiplication(
+                Box::new(Term::Value(slope)),
+                Box::new(Term::Variable(var.into())),
+            )),
+            Box::new(Term::Value(intercept)),
+        ),
+    })
+}
+
+fn square(term: Term) -> Term {
+    Term::Exponentation(Box::new(term), Box::new(integer(2)))
+}
+
+/// `derivatives` starts at the second derivative.
+fn classify(derivatives: &[RationalFunction], at: Number) -> CriticalKind {
+    for (order, derivative) in derivatives.iter().enumerate() {
+        let sign = match at {
+            Number::Rational(x) => derivative
+                .evaluate(x)
+                .and_then(|value| value.to_f64())
+                .unwrap_or(0.0),
+            Number::Irrational(x) => {
+                let value = evaluate_f64(derivative.numerator(), x)
+                    / evaluate_f64(derivative.denominator(), x);
+                if value.abs() < TOLERANCE {
+                    0.0
+                } else {
+                    value
+                }
+            }
+        };
+
+        if sign != 0.0 {
+            return match (order.is_multiple_of(2), sign > 0.0) {
+                (true, true) => CriticalKind::Minimum,
+                (true, false) => CriticalKind::Maximum,
+                (false, _) => CriticalKind::Inflection,
+            };
+        }
+    }
+
+    CriticalKind::Inflection
+}
+
+/// Like `classify`, with the derivatives of a term evaluated in `f64`. Derivatives smaller than
+/// `TOLERANCE` count as zero, and a point is an inflection if all up to `MAX_ORDER` vanish.
+fn classify_numerically(derivatives: &mut Derivatives, at: f64) -> CriticalKind {
+    let var = derivatives.var().to_string();
+    for order in 2..=MAX_ORDER {
+        let Some(value) = derivatives
+            .nth(order)
+            .and_then(|d| numeric::evaluate(d, &var, at))
+        else {
+            break;
+        };
+        if value.abs() >= TOLERANCE {
+            return match (order.is_multiple_of(2), value > 0.0) {
+                (true, true) => CriticalKind::Minimum,
+                (true, false) => CriticalKind::Maximum,
+                (false, _) => CriticalKind::Inflection,
+            };
+        }
+    }
+
+    CriticalKind::Inflection
+}
+
+fn evaluate_f64(polynomial: &Polynomial, x: f64) -> f64 {
+    polynomial
+        .coefficients()
+        .iter()
+        .rev()
+        .fold(0.0, |acc, c| acc * x + c.to_f64().unwrap_or(f64::NAN))
+}
+
+/// Roots of `polynomial` in `interval` found by bisecting sign changes. Unbounded ends are
+/// clamped to the Cauchy bound, outside of which there are no roots.
+fn approximate_roots(polynomial: &Polynomial, interval: &Range) -> Vec<Number> {
+    let leading = polynomial.leading_coefficient();
+    let bound = polynomial
+        .coefficients()
+        .iter()
+        .map(|c| (c / leading).abs().to_f64().unwrap_or(f64::INFINITY))
+        .fold(0.0, f64::max)
+        + 1.0;
+
+    let lo = f64::from(interval.lo).max(-bound);
+    let hi = f64::from(interval.hi).min(bound);
+    if lo >= hi {
+        return Vec::new();
+    }
+
+    bisect_roots(|x| Some(evaluate_f64(polynomial, x)), lo, hi)
+        .into_iter()
+        .map(Number::Irrational)
+        .collect()
+}
+
+/// Zeros and sign changes of `f` in `[lo, hi]`, bisected to full precision. Samples where `f` is
+/// undefined are skipped, and a sign change across a pole is found like one across a root.
+fn bisect_roots(f: impl Fn(f64) -> Option<f64>, lo: f64, hi: f64) -> Vec<f64> {
+    let step = (hi - lo) / SAMPLES as f64;
+    let mut roots = Vec::new();
+
+    for i in 0..SAMPLES {
+        let (mut a, mut b) = (lo + step * i as f64, lo + step * (i + 1) as f64);
+        let (Some(fa), Some(fb)) = (f(a), f(b)) else {
+            continue;
+        };
+
+        if fa == 0.0 {
+            roots.push(a);
+            continue;
+        }
+        if fa.signum() == fb.signum() {
+            continue;
+        }
+
+        for _ in 0..BISECTIONS {
+            let mid = (a + b) / 2.0;
+            if f(mid).is_some_and(|value| value.signum() == fa.signum()) {
+                a = mid;
+            } else {
+                b = mid;
+            }
+        }
+        roots.push((a + b) / 2.0);
+    }
+
+    roots
+}
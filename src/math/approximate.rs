@@ -0,0 +1,563 @@
+use std::cell::OnceCell;
+use std::fmt::{self, Display, Formatter};
+
+use fraction::{BigInt, Integer, One, Signed, ToPrimitive, Zero};
+
+use super::big::{from_fraction, BigRational as Big};
+use super::budget::Budget;
+use super::constants::Constant;
+use super::functions::Function;
+use super::terms::{EvalContext, EvalError, Number, Term};
+
+/// How often `approximate` doubles its working precision before giving up.
+const REFINEMENTS: usize = 6;
+
+/// Why `Term::approximate` could not produce its digits.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum ApproximationError {
+    Eval(EvalError),
+    /// The value is too close to a rounding boundary, or an operation too close to a
+    /// discontinuity like a division by zero, to be decided at any precision tried.
+    Undecided,
+}
+
+impl Display for ApproximationError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        match self {
+            ApproximationError::Eval(error) => error.fmt(f),
+            ApproximationError::Undecided => write!(f, "value could not be narrowed down enough"),
+        }
+    }
+}
+
+impl std::error::Error for ApproximationError {}
+
+impl Term {
+    /// The value rounded to `digits` digits after the decimal point, like `"3.14159"` for `pi`
+    /// and 5 digits.
+    ///
+    /// Unlike `evaluate`, every digit is guaranteed: the term is evaluated with interval
+    /// arithmetic on arbitrary-precision rationals, at increasing precision until the whole
+    /// interval rounds to the same digits.
+    pub fn approximate(&self, digits: usize) -> Result<String, ApproximationError> {
+        self.approximate_in(&EvalContext::new(), digits)
+    }
+
+    /// Like `approximate`, with the variables bound in `context` replaced by their values.
+    pub fn approximate_in(
+        &self,
+        context: &EvalContext,
+        digits: usize,
+    ) -> Result<String, ApproximationError> {
+        self.approximate_with_budget(context, digits, REFINEMENTS).0
+    }
+
+    /// Like `approximate_in`, trying at most `limit` precisions. The budget has not converged if
+    /// the value was `Undecided` at every one of them.
+    pub fn approximate_with_budget(
+        &self,
+        context: &EvalContext,
+        digits: usize,
+        limit: usize,
+    ) -> (Result<String, ApproximationError>, Budget) {
+        let mut budget = Budget {
+            used: 0,
+            limit,
+            converged: true,
+        };
+        // Enough bits for the digits, and some to absorb the rounding of every operation
+        let mut bits = digits as u64 * 10 / 3 + 64;
+        while budget.used < limit {
+            budget.used += 1;
+            let approximator = Approximator::new(bits, context);
+            match approximator.eval(self) {
+                Ok(value) => {
+                    let (lo, hi) = (decimal(&value.lo, digits), decimal(&value.hi, digits));
+                    if lo == hi {
+                        return (Ok(lo), budget);
+                    }
+                }
+                Err(Failure::Eval(error)) => return (Err(ApproximationError::Eval(error)), budget),
+                Err(Failure::Refine) => {}
+            }
+            bits *= 2;
+        }
+        budget.converged = false;
+        (Err(ApproximationError::Undecided), budget)
+    }
+}
+
+/// `value` rounded to `digits` decimals, with ties away from zero.
+fn decimal(value: &Big, digits: usize) -> String {
+    let scale = BigInt::from(10).pow(digits as u32);
+    let rounded = (value * Big::from_integer(scale)).round().to_integer();
+    let magnitude = format!("{:0>width$}", rounded.abs(), width = digits + 1);
+    let (integer, fraction) = magnitude.split_at(magnitude.len() - digits);
+    let sign = if rounded.is_negative() { "-" } else { "" };
+    match digits {
+        0 => format!("{sign}{integer}"),
+        _ => format!("{sign}{integer}.{fraction}"),
+    }
+}
+
+enum Failure {
+    Eval(EvalError),
+    /// The intervals are too wide to decide something, like the sign of a divisor.
+    Refine,
+}
+
+impl From<EvalError> for Failure {
+    fn from(error: EvalError) -> Self {
+        Failure::Eval(error)
+    }
+}
+
+/// A closed interval known to contain a value.
+#[derive(Clone, Debug)]
+struct Interval {
+    lo: Big,
+    hi: Big,
+}
+
+impl Interval {
+    fn point(value: Big) -> Self {
+        Interval {
+            lo: value.clone(),
+            hi: value,
+        }
+    }
+
+    fn integer(value: i64) -> Self {
+        Interval::point(Big::from_integer(value.into()))
+    }
+
+    fn magnitude(&self) -> Big {
+        self.lo.abs().max(self.hi.abs())
+    }
+
+    fn exact_integer(&self) -> Option<BigInt> {
+        (self.lo == self.hi && self.lo.is_integer()).then(|| self.lo.to_integer())
+    }
+
+    fn widen(&self, by: &Big) -> Interval {
+        Interval {
+            lo: &self.lo - by,
+            hi: &self.hi + by,
+        }
+    }
+
+    fn neg(&self) -> Interval {
+        Interval {
+            lo: -&self.hi,
+            hi: -&self.lo,
+        }
+    }
+}
+
+fn big(value: Number) -> Result<Big, Failure> {
+    match value {
+        Number::Rational(value) => Ok(from_fraction(value)),
+        Number::Irrational(value) => {
+            Big::from_float(value).ok_or(Failure::Eval(EvalError::InvalidDomain))
+        }
+    }
+}
+
+/// Interval arithmetic that rounds every bound outwards to a multiple of `2^-bits`, so the
+/// sizes of the rationals stay bounded.
+struct Approximator<'a> {
+    bits: u64,
+    scale: BigInt,
+    epsilon: Big,
+    context: &'a EvalContext,
+    pi: OnceCell<Interval>,
+    ln2: OnceCell<Interval>,
+}
+
+impl<'a> Approximator<'a> {
+    fn new(bits: u64, context: &'a EvalContext) -> Self {
+        let scale = BigInt::one() << bits;
+        Approximator {
+            bits,
+            epsilon: Big::new(BigInt::one(), scale.clone()),
+            scale,
+            context,
+            pi: OnceCell::new(),
+            ln2: OnceCell::new(),
+        }
+    }
+
+    fn round(&self, lo: Big, hi: Big) -> Interval {
+        let scale = Big::from_integer(self.scale.clone());
+        Interval {
+            lo: Big::new((&lo * &scale).floor().to_integer(), self.scale.clone()),
+            hi: Big::new((&hi * &scale).ceil().to_integer(), self.scale.clone()),
+        }
+    }
+
+    fn add(&self, a: &Interval, b: &Interval) -> Interval {
+        self.round(&a.lo + &b.lo, &a.hi + &b.hi)
+    }
+
+    fn sub(&self, a: &Interval, b: &Interval) -> Interval {
+        self.round(&a.lo - &b.hi, &a.hi - &b.lo)
+    }
+
+    fn mul(&self, a: &Interval, b: &Interval) -> Interval {
+        let products = [&a.lo * &b.lo, &a.lo * &b.hi, &a.hi * &b.lo, &a.hi * &b.hi];
+        let lo = products.iter().min().unwrap().clone();
+        let hi = products.iter().max().unwrap().clone();
+        self.round(lo, hi)
+    }
+
+    fn square(&self, a: &Interval) -> Interval {
+        let (lo, hi) = (&a.lo * &a.lo, &a.hi * &a.hi);
+        if a.lo.is_negative() && a.hi.is_positive() {
+            self.round(Big::zero(), lo.max(hi))
+        } else {
+            self.round(lo.clone().min(hi.clone()), lo.max(hi))
+        }
+    }
+
+    fn div(&self, a: &Interval, b: &Interval) -> Result<Interval, Failure> {
+        if b.lo.is_positive() || b.hi.is_negative() {
+            let reciprocal = Interval {
+                lo: b.hi.recip(),
+                hi: b.lo.recip(),
+            };
+            Ok(self.mul(a, &reciprocal))
+        } else if b.lo.is_zero() && b.hi.is_zero() {
+            Err(EvalError::DivisionByZero.into())
+        } else {
+            Err(Failure::Refine)
+        }
+    }
+
+    fn div_integer(&self, a: &Interval, n: i64) -> Interval {
+        let n = Big::from_integer(n.into());
+        let (lo, hi) = (&a.lo / &n, &a.hi / &n);
+        if lo <= hi {
+            self.round(lo, hi)
+        } else {
+            self.round(hi, lo)
+        }
+    }
+
+    fn power(&self, a: &Interval, n: &BigInt) -> Result<Interval, Failure> {
+        if n.is_negative() {
+            return self.div(&Interval::integer(1), &self.power(a, &-n)?);
+        }
+        let mut n = n.to_u64().ok_or(EvalError::Overflow)?;
+        let (mut result, mut square) = (Interval::integer(1), a.clone());
+        while n > 0 {
+            if n % 2 == 1 {
+                result = self.mul(&result, &square);
+            }
+            n /= 2;
+            if n > 0 {
+                square = self.square(&square);
+            }
+        }
+        Ok(result)
+    }
+
+    /// The `n`th root of a non-negative bound, rounded down or up.
+    fn root_bound(&self, x: &Big, n: u32, up: bool) -> Big {
+        let shifted = x * Big::from_integer(BigInt::one() << (self.bits * u64::from(n)));
+        let floor = shifted.floor().to_integer();
+        let mut root = floor.nth_root(n);
+        if up && (root.pow(n) != floor || !shifted.is_integer()) {
+            root += 1;
+        }
+        Big::new(root, self.scale.clone())
+    }
+
+    fn signed_root_bound(&self, x: &Big, n: u32, up: bool) -> Big {
+        if x.is_negative() {
+            -self.root_bound(&-x, n, !up)
+        } else {
+            self.root_bound(x, n, up)
+        }
+    }
+
+    fn root(&self, a: &Interval, n: u32) -> Result<Interval, Failure> {
+        if n.is_multiple_of(2) && a.lo.is_negative() {
+            return Err(if a.hi.is_negative() {
+                EvalError::InvalidDomain.into()
+            } else {
+                Failure::Refine
+            });
+        }
+        Ok(Interval {
+            lo: self.signed_root_bound(&a.lo, n, false),
+            hi: self.signed_root_bound(&a.hi, n, true),
+        })
+    }
+
+    /// Sums terms `first, next(first, 1), next(next(first, 1), 2), ...` whose ratio is at most
+    /// `1/2` in magnitude from the `from`th term on, adding the tail as an error bound.
+    fn series(
+        &self,
+        first: Interval,
+        from: usize,
+        next: impl Fn(&Interval, usize) -> Interval,
+    ) -> Interval {
+        let (mut sum, mut term, mut i) = (Interval::integer(0), first, 0);
+        loop {
+            if i >= from && term.magnitude() <= self.epsilon {
+                return sum.widen(&(term.magnitude() * Big::from_integer(2.into())));
+            }
+            i += 1;
+            sum = self.add(&sum, &term);
+            term = next(&term, i);
+        }
+    }
+
+    fn exp_point(&self, x: &Big) -> Interval {
+        // exp(x) = exp(x / 2^k)^(2^k) with |x / 2^k| <= 1/2
+        let magnitude = x.abs().ceil().to_integer();
+        let k = if magnitude.is_zero() {
+            1
+        } else {
+            magnitude.bits() + 1
+        };
+        let r = Interval::point(x / Big::from_integer(BigInt::one() << k));
+        let mut value = self.series(Interval::integer(1), 0, |term, i| {
+            self.div_integer(&self.mul(term, &r), i as i64)
+        });
+        for _ in 0..k {
+            value = self.square(&value);
+        }
+        value
+    }
+
+    fn exp(&self, a: &Interval) -> Interval {
+        Interval {
+            lo: self.exp_point(&a.lo).lo,
+            hi: self.exp_point(&a.hi).hi,
+        }
+    }
+
+    /// `atanh(z)` for `|z| <= 1/3`, as the sum of `z^(2i + 1) / (2i + 1)`.
+    fn atanh(&self, z: &Interval) -> Interval {
+        let z2 = self.square(z);
+        let (mut sum, mut power, mut i) = (Interval::integer(0), z.clone(), 0);
+        loop {
+            let term = self.div_integer(&power, 2 * i + 1);
+            if term.magnitude() <= self.epsilon {
+                return sum.widen(&(term.magnitude() * Big::from_integer(2.into())));
+            }
+            sum = self.add(&sum, &term);
+            power = self.mul(&power, &z2);
+            i += 1;
+        }
+    }
+
+    fn ln2(&self) -> &Interval {
+        self.ln2.get_or_init(|| {
+            let third = Interval::point(Big::new(1.into(), 3.into()));
+            let atanh = self.atanh(&third);
+            self.add(&atanh, &atanh)
+        })
+    }
+
+    fn ln_point(&self, x: &Big) -> Interval {
+        // ln(x) = ln(m) + k ln(2) with 1/2 < m < 2
+        let k = x.numer().bits() as i64 - x.denom().bits() as i64;
+        let m = if k >= 0 {
+            x / Big::from_integer(BigInt::one() << k)
+        } else {
+            x * Big::from_integer(BigInt::one() << -k)
+        };
+        let one = Big::one();
+        let z = Interval::point((&m - &one) / (&m + &one));
+        let atanh = self.atanh(&z);
+        let ln_m = self.add(&atanh, &atanh);
+        self.add(&ln_m, &self.mul(self.ln2(), &Interval::integer(k)))
+    }
+
+    fn ln(&self, a: &Interval) -> Result<Interval, Failure> {
+        if !a.lo.is_positive() {
+            return Err(if a.hi.is_positive() {
+                Failure::Refine
+            } else {
+                EvalError::InvalidDomain.into()
+            });
+        }
+        Ok(Interval {
+            lo: self.ln_point(&a.lo).lo,
+            hi: self.ln_point(&a.hi).hi,
+        })
+    }
+
+    /// `atan(1/q)` for an integer `q > 1`.
+    fn atan_reciprocal(&self, q: i64) -> Interval {
+        let first = Interval::point(Big::new(1.into(), q.into()));
+        let q2 = Big::from_integer((q * q).into());
+        let mut sum = Interval::integer(0);
+        let mut power = first;
+        let mut i = 0;
+        loop {
+            let term = self.div_integer(&power, 2 * i + 1);
+            if term.magnitude() <= self.epsilon {
+                // An alternating series with decreasing terms
+                return sum.widen(&term.magnitude());
+            }
+            sum = match i % 2 {
+                0 => self.add(&sum, &term),
+                _ => self.sub(&sum, &term),
+            };
+            power = self.round(&power.lo / &q2, &power.hi / &q2);
+            i += 1;
+        }
+    }
+
+    fn pi(&self) -> &Interval {
+        self.pi.get_or_init(|| {
+            // Machin's formula pi = 16 atan(1/5) - 4 atan(1/239)
+            let fifth = self.mul(&self.atan_reciprocal(5), &Interval::integer(16));
+            let rest = self.mul(&self.atan_reciprocal(239), &Interval::integer(4));
+            self.sub(&fifth, &rest)
+        })
+    }
+
+    fn sin(&self, a: &Interval) -> Interval {
+        // Reduce to [-pi, pi], then use that sin is 1-Lipschitz around the midpoint
+        let two_pi = self.mul(self.pi(), &Interval::integer(2));
+        let mid = (&a.lo + &a.hi) / Big::from_integer(2.into());
+        let turns = (&mid / &two_pi.lo).round().to_integer();
+        let reduced = self.sub(
+            &Interval::point(mid),
+            &self.mul(&two_pi, &Interval::point(turns.into())),
+        );
+
+        let y = (&reduced.lo + &reduced.hi) / Big::from_integer(2.into());
+        let radius = (&reduced.hi - &reduced.lo) / Big::from_integer(2.into())
+            + (&a.hi - &a.lo) / Big::from_integer(2.into());
+        let y2 = Interval::point(&y * &y);
+        let value = self.series(Interval::point(y), 2, |term, i| {
+            let term = self.mul(term, &y2);
+            self.div_integer(&term, -((2 * i * (2 * i + 1)) as i64))
+        });
+        value.widen(&radius)
+    }
+
+    fn cos(&self, a: &Interval) -> Interval {
+        let half_pi = self.div_integer(self.pi(), 2);
+        self.sin(&self.add(a, &half_pi))
+    }
+
+    fn eval(&self, term: &Term) -> Result<Interval, Failure> {
+        let eval = |term| self.eval(term);
+
+        Ok(match term {
+            Term::Value(value) => Interval::point(big(*value)?),
+            Term::Variable(name) => match self.context.get(name) {
+                Some(value) => Interval::point(big(value)?),
+                None => return Err(self.context.unbound(name).into()),
+            },
+            Term::Constant(Constant::Pi) => self.pi().clone(),
+            Term::Constant(Constant::E) => self.exp_point(&Big::one()),
+            Term::Constant(Constant::Phi) => {
+                let root = self.root(&Interval::integer(5), 2)?;
+                self.div_integer(&self.add(&root, &Interval::integer(1)), 2)
+            }
+            Term::Addition(lhs, rhs) => self.add(&eval(lhs)?, &eval(rhs)?),
+            Term::Subtraction(lhs, rhs) => self.sub(&eval(lhs)?, &eval(rhs)?),
+            Term::Multiplication(lhs, rhs) => self.mul(&eval(lhs)?, &eval(rhs)?),
+            Term::Division(lhs, rhs) => self.div(&eval(lhs)?, &eval(rhs)?)?,
+            Term::Exponentation(base, power) => self.pow(&eval(base)?, &eval(power)?)?,
+            Term::RootExtraction(radicand, degree) => {
+                let (radicand, degree) = (eval(radicand)?, eval(degree)?);
+                match degree.exact_integer().and_then(|degree| degree.to_u32()) {
+                    Some(0) => return Err(EvalError::DivisionByZero.into()),
+                    Some(degree) => self.root(&radicand, degree)?,
+                    None => {
+                        let power = self.div(&Interval::integer(1), &degree)?;
+                        self.pow(&radicand, &power)?
+                    }
+                }
+            }
+            Term::Function(function, _) if !self.context.allows(*function) => {
+                return Err(EvalError::RestrictedFunction(*function).into())
+            }
+            Term::Function(function, argument) if function.is_discrete() => {
+                let mut arguments = Vec::new();
+                for argument in function.arguments(argument) {
+                    let x = eval(argument)?;
+                    match x.exact_integer().and_then(|index| index.to_i64()) {
+                        Some(index) => arguments.push(Number::Rational(index.into())),
+                        None if x.lo == x.hi => return Err(EvalError::InvalidDomain.into()),
+                        None => return Err(Failure::Refine),
+                    }
+                }
+                Interval::point(big(function.evaluate(&arguments)?)?)
+            }
+            Term::Function(function, argument) => {
+                let x = eval(argument)?;
+                match function {
+                    Function::Sin => self.sin(&x),
+                    Function::Cos => self.cos(&x),
+                    Function::Tan => self.div(&self.sin(&x), &self.cos(&x))?,
+                    Function::Ln => self.ln(&x)?,
+                    Function::Exp => self.exp(&x),
+                    Function::Abs if x.lo.is_negative() && x.hi.is_positive() => Interval {
+                        lo: Big::zero(),
+                        hi: x.magnitude(),
+                    },
+                    Function::Abs if x.hi.is_negative() => x.neg(),
+                    Function::Abs => x,
+                    Function::Floor | Function::Ceil => {
+                        let round = |bound: &Big| match function {
+                            Function::Floor => bound.floor(),
+                            _ => bound.ceil(),
+                        };
+                        let (lo, hi) = (round(&x.lo), round(&x.hi));
+                        if lo != hi {
+                            return Err(Failure::Refine);
+                        }
+                        Interval::point(lo)
+                    }
+                    _ => unreachable!("discrete functions are handled above"),
+                }
+            }
+            Term::Vector(_) | Term::List(_) | Term::Set(_) => {
+                return Err(EvalError::NotAScalar.into())
+            }
+            Term::Index(collection, index) => {
+                let position = eval(index)?
+                    .exact_integer()
+                    .and_then(|position| position.to_usize());
+                match &**collection {
+                    Term::Vector(elements) | Term::List(elements) => {
+                        let element = position.and_then(|position| elements.get(position));
+                        eval(element.ok_or(EvalError::InvalidIndex)?)?
+                    }
+                    _ => return Err(EvalError::InvalidIndex.into()),
+                }
+            }
+        })
+    }
+
+    fn pow(&self, base: &Interval, power: &Interval) -> Result<Interval, Failure> {
+        if let Some(power) = power.exact_integer() {
+            return self.power(base, &power);
+        }
+        // Rational powers with an odd denominator are real for negative bases too
+        if power.lo == power.hi && power.lo.denom().is_odd() {
+            if let Some(denom) = power.lo.denom().to_u32() {
+                let root = self.root(base, denom)?;
+                return self.power(&root, power.lo.numer());
+            }
+        }
+        if base.lo.is_zero() && base.hi.is_zero() {
+            return if power.lo.is_positive() {
+                Ok(Interval::integer(0))
+            } else {
+                Err(Failure::Refine)
+            };
+        }
+        let ln = self.ln(base)?;
+        Ok(self.exp(&self.mul(power, &ln)))
+    }
+}
@@ -0,0 +1,320 @@
+use super::constants::Constant;
+use super::functions::Function;
+use super::terms::{EvalContext, EvalError, Fraction, Number, Term};
+
+/// A term stored in a `TermArena`.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, PartialOrd, Ord)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct TermId(u32);
+
+impl TermId {
+    pub fn index(self) -> usize {
+        self.0 as usize
+    }
+}
+
+/// A node of a `TermArena`, like a `Term` with ids in place of boxed children.
+#[derive(Clone, Debug, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "serde", serde(rename_all = "snake_case"))]
+pub enum ArenaNode {
+    Value(Number),
+    Variable(Box<str>),
+    Constant(Constant),
+    Addition(TermId, TermId),
+    Subtraction(TermId, TermId),
+    Multiplication(TermId, TermId),
+    Division(TermId, TermId),
+    Exponentation(TermId, TermId),
+    RootExtraction(TermId, TermId),
+    Function(Function, TermId),
+    Vector(Box<[TermId]>),
+    List(Box<[TermId]>),
+    Set(Box<[TermId]>),
+    Index(TermId, TermId),
+}
+
+impl ArenaNode {
+    fn for_each_child(&self, mut visit: impl FnMut(TermId)) {
+        match self {
+            ArenaNode::Value(_) | ArenaNode::Variable(_) | ArenaNode::Constant(_) => {}
+            ArenaNode::Addition(lhs, rhs)
+            | ArenaNode::Subtraction(lhs, rhs)
+            | ArenaNode::Multiplication(lhs, rhs)
+            | ArenaNode::Division(lhs, rhs)
+            | ArenaNode::Exponentation(lhs, rhs)
+            | ArenaNode::RootExtraction(lhs, rhs)
+            | ArenaNode::Index(lhs, rhs) => {
+                visit(*lhs);
+                visit(*rhs);
+            }
+            ArenaNode::Function(_, argument) => visit(*argument),
+            ArenaNode::Vector(elements) | ArenaNode::List(elements) | ArenaNode::Set(elements) => {
+                elements.iter().copied().for_each(visit)
+            }
+        }
+    }
+}
+
+/// Terms stored as nodes in one contiguous vector, with children referred to by `TermId`. Large
+/// terms are cheaper to build and drop than boxed `Term`s, and subterms can be shared.
+///
+/// Nodes can only refer to nodes added before them, so every id is smaller than the ids of the
+/// nodes that use it.
+#[derive(Clone, Debug, Default)]
+pub struct TermArena {
+    nodes: Vec<ArenaNode>,
+}
+
+impl TermArena {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn with_capacity(capacity: usize) -> Self {
+        Self {
+            nodes: Vec::with_capacity(capacity),
+        }
+    }
+
+    pub fn len(&self) -> usize {
+        self.nodes.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.nodes.is_empty()
+    }
+
+    pub fn get(&self, id: TermId) -> &ArenaNode {
+        &self.nodes[id.index()]
+    }
+
+    /// Every node in the order they were added.
+    pub fn nodes(&self) -> &[ArenaNode] {
+        &self.nodes
+    }
+
+    /// Adds a node. Panics if it refers to an id that is not in this arena yet.
+    pub fn push(&mut self, node: ArenaNode) -> TermId {
+        let id = TermId(u32::try_from(self.nodes.len()).expect("too many nodes in the arena"));
+        node.for_each_child(|child| assert!(child < id, "{child:?} is not in the arena"));
+        self.nodes.push(node);
+        id
+    }
+
+    /// Like `push`, but returns `None` instead of panicking.
+    pub fn try_push(&mut self, node: ArenaNode) -> Option<TermId> {
+        let id = TermId(u32::try_from(self.nodes.len()).ok()?);
+        let mut known = true;
+        node.for_each_child(|child| known &= child < id);
+        if !known {
+            return None;
+        }
+        self.nodes.push(node);
+        Some(id)
+    }
+
+    pub fn value(&mut self, value: Number) -> TermId {
+        self.push(ArenaNode::Value(value))
+    }
+
+    pub fn integer(&mut self, value: i64) -> TermId {
+        self.value(Number::Rational(Fraction::from(value)))
+    }
+
+    pub fn variable(&mut self, name: &str) -> TermId {
+        self.push(ArenaNode::Variable(name.into()))
+    }
+
+    pub fn add(&mut self, lhs: TermId, rhs: TermId) -> TermId {
+        self.push(ArenaNode::Addition(lhs, rhs))
+    }
+
+    pub fn sub(&mut self, lhs: TermId, rhs: TermId) -> TermId {
+        self.push(ArenaNode::Subtraction(lhs, rhs))
+    }
+
+    pub fn mul(&mut self, lhs: TermId, rhs: TermId) -> TermId {
+        self.push(ArenaNode::Multiplication(lhs, rhs))
+    }
+
+    pub fn div(&mut self, lhs: TermId, rhs: TermId) -> TermId {
+        self.push(ArenaNode::Division(lhs, rhs))
+    }
+
+    pub fn pow(&mut self, base: TermId, power: TermId) -> TermId {
+        self.push(ArenaNode::Exponentation(base, power))
+    }
+
+    pub fn apply(&mut self, function: Function, argument: TermId) -> TermId {
+        self.push(ArenaNode::Function(function, argument))
+    }
+
+    /// Copies a boxed term into the arena and returns the id of its root.
+    pub fn insert(&mut self, term: &Term) -> TermId {
+        // Children are inserted before their parent, with an explicit stack so deep terms
+        // cannot overflow the call stack
+        let (mut pending, mut ids) = (vec![(term, false)], Vec::new());
+        while let Some((term, children_done)) = pending.pop() {
+            if !children_done {
+                let children = term.children();
+                if !children.is_empty() {
+                    pending.push((term, true));
+                    pending.extend(children.into_iter().rev().map(|child| (child, false)));
+                    continue;
+                }
+            }
+
+            let mut take = |count: usize| ids.split_off(ids.len() - count);
+            let node = match term {
+                Term::Value(value) => ArenaNode::Value(*value),
+                Term::Variable(name) => ArenaNode::Variable(name.clone()),
+                Term::Constant(constant) => ArenaNode::Constant(*constant),
+                Term::Function(function, _) => ArenaNode::Function(*function, take(1)[0]),
+                Term::Vector(elements) => ArenaNode::Vector(take(elements.len()).into()),
+                Term::List(elements) => ArenaNode::List(take(elements.len()).into()),
+                Term::Set(elements) => ArenaNode::Set(take(elements.len()).into()),
+                binary => {
+                    let operands = take(2);
+                    let (lhs, rhs) = (operands[0], operands[1]);
+                    match binary {
+                        Term::Addition(..) => ArenaNode::Addition(lhs, rhs),
+                        Term::Subtraction(..) => ArenaNode::Subtraction(lhs, rhs),
+                        Term::Multiplication(..) => ArenaNode::Multiplication(lhs, rhs),
+                        Term::Division(..) => ArenaNode::Division(lhs, rhs),
+                        Term::Exponentation(..) => ArenaNode::Exponentation(lhs, rhs),
+                        Term::RootExtraction(..) => ArenaNode::RootExtraction(lhs, rhs),
+                        _ => ArenaNode::Index(lhs, rhs),
+                    }
+                }
+            };
+            let id = self.push(node);
+            ids.push(id);
+        }
+        ids.pop().expect("the root leaves one id")
+    }
+
+    /// The boxed term rooted at `id`. Shared subterms are copied wherever they are used.
+    pub fn to_term(&self, id: TermId) -> Term {
+        let (mut pending, mut terms) = (vec![(id, false)], Vec::<Term>::new());
+        while let Some((id, children_done)) = pending.pop() {
+            let node = self.get(id);
+            if !children_done
+                && !matches!(
+                    node,
+                    ArenaNode::Value(_) | ArenaNode::Variable(_) | ArenaNode::Constant(_)
+                )
+            {
+                pending.push((id, true));
+                let start = pending.len();
+                node.for_each_child(|child| pending.push((child, false)));
+                pending[start..].reverse();
+                continue;
+            }
+
+            let mut take = |count: usize| terms.split_off(terms.len() - count);
+            let mut binary = |make: fn(Box<Term>, Box<Term>) -> Term| {
+                let [lhs, rhs]: [Term; 2] = take(2).try_into().expect("two operands");
+                make(Box::new(lhs), Box::new(rhs))
+            };
+            let term = match node {
+                ArenaNode::Value(value) => Term::Value(*value),
+                ArenaNode::Variable(name) => Term::Variable(name.clone()),
+                ArenaNode::Constant(constant) => Term::Constant(*constant),
+                ArenaNode::Addition(..) => binary(Term::Addition),
+                ArenaNode::Subtraction(..) => binary(Term::Subtraction),
+                ArenaNode::Multiplication(..) => binary(Term::Multiplication),
+                ArenaNode::Division(..) => binary(Term::Division),
+                ArenaNode::Exponentation(..) => binary(Term::Exponentation),
+                ArenaNode::RootExtraction(..) => binary(Term::RootExtraction),
+                ArenaNode::Index(..) => binary(Term::Index),
+                ArenaNode::Function(function, _) => {
+                    Term::Function(*function, Box::new(take(1).pop().unwrap()))
+                }
+                ArenaNode::Vector(elements) => Term::Vector(take(elements.len())),
+                ArenaNode::List(elements) => Term::List(take(elements.len())),
+                ArenaNode::Set(elements) => Term::Set(take(elements.len())),
+            };
+            terms.push(term);
+        }
+        terms.pop().expect("the root leaves one term")
+    }
+
+    /// Evaluates the term rooted at `id`. Results match `Term::evaluate_in`, except that no
+    /// fallback diagnostics are reported. Shared subterms are evaluated once.
+    pub fn evaluate(&self, id: TermId, context: &EvalContext) -> Result<Number, EvalError> {
+        // Children have smaller ids, so one backward sweep finds everything `id` depends on and
+        // one forward sweep evaluates it
+        let mut reachable = vec![false; id.index() + 1];
+        reachable[id.index()] = true;
+        for index in (0..=id.index()).rev() {
+            if reachable[index] {
+                self.nodes[index].for_each_child(|child| reachable[child.index()] = true);
+            }
+        }
+
+        let mut values: Vec<Result<Number, EvalError>> = Vec::with_capacity(id.index() + 1);
+        for (node, reachable) in self.nodes.iter().zip(reachable) {
+            let value = if reachable {
+                self.evaluate_node(node, &values, context)
+            } else {
+                Err(EvalError::NotAScalar)
+            };
+            values.push(value);
+        }
+        values.pop().expect("the root is evaluated last")
+    }
+
+    fn evaluate_node(
+        &self,
+        node: &ArenaNode,
+        values: &[Result<Number, EvalError>],
+        context: &EvalContext,
+    ) -> Result<Number, EvalError> {
+        let value = |id: &TermId| values[id.index()].clone();
+
+        match node {
+            ArenaNode::Value(value) => Ok(*value),
+            ArenaNode::Variable(name) => context.get(name).ok_or_else(|| context.unbound(name)),
+            ArenaNode::Constant(constant) => Ok(Number::Irrational(constant.value())),
+            ArenaNode::Addition(lhs, rhs) => value(lhs)?.checked_add(value(rhs)?),
+            ArenaNode::Subtraction(lhs, rhs) => value(lhs)?.checked_sub(value(rhs)?),
+            ArenaNode::Multiplication(lhs, rhs) => value(lhs)?.checked_mul(value(rhs)?),
+            ArenaNode::Division(lhs, rhs) => value(lhs)?.checked_div(value(rhs)?),
+            ArenaNode::Exponentation(base, power) => value(base)?.checked_pow(&value(power)?),
+            ArenaNode::RootExtraction(radicand, degree) => {
+                let radicand = value(radicand)?;
+                let power = Number::Rational(Fraction::from(1)).checked_div(value(degree)?)?;
+                radicand.checked_pow(&power)
+            }
+            ArenaNode::Function(function, _) if !context.allows(*function) => {
+                Err(EvalError::RestrictedFunction(*function))
+            }
+            ArenaNode::Function(function, argument) => match self.get(*argument) {
+                ArenaNode::List(elements) if function.arity() > 1 => {
+                    let arguments = elements.iter().map(value).collect::<Result<Vec<_>, _>>()?;
+                    function.evaluate(&arguments)
+                }
+                _ => function.apply(value(argument)?),
+            },
+            ArenaNode::Vector(_) | ArenaNode::List(_) | ArenaNode::Set(_) => {
+                Err(EvalError::NotAScalar)
+            }
+            ArenaNode::Index(collection, index) => {
+                let position = match value(index)? {
+                    Number::Rational(position) if position.is_integer() => {
+                        usize::try_from(position.to_integer()).ok()
+                    }
+                    _ => None,
+                };
+                let element = match self.get(*collection) {
+                    ArenaNode::Vector(elements) | ArenaNode::List(elements) => {
+                        position.and_then(|position| elements.get(position))
+                    }
+                    _ => None,
+                };
+                value(element.ok_or(EvalError::InvalidIndex)?)
+            }
+        }
+    }
+}
@@ -0,0 +1,199 @@
+use fraction::{BigInt, Ratio, Signed, ToPrimitive, Zero};
+
+use super::exact::{ExactnessReason, ExactnessViolation};
+use super::functions::Function;
+use super::path::TermPath;
+use super::terms::{EvalContext, Fraction, Number, Term};
+
+/// An exact rational of any size.
+pub type BigRational = Ratio<BigInt>;
+
+/// The most bits the numerator or denominator of a power may have.
+pub const MAX_POWER_BITS: u64 = 1 << 24;
+
+pub fn from_fraction(value: Fraction) -> BigRational {
+    BigRational::new((*value.numer()).into(), (*value.denom()).into())
+}
+
+/// The value as a `Number`: rational if it fits in a `Fraction`, and the nearest float otherwise.
+pub fn to_number(value: &BigRational) -> Number {
+    match (value.numer().to_i64(), value.denom().to_i64()) {
+        (Some(numer), Some(denom)) => Number::Rational(Fraction::new(numer, denom)),
+        _ => Number::Irrational(value.to_f64().unwrap_or(f64::NAN)),
+    }
+}
+
+impl Term {
+    /// Like `evaluate_exact`, but with rationals of any size, so that exact arithmetic never fails
+    /// with `Overflow` just because a result does not fit in a `Fraction`. Only powers larger than
+    /// `MAX_POWER_BITS` still overflow, rather than exhausting memory.
+    ///
+    /// `Number` has no room for rationals of any size, so literals are still read as
+    /// `Fraction`s and integers beyond `i64` are irrational. `evaluate` falls back to this when
+    /// its fractions overflow.
+    pub fn evaluate_big(&self) -> Result<BigRational, ExactnessViolation> {
+        self.evaluate_big_in(&EvalContext::new())
+    }
+
+    /// Like `evaluate_big`, with the variables bound to rationals in `context` replaced by their
+    /// values.
+    pub fn evaluate_big_in(
+        &self,
+        context: &EvalContext,
+    ) -> Result<BigRational, ExactnessViolation> {
+        self.evaluate_big_at(context, &TermPath::root())
+    }
+
+    fn evaluate_big_at(
+        &self,
+        context: &EvalContext,
+        path: &TermPath,
+    ) -> Result<BigRational, ExactnessViolation> {
+        let violation = |reason| ExactnessViolation {
+            at: path.clone(),
+            reason,
+        };
+        let operands = |lhs: &Term, rhs: &Term| {
+            Ok((
+                lhs.evaluate_big_at(context, &path.child(0))?,
+                rhs.evaluate_big_at(context, &path.child(1))?,
+            ))
+        };
+
+        match self {
+            Term::Value(Number::Rational(value)) => Ok(from_fraction(*value)),
+            Term::Value(Number::Irrational(_)) => {
+                Err(violation(ExactnessReason::IrrationalLiteral))
+            }
+            Term::Variable(name) => match context.get(name) {
+                Some(Number::Rational(value)) => Ok(from_fraction(value)),
+                Some(Number::Irrational(_)) => Err(violation(ExactnessReason::IrrationalLiteral)),
+                None => Err(violation(ExactnessReason::UnboundVariable)),
+            },
+            Term::Constant(_) => Err(violation(ExactnessReason::IrrationalConstant)),
+            Term::Addition(lhs, rhs) => operands(lhs, rhs).map(|(lhs, rhs)| lhs + rhs),
+            Term::Subtraction(lhs, rhs) => operands(lhs, rhs).map(|(lhs, rhs)| lhs - rhs),
+            Term::Multiplication(lhs, rhs) => operands(lhs, rhs).map(|(lhs, rhs)| lhs * rhs),
+            Term::Division(lhs, rhs) => {
+                let (lhs, rhs) = operands(lhs, rhs)?;
+                if rhs.is_zero() {
+                    return Err(violation(ExactnessReason::DivisionByZero));
+                }
+                Ok(lhs / rhs)
+            }
+            Term::Exponentation(base, power) => {
+                let (base, power) = operands(base, power)?;
+                big_power(base, power).map_err(violation)
+            }
+            Term::RootExtraction(radicand, degree) => {
+                let (radicand, degree) = operands(radicand, degree)?;
+                if degree.is_zero() {
+                    return Err(violation(ExactnessReason::DivisionByZero));
+                }
+                big_power(radicand, degree.recip()).map_err(violation)
+            }
+            Term::Function(function, argument) => {
+                let arguments = function.arguments(argument);
+                let arguments: Vec<BigRational> = match arguments {
+                    [argument] => vec![argument.evaluate_big_at(context, &path.child(0))?],
+                    _ => (arguments.iter().enumerate())
+                        .map(|(i, argument)| {
+                            argument.evaluate_big_at(context, &path.child(0).child(i))
+                        })
+                        .collect::<Result<_, _>>()?,
+                };
+                match (function, &arguments[..]) {
+                    (Function::Abs, [argument]) => Ok(argument.abs()),
+                    (Function::Floor, [argument]) => Ok(argument.floor()),
+                    (Function::Ceil, [argument]) => Ok(argument.ceil()),
+                    // The other functions are only rational at small arguments
+                    _ => arguments
+                        .iter()
+                        .map(|argument| match to_number(argument) {
+                            Number::Rational(argument) => Some(argument),
+                            Number::Irrational(_) => None,
+                        })
+                        .collect::<Option<Vec<_>>>()
+                        .and_then(|arguments| function.evaluate_exact(&arguments))
+                        .map(from_fraction)
+                        .ok_or_else(|| violation(ExactnessReason::Transcendental)),
+                }
+            }
+            Term::Vector(_) | Term::List(_) | Term::Set(_) => {
+                Err(violation(ExactnessReason::NotAScalar))
+            }
+            Term::Index(collection, index) => {
+                let position = index.evaluate_big_at(context, &path.child(1))?;
+                let (Term::Vector(elements) | Term::List(elements)) = &**collection else {
+                    return Err(ExactnessViolation {
+                        at: path.child(0),
+                        reason: ExactnessReason::NotAScalar,
+                    });
+                };
+
+                let element = position
+                    .is_integer()
+                    .then(|| position.to_integer().to_usize())
+                    .flatten()
+                    .and_then(|position| elements.get(position).map(|element| (position, element)));
+
+                match element {
+                    Some((position, element)) => {
+                        element.evaluate_big_at(context, &path.child(0).child(position))
+                    }
+                    None => Err(ExactnessViolation {
+                        at: path.child(1),
+                        reason: ExactnessReason::IndexOutOfBounds,
+                    }),
+                }
+            }
+        }
+    }
+}
+
+/// The exact `degree`-th root of an integer, if there is one.
+fn integer_root(n: &BigInt, degree: u32) -> Option<BigInt> {
+    let root = n.abs().nth_root(degree);
+    let root = if n.is_negative() { -root } else { root };
+    (root.pow(degree) == *n).then_some(root)
+}
+
+fn big_power(base: BigRational, power: BigRational) -> Result<BigRational, ExactnessReason> {
+    let base = if power.is_integer() {
+        base
+    } else {
+        let degree = power
+            .denom()
+            .to_u32()
+            .ok_or(ExactnessReason::IrrationalPower)?;
+        if base.is_negative() && degree % 2 == 0 {
+            return Err(ExactnessReason::IrrationalPower);
+        }
+        let numer = integer_root(base.numer(), degree).ok_or(ExactnessReason::IrrationalPower)?;
+        let denom = integer_root(base.denom(), degree).ok_or(ExactnessReason::IrrationalPower)?;
+        BigRational::new(numer, denom)
+    };
+
+    let exponent = power.numer();
+    let base = if exponent.is_negative() {
+        if base.is_zero() {
+            return Err(ExactnessReason::DivisionByZero);
+        }
+        base.recip()
+    } else {
+        base
+    };
+    let exponent = exponent
+        .abs()
+        .to_u32()
+        .filter(|&exponent| {
+            // Powers of one cannot grow, and those of anything else grow by at least a bit each
+            let bits = base.numer().bits().max(base.denom().bits()) - 1;
+            bits.saturating_mul(exponent.into()) <= MAX_POWER_BITS
+        })
+        .ok_or(ExactnessReason::Overflow)?;
+    Ok(BigRational::new(
+        base.numer().pow(exponent),
+        base.denom().pow(exponent),
+    ))
+}
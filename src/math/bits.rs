@@ -0,0 +1,88 @@
+use std::fmt::{self, Display, Formatter};
+
+use super::terms::{Fraction, Number};
+
+/// Why a bit operation could not be applied.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum BitError {
+    /// Bit operations are only defined on integers.
+    NotAnInteger(Number),
+    /// A shift amount or `popcount` argument was negative.
+    Negative(Number),
+    /// A left shift lost bits, or a shift amount was at least 64.
+    Overflow,
+}
+
+impl Display for BitError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        match self {
+            BitError::NotAnInteger(value) => write!(f, "{value} is not an integer"),
+            BitError::Negative(value) => write!(f, "{value} is negative"),
+            BitError::Overflow => write!(f, "shift overflows a 64-bit integer"),
+        }
+    }
+}
+
+impl std::error::Error for BitError {}
+
+impl Number {
+    fn integer(self) -> Result<i64, BitError> {
+        match self {
+            Number::Rational(value) if value.is_integer() => Ok(value.to_integer()),
+            _ => Err(BitError::NotAnInteger(self)),
+        }
+    }
+
+    fn shift_amount(self) -> Result<u32, BitError> {
+        let amount = self.integer()?;
+        if amount < 0 {
+            return Err(BitError::Negative(self));
+        }
+        u32::try_from(amount)
+            .ok()
+            .filter(|amount| *amount < 64)
+            .ok_or(BitError::Overflow)
+    }
+
+    /// Bitwise operations act on the two's complement representation, like `i64`.
+    pub fn bit_and(self, other: Number) -> Result<Number, BitError> {
+        Ok(integer(self.integer()? & other.integer()?))
+    }
+
+    pub fn bit_or(self, other: Number) -> Result<Number, BitError> {
+        Ok(integer(self.integer()? | other.integer()?))
+    }
+
+    pub fn bit_xor(self, other: Number) -> Result<Number, BitError> {
+        Ok(integer(self.integer()? ^ other.integer()?))
+    }
+
+    /// Shifts left, failing rather than dropping bits.
+    pub fn shift_left(self, amount: Number) -> Result<Number, BitError> {
+        let (value, amount) = (self.integer()?, amount.shift_amount()?);
+        let shifted = value << amount;
+        if shifted >> amount == value {
+            Ok(integer(shifted))
+        } else {
+            Err(BitError::Overflow)
+        }
+    }
+
+    /// An arithmetic shift right, rounding towards negative infinity.
+    pub fn shift_right(self, amount: Number) -> Result<Number, BitError> {
+        Ok(integer(self.integer()? >> amount.shift_amount()?))
+    }
+
+    /// The number of set bits of a non-negative integer.
+    pub fn popcount(self) -> Result<Number, BitError> {
+        let value = self.integer()?;
+        if value < 0 {
+            return Err(BitError::Negative(self));
+        }
+        Ok(integer(value.count_ones() as i64))
+    }
+}
+
+fn integer(value: i64) -> Number {
+    Number::Rational(Fraction::from(value))
+}
@@ -0,0 +1,61 @@
+use std::cell::Cell;
+
+use super::cancel::{CancellationToken, Cancelled};
+
+/// How much of an iteration budget an operation consumed.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
+pub struct Budget {
+    pub used: usize,
+    pub limit: usize,
+    /// Whether the operation reached its goal, as opposed to stopping at the limit.
+    pub converged: bool,
+}
+
+impl Budget {
+    /// Whether the operation gave up because it ran out of budget.
+    pub fn exhausted(&self) -> bool {
+        !self.converged && self.used >= self.limit
+    }
+}
+
+/// Counts the steps of a recursive operation against a limit, checking a cancellation token
+/// with every step.
+pub(crate) struct Meter<'a> {
+    limit: usize,
+    used: Cell<usize>,
+    exhausted: Cell<bool>,
+    token: &'a CancellationToken,
+}
+
+impl<'a> Meter<'a> {
+    pub(crate) fn new(limit: usize, token: &'a CancellationToken) -> Self {
+        Meter {
+            limit,
+            used: Cell::new(0),
+            exhausted: Cell::new(false),
+            token,
+        }
+    }
+
+    /// Takes a step, returning whether any were left. `Err(Cancelled)` once the token has been
+    /// cancelled.
+    pub(crate) fn step(&self) -> Result<bool, Cancelled> {
+        self.token.check()?;
+        if self.used.get() >= self.limit {
+            self.exhausted.set(true);
+            return Ok(false);
+        }
+        self.used.set(self.used.get() + 1);
+        Ok(true)
+    }
+
+    /// The steps taken so far. The operation converged if it never ran out of them.
+    pub(crate) fn budget(&self) -> Budget {
+        Budget {
+            used: self.used.get(),
+            limit: self.limit,
+            converged: !self.exhausted.get(),
+        }
+    }
+}
@@ -0,0 +1,178 @@
+use std::ops::{Add, Div, Mul, Neg, Sub};
+
+use super::constants::Constant;
+use super::functions::Function;
+use super::parser::{self, negate};
+use super::terms::{Fraction, Number, Term};
+
+/// Builds a term from infix notation with the precedence of `Term::parse`, like
+/// `term!(3 * x^2 + 1/2)`. Identifiers become constants or variables, and calls like `sin(x)`
+/// and `root(x, 3)` become functions and roots.
+///
+/// Malformed input fails to compile, but unknown functions and wrong arities panic at runtime.
+#[macro_export]
+macro_rules! term {
+    // Operators between operands are passed on to the overloads for `Term`
+    (@expr [$($out:tt)*]) => { $($out)* };
+    (@expr [$($out:tt)*] + $($rest:tt)*) => { $crate::term!(@expr [$($out)* +] $($rest)*) };
+    (@expr [$($out:tt)*] - $($rest:tt)*) => { $crate::term!(@expr [$($out)* -] $($rest)*) };
+    (@expr [$($out:tt)*] * $($rest:tt)*) => { $crate::term!(@expr [$($out)* *] $($rest)*) };
+    (@expr [$($out:tt)*] / $($rest:tt)*) => { $crate::term!(@expr [$($out)* /] $($rest)*) };
+    (@expr [$($out:tt)*] $($rest:tt)+) => { $crate::term!(@atom [$($out)*] [] $($rest)+) };
+
+    // A chain of operands joined by `^`, which binds tighter than Rust's operators
+    (@atom [$($out:tt)*] [$($chain:tt)+] - $($rest:tt)+) => {
+        $crate::term!(@atom [$($out)*] [$($chain)+ -] $($rest)+)
+    };
+    (@atom [$($out:tt)*] [$($chain:tt)*] $name:ident ($($args:tt)*) $($rest:tt)*) => {
+        $crate::term!(@after [$($out)*] [$($chain)* ($crate::term!(@call $name [] [] $($args)*))] $($rest)*)
+    };
+    (@atom [$($out:tt)*] [$($chain:tt)*] $name:ident $($rest:tt)*) => {
+        $crate::term!(@after [$($out)*] [$($chain)* ($crate::math::builder::identifier(stringify!($name)))] $($rest)*)
+    };
+    (@atom [$($out:tt)*] [$($chain:tt)*] $value:literal $($rest:tt)*) => {
+        $crate::term!(@after [$($out)*] [$($chain)* ($crate::math::builder::literal(stringify!($value)))] $($rest)*)
+    };
+    (@atom [$($out:tt)*] [$($chain:tt)*] ($($inner:tt)+) $($rest:tt)*) => {
+        $crate::term!(@after [$($out)*] [$($chain)* ($crate::term!($($inner)+))] $($rest)*)
+    };
+    (@after [$($out:tt)*] [$($chain:tt)+] ^ $($rest:tt)+) => {
+        $crate::term!(@atom [$($out)*] [$($chain)+] $($rest)+)
+    };
+    (@after [$($out:tt)*] [$($chain:tt)+] $($rest:tt)*) => {
+        $crate::term!(@expr [$($out)* ($crate::term!(@power $($chain)+))] $($rest)*)
+    };
+
+    // `^` is right-associative, and a minus after it negates the rest of the chain
+    (@power $base:tt) => { $base };
+    (@power - $($rest:tt)+) => { (-$crate::term!(@power $($rest)+)) };
+    (@power $base:tt $($rest:tt)+) => { $base.pow($crate::term!(@power $($rest)+)) };
+
+    // Arguments of a call, split at top-level commas
+    (@call $name:ident [$($done:expr,)*] []) => {
+        $crate::math::builder::call(stringify!($name), vec![$($done),*])
+    };
+    (@call $name:ident [$($done:expr,)*] [$($arg:tt)+]) => {
+        $crate::math::builder::call(stringify!($name), vec![$($done,)* $crate::term!($($arg)+)])
+    };
+    (@call $name:ident [$($done:expr,)*] [$($arg:tt)+] , $($rest:tt)*) => {
+        $crate::term!(@call $name [$($done,)* $crate::term!($($arg)+),] [] $($rest)*)
+    };
+    (@call $name:ident [$($done:expr,)*] [$($arg:tt)*] $next:tt $($rest:tt)*) => {
+        $crate::term!(@call $name [$($done,)*] [$($arg)* $next] $($rest)*)
+    };
+
+    ($($tokens:tt)+) => { $crate::term!(@expr [] $($tokens)+) };
+}
+
+impl Term {
+    pub fn var(name: &str) -> Term {
+        Term::Variable(name.into())
+    }
+
+    pub fn pow(self, power: impl Into<Term>) -> Term {
+        Term::Exponentation(Box::new(self), Box::new(power.into()))
+    }
+
+    /// The `degree`th root, like `Term::var("x").root(3)` for the cube root of `x`.
+    pub fn root(self, degree: impl Into<Term>) -> Term {
+        Term::RootExtraction(Box::new(self), Box::new(degree.into()))
+    }
+
+    pub fn apply(function: Function, argument: impl Into<Term>) -> Term {
+        Term::Function(function, Box::new(argument.into()))
+    }
+}
+
+#[doc(hidden)]
+pub fn identifier(name: &str) -> Term {
+    match Constant::from_name(name) {
+        Some(constant) => Term::Constant(constant),
+        None => Term::var(name),
+    }
+}
+
+#[doc(hidden)]
+pub fn literal(value: &str) -> Term {
+    Term::Value(value.parse().expect("not a number literal"))
+}
+
+#[doc(hidden)]
+pub fn call(name: &str, arguments: Vec<Term>) -> Term {
+    parser::call(name.into(), arguments, 0).unwrap_or_else(|error| panic!("{error}"))
+}
+
+/// A number, converted to an exact fraction where possible like numbers parsed from text.
+impl From<f64> for Term {
+    fn from(value: f64) -> Self {
+        Term::Value(Number::from(value))
+    }
+}
+
+impl From<i64> for Term {
+    fn from(value: i64) -> Self {
+        Term::Value(Number::Rational(Fraction::from(value)))
+    }
+}
+
+impl From<Number> for Term {
+    fn from(value: Number) -> Self {
+        Term::Value(value)
+    }
+}
+
+impl From<Constant> for Term {
+    fn from(constant: Constant) -> Self {
+        Term::Constant(constant)
+    }
+}
+
+/// A variable with the given name.
+impl From<&str> for Term {
+    fn from(name: &str) -> Self {
+        Term::var(name)
+    }
+}
+
+impl Neg for Term {
+    type Output = Term;
+
+    fn neg(self) -> Term {
+        negate(self)
+    }
+}
+
+/// Implements an operator for `Term` with anything convertible into a term on the right, and for
+/// numbers with a term on the right.
+macro_rules! operator {
+    ($trait:ident, $method:ident, $variant:ident) => {
+        impl<T: Into<Term>> $trait<T> for Term {
+            type Output = Term;
+
+            fn $method(self, rhs: T) -> Term {
+                Term::$variant(Box::new(self), Box::new(rhs.into()))
+            }
+        }
+
+        impl $trait<Term> for f64 {
+            type Output = Term;
+
+            fn $method(self, rhs: Term) -> Term {
+                Term::from(self).$method(rhs)
+            }
+        }
+
+        impl $trait<Term> for i64 {
+            type Output = Term;
+
+            fn $method(self, rhs: Term) -> Term {
+                Term::from(self).$method(rhs)
+            }
+        }
+    };
+}
+
+operator!(Add, add, Addition);
+operator!(Sub, sub, Subtraction);
+operator!(Mul, mul, Multiplication);
+operator!(Div, div, Division);
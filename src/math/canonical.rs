@@ -0,0 +1,95 @@
+use std::cmp::Ordering;
+use std::convert::Infallible;
+use std::mem;
+
+use super::terms::Term;
+
+impl Term {
+    /// The term with the operands of every sum and product chain, and the elements of every set
+    /// literal, sorted by `canonical_cmp`. Terms that differ only in the order of commutative
+    /// children canonicalize to the same term, so their `Display` output and `fingerprint`
+    /// agree on every run and platform.
+    ///
+    /// Differences and quotients are not commutative and keep their operand order.
+    pub fn canonicalize(&self) -> Term {
+        self.try_map(|term| {
+            Ok::<_, Infallible>(match term {
+                Term::Addition(..) => sorted_chain(term, Term::Addition),
+                Term::Multiplication(..) => sorted_chain(term, Term::Multiplication),
+                mut term => {
+                    if let Term::Set(elements) = &mut term {
+                        elements.sort_by(Term::canonical_cmp);
+                    }
+                    term
+                }
+            })
+        })
+        .unwrap()
+    }
+
+    /// A total order on terms that depends only on their structure: numbers before constants,
+    /// variables and compound terms, numbers by their canonical value, variables by name, and
+    /// compound terms of the same kind by their children from left to right.
+    pub fn canonical_cmp(&self, other: &Term) -> Ordering {
+        rank(self)
+            .cmp(&rank(other))
+            .then_with(|| match (self, other) {
+                (Term::Value(lhs), Term::Value(rhs)) => lhs.cmp(rhs),
+                (Term::Constant(lhs), Term::Constant(rhs)) => (*lhs as u8).cmp(&(*rhs as u8)),
+                (Term::Variable(lhs), Term::Variable(rhs)) => lhs.cmp(rhs),
+                (Term::Function(lhs, _), Term::Function(rhs, _)) if lhs != rhs => {
+                    (*lhs as u8).cmp(&(*rhs as u8))
+                }
+                _ => {
+                    let (lhs, rhs) = (self.children(), other.children());
+                    lhs.iter()
+                        .zip(&rhs)
+                        .map(|(lhs, rhs)| lhs.canonical_cmp(rhs))
+                        .find(|ordering| ordering.is_ne())
+                        .unwrap_or_else(|| lhs.len().cmp(&rhs.len()))
+                }
+            })
+    }
+}
+
+fn rank(term: &Term) -> u8 {
+    match term {
+        Term::Value(_) => 0,
+        Term::Constant(_) => 1,
+        Term::Variable(_) => 2,
+        Term::Function(..) => 3,
+        Term::Exponentation(..) => 4,
+        Term::RootExtraction(..) => 5,
+        Term::Multiplication(..) => 6,
+        Term::Division(..) => 7,
+        Term::Addition(..) => 8,
+        Term::Subtraction(..) => 9,
+        Term::Index(..) => 10,
+        Term::Vector(_) => 11,
+        Term::List(_) => 12,
+        Term::Set(_) => 13,
+    }
+}
+
+/// Flattens a chain of nodes of the same kind as `term`, sorts the operands and rebuilds the
+/// chain left-associated with `combine`.
+fn sorted_chain(term: Term, combine: fn(Box<Term>, Box<Term>) -> Term) -> Term {
+    let kind = mem::discriminant(&term);
+    let (mut operands, mut pending) = (Vec::new(), vec![term]);
+    while let Some(term) = pending.pop() {
+        if mem::discriminant(&term) != kind {
+            operands.push(term);
+            continue;
+        }
+        match term.into_operands() {
+            Ok((lhs, rhs)) => pending.extend([lhs, rhs]),
+            Err(term) => operands.push(term),
+        }
+    }
+    operands.sort_by(Term::canonical_cmp);
+
+    operands
+        .into_iter()
+        .reduce(|acc, operand| combine(Box::new(acc), Box::new(operand)))
+        .unwrap()
+}
@@ -0,0 +1,164 @@
+#[cfg(feature = "parquet")]
+use std::io::Write;
+#[cfg(feature = "parquet")]
+use std::mem;
+use std::sync::Arc;
+
+use arrow_array::{Array, ArrayRef, Float64Array, RecordBatch};
+use arrow_schema::ArrowError;
+#[cfg(feature = "parquet")]
+use arrow_schema::{DataType, Field, Schema, SchemaRef};
+#[cfg(feature = "parquet")]
+use parquet::{arrow::ArrowWriter, errors::ParquetError};
+
+#[cfg(feature = "parquet")]
+use super::grid::Grid;
+use super::numeric::{self, DomainError};
+use super::terms::Term;
+
+/// Rows a `ParquetWriter` buffers before writing them as one row group.
+#[cfg(feature = "parquet")]
+pub const ROW_GROUP_ROWS: usize = 8192;
+
+/// The results as an Arrow array, with undefined values as nulls.
+pub fn results_array(values: &[Result<f64, DomainError>]) -> Float64Array {
+    values
+        .iter()
+        .map(|value| value.as_ref().ok().copied())
+        .collect()
+}
+
+/// A record batch with one nullable `f64` column of results per name in `header`, for handing
+/// results to Arrow-based dataframe libraries. Fails if `header` and `columns` differ in length
+/// or the columns differ in length.
+pub fn results_batch(
+    header: &[&str],
+    columns: &[&[Result<f64, DomainError>]],
+) -> Result<RecordBatch, ArrowError> {
+    if header.len() != columns.len() {
+        return Err(ArrowError::InvalidArgumentError(format!(
+            "expected {} columns, got {}",
+            header.len(),
+            columns.len()
+        )));
+    }
+    RecordBatch::try_from_iter(
+        header
+            .iter()
+            .zip(columns)
+            .map(|(name, column)| (name, Arc::new(results_array(column)) as ArrayRef)),
+    )
+}
+
+/// Evaluates `term` row by row over Arrow columns of variable values, like
+/// `numeric::evaluate_columns` on their value buffers, which are read in place. Rows where an
+/// input is null or the term is undefined are null. Returns `None` if the columns differ in
+/// length.
+pub fn evaluate_arrays(term: &Term, columns: &[(&str, &Float64Array)]) -> Option<Float64Array> {
+    let buffers: Vec<(&str, &[f64])> = (columns.iter())
+        .map(|(var, column)| (*var, &**column.values()))
+        .collect();
+    let values = numeric::evaluate_columns(term, &buffers)?;
+    Some(
+        (values.iter().enumerate())
+            .map(|(row, value)| match value {
+                Ok(value) if columns.iter().all(|(_, column)| column.is_valid(row)) => Some(*value),
+                _ => None,
+            })
+            .collect(),
+    )
+}
+
+/// Streams rows of evaluation results as a Parquet file with one nullable `f64` column per
+/// header field. Rows are buffered and written in row groups of `ROW_GROUP_ROWS`.
+#[cfg(feature = "parquet")]
+pub struct ParquetWriter<W: Write + Send> {
+    writer: ArrowWriter<W>,
+    schema: SchemaRef,
+    columns: Vec<Vec<Option<f64>>>,
+}
+
+#[cfg(feature = "parquet")]
+impl<W: Write + Send> ParquetWriter<W> {
+    pub fn new(out: W, header: &[&str]) -> Result<Self, ParquetError> {
+        let fields: Vec<Field> = header
+            .iter()
+            .map(|name| Field::new(*name, DataType::Float64, true))
+            .collect();
+        let schema = Arc::new(Schema::new(fields));
+        Ok(Self {
+            writer: ArrowWriter::try_new(out, schema.clone(), None)?,
+            schema,
+            columns: vec![Vec::new(); header.len()],
+        })
+    }
+
+    /// Writes one row, leaving undefined values null. Fails with `ParquetError::General` if the
+    /// row does not match the header.
+    pub fn write_row(&mut self, values: &[Result<f64, DomainError>]) -> Result<(), ParquetError> {
+        if values.len() != self.columns.len() {
+            return Err(ParquetError::General(format!(
+                "expected {} columns, got {}",
+                self.columns.len(),
+                values.len()
+            )));
+        }
+
+        for (column, value) in self.columns.iter_mut().zip(values) {
+            column.push(value.as_ref().ok().copied());
+        }
+        if self.columns.first().map_or(0, Vec::len) >= ROW_GROUP_ROWS {
+            self.flush()?;
+        }
+        Ok(())
+    }
+
+    /// Writes the buffered rows and the file footer, returning the output.
+    pub fn into_inner(mut self) -> Result<W, ParquetError> {
+        self.flush()?;
+        self.writer.into_inner()
+    }
+
+    fn flush(&mut self) -> Result<(), ParquetError> {
+        if self.columns.first().is_none_or(Vec::is_empty) {
+            return Ok(());
+        }
+        let arrays: Vec<ArrayRef> = (self.columns.iter_mut())
+            .map(|column| Arc::new(Float64Array::from(mem::take(column))) as ArrayRef)
+            .collect();
+        self.writer
+            .write(&RecordBatch::try_new(self.schema.clone(), arrays)?)?;
+        self.writer.flush()
+    }
+}
+
+#[cfg(feature = "parquet")]
+impl Term {
+    /// Like `write_samples_csv`, writing the `var` and `value` columns as Parquet.
+    pub fn write_samples_parquet(
+        &self,
+        var: &str,
+        xs: impl IntoIterator<Item = f64>,
+        out: impl Write + Send,
+    ) -> Result<(), ParquetError> {
+        let mut writer = ParquetWriter::new(out, &[var, "value"])?;
+        for x in xs {
+            writer.write_row(&[Ok(x), numeric::try_evaluate(self, &[(var, x)])])?;
+        }
+        writer.into_inner().map(drop)
+    }
+}
+
+#[cfg(feature = "parquet")]
+impl Grid {
+    /// Like `write_csv`, writing the `x`, `y` and `z` columns as Parquet.
+    pub fn write_parquet(&self, out: impl Write + Send) -> Result<(), ParquetError> {
+        let mut writer = ParquetWriter::new(out, &["x", "y", "z"])?;
+        for (row, y) in self.ys.iter().enumerate() {
+            for (col, x) in self.xs.iter().enumerate() {
+                writer.write_row(&[Ok(*x), Ok(*y), *self.values.get(row, col)])?;
+            }
+        }
+        writer.into_inner().map(drop)
+    }
+}
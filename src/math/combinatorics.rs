@@ -0,0 +1,139 @@
+use fraction::{CheckedAdd, CheckedMul, CheckedSub, One, Zero};
+
+use super::terms::Fraction;
+
+/// The harmonic number `H_n = 1 + 1/2 + ... + 1/n`, or `None` on overflow.
+pub fn harmonic(n: u32) -> Option<Fraction> {
+    (1..=n as i64).try_fold(Fraction::zero(), |sum, k| {
+        sum.checked_add(&Fraction::new(1, k))
+    })
+}
+
+/// The Bernoulli number `B_n` with the convention `B_1 = -1/2`, or `None` on overflow.
+pub fn bernoulli(n: u32) -> Option<Fraction> {
+    if n > 1 && n % 2 == 1 {
+        return Some(Fraction::zero());
+    }
+
+    // The Akiyama–Tanigawa algorithm, which yields B_1 = +1/2. Rows overflow long before they
+    // get large, so the row is not allocated for all of `n` up front.
+    let mut row: Vec<Fraction> = Vec::new();
+    for m in 0..=n as i64 {
+        row.push(Fraction::new(1, m + 1));
+        for j in (1..=m as usize).rev() {
+            let difference = row[j - 1].checked_sub(&row[j])?;
+            row[j - 1] = difference.checked_mul(&Fraction::from(j as i64))?;
+        }
+    }
+
+    Some(if n == 1 { -row[0] } else { row[0] })
+}
+
+/// The unsigned Stirling number of the first kind: permutations of `n` elements with `k`
+/// cycles. `None` on overflow.
+pub fn stirling_first(n: u32, k: u32) -> Option<i64> {
+    if k <= n && n - k < k {
+        // [n, n - d] = sum over j of <<d, j>> C(n + j, 2d)
+        return near_diagonal(n - k, |j| u64::from(n) + j);
+    }
+    stirling(n, k, |row, _| row as i64 - 1)
+}
+
+/// The Stirling number of the second kind: partitions of `n` elements into `k` non-empty
+/// subsets. `None` on overflow.
+pub fn stirling_second(n: u32, k: u32) -> Option<i64> {
+    if k == 1 && n > 0 {
+        return Some(1);
+    }
+    if k <= n && n - k < k {
+        // {n, n - d} = sum over j of <<d, j>> C(n + d - 1 - j, 2d)
+        let d = u64::from(n - k);
+        return near_diagonal(n - k, |j| u64::from(n) + d - 1 - j);
+    }
+    stirling(n, k, |_, column| column as i64)
+}
+
+/// Sums `<<d, j>> C(top(j), 2d)` over `j`, where `<<d, j>>` are the second-order Eulerian
+/// numbers. This gives the Stirling numbers `d` below the diagonal in `O(d^2)` steps, however
+/// large `n` is. Every term is positive and at most the sum, so an overflow in any of them means
+/// the result overflows too.
+fn near_diagonal(d: u32, top: impl Fn(u64) -> u64) -> Option<i64> {
+    // <<m, j>> = (j + 1) <<m - 1, j>> + (2m - 1 - j) <<m - 1, j - 1>>, starting from <<0, 0>> = 1
+    let mut eulerian = vec![1i64];
+    for m in 1..=i64::from(d) {
+        eulerian.push(0);
+        for j in (0..eulerian.len()).rev() {
+            let kept = (j as i64 + 1).checked_mul(eulerian[j])?;
+            let shifted = match j {
+                0 => 0,
+                _ => (2 * m - 1 - j as i64).checked_mul(eulerian[j - 1])?,
+            };
+            eulerian[j] = kept.checked_add(shifted)?;
+        }
+    }
+
+    eulerian
+        .into_iter()
+        .zip(0..)
+        .filter(|&(count, _)| count != 0)
+        .try_fold(0i64, |sum, (count, j)| {
+            let term = count.checked_mul(binomial(top(j), 2 * u64::from(d))?)?;
+            sum.checked_add(term)
+        })
+}
+
+/// Fills the triangle `s(n, k) = s(n - 1, k - 1) + factor(n, k) * s(n - 1, k)` row by row, up
+/// to column `k`. Only used when `k` is at most `n - k`; column 1 or 2 of both kinds overflows
+/// within the first 64 rows, so this stops early for large `n`.
+fn stirling(n: u32, k: u32, factor: impl Fn(usize, usize) -> i64) -> Option<i64> {
+    if k > n {
+        return Some(0);
+    }
+    if k == n {
+        return Some(1);
+    }
+    if k == 0 {
+        return Some(0);
+    }
+
+    let k = k as usize;
+    let mut row = vec![i64::one()];
+    for i in 1..=n as usize {
+        if row.len() <= k {
+            row.push(i64::zero());
+        }
+        for j in (1..row.len()).rev() {
+            let kept = factor(i, j).checked_mul(row[j])?;
+            row[j] = row[j - 1].checked_add(kept)?;
+        }
+        row[0] = 0;
+    }
+
+    Some(row[k])
+}
+
+/// The binomial coefficient `C(n, k)`: the ways to choose `k` of `n` things. `None` on overflow.
+pub fn binomial(n: u64, k: u64) -> Option<i64> {
+    if k > n {
+        return Some(0);
+    }
+
+    // Every partial product is C(n, i + 1) = C(n, i) (n - i) / (i + 1), which is at least 2^i,
+    // so the loop overflows after at most 63 steps
+    (0..k.min(n - k)).try_fold(1i64, |coefficient, i| {
+        let product = i128::from(coefficient) * i128::from(n - i);
+        i64::try_from(product / i128::from(i + 1)).ok()
+    })
+}
+
+/// The number of ways to arrange `k` of `n` things, `n (n - 1) ... (n - k + 1)`. `None` on
+/// overflow.
+pub fn permutations(n: u64, k: u64) -> Option<i64> {
+    if k > n {
+        return Some(0);
+    }
+
+    (0..k).try_fold(1i64, |product, i| {
+        product.checked_mul(i64::try_from(n - i).ok()?)
+    })
+}
@@ -0,0 +1,288 @@
+use super::functions::Function;
+use super::numeric::DomainError;
+use super::terms::Term;
+
+/// How many stack slots `CompiledExpr::eval` keeps on the call stack instead of the heap.
+const INLINE_STACK: usize = 32;
+/// How many inputs `CompiledExpr::eval_batch` runs each instruction over at once.
+const BLOCK: usize = 256;
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+enum Instruction {
+    Push(f64),
+    /// Pushes the input at this position.
+    Load(usize),
+    Add,
+    Sub,
+    Mul,
+    Div,
+    Pow,
+    Root,
+    Apply(Function),
+}
+
+fn binary(instruction: Instruction, lhs: f64, rhs: f64) -> f64 {
+    match instruction {
+        Instruction::Add => lhs + rhs,
+        Instruction::Sub => lhs - rhs,
+        Instruction::Mul => lhs * rhs,
+        Instruction::Div => lhs / rhs,
+        Instruction::Pow => lhs.powf(rhs),
+        Instruction::Root => lhs.powf(rhs.recip()),
+        _ => unreachable!("{instruction:?} is not a binary operation"),
+    }
+}
+
+/// A term compiled to a flat sequence of stack instructions, for evaluating the same term many
+/// times without walking it. Created by `Term::compile`.
+#[derive(Clone, Debug, PartialEq)]
+pub struct CompiledExpr {
+    code: Vec<Instruction>,
+    /// The most values on the stack at any point of `code`.
+    depth: usize,
+    arity: usize,
+}
+
+impl CompiledExpr {
+    /// How many inputs `eval` takes.
+    pub fn arity(&self) -> usize {
+        self.arity
+    }
+
+    /// The value of the term with the variables bound to `inputs`, in the order they were given
+    /// to `Term::compile`. Follows `numeric::evaluate_ieee`, so infinities and NaN propagate.
+    ///
+    /// Panics if `inputs` does not have exactly `arity` values.
+    pub fn eval(&self, inputs: &[f64]) -> f64 {
+        assert_eq!(inputs.len(), self.arity, "wrong number of inputs");
+        let (mut inline, mut heap) = ([0.0; INLINE_STACK], Vec::new());
+        let stack = if self.depth <= INLINE_STACK {
+            &mut inline[..]
+        } else {
+            heap.resize(self.depth, 0.0);
+            &mut heap[..]
+        };
+
+        let mut top = 0;
+        for &instruction in &self.code {
+            match instruction {
+                Instruction::Push(value) => {
+                    stack[top] = value;
+                    top += 1;
+                }
+                Instruction::Load(position) => {
+                    stack[top] = inputs[position];
+                    top += 1;
+                }
+                Instruction::Apply(function) => {
+                    let start = top - function.arity();
+                    stack[start] = function.evaluate_f64(&stack[start..top]);
+                    top = start + 1;
+                }
+                _ => {
+                    top -= 1;
+                    stack[top - 1] = binary(instruction, stack[top - 1], stack[top]);
+                }
+            }
+        }
+        stack[0]
+    }
+
+    /// The value at each of `inputs`, like `eval` but running every instruction over a block of
+    /// inputs at a time. The inner loops then work on plain arrays, which the compiler can
+    /// vectorize.
+    ///
+    /// Panics unless the term was compiled for exactly one variable.
+    pub fn eval_batch(&self, inputs: &[f64]) -> Vec<f64> {
+        assert_eq!(self.arity, 1, "batches bind exactly one variable");
+        let mut stack = vec![[0.0; BLOCK]; self.depth];
+        let mut results = Vec::with_capacity(inputs.len());
+
+        for block in inputs.chunks(BLOCK) {
+            let len = block.len();
+            let mut top = 0;
+            for &instruction in &self.code {
+                match instruction {
+                    Instruction::Push(value) => {
+                        stack[top][..len].fill(value);
+                        top += 1;
+                    }
+                    Instruction::Load(_) => {
+                        stack[top][..len].copy_from_slice(block);
+                        top += 1;
+                    }
+                    Instruction::Apply(function) if function.arity() == 1 => {
+                        for x in &mut stack[top - 1][..len] {
+                            *x = function.apply_f64(*x);
+                        }
+                    }
+                    Instruction::Apply(function) => {
+                        let start = top - function.arity();
+                        let mut arguments = vec![0.0; function.arity()];
+                        for i in 0..len {
+                            for (argument, column) in arguments.iter_mut().zip(&stack[start..top]) {
+                                *argument = column[i];
+                            }
+                            stack[start][i] = function.evaluate_f64(&arguments);
+                        }
+                        top = start + 1;
+                    }
+                    _ => {
+                        top -= 1;
+                        let (below, above) = stack.split_at_mut(top);
+                        let (lhs, rhs) = (&mut below[top - 1][..len], &above[0][..len]);
+                        match instruction {
+                            Instruction::Add => combine(lhs, rhs, |lhs, rhs| lhs + rhs),
+                            Instruction::Sub => combine(lhs, rhs, |lhs, rhs| lhs - rhs),
+                            Instruction::Mul => combine(lhs, rhs, |lhs, rhs| lhs * rhs),
+                            Instruction::Div => combine(lhs, rhs, |lhs, rhs| lhs / rhs),
+                            _ => combine(lhs, rhs, |lhs, rhs| binary(instruction, lhs, rhs)),
+                        }
+                    }
+                }
+            }
+            results.extend_from_slice(&stack[0][..len]);
+        }
+        results
+    }
+}
+
+/// Replaces each of `lhs` by `operation` of it and its counterpart in `rhs`.
+fn combine(lhs: &mut [f64], rhs: &[f64], operation: impl Fn(f64, f64) -> f64) {
+    for (lhs, &rhs) in lhs.iter_mut().zip(rhs) {
+        *lhs = operation(*lhs, rhs);
+    }
+}
+
+struct Compiler<'a> {
+    variables: &'a [&'a str],
+    code: Vec<Instruction>,
+    depth: usize,
+    max_depth: usize,
+}
+
+impl Compiler<'_> {
+    /// Emits a `Push` or `Load`.
+    fn push(&mut self, instruction: Instruction) {
+        self.code.push(instruction);
+        self.depth += 1;
+        self.max_depth = self.max_depth.max(self.depth);
+    }
+
+    /// Emits `instruction` after the code for both operands, folding it if both are constants.
+    fn binary(
+        &mut self,
+        instruction: Instruction,
+        lhs: &Term,
+        rhs: &Term,
+    ) -> Result<(), DomainError> {
+        self.term(lhs)?;
+        self.term(rhs)?;
+        self.depth -= 1;
+        if let [.., Instruction::Push(lhs), Instruction::Push(rhs)] = self.code[..] {
+            self.code.truncate(self.code.len() - 2);
+            self.code
+                .push(Instruction::Push(binary(instruction, lhs, rhs)));
+        } else {
+            self.code.push(instruction);
+        }
+        Ok(())
+    }
+
+    fn term(&mut self, term: &Term) -> Result<(), DomainError> {
+        match term {
+            Term::Value(value) => self.push(Instruction::Push(f64::from(*value))),
+            Term::Constant(constant) => self.push(Instruction::Push(constant.value())),
+            Term::Variable(name) => {
+                let position = self
+                    .variables
+                    .iter()
+                    .position(|var| *var == &**name)
+                    .ok_or(DomainError::UnboundVariable)?;
+                self.push(Instruction::Load(position));
+            }
+            Term::Addition(lhs, rhs) => self.binary(Instruction::Add, lhs, rhs)?,
+            Term::Subtraction(lhs, rhs) => self.binary(Instruction::Sub, lhs, rhs)?,
+            Term::Multiplication(lhs, rhs) => self.binary(Instruction::Mul, lhs, rhs)?,
+            Term::Division(lhs, rhs) => self.binary(Instruction::Div, lhs, rhs)?,
+            Term::Exponentation(base, power) => self.binary(Instruction::Pow, base, power)?,
+            Term::RootExtraction(radicand, degree) => {
+                self.binary(Instruction::Root, radicand, degree)?
+            }
+            Term::Function(function, argument) => {
+                let arguments = function.arguments(argument);
+                if arguments.len() != function.arity() {
+                    // Undefined, like `Function::evaluate_f64` with the wrong number of arguments
+                    self.push(Instruction::Push(f64::NAN));
+                    return Ok(());
+                }
+                for argument in arguments {
+                    self.term(argument)?;
+                }
+                self.depth -= arguments.len() - 1;
+                let start = self.code.len() - arguments.len();
+                let constants: Option<Vec<f64>> = (self.code[start..].iter())
+                    .map(|instruction| match instruction {
+                        Instruction::Push(value) => Some(*value),
+                        _ => None,
+                    })
+                    .collect();
+                match constants {
+                    Some(constants) => {
+                        self.code.truncate(start);
+                        self.code
+                            .push(Instruction::Push(function.evaluate_f64(&constants)));
+                    }
+                    None => self.code.push(Instruction::Apply(*function)),
+                }
+            }
+            Term::Index(collection, index) => {
+                let (Term::Vector(elements) | Term::List(elements)) = &**collection else {
+                    return Err(DomainError::NotAScalar);
+                };
+                self.term(index)?;
+                let Some(Instruction::Push(position)) = self.code.pop() else {
+                    return Err(DomainError::InvalidIndex);
+                };
+                self.depth -= 1;
+                if position.fract() != 0.0 || position < 0.0 {
+                    return Err(DomainError::InvalidIndex);
+                }
+                let element = elements
+                    .get(position as usize)
+                    .ok_or(DomainError::InvalidIndex)?;
+                self.term(element)?;
+            }
+            Term::Vector(_) | Term::List(_) | Term::Set(_) => return Err(DomainError::NotAScalar),
+        }
+        Ok(())
+    }
+}
+
+impl Term {
+    /// Compiles the term for `CompiledExpr::eval`, which binds `variables` to its inputs in order.
+    /// Subterms without variables are evaluated once, here.
+    ///
+    /// Fails with `UnboundVariable` for variables missing from `variables`, `NotAScalar` for
+    /// collections and `InvalidIndex` for indices that are invalid or depend on a variable.
+    pub fn compile(&self, variables: &[&str]) -> Result<CompiledExpr, DomainError> {
+        let mut compiler = Compiler {
+            variables,
+            code: Vec::new(),
+            depth: 0,
+            max_depth: 0,
+        };
+        compiler.term(self)?;
+        Ok(CompiledExpr {
+            code: compiler.code,
+            depth: compiler.max_depth,
+            arity: variables.len(),
+        })
+    }
+
+    /// The value at each of `inputs` for `var`, with the semantics of `numeric::evaluate_ieee`.
+    /// Compiles the term once and evaluates blocks of inputs with `CompiledExpr::eval_batch`.
+    pub fn evaluate_batch_f64(&self, var: &str, inputs: &[f64]) -> Result<Vec<f64>, DomainError> {
+        Ok(self.compile(&[var])?.eval_batch(inputs))
+    }
+}
@@ -0,0 +1,109 @@
+use super::constants::Constant;
+use super::functions::Function;
+use super::parser::BUILTIN_CALLS;
+use super::terms::EvalContext;
+
+/// What a `Completion` inserts. Completions are ordered by kind first, in declaration order.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub enum CompletionKind {
+    Variable,
+    Function,
+    Constant,
+}
+
+/// A name the parser accepts, for an editor to offer while the user has typed part of it.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Completion {
+    pub label: Box<str>,
+    pub kind: CompletionKind,
+    /// A short description, like `root(x, n)` for a function or `x = 1/2` for a variable.
+    pub detail: Box<str>,
+}
+
+/// Every name starting with `prefix`: the variables bound in `context`, the functions, including
+/// `sqrt`, `root`, `nPr` and `nCr`, and the constants. Sorted by kind, then by name.
+pub fn complete(prefix: &str, context: &EvalContext) -> Vec<Completion> {
+    let variables = context.iter().map(|(name, value)| Completion {
+        label: name.into(),
+        kind: CompletionKind::Variable,
+        detail: format!("{name} = {value}").into(),
+    });
+    let functions = Function::ALL
+        .into_iter()
+        .map(|function| (function.name(), function.parameters()))
+        .chain(BUILTIN_CALLS)
+        .map(|(name, parameters)| Completion {
+            label: name.into(),
+            kind: CompletionKind::Function,
+            detail: format!("{name}({parameters})").into(),
+        });
+    let constants = Constant::ALL.into_iter().map(|constant| Completion {
+        label: constant.name().into(),
+        kind: CompletionKind::Constant,
+        detail: format!("{} ≈ {}", constant.symbol(), constant.value()).into(),
+    });
+
+    let mut completions: Vec<Completion> = variables
+        .chain(functions)
+        .chain(constants)
+        .filter(|completion| completion.label.starts_with(prefix))
+        .collect();
+    completions.sort_by(|a, b| (a.kind, &a.label).cmp(&(b.kind, &b.label)));
+    completions
+}
+
+/// The names among `candidates` within a few edits of `name`, closest first and at most three,
+/// for "did you mean" hints. Swapping two adjacent characters counts as one edit, and names are
+/// never more than a third changed, so single letters get no suggestions.
+pub fn similar_names<'a>(
+    name: &str,
+    candidates: impl IntoIterator<Item = &'a str>,
+) -> Vec<Box<str>> {
+    let length = name.chars().count();
+    let allowed = (length / 3).max(1);
+    let mut similar: Vec<(usize, &str)> = candidates
+        .into_iter()
+        .filter(|&candidate| candidate != name)
+        .map(|candidate| (edit_distance(name, candidate), candidate))
+        .filter(|&(distance, _)| distance <= allowed && distance < length)
+        .collect();
+    similar.sort();
+    similar.dedup();
+    similar
+        .into_iter()
+        .take(3)
+        .map(|(_, candidate)| candidate.into())
+        .collect()
+}
+
+/// The names `call` accepts that `similar_names` suggests for an unknown function.
+pub(super) fn function_names() -> impl Iterator<Item = &'static str> {
+    Function::ALL
+        .into_iter()
+        .map(Function::name)
+        .chain(BUILTIN_CALLS.map(|(name, _)| name))
+}
+
+/// The optimal string alignment distance: insertions, deletions, substitutions and swaps of
+/// adjacent characters.
+fn edit_distance(a: &str, b: &str) -> usize {
+    let (a, b): (Vec<char>, Vec<char>) = (a.chars().collect(), b.chars().collect());
+    let mut rows = vec![vec![0; b.len() + 1]; a.len() + 1];
+    for (i, row) in rows.iter_mut().enumerate() {
+        row[0] = i;
+    }
+    rows[0] = (0..=b.len()).collect();
+    for i in 1..=a.len() {
+        for j in 1..=b.len() {
+            let cost = usize::from(a[i - 1] != b[j - 1]);
+            let mut distance = (rows[i - 1][j] + 1)
+                .min(rows[i][j - 1] + 1)
+                .min(rows[i - 1][j - 1] + cost);
+            if i > 1 && j > 1 && a[i - 1] == b[j - 2] && a[i - 2] == b[j - 1] {
+                distance = distance.min(rows[i - 2][j - 2] + 1);
+            }
+            rows[i][j] = distance;
+        }
+    }
+    rows[a.len()][b.len()]
+}
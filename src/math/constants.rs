@@ -0,0 +1,49 @@
+use std::f64::consts::{E, PI};
+
+/// A mathematical constant that stays symbolic until a term is evaluated numerically.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "serde", serde(rename_all = "lowercase"))]
+pub enum Constant {
+    Pi,
+    E,
+    /// The golden ratio `(1 + sqrt(5)) / 2`.
+    Phi,
+}
+
+impl Constant {
+    pub const ALL: [Constant; 3] = [Constant::Pi, Constant::E, Constant::Phi];
+
+    /// The name the parser and `Display` use, like `pi`.
+    pub fn name(self) -> &'static str {
+        match self {
+            Constant::Pi => "pi",
+            Constant::E => "e",
+            Constant::Phi => "phi",
+        }
+    }
+
+    /// The symbol for this constant, like `π`.
+    pub fn symbol(self) -> &'static str {
+        match self {
+            Constant::Pi => "π",
+            Constant::E => "e",
+            Constant::Phi => "φ",
+        }
+    }
+
+    /// Looks a constant up by its name or its symbol.
+    pub fn from_name(name: &str) -> Option<Constant> {
+        Constant::ALL
+            .into_iter()
+            .find(|constant| constant.name() == name || constant.symbol() == name)
+    }
+
+    pub fn value(self) -> f64 {
+        match self {
+            Constant::Pi => PI,
+            Constant::E => E,
+            Constant::Phi => 1.618_033_988_749_895,
+        }
+    }
+}
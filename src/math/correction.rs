@@ -0,0 +1,243 @@
+use std::collections::HashSet;
+
+use super::functions::Function;
+use super::parser::{self, Token, BUILTIN_CALLS};
+use super::terms::Term;
+
+/// How many fixes `suggest_corrections` combines at most.
+const MAX_FIXES: usize = 3;
+
+/// A repair for a common input mistake. Each one changes every place it applies to at once.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub enum Fix {
+    /// Writes `**` as `^`.
+    PowerOperator,
+    /// Closes the brackets left open at the end and drops closing ones without a partner.
+    BalanceBrackets,
+    /// Puts parentheses around the argument of a call written without them, so `sin 2x` becomes
+    /// `sin(2x)`.
+    CallParentheses,
+    /// Inserts `*` between adjacent operands, as in `2x` or `(x + 1)(x - 1)`.
+    ImplicitMultiplication,
+}
+
+impl Fix {
+    pub const ALL: [Fix; 4] = [
+        Fix::PowerOperator,
+        Fix::BalanceBrackets,
+        Fix::CallParentheses,
+        Fix::ImplicitMultiplication,
+    ];
+
+    /// `input` with the fix applied, or `None` if it does not apply anywhere.
+    pub fn apply(self, input: &str) -> Option<String> {
+        match self {
+            Fix::PowerOperator => input.contains("**").then(|| input.replace("**", "^")),
+            Fix::BalanceBrackets => balance(input),
+            Fix::CallParentheses => {
+                let tokens = Tokens::new(input)?;
+                let mut edits = Vec::new();
+                let mut i = 0;
+                while i + 1 < tokens.len() {
+                    if !tokens.is_function(i) || !tokens.is_operand(i + 1) {
+                        i += 1;
+                        continue;
+                    }
+                    // The argument runs over an implicit product like `2x`
+                    let mut last = i + 1;
+                    while last + 1 < tokens.len() && tokens.is_operand(last + 1) {
+                        last += 1;
+                    }
+                    edits.push((tokens.end(i), tokens.start(i + 1), "("));
+                    edits.push((tokens.end(last), tokens.end(last), ")"));
+                    i = last + 1;
+                }
+                edit(input, edits)
+            }
+            Fix::ImplicitMultiplication => {
+                let tokens = Tokens::new(input)?;
+                let edits = (1..tokens.len())
+                    .filter(|&i| tokens.implicit_product(i - 1, i))
+                    .map(|i| (tokens.end(i - 1), tokens.end(i - 1), "*"))
+                    .collect();
+                edit(input, edits)
+            }
+        }
+    }
+}
+
+/// An input that parses once `fixes` are applied to it, in order.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Correction {
+    pub input: String,
+    pub term: Term,
+    pub fixes: Vec<Fix>,
+}
+
+/// Corrected versions of an `input` that does not parse, combining up to `MAX_FIXES` fixes.
+/// Those needing the fewest fixes come first, and ties are broken by the order of `Fix`. Empty if
+/// `input` parses or no combination of fixes helps.
+pub fn suggest_corrections(input: &str) -> Vec<Correction> {
+    if Term::parse(input).is_ok() {
+        return Vec::new();
+    }
+
+    let mut corrections = Vec::new();
+    let mut seen = HashSet::from([input.to_string()]);
+    let mut candidates = vec![(input.to_string(), Vec::new())];
+    for _ in 0..MAX_FIXES {
+        let mut next = Vec::new();
+        for (candidate, fixes) in &candidates {
+            // Fixes are combined in the order of `Fix`, so every combination is tried once
+            let later = Fix::ALL
+                .into_iter()
+                .filter(|fix| fixes.last().is_none_or(|last| last < fix));
+            for fix in later {
+                let Some(fixed) = fix.apply(candidate) else {
+                    continue;
+                };
+                if !seen.insert(fixed.clone()) {
+                    continue;
+                }
+                let fixes = [fixes.as_slice(), &[fix]].concat();
+                match Term::parse(&fixed) {
+                    Ok(term) => corrections.push(Correction {
+                        input: fixed,
+                        term,
+                        fixes,
+                    }),
+                    Err(_) => next.push((fixed, fixes)),
+                }
+            }
+        }
+        candidates = next;
+    }
+
+    corrections.sort_by(|a, b| (a.fixes.len(), &a.fixes).cmp(&(b.fixes.len(), &b.fixes)));
+    corrections
+}
+
+fn balance(input: &str) -> Option<String> {
+    let (mut balanced, mut open) = (String::with_capacity(input.len()), Vec::new());
+    let mut quoted = false;
+    for c in input.chars() {
+        match c {
+            '`' => quoted = !quoted,
+            '(' | '[' | '{' if !quoted => open.push(c),
+            ')' | ']' | '}' if !quoted => {
+                if open.last().copied() != Some(partner(c)) {
+                    continue;
+                }
+                open.pop();
+            }
+            _ => {}
+        }
+        balanced.push(c);
+    }
+    balanced.extend(open.into_iter().rev().map(partner));
+    (balanced != input).then_some(balanced)
+}
+
+fn partner(bracket: char) -> char {
+    match bracket {
+        '(' => ')',
+        '[' => ']',
+        '{' => '}',
+        ')' => '(',
+        ']' => '[',
+        _ => '{',
+    }
+}
+
+/// Replaces the byte ranges `(start, end)` of `input` with their text.
+fn edit(input: &str, mut edits: Vec<(usize, usize, &str)>) -> Option<String> {
+    if edits.is_empty() {
+        return None;
+    }
+    let mut edited = input.to_string();
+    edits.sort_by_key(|&(start, end, _)| (start, end));
+    for (start, end, text) in edits.into_iter().rev() {
+        edited.replace_range(start..end, text);
+    }
+    Some(edited)
+}
+
+/// The tokens of an input along with where each one ends.
+pub(super) struct Tokens<'a> {
+    input: &'a str,
+    tokens: Vec<(Token, usize)>,
+}
+
+impl<'a> Tokens<'a> {
+    pub(super) fn new(input: &'a str) -> Option<Self> {
+        let tokens = parser::tokenize(input).ok()?;
+        Some(Self { input, tokens })
+    }
+
+    pub(super) fn len(&self) -> usize {
+        self.tokens.len()
+    }
+
+    pub(super) fn start(&self, i: usize) -> usize {
+        self.tokens[i].1
+    }
+
+    fn end(&self, i: usize) -> usize {
+        let start = self.start(i);
+        match &self.tokens[i].0 {
+            Token::Identifier(name) => start + name.len(),
+            Token::Quoted(name) => start + name.len() + 2,
+            Token::Symbol(c) => start + c.len_utf8(),
+            Token::Number(_) => {
+                let rest = &self.input[start..];
+                let len = rest
+                    .find(|c: char| !(c.is_ascii_digit() || c == '.' || c == '_'))
+                    .unwrap_or(rest.len());
+                start + len + parser::exponent_len(&rest[len..])
+            }
+        }
+    }
+
+    /// The bracket or operator at token `i`, if it is one.
+    pub(super) fn symbol(&self, i: usize) -> Option<char> {
+        match self.tokens[i].0 {
+            Token::Symbol(c) => Some(c),
+            _ => None,
+        }
+    }
+
+    /// Whether token `i` names a function that can be called, like `sin` or `sqrt`.
+    fn is_function(&self, i: usize) -> bool {
+        match &self.tokens[i].0 {
+            Token::Identifier(name) => {
+                Function::from_name(name).is_some()
+                    || BUILTIN_CALLS.iter().any(|(builtin, _)| builtin == &&**name)
+            }
+            _ => false,
+        }
+    }
+
+    /// Whether token `i` is a number or a name other than a function's.
+    fn is_operand(&self, i: usize) -> bool {
+        match &self.tokens[i].0 {
+            Token::Number(_) | Token::Quoted(_) => true,
+            Token::Identifier(_) => !self.is_function(i),
+            Token::Symbol(_) => false,
+        }
+    }
+
+    /// Whether tokens `lhs` and `rhs` are operands written next to each other.
+    pub(super) fn implicit_product(&self, lhs: usize, rhs: usize) -> bool {
+        let ends_operand =
+            self.is_operand(lhs) || matches!(self.tokens[lhs].0, Token::Symbol(')' | ']' | '}'));
+        let starts_operand = match &self.tokens[rhs].0 {
+            // `5P2` is `nPr(5, 2)`
+            Token::Identifier(name) if matches!(self.tokens[lhs].0, Token::Number(_)) => {
+                parser::selection_notation(name).is_none()
+            }
+            Token::Identifier(_) | Token::Number(_) | Token::Quoted(_) => true,
+            Token::Symbol(c) => *c == '(',
+        };
+        ends_operand && starts_operand
+    }
+}
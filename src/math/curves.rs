@@ -0,0 +1,115 @@
+use super::functions::Function;
+use super::numeric;
+use super::terms::Term;
+
+/// A plane curve `(x(param), y(param))`.
+#[derive(Clone, Debug)]
+pub struct ParametricCurve {
+    pub x: Term,
+    pub y: Term,
+    pub param: Box<str>,
+}
+
+impl ParametricCurve {
+    pub fn new(x: Term, y: Term, param: &str) -> Self {
+        Self {
+            x,
+            y,
+            param: param.into(),
+        }
+    }
+
+    /// The graph of `term` as the curve `(var, term)`.
+    pub fn from_graph(term: Term, var: &str) -> Self {
+        Self::new(Term::Variable(var.into()), term, var)
+    }
+
+    /// The point at parameter `t`, if both coordinates are finite.
+    pub fn point(&self, t: f64) -> Option<(f64, f64)> {
+        Some((
+            numeric::evaluate(&self.x, &self.param, t)?,
+            numeric::evaluate(&self.y, &self.param, t)?,
+        ))
+    }
+
+    /// `samples` evenly spaced points for `param` in `[t0, t1]`, skipping parameters where the
+    /// curve is undefined.
+    pub fn sample(&self, t0: f64, t1: f64, samples: usize) -> Vec<(f64, f64)> {
+        sample_parameters(t0, t1, samples)
+            .filter_map(|t| self.point(t))
+            .collect()
+    }
+
+    /// `dy/dx` as `(dy/dt) / (dx/dt)`, a term in `param`. Returns `None` if either coordinate has
+    /// no derivative in `param`. Where `dx/dt` is zero the slope is undefined.
+    pub fn slope(&self) -> Option<Term> {
+        let dx = self.x.derivative_simplified(&self.param)?;
+        let dy = self.y.derivative_simplified(&self.param)?;
+        Some(Term::Division(Box::new(dy), Box::new(dx)))
+    }
+}
+
+/// A curve `r(theta)` in polar coordinates.
+#[derive(Clone, Debug)]
+pub struct PolarCurve {
+    pub r: Term,
+    pub theta: Box<str>,
+}
+
+impl PolarCurve {
+    pub fn new(r: Term, theta: &str) -> Self {
+        Self {
+            r,
+            theta: theta.into(),
+        }
+    }
+
+    /// The cartesian point at angle `theta`, if the radius is finite.
+    pub fn point(&self, theta: f64) -> Option<(f64, f64)> {
+        let r = numeric::evaluate(&self.r, &self.theta, theta)?;
+        Some(polar_to_cartesian(r, theta))
+    }
+
+    /// `samples` evenly spaced angles in `[theta0, theta1]` as cartesian points.
+    pub fn sample(&self, theta0: f64, theta1: f64, samples: usize) -> Vec<(f64, f64)> {
+        sample_parameters(theta0, theta1, samples)
+            .filter_map(|theta| self.point(theta))
+            .collect()
+    }
+
+    /// The same curve as `(r(theta) cos(theta), r(theta) sin(theta))`, parametrized by `theta`.
+    pub fn to_parametric(&self) -> ParametricCurve {
+        let coordinate = |function| {
+            Term::Multiplication(
+                Box::new(self.r.clone()),
+                Box::new(Term::Function(
+                    function,
+                    Box::new(Term::Variable(self.theta.clone())),
+                )),
+            )
+        };
+        ParametricCurve::new(
+            coordinate(Function::Cos),
+            coordinate(Function::Sin),
+            &self.theta,
+        )
+    }
+}
+
+pub fn polar_to_cartesian(r: f64, theta: f64) -> (f64, f64) {
+    (r * theta.cos(), r * theta.sin())
+}
+
+/// The polar coordinates `(r, theta)` of a point, with `theta` in `(-pi, pi]`.
+pub fn cartesian_to_polar(x: f64, y: f64) -> (f64, f64) {
+    (x.hypot(y), y.atan2(x))
+}
+
+fn sample_parameters(t0: f64, t1: f64, samples: usize) -> impl Iterator<Item = f64> {
+    let step = if samples > 1 {
+        (t1 - t0) / (samples - 1) as f64
+    } else {
+        0.0
+    };
+    (0..samples).map(move |i| t0 + step * i as f64)
+}
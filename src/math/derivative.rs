@@ -0,0 +1,306 @@
+use fraction::{CheckedAdd, CheckedMul, CheckedSub, One, Zero};
+
+use super::budget::{Budget, Meter};
+use super::cancel::{CancellationToken, Cancelled};
+use super::functions::Function;
+use super::terms::{Fraction, Term};
+use super::values::{as_rational, integer, rational};
+
+impl Term {
+    /// The derivative with respect to `var` by the sum, product, quotient, power and chain rules.
+    /// Collections are differentiated element by element, and `floor` and `ceil` have derivative
+    /// zero away from their steps.
+    ///
+    /// Returns `None` for indices and sequences like `prime(n)` that depend on `var`. Trivial
+    /// factors and summands are dropped along the way, but the result is not simplified
+    /// otherwise.
+    pub fn derivative(&self, var: &str) -> Option<Term> {
+        self.derivative_cancellable(var, &CancellationToken::new())
+            .unwrap()
+    }
+
+    /// `derivative` followed by `simplify`, so constants are folded and the zero and unit
+    /// factors the product and chain rules produce are gone before differentiating again.
+    pub fn derivative_simplified(&self, var: &str) -> Option<Term> {
+        self.derivative(var).map(|derivative| derivative.simplify())
+    }
+
+    /// The `n`-th derivative with respect to `var`, simplified after every order. The zeroth
+    /// derivative is the term itself. Use `Derivatives` to keep the lower orders around.
+    pub fn nth_derivative(&self, var: &str, n: usize) -> Option<Term> {
+        Derivatives::new(self.clone(), var).nth(n).cloned()
+    }
+
+    /// Like `derivative`, but aborts with `Err(Cancelled)` once `token` is cancelled.
+    pub fn derivative_cancellable(
+        &self,
+        var: &str,
+        token: &CancellationToken,
+    ) -> Result<Option<Term>, Cancelled> {
+        self.derivative_metered(var, usize::MAX, token)
+            .map(|(derivative, _)| derivative)
+    }
+
+    /// Like `derivative`, giving up with `None` after differentiating `limit` subterms. The
+    /// budget tells this apart from terms without a derivative.
+    pub fn derivative_with_budget(&self, var: &str, limit: usize) -> (Option<Term>, Budget) {
+        self.derivative_metered(var, limit, &CancellationToken::new())
+            .unwrap()
+    }
+
+    pub(crate) fn derivative_metered(
+        &self,
+        var: &str,
+        limit: usize,
+        token: &CancellationToken,
+    ) -> Result<(Option<Term>, Budget), Cancelled> {
+        let meter = Meter::new(limit, token);
+        let derivative = match self.derive(var, &meter) {
+            Ok(derivative) => Some(derivative),
+            Err(Failure::Undefined | Failure::Exhausted) => None,
+            Err(Failure::Cancelled) => return Err(Cancelled),
+        };
+        Ok((derivative, meter.budget()))
+    }
+
+    fn derive(&self, var: &str, meter: &Meter) -> Result<Term, Failure> {
+        if !meter.step()? {
+            return Err(Failure::Exhausted);
+        }
+        if !self.depends_on(var) && !self.is_collection() {
+            return Ok(integer(0));
+        }
+
+        let d = |term: &Term| term.derive(var, meter);
+        let each = |terms: &[Term]| terms.iter().map(d).collect::<Result<Vec<_>, _>>();
+
+        Ok(match self {
+            Term::Value(_) | Term::Constant(_) => integer(0),
+            Term::Variable(name) => integer(if &**name == var { 1 } else { 0 }),
+            Term::Addition(lhs, rhs) => sum(d(lhs)?, d(rhs)?),
+            Term::Subtraction(lhs, rhs) => difference(d(lhs)?, d(rhs)?),
+            Term::Multiplication(lhs, rhs) => sum(
+                product(d(lhs)?, (**rhs).clone()),
+                product((**lhs).clone(), d(rhs)?),
+            ),
+            Term::Division(lhs, rhs) => quotient(
+                difference(
+                    product(d(lhs)?, (**rhs).clone()),
+                    product((**lhs).clone(), d(rhs)?),
+                ),
+                power((**rhs).clone(), integer(2)),
+            ),
+            Term::Exponentation(base, exponent) if !exponent.depends_on(var) => {
+                // n * b^(n - 1) * b'
+                product(
+                    product(
+                        (**exponent).clone(),
+                        power(
+                            (**base).clone(),
+                            difference((**exponent).clone(), integer(1)),
+                        ),
+                    ),
+                    d(base)?,
+                )
+            }
+            Term::Exponentation(base, exponent) if !base.depends_on(var) => {
+                // c^g * ln(c) * g'
+                product(
+                    product(self.clone(), apply(Function::Ln, (**base).clone())),
+                    d(exponent)?,
+                )
+            }
+            Term::Exponentation(base, exponent) => {
+                // f^g * (g' * ln(f) + g * f' / f)
+                product(
+                    self.clone(),
+                    sum(
+                        product(d(exponent)?, apply(Function::Ln, (**base).clone())),
+                        quotient(product((**exponent).clone(), d(base)?), (**base).clone()),
+                    ),
+                )
+            }
+            Term::RootExtraction(radicand, degree) if !degree.depends_on(var) => {
+                // a' / (n * root(a, n)^(n - 1))
+                quotient(
+                    d(radicand)?,
+                    product(
+                        (**degree).clone(),
+                        power(self.clone(), difference((**degree).clone(), integer(1))),
+                    ),
+                )
+            }
+            Term::RootExtraction(radicand, degree) => Term::Exponentation(
+                radicand.clone(),
+                Box::new(Term::Division(Box::new(integer(1)), degree.clone())),
+            )
+            .derive(var, meter)?,
+            Term::Function(function, argument) if function.is_discrete() => {
+                if argument.depends_on(var) {
+                    return Err(Failure::Undefined);
+                }
+                integer(0)
+            }
+            Term::Function(function, argument) => {
+                let (u, du) = ((**argument).clone(), d(argument)?);
+                match function {
+                    Function::Sin => product(apply(Function::Cos, u), du),
+                    Function::Cos => product(product(integer(-1), apply(Function::Sin, u)), du),
+                    Function::Tan => quotient(du, power(apply(Function::Cos, u), integer(2))),
+                    Function::Ln => quotient(du, u),
+                    Function::Exp => product(self.clone(), du),
+                    Function::Abs => quotient(product(du, u), self.clone()),
+                    Function::Floor | Function::Ceil => integer(0),
+                    _ => unreachable!("discrete functions are handled above"),
+                }
+            }
+            Term::Vector(elements) => Term::Vector(each(elements)?),
+            Term::List(elements) => Term::List(each(elements)?),
+            Term::Set(elements) => Term::Set(each(elements)?),
+            Term::Index(collection, index) if !index.depends_on(var) => {
+                Term::Index(Box::new(d(collection)?), index.clone())
+            }
+            Term::Index(..) => return Err(Failure::Undefined),
+        })
+    }
+
+    fn is_collection(&self) -> bool {
+        matches!(self, Term::Vector(_) | Term::List(_) | Term::Set(_))
+    }
+}
+
+/// The successive derivatives of a term with respect to one variable, each simplified and
+/// computed only once, for the Taylor coefficients or curvature that need several orders.
+#[derive(Clone, Debug)]
+pub struct Derivatives {
+    var: Box<str>,
+    /// The orders computed so far, starting with the term itself.
+    orders: Vec<Term>,
+    /// Set once an order has no derivative, so neither has any above it.
+    exhausted: bool,
+}
+
+impl Derivatives {
+    pub fn new(term: Term, var: &str) -> Self {
+        Self {
+            var: var.into(),
+            orders: vec![term],
+            exhausted: false,
+        }
+    }
+
+    pub fn var(&self) -> &str {
+        &self.var
+    }
+
+    /// The `n`-th derivative, computing the orders below it that are not cached yet.
+    pub fn nth(&mut self, n: usize) -> Option<&Term> {
+        while self.orders.len() <= n {
+            if self.exhausted {
+                return None;
+            }
+            match self.orders.last().unwrap().derivative_simplified(&self.var) {
+                Some(derivative) => self.orders.push(derivative),
+                None => {
+                    self.exhausted = true;
+                    return None;
+                }
+            }
+        }
+        self.orders.get(n)
+    }
+
+    /// The derivatives already computed, lowest order first.
+    pub fn cached(&self) -> &[Term] {
+        &self.orders
+    }
+}
+
+/// Why `derive` stopped without a derivative.
+enum Failure {
+    /// The term has no derivative, and `derivative` returns `None`.
+    Undefined,
+    /// The budget ran out.
+    Exhausted,
+    Cancelled,
+}
+
+impl From<Cancelled> for Failure {
+    fn from(_: Cancelled) -> Self {
+        Failure::Cancelled
+    }
+}
+
+fn apply(function: Function, argument: Term) -> Term {
+    Term::Function(function, Box::new(argument))
+}
+
+fn is(term: &Term, value: impl Fn(&Fraction) -> bool) -> bool {
+    as_rational(term).is_some_and(|term| value(&term))
+}
+
+/// `op` applied to two rational constants, unless it overflows.
+fn fold(lhs: &Term, rhs: &Term, op: fn(&Fraction, &Fraction) -> Option<Fraction>) -> Option<Term> {
+    Some(rational(op(&as_rational(lhs)?, &as_rational(rhs)?)?))
+}
+
+fn sum(lhs: Term, rhs: Term) -> Term {
+    if let Some(value) = fold(&lhs, &rhs, CheckedAdd::checked_add) {
+        return value;
+    }
+    if is(&lhs, Fraction::is_zero) {
+        rhs
+    } else if is(&rhs, Fraction::is_zero) {
+        lhs
+    } else {
+        Term::Addition(Box::new(lhs), Box::new(rhs))
+    }
+}
+
+fn difference(lhs: Term, rhs: Term) -> Term {
+    if let Some(value) = fold(&lhs, &rhs, CheckedSub::checked_sub) {
+        return value;
+    }
+    if is(&rhs, Fraction::is_zero) {
+        lhs
+    } else if is(&lhs, Fraction::is_zero) {
+        product(integer(-1), rhs)
+    } else {
+        Term::Subtraction(Box::new(lhs), Box::new(rhs))
+    }
+}
+
+fn product(lhs: Term, rhs: Term) -> Term {
+    if let Some(value) = fold(&lhs, &rhs, CheckedMul::checked_mul) {
+        return value;
+    }
+    if is(&lhs, Fraction::is_zero) || is(&rhs, Fraction::is_zero) {
+        integer(0)
+    } else if is(&lhs, Fraction::is_one) {
+        rhs
+    } else if is(&rhs, Fraction::is_one) {
+        lhs
+    } else {
+        Term::Multiplication(Box::new(lhs), Box::new(rhs))
+    }
+}
+
+fn quotient(lhs: Term, rhs: Term) -> Term {
+    if is(&lhs, Fraction::is_zero) {
+        integer(0)
+    } else if is(&rhs, Fraction::is_one) {
+        lhs
+    } else {
+        Term::Division(Box::new(lhs), Box::new(rhs))
+    }
+}
+
+fn power(base: Term, exponent: Term) -> Term {
+    if is(&exponent, Fraction::is_zero) {
+        integer(1)
+    } else if is(&exponent, Fraction::is_one) {
+        base
+    } else {
+        Term::Exponentation(Box::new(base), Box::new(exponent))
+    }
+}
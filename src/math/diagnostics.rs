@@ -0,0 +1,63 @@
+use std::cell::RefCell;
+
+use super::terms::Term;
+
+/// Why an evaluation stopped being exact.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum FallbackReason {
+    /// A rational was raised to a non-integer rational power (including roots), which is
+    /// computed in floating point.
+    NonIntegerPower,
+    /// The term contains an `Irrational` literal.
+    IrrationalLiteral,
+    /// An elementary function without a known exact value at its argument, like `sin(1)`, which
+    /// is computed in floating point.
+    Transcendental,
+    /// A symbolic constant like `pi`, which is replaced by its floating-point value.
+    Constant,
+    /// An exact result too large for a `Fraction`, which is rounded to the nearest float.
+    Overflow,
+}
+
+/// A place where evaluation left exact arithmetic.
+#[derive(Clone, Debug)]
+pub struct FallbackEvent {
+    pub reason: FallbackReason,
+    pub subterm: Term,
+}
+
+thread_local! {
+    static EVENTS: RefCell<Option<Vec<FallbackEvent>>> = const { RefCell::new(None) };
+}
+
+/// Runs `f`, collecting every fallback-to-float event raised by evaluations on this thread.
+/// Events recorded in a nested call are also passed on to the enclosing one.
+///
+/// Recording is off unless a `record_fallbacks` call is active, so evaluation outside of it pays
+/// nothing beyond a thread-local check.
+pub fn record_fallbacks<R>(f: impl FnOnce() -> R) -> (R, Vec<FallbackEvent>) {
+    let outer = EVENTS.with(|events| events.borrow_mut().replace(Vec::new()));
+    let result = f();
+    let recorded = EVENTS.with(|events| {
+        let mut events = events.borrow_mut();
+        let recorded = events.take().unwrap_or_default();
+        *events = outer.map(|mut outer| {
+            outer.extend(recorded.iter().cloned());
+            outer
+        });
+        recorded
+    });
+
+    (result, recorded)
+}
+
+pub(crate) fn report(reason: FallbackReason, subterm: impl FnOnce() -> Term) {
+    EVENTS.with(|events| {
+        if let Some(events) = events.borrow_mut().as_mut() {
+            events.push(FallbackEvent {
+                reason,
+                subterm: subterm(),
+            });
+        }
+    });
+}
@@ -0,0 +1,61 @@
+use super::numeric::{self, DomainError};
+use super::terms::{EvalContext, EvalError, Fraction, Number, Term};
+use super::verify::{Samples, SEED};
+
+/// An assignment under which the exact evaluator and the `f64` reference disagree.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Discrepancy {
+    pub assignment: Vec<(Box<str>, Fraction)>,
+    /// The result of `Term::evaluate_in`.
+    pub exact: Result<Number, EvalError>,
+    /// The result of `numeric::try_evaluate`.
+    pub reference: Result<f64, DomainError>,
+}
+
+/// Evaluates `term` on `samples` pseudo-random rational assignments of its variables, both with
+/// `Term::evaluate_in` and with plain `f64` arithmetic, and returns every assignment where the
+/// results differ by more than `tolerance` relative to their magnitude.
+///
+/// Both sides failing counts as agreement, since `f64` arithmetic reports a division by zero or
+/// a root of a negative number only as a non-finite result.
+pub fn differential_test(term: &Term, samples: usize, tolerance: f64) -> Vec<Discrepancy> {
+    let variables = term.variables();
+    let mut rng = Samples(SEED);
+    let mut discrepancies = Vec::new();
+
+    for _ in 0..samples {
+        let assignment: Vec<(Box<str>, Fraction)> = variables
+            .iter()
+            .map(|name| (Box::from(*name), rng.rational()))
+            .collect();
+
+        let mut context = EvalContext::new();
+        for (name, value) in &assignment {
+            context.set(name, Number::Rational(*value));
+        }
+        let bindings: Vec<(&str, f64)> = assignment
+            .iter()
+            .map(|(name, value)| (&**name, f64::from(Number::Rational(*value))))
+            .collect();
+
+        let exact = term.evaluate_in(&context);
+        let reference = numeric::try_evaluate(term, &bindings);
+        let agree = match (&exact, &reference) {
+            (Err(_), Err(_)) => true,
+            (Ok(exact), Ok(reference)) => {
+                let (a, b) = (f64::from(*exact), *reference);
+                a == b || (a - b).abs() <= tolerance * a.abs().max(b.abs()).max(1.0)
+            }
+            _ => false,
+        };
+        if !agree {
+            discrepancies.push(Discrepancy {
+                assignment,
+                exact,
+                reference,
+            });
+        }
+    }
+
+    discrepancies
+}
@@ -0,0 +1,149 @@
+use std::fmt::{self, Display, Formatter};
+
+use super::constants::Constant;
+use super::terms::{Fraction, Number, Term};
+use super::values::{absolute, is_minus_one, is_negative, negated, reciprocal};
+
+pub(super) const SUM: u8 = 1;
+pub(super) const PRODUCT: u8 = 2;
+pub(super) const UNARY: u8 = 3;
+pub(super) const POWER: u8 = 4;
+pub(super) const ATOM: u8 = 5;
+
+fn precedence(term: &Term) -> u8 {
+    match term {
+        Term::Value(value) => match value {
+            Number::Rational(value) if !value.is_integer() => PRODUCT,
+            value if is_negative(value) => UNARY,
+            _ => ATOM,
+        },
+        Term::Addition(..) | Term::Subtraction(..) => SUM,
+        Term::Multiplication(..) if negated(term).is_some() => UNARY,
+        Term::Multiplication(..) | Term::Division(..) => PRODUCT,
+        Term::Exponentation(..) => POWER,
+        _ => ATOM,
+    }
+}
+
+/// Writes `term`, parenthesized unless its precedence is at least `min`.
+fn operand(f: &mut Formatter<'_>, term: &Term, min: u8) -> fmt::Result {
+    if precedence(term) >= min {
+        write!(f, "{term}")
+    } else {
+        write!(f, "({term})")
+    }
+}
+
+fn elements(f: &mut Formatter<'_>, elements: &[Term]) -> fmt::Result {
+    for (i, element) in elements.iter().enumerate() {
+        if i > 0 {
+            write!(f, ", ")?;
+        }
+        write!(f, "{element}")?;
+    }
+    Ok(())
+}
+
+/// Infix notation with as few parentheses as `Term::parse` needs to read it back, e.g. `a*b + c`.
+/// The desugared forms `a + (-1)*b`, `a * b^-1` and `(-1) * a` are printed as `a - b`, `a/b` and
+/// `-a` too.
+///
+/// Variables named like constants are quoted, as in `` `e` ``, so that they read back as
+/// variables. Floats read back as the rationals they print as where those fit, non-finite floats
+/// print as `NaN`, `inf` and `-inf`, which do not read back, and neither do variables whose names
+/// are not identifiers unless they are quoted by hand.
+impl Display for Term {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        match self {
+            Term::Value(value) => write!(f, "{value}"),
+            Term::Variable(name) if Constant::from_name(name).is_some() => write!(f, "`{name}`"),
+            Term::Variable(name) => write!(f, "{name}"),
+            Term::Constant(constant) => write!(f, "{}", constant.name()),
+            Term::Addition(lhs, rhs) => {
+                operand(f, lhs, SUM)?;
+                match &**rhs {
+                    Term::Value(value) if is_negative(value) => {
+                        write!(f, " - {}", absolute(value))
+                    }
+                    rhs => match negated(rhs) {
+                        Some(rhs) => {
+                            write!(f, " - ")?;
+                            operand(f, rhs, PRODUCT)
+                        }
+                        None => {
+                            write!(f, " + ")?;
+                            operand(f, rhs, PRODUCT)
+                        }
+                    },
+                }
+            }
+            Term::Subtraction(lhs, rhs) => {
+                operand(f, lhs, SUM)?;
+                write!(f, " - ")?;
+                operand(f, rhs, PRODUCT)
+            }
+            Term::Division(lhs, rhs) => {
+                operand(f, lhs, PRODUCT)?;
+                write!(f, "/")?;
+                operand(f, rhs, UNARY)
+            }
+            Term::Multiplication(lhs, rhs) => {
+                if is_minus_one(lhs) {
+                    write!(f, "-")?;
+                    return operand(f, rhs, UNARY);
+                }
+
+                operand(f, lhs, PRODUCT)?;
+                match reciprocal(rhs) {
+                    Some(divisor) => {
+                        write!(f, "/")?;
+                        operand(f, divisor, UNARY)
+                    }
+                    None => {
+                        write!(f, "*")?;
+                        operand(f, rhs, UNARY)
+                    }
+                }
+            }
+            Term::Exponentation(base, power) => {
+                operand(f, base, ATOM)?;
+                write!(f, "^")?;
+                operand(f, power, UNARY)
+            }
+            Term::RootExtraction(radicand, degree) => {
+                if **degree == Term::Value(Number::Rational(Fraction::from(2))) {
+                    write!(f, "sqrt({radicand})")
+                } else {
+                    write!(f, "root({radicand}, {degree})")
+                }
+            }
+            Term::Function(function, argument) => {
+                write!(f, "{}(", function.name())?;
+                elements(f, function.arguments(argument))?;
+                write!(f, ")")
+            }
+            Term::Vector(items) => {
+                write!(f, "[")?;
+                elements(f, items)?;
+                write!(f, "]")
+            }
+            Term::List(items) => {
+                write!(f, "(")?;
+                elements(f, items)?;
+                if items.len() == 1 {
+                    write!(f, ",")?;
+                }
+                write!(f, ")")
+            }
+            Term::Set(items) => {
+                write!(f, "{{")?;
+                elements(f, items)?;
+                write!(f, "}}")
+            }
+            Term::Index(collection, index) => {
+                operand(f, collection, ATOM)?;
+                write!(f, "[{index}]")
+            }
+        }
+    }
+}
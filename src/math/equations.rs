@@ -0,0 +1,261 @@
+use std::fmt::{self, Display, Formatter};
+
+use fraction::Zero;
+
+use super::cancel::{CancellationToken, Cancelled};
+use super::terms::{Fraction, Number, Term};
+use super::values::{integer, is_minus_one};
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct Equation {
+    pub lhs: Term,
+    pub rhs: Term,
+}
+
+impl Display for Equation {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        write!(f, "{} = {}", self.lhs, self.rhs)
+    }
+}
+
+/// An inverse operation applied to both sides of an equation.
+#[derive(Clone, Debug, PartialEq)]
+pub enum Operation {
+    SwapSides,
+    Add(Term),
+    Subtract(Term),
+    Multiply(Term),
+    Divide(Term),
+    Reciprocal,
+    Root(Term),
+    Raise(Term),
+}
+
+impl Display for Operation {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        match self {
+            Operation::SwapSides => write!(f, "swap sides"),
+            Operation::Add(term) => write!(f, "add {term} to both sides"),
+            Operation::Subtract(term) => write!(f, "subtract {term} from both sides"),
+            Operation::Multiply(term) => write!(f, "multiply both sides by {term}"),
+            Operation::Divide(term) => write!(f, "divide both sides by {term}"),
+            Operation::Reciprocal => write!(f, "take the reciprocal of both sides"),
+            Operation::Root(degree) => write!(f, "take root {degree} of both sides"),
+            Operation::Raise(power) => write!(f, "raise both sides to the power {power}"),
+        }
+    }
+}
+
+/// An operation together with the equation it produced.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Step {
+    pub operation: Operation,
+    pub equation: Equation,
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub enum IsolationError {
+    /// The variable does not occur in the equation.
+    NotFound,
+    /// The variable occurs more than once, so no single inverse operation isolates it.
+    Repeated,
+    /// The variable sits inside something without an inverse here, like an exponent or an
+    /// index.
+    Unsupported(Term),
+    /// Isolating the variable would divide both sides by zero.
+    DivisionByZero,
+}
+
+impl Display for IsolationError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        match self {
+            IsolationError::NotFound => write!(f, "the variable does not occur"),
+            IsolationError::Repeated => write!(f, "the variable occurs more than once"),
+            IsolationError::Unsupported(term) => write!(f, "cannot invert `{term}`"),
+            IsolationError::DivisionByZero => write!(f, "isolating would divide by zero"),
+        }
+    }
+}
+
+impl std::error::Error for IsolationError {}
+
+impl Equation {
+    pub fn new(lhs: Term, rhs: Term) -> Self {
+        Self { lhs, rhs }
+    }
+
+    /// Solves for `var` the way it is done by hand, peeling off the outermost operation around
+    /// `var` with its inverse until `var` stands alone on the left. Returns every step taken;
+    /// the last one holds the solution. Constant right-hand sides are folded after each step.
+    ///
+    /// Roots of even degree only give the principal solution, so `x^2 = 4` yields `x = 2`.
+    pub fn isolate(&self, var: &str) -> Result<Vec<Step>, IsolationError> {
+        self.isolate_cancellable(var, &CancellationToken::new())
+            .unwrap()
+    }
+
+    /// Like `isolate`, but aborts with `Err(Cancelled)` once `token` is cancelled.
+    pub fn isolate_cancellable(
+        &self,
+        var: &str,
+        token: &CancellationToken,
+    ) -> Result<Result<Vec<Step>, IsolationError>, Cancelled> {
+        let mut steps = Vec::new();
+        let mut equation = match (self.lhs.depends_on(var), self.rhs.depends_on(var)) {
+            (true, true) => return Ok(Err(IsolationError::Repeated)),
+            (false, false) => return Ok(Err(IsolationError::NotFound)),
+            (true, false) => self.clone(),
+            (false, true) => {
+                let equation = Equation::new(self.rhs.clone(), self.lhs.clone());
+                steps.push(Step {
+                    operation: Operation::SwapSides,
+                    equation: equation.clone(),
+                });
+                equation
+            }
+        };
+
+        while !matches!(&equation.lhs, Term::Variable(name) if &**name == var) {
+            token.check()?;
+            let (operation, lhs) = match invert(&equation.lhs, var) {
+                Ok(inverted) => inverted,
+                Err(error) => return Ok(Err(error)),
+            };
+            let rhs = fold(apply(&operation, equation.rhs));
+            equation = Equation::new(lhs, rhs);
+            steps.push(Step {
+                operation,
+                equation: equation.clone(),
+            });
+        }
+
+        Ok(Ok(steps))
+    }
+
+    /// Solves for `symbol` and returns its value as a term in the remaining symbols, like `a =
+    /// (v - u)/t` from `v = u + a*t`. `symbol` must occur exactly once, as for `isolate`.
+    pub fn rearrange_for(&self, symbol: &str) -> Result<Term, IsolationError> {
+        match self.isolate(symbol)?.pop() {
+            Some(step) => Ok(step.equation.rhs),
+            None => Ok(self.rhs.clone()),
+        }
+    }
+}
+
+/// The operation that removes the outermost layer of `term`, and what is left of `term`.
+fn invert(term: &Term, var: &str) -> Result<(Operation, Term), IsolationError> {
+    let unsupported = || IsolationError::Unsupported(term.clone());
+
+    match term {
+        Term::Addition(lhs, rhs) => {
+            let (inner, other) = pick(lhs, rhs, var)?;
+            let operation = match negated(other) {
+                Some(other) => Operation::Add(other),
+                None => Operation::Subtract(other.clone()),
+            };
+            Ok((operation, inner.clone()))
+        }
+        Term::Subtraction(lhs, rhs) => match pick(lhs, rhs, var)? {
+            (inner, other) if inner == &**lhs => Ok((Operation::Add(other.clone()), inner.clone())),
+            // `other - inner` becomes `-inner`, which the next step divides by -1
+            (inner, other) => Ok((
+                Operation::Subtract(other.clone()),
+                Term::Multiplication(Box::new(integer(-1)), Box::new(inner.clone())),
+            )),
+        },
+        Term::Division(lhs, rhs) => match pick(lhs, rhs, var)? {
+            (inner, other) if inner == &**lhs => {
+                Ok((Operation::Multiply(other.clone()), inner.clone()))
+            }
+            // `other / inner` becomes `inner / other`, which the next step multiplies out
+            (inner, other) => Ok((
+                Operation::Reciprocal,
+                Term::Division(Box::new(inner.clone()), Box::new(other.clone())),
+            )),
+        },
+        Term::Multiplication(lhs, rhs) => {
+            let (inner, other) = pick(lhs, rhs, var)?;
+            let operation = match other {
+                Term::Exponentation(base, power) if is_minus_one(power) => {
+                    Operation::Multiply((**base).clone())
+                }
+                other if other.evaluate_exact().is_ok_and(|value| value.is_zero()) => {
+                    return Err(IsolationError::DivisionByZero)
+                }
+                other => Operation::Divide(other.clone()),
+            };
+            Ok((operation, inner.clone()))
+        }
+        Term::Exponentation(base, power) if !power.depends_on(var) => {
+            if is_minus_one(power) {
+                Ok((Operation::Reciprocal, (**base).clone()))
+            } else if power.evaluate_exact().is_ok_and(|value| value.is_zero()) {
+                Err(unsupported())
+            } else {
+                Ok((Operation::Root((**power).clone()), (**base).clone()))
+            }
+        }
+        Term::RootExtraction(radicand, degree) if !degree.depends_on(var) => {
+            Ok((Operation::Raise((**degree).clone()), (**radicand).clone()))
+        }
+        _ => Err(unsupported()),
+    }
+}
+
+/// Orders the operands of a binary term as `(containing var, not containing var)`.
+fn pick<'a>(
+    lhs: &'a Term,
+    rhs: &'a Term,
+    var: &str,
+) -> Result<(&'a Term, &'a Term), IsolationError> {
+    match (lhs.depends_on(var), rhs.depends_on(var)) {
+        (true, true) => Err(IsolationError::Repeated),
+        (true, false) => Ok((lhs, rhs)),
+        (false, true) => Ok((rhs, lhs)),
+        (false, false) => Err(IsolationError::NotFound),
+    }
+}
+
+fn apply(operation: &Operation, rhs: Term) -> Term {
+    let binary = |make: fn(Box<Term>, Box<Term>) -> Term, other: Term| {
+        make(Box::new(rhs.clone()), Box::new(other))
+    };
+
+    match operation {
+        Operation::SwapSides => rhs,
+        Operation::Add(term) => binary(Term::Addition, term.clone()),
+        Operation::Subtract(term) => binary(Term::Subtraction, term.clone()),
+        // `1/a * b` reads better as `b/a`, and arises after taking the reciprocal
+        Operation::Multiply(term) => match &rhs {
+            Term::Division(one, divisor) if **one == integer(1) => {
+                Term::Division(Box::new(term.clone()), divisor.clone())
+            }
+            _ => binary(Term::Multiplication, term.clone()),
+        },
+        Operation::Divide(term) => binary(Term::Division, term.clone()),
+        Operation::Reciprocal => Term::Division(Box::new(integer(1)), Box::new(rhs)),
+        Operation::Root(degree) => binary(Term::RootExtraction, degree.clone()),
+        Operation::Raise(power) => binary(Term::Exponentation, power.clone()),
+    }
+}
+
+fn fold(term: Term) -> Term {
+    match term.evaluate_exact() {
+        Ok(value) => Term::Value(Number::Rational(value)),
+        Err(_) => term,
+    }
+}
+
+/// `term` without its sign, if it is negative or a negation.
+fn negated(term: &Term) -> Option<Term> {
+    match term {
+        Term::Multiplication(lhs, rhs) if is_minus_one(lhs) => Some((**rhs).clone()),
+        Term::Value(Number::Rational(value)) if *value < Fraction::zero() => {
+            Some(Term::Value(Number::Rational(-*value)))
+        }
+        Term::Value(Number::Irrational(value)) if *value < 0.0 => {
+            Some(Term::Value(Number::Irrational(-*value)))
+        }
+        _ => None,
+    }
+}
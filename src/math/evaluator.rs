@@ -0,0 +1,86 @@
+use super::compile::CompiledExpr;
+use super::numeric::{self, DomainError};
+use super::terms::Term;
+
+/// Terms with more nodes than this are compiled as soon as they are first evaluated.
+pub const COMPILE_SIZE: usize = 64;
+/// How many times a smaller term is interpreted before it is compiled.
+pub const COMPILE_CALLS: usize = 16;
+
+/// How an `Evaluator` currently computes its values.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Tier {
+    /// Walking the term with `numeric::evaluate_ieee`, which costs nothing up front.
+    Interpreted,
+    /// Running the instructions of `Term::compile`, which pays off once the term is large or
+    /// evaluated often.
+    Compiled,
+}
+
+/// Evaluates a term in `f64` for many inputs, choosing between interpreting it and compiling it
+/// by its size and how often it has been evaluated. Every tier follows `numeric::evaluate_ieee`,
+/// so the choice never changes a result.
+#[derive(Clone, Debug)]
+pub struct Evaluator {
+    term: Term,
+    variables: Vec<Box<str>>,
+    size: usize,
+    calls: usize,
+    compiled: Option<CompiledExpr>,
+    /// Set once compiling failed, so the term is interpreted from then on.
+    uncompilable: bool,
+}
+
+impl Evaluator {
+    /// Binds `variables` to the inputs of `eval`, in order.
+    pub fn new(term: Term, variables: &[&str]) -> Self {
+        Self {
+            size: term.iter_subterms().count(),
+            term,
+            variables: variables.iter().map(|&var| var.into()).collect(),
+            calls: 0,
+            compiled: None,
+            uncompilable: false,
+        }
+    }
+
+    pub fn term(&self) -> &Term {
+        &self.term
+    }
+
+    pub fn tier(&self) -> Tier {
+        match self.compiled {
+            Some(_) => Tier::Compiled,
+            None => Tier::Interpreted,
+        }
+    }
+
+    /// The value of the term with the variables bound to `inputs`.
+    ///
+    /// Panics if `inputs` does not have one value for every variable.
+    pub fn eval(&mut self, inputs: &[f64]) -> Result<f64, DomainError> {
+        assert_eq!(inputs.len(), self.variables.len(), "wrong number of inputs");
+        self.calls += 1;
+        if self.compiled.is_none()
+            && !self.uncompilable
+            && (self.size > COMPILE_SIZE || self.calls > COMPILE_CALLS)
+        {
+            let variables: Vec<&str> = self.variables.iter().map(|var| &**var).collect();
+            match self.term.compile(&variables) {
+                Ok(compiled) => self.compiled = Some(compiled),
+                Err(_) => self.uncompilable = true,
+            }
+        }
+
+        match &self.compiled {
+            Some(compiled) => Ok(compiled.eval(inputs)),
+            None => {
+                let bindings: Vec<(&str, f64)> = (self.variables.iter())
+                    .map(|var| &**var)
+                    .zip(inputs.iter().copied())
+                    .collect();
+                numeric::evaluate_ieee(&self.term, &bindings)
+            }
+        }
+    }
+}
@@ -0,0 +1,227 @@
+use fraction::{CheckedAdd, CheckedDiv, CheckedMul, CheckedSub, One, ToPrimitive, Zero};
+
+use super::path::TermPath;
+use super::terms::{Fraction, Number, Term};
+
+/// Why a term has no exact rational value.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ExactnessReason {
+    UnboundVariable,
+    IrrationalLiteral,
+    /// A power or root whose result is not a rational number.
+    IrrationalPower,
+    /// A function value that is not rational, like `sin(1)`.
+    Transcendental,
+    /// A symbolic constant like `pi`.
+    IrrationalConstant,
+    Overflow,
+    DivisionByZero,
+    /// A collection where a single number was expected.
+    NotAScalar,
+    IndexOutOfBounds,
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct ExactnessViolation {
+    pub at: TermPath,
+    pub reason: ExactnessReason,
+}
+
+impl Term {
+    /// Evaluates the term without ever leaving exact arithmetic.
+    ///
+    /// Unlike `get_value`, overflow and results that are not rational are reported as errors
+    /// pointing at the offending subterm rather than degrading to floats.
+    pub fn evaluate_exact(&self) -> Result<Fraction, ExactnessViolation> {
+        self.evaluate_exact_at(&TermPath::root())
+    }
+
+    fn evaluate_exact_at(&self, path: &TermPath) -> Result<Fraction, ExactnessViolation> {
+        let violation = |reason| ExactnessViolation {
+            at: path.clone(),
+            reason,
+        };
+
+        match self {
+            Term::Value(Number::Rational(value)) => Ok(*value),
+            Term::Value(Number::Irrational(_)) => {
+                Err(violation(ExactnessReason::IrrationalLiteral))
+            }
+            Term::Variable(_) => Err(violation(ExactnessReason::UnboundVariable)),
+            Term::Constant(_) => Err(violation(ExactnessReason::IrrationalConstant)),
+            Term::Addition(lhs, rhs) => {
+                let (lhs, rhs) = (
+                    lhs.evaluate_exact_at(&path.child(0))?,
+                    rhs.evaluate_exact_at(&path.child(1))?,
+                );
+                lhs.checked_add(&rhs)
+                    .ok_or_else(|| violation(ExactnessReason::Overflow))
+            }
+            Term::Multiplication(lhs, rhs) => {
+                let (lhs, rhs) = (
+                    lhs.evaluate_exact_at(&path.child(0))?,
+                    rhs.evaluate_exact_at(&path.child(1))?,
+                );
+                lhs.checked_mul(&rhs)
+                    .ok_or_else(|| violation(ExactnessReason::Overflow))
+            }
+            Term::Subtraction(lhs, rhs) => {
+                let (lhs, rhs) = (
+                    lhs.evaluate_exact_at(&path.child(0))?,
+                    rhs.evaluate_exact_at(&path.child(1))?,
+                );
+                lhs.checked_sub(&rhs)
+                    .ok_or_else(|| violation(ExactnessReason::Overflow))
+            }
+            Term::Division(lhs, rhs) => {
+                let (lhs, rhs) = (
+                    lhs.evaluate_exact_at(&path.child(0))?,
+                    rhs.evaluate_exact_at(&path.child(1))?,
+                );
+                if rhs.is_zero() {
+                    return Err(violation(ExactnessReason::DivisionByZero));
+                }
+                lhs.checked_div(&rhs)
+                    .ok_or_else(|| violation(ExactnessReason::Overflow))
+            }
+            Term::Exponentation(base, power) => {
+                let (base, power) = (
+                    base.evaluate_exact_at(&path.child(0))?,
+                    power.evaluate_exact_at(&path.child(1))?,
+                );
+                exact_power(base, power).map_err(violation)
+            }
+            Term::RootExtraction(radicand, degree) => {
+                let (radicand, degree) = (
+                    radicand.evaluate_exact_at(&path.child(0))?,
+                    degree.evaluate_exact_at(&path.child(1))?,
+                );
+                if degree.is_zero() {
+                    return Err(violation(ExactnessReason::DivisionByZero));
+                }
+                exact_power(radicand, degree.recip()).map_err(violation)
+            }
+            Term::Function(function, argument) => {
+                let arguments = function.arguments(argument);
+                let arguments = match arguments {
+                    [argument] => vec![argument.evaluate_exact_at(&path.child(0))?],
+                    _ => (arguments.iter().enumerate())
+                        .map(|(i, argument)| argument.evaluate_exact_at(&path.child(0).child(i)))
+                        .collect::<Result<_, _>>()?,
+                };
+                function
+                    .evaluate_exact(&arguments)
+                    .ok_or_else(|| violation(ExactnessReason::Transcendental))
+            }
+            Term::Vector(_) | Term::List(_) | Term::Set(_) => {
+                Err(violation(ExactnessReason::NotAScalar))
+            }
+            Term::Index(collection, index) => {
+                let position = index.evaluate_exact_at(&path.child(1))?;
+                let elements = match &**collection {
+                    Term::Vector(elements) | Term::List(elements) => elements,
+                    _ => {
+                        return Err(ExactnessViolation {
+                            at: path.child(0),
+                            reason: ExactnessReason::NotAScalar,
+                        })
+                    }
+                };
+
+                let element = position
+                    .is_integer()
+                    .then(|| usize::try_from(position.to_integer()).ok())
+                    .flatten()
+                    .and_then(|position| elements.get(position).map(|element| (position, element)));
+
+                match element {
+                    Some((position, element)) => {
+                        element.evaluate_exact_at(&path.child(0).child(position))
+                    }
+                    None => Err(ExactnessViolation {
+                        at: path.child(1),
+                        reason: ExactnessReason::IndexOutOfBounds,
+                    }),
+                }
+            }
+        }
+    }
+}
+
+/// The integer `k` with `base^k = n`, if there is one, as in `ilog(2, 1024) = 10`. Works for
+/// rational arguments, so `ilog(2, 1/8) = -3`. The base must be positive and not one.
+pub fn ilog(base: Fraction, n: Fraction) -> Option<i64> {
+    let positive = |value: Fraction| *value.numer() > 0;
+    if !positive(base) || !positive(n) || base.is_one() {
+        return None;
+    }
+
+    let estimate = (n.to_f64()?.ln() / base.to_f64()?.ln()).round() as i64;
+    (estimate.saturating_sub(1)..=estimate.saturating_add(1))
+        .find(|k| checked_pow(base, *k) == Ok(n))
+}
+
+/// Raises `base` to an integer power by squaring, failing on overflow.
+fn checked_pow(base: Fraction, exponent: i64) -> Result<Fraction, ExactnessReason> {
+    let mut base = if exponent < 0 {
+        if base.is_zero() {
+            return Err(ExactnessReason::DivisionByZero);
+        }
+        base.recip()
+    } else {
+        base
+    };
+
+    let mut exponent = exponent.unsigned_abs();
+    let mut result = Fraction::one();
+
+    while exponent > 0 {
+        if exponent & 1 == 1 {
+            result = result.checked_mul(&base).ok_or(ExactnessReason::Overflow)?;
+        }
+        exponent >>= 1;
+        if exponent > 0 {
+            base = base.checked_mul(&base).ok_or(ExactnessReason::Overflow)?;
+        }
+    }
+
+    Ok(result)
+}
+
+/// The exact `degree`-th root of a non-negative integer, if there is one.
+fn integer_root(n: i64, degree: u32) -> Option<i64> {
+    let guess = (n as f64).powf(1.0 / degree as f64).round() as i64;
+
+    (guess.saturating_sub(1)..=guess.saturating_add(1))
+        .filter(|candidate| *candidate >= 0)
+        .find(|candidate| candidate.checked_pow(degree) == Some(n))
+}
+
+fn exact_root(value: Fraction, degree: i64) -> Result<Fraction, ExactnessReason> {
+    let degree = u32::try_from(degree).map_err(|_| ExactnessReason::IrrationalPower)?;
+    let negative = *value.numer() < 0;
+
+    if negative && degree % 2 == 0 {
+        return Err(ExactnessReason::IrrationalPower);
+    }
+
+    let magnitude = value
+        .numer()
+        .checked_abs()
+        .ok_or(ExactnessReason::Overflow)?;
+    let numer = integer_root(magnitude, degree).ok_or(ExactnessReason::IrrationalPower)?;
+    let denom = integer_root(*value.denom(), degree).ok_or(ExactnessReason::IrrationalPower)?;
+
+    Ok(Fraction::new(if negative { -numer } else { numer }, denom))
+}
+
+/// `base^power` when the root it takes is exact, failing on overflow.
+pub(super) fn exact_power(base: Fraction, power: Fraction) -> Result<Fraction, ExactnessReason> {
+    let root = if power.is_integer() {
+        base
+    } else {
+        exact_root(base, *power.denom())?
+    };
+
+    checked_pow(root, *power.numer())
+}
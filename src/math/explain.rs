@@ -0,0 +1,120 @@
+use super::terms::{EvalContext, EvalError, Number, Term};
+
+/// One operation performed while evaluating a term.
+#[derive(Clone, Debug, PartialEq)]
+pub struct EvalStep {
+    /// The operation with its operands replaced by their values, like `2^3`.
+    pub term: Term,
+    /// The value of `term`, or why it has none.
+    pub value: Result<Number, EvalError>,
+}
+
+impl Term {
+    /// The operations `evaluate_in` performs, in order, with each operand already evaluated.
+    /// Leaves are not steps of their own. The trace stops at the first operation that fails.
+    pub fn evaluation_steps(&self, context: &EvalContext) -> Vec<EvalStep> {
+        let mut steps = Vec::new();
+        let _ = trace(self, context, &mut steps);
+        steps
+    }
+
+    /// A plain-text account of how the value of the term is obtained, like "First we evaluated
+    /// the power 2^3 = 8, and then the product 5*8 = 40."
+    pub fn explain(&self, context: &EvalContext) -> String {
+        explain(self, context, |term| term.to_string())
+    }
+
+    /// Like `explain`, with the terms and values as inline LaTeX math.
+    pub fn explain_latex(&self, context: &EvalContext) -> String {
+        explain(self, context, |term| format!("${}$", term.to_latex()))
+    }
+}
+
+/// Evaluates `term`, recording a step for every operation.
+fn trace(
+    term: &Term,
+    context: &EvalContext,
+    steps: &mut Vec<EvalStep>,
+) -> Result<Number, EvalError> {
+    let mut operand =
+        |term: &Term| trace(term, context, steps).map(|value| Box::new(Term::Value(value)));
+
+    let shown = match term {
+        Term::Value(_) | Term::Variable(_) | Term::Constant(_) => return term.evaluate_in(context),
+        Term::Addition(lhs, rhs) => Term::Addition(operand(lhs)?, operand(rhs)?),
+        Term::Subtraction(lhs, rhs) => Term::Subtraction(operand(lhs)?, operand(rhs)?),
+        Term::Multiplication(lhs, rhs) => Term::Multiplication(operand(lhs)?, operand(rhs)?),
+        Term::Division(lhs, rhs) => Term::Division(operand(lhs)?, operand(rhs)?),
+        Term::Exponentation(base, power) => Term::Exponentation(operand(base)?, operand(power)?),
+        Term::RootExtraction(radicand, degree) => {
+            Term::RootExtraction(operand(radicand)?, operand(degree)?)
+        }
+        Term::Function(function, argument) => Term::Function(*function, operand(argument)?),
+        Term::Index(collection, index) => Term::Index(collection.clone(), operand(index)?),
+        Term::Vector(_) | Term::List(_) | Term::Set(_) => return Err(EvalError::NotAScalar),
+    };
+
+    let value = shown.evaluate_in(context);
+    steps.push(EvalStep {
+        term: shown,
+        value: value.clone(),
+    });
+    value
+}
+
+fn describe(term: &Term) -> &'static str {
+    match term {
+        Term::Addition(..) => "the sum",
+        Term::Subtraction(..) => "the difference",
+        Term::Multiplication(..) => "the product",
+        Term::Division(..) => "the quotient",
+        Term::Exponentation(..) => "the power",
+        Term::RootExtraction(..) => "the root",
+        Term::Index(..) => "the element",
+        _ => "",
+    }
+}
+
+fn explain(term: &Term, context: &EvalContext, show: impl Fn(&Term) -> String) -> String {
+    let mut steps = Vec::new();
+    let result = trace(term, context, &mut steps);
+    let sentence = |step: &EvalStep| {
+        let shown = match describe(&step.term) {
+            "" => show(&step.term),
+            kind => format!("{kind} {}", show(&step.term)),
+        };
+        match &step.value {
+            Ok(value) => format!("{shown} = {}", show(&Term::Value(*value))),
+            Err(error) => format!("{shown}, which fails: {error}"),
+        }
+    };
+
+    let Some((last, rest)) = steps.split_last() else {
+        return match result {
+            Ok(value) => format!("The value is {}.", show(&Term::Value(value))),
+            Err(error) => format!("The term has no value: {error}."),
+        };
+    };
+
+    let mut text = String::new();
+    for (i, step) in rest.iter().enumerate() {
+        text.push_str(if i == 0 {
+            "First we evaluated "
+        } else {
+            ", then "
+        });
+        text.push_str(&sentence(step));
+    }
+    text.push_str(match rest.len() {
+        0 => "We evaluated ",
+        1 => ", and then ",
+        _ => ", and finally ",
+    });
+    text.push_str(&sentence(last));
+    // A leaf like an unbound variable failed, which is not a step of its own
+    if let (Err(error), Ok(_)) = (result, &last.value) {
+        text.push_str(&format!(", but then failed: {error}"));
+    }
+    text.push('.');
+    text
+}
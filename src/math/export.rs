@@ -0,0 +1,71 @@
+use std::io::{self, Write};
+
+use super::numeric::{self, DomainError};
+use super::terms::Term;
+
+/// Streams rows of evaluation results as CSV, one line per row.
+pub struct CsvWriter<W: Write> {
+    out: W,
+    columns: usize,
+}
+
+impl<W: Write> CsvWriter<W> {
+    /// Writes the header line right away.
+    pub fn new(mut out: W, header: &[&str]) -> io::Result<Self> {
+        let fields: Vec<String> = header.iter().map(|field| quote(field)).collect();
+        writeln!(out, "{}", fields.join(","))?;
+        Ok(Self {
+            out,
+            columns: header.len(),
+        })
+    }
+
+    /// Writes one row, leaving undefined values empty. Fails with `InvalidInput` if the row does
+    /// not match the header.
+    pub fn write_row(&mut self, values: &[Result<f64, DomainError>]) -> io::Result<()> {
+        if values.len() != self.columns {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidInput,
+                format!("expected {} columns, got {}", self.columns, values.len()),
+            ));
+        }
+
+        let fields: Vec<String> = values
+            .iter()
+            .map(|value| match value {
+                Ok(value) => value.to_string(),
+                Err(_) => String::new(),
+            })
+            .collect();
+        writeln!(self.out, "{}", fields.join(","))
+    }
+
+    pub fn into_inner(self) -> W {
+        self.out
+    }
+}
+
+/// Quotes a field if it would otherwise break the row.
+fn quote(field: &str) -> String {
+    if field.contains([',', '"', '\n', '\r']) {
+        format!("\"{}\"", field.replace('"', "\"\""))
+    } else {
+        field.to_string()
+    }
+}
+
+impl Term {
+    /// Evaluates the term at each of `xs` for `var` and streams `var,value` rows to `out`.
+    pub fn write_samples_csv(
+        &self,
+        var: &str,
+        xs: impl IntoIterator<Item = f64>,
+        out: impl Write,
+    ) -> io::Result<()> {
+        let mut writer = CsvWriter::new(out, &[var, "value"])?;
+        for x in xs {
+            writer.write_row(&[Ok(x), numeric::try_evaluate(self, &[(var, x)])])?;
+        }
+        Ok(())
+    }
+}
@@ -0,0 +1,104 @@
+use super::terms::{Number, Term};
+
+const OFFSET: u128 = 0x6c62272e07bb014262b821756295c58d;
+const PRIME: u128 = 0x0000000001000000000000000000013b;
+
+/// 128-bit FNV-1a, which unlike `std`'s hashers is specified and stable across releases.
+struct Fnv(u128);
+
+impl Fnv {
+    fn write(&mut self, bytes: &[u8]) {
+        for byte in bytes {
+            self.0 ^= *byte as u128;
+            self.0 = self.0.wrapping_mul(PRIME);
+        }
+    }
+
+    fn write_len(&mut self, len: usize) {
+        self.write(&(len as u64).to_le_bytes());
+    }
+}
+
+impl Term {
+    /// A hash of the term that is stable across runs, platforms and crate versions that keep
+    /// the same structure. Numbers are hashed in canonical form, so `0.5` and `1/2` agree, and
+    /// set literals ignore element order. Otherwise the hash is structural: `a + b` and `b + a`
+    /// differ unless both are `canonicalize`d first.
+    pub fn fingerprint(&self) -> u128 {
+        let mut hasher = Fnv(OFFSET);
+        self.feed(&mut hasher);
+        hasher.0
+    }
+
+    fn feed(&self, hasher: &mut Fnv) {
+        let binary = |hasher: &mut Fnv, tag: u8, lhs: &Term, rhs: &Term| {
+            hasher.write(&[tag]);
+            lhs.feed(hasher);
+            rhs.feed(hasher);
+        };
+
+        match self {
+            Term::Value(number) => match number.canonical() {
+                Number::Rational(fraction) => {
+                    hasher.write(&[0]);
+                    hasher.write(&fraction.numer().to_le_bytes());
+                    hasher.write(&fraction.denom().to_le_bytes());
+                }
+                Number::Irrational(value) => {
+                    hasher.write(&[1]);
+                    hasher.write(&value.to_bits().to_le_bytes());
+                }
+            },
+            Term::Variable(name) => {
+                hasher.write(&[2]);
+                hasher.write_len(name.len());
+                hasher.write(name.as_bytes());
+            }
+            Term::Addition(lhs, rhs) => binary(hasher, 3, lhs, rhs),
+            Term::Multiplication(lhs, rhs) => binary(hasher, 4, lhs, rhs),
+            Term::Exponentation(base, power) => binary(hasher, 5, base, power),
+            Term::RootExtraction(radicand, degree) => binary(hasher, 6, radicand, degree),
+            Term::Vector(elements) | Term::List(elements) => {
+                hasher.write(&[if matches!(self, Term::Vector(_)) {
+                    7
+                } else {
+                    8
+                }]);
+                hasher.write_len(elements.len());
+                for element in elements {
+                    element.feed(hasher);
+                }
+            }
+            Term::Set(elements) => {
+                let mut fingerprints: Vec<u128> = elements.iter().map(Term::fingerprint).collect();
+                fingerprints.sort_unstable();
+                fingerprints.dedup();
+
+                hasher.write(&[9]);
+                hasher.write_len(fingerprints.len());
+                for fingerprint in fingerprints {
+                    hasher.write(&fingerprint.to_le_bytes());
+                }
+            }
+            Term::Index(collection, index) => binary(hasher, 10, collection, index),
+            Term::Subtraction(lhs, rhs) => binary(hasher, 11, lhs, rhs),
+            Term::Division(lhs, rhs) => binary(hasher, 12, lhs, rhs),
+            Term::Function(function, argument) => {
+                hasher.write(&[13, *function as u8]);
+                argument.feed(hasher);
+            }
+            Term::Constant(constant) => hasher.write(&[14, *constant as u8]),
+        }
+    }
+
+    /// Whether `var` occurs in the term, so results cached for it change with its binding.
+    pub fn depends_on(&self, var: &str) -> bool {
+        match self {
+            Term::Variable(name) => &**name == var,
+            _ => self
+                .children()
+                .into_iter()
+                .any(|child| child.depends_on(var)),
+        }
+    }
+}
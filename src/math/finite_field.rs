@@ -0,0 +1,182 @@
+use std::ops::{Add, Div, Mul, Neg, Sub};
+
+use fraction::{One, Zero};
+
+use super::cancel::{CancellationToken, Cancelled};
+use super::polynomial::Polynomial;
+
+/// An element of the prime field `GF(P)`. `P` must be prime for division to be meaningful.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub struct Gf<const P: u64>(u64);
+
+impl<const P: u64> Gf<P> {
+    pub fn new(value: i64) -> Self {
+        Self(value.rem_euclid(P as i64) as u64)
+    }
+
+    pub fn value(self) -> u64 {
+        self.0
+    }
+
+    pub fn pow(self, mut exponent: u64) -> Self {
+        let (mut base, mut result) = (self, Self::one());
+        while exponent > 0 {
+            if exponent & 1 == 1 {
+                result = result * base;
+            }
+            base = base * base;
+            exponent >>= 1;
+        }
+        result
+    }
+
+    /// The multiplicative inverse by Fermat's little theorem, or `None` for zero.
+    pub fn inverse(self) -> Option<Self> {
+        (!self.is_zero()).then(|| self.pow(P - 2))
+    }
+}
+
+impl<const P: u64> Add for Gf<P> {
+    type Output = Self;
+
+    fn add(self, rhs: Self) -> Self {
+        Self((self.0 + rhs.0) % P)
+    }
+}
+
+impl<const P: u64> Sub for Gf<P> {
+    type Output = Self;
+
+    fn sub(self, rhs: Self) -> Self {
+        Self((self.0 + P - rhs.0) % P)
+    }
+}
+
+impl<const P: u64> Mul for Gf<P> {
+    type Output = Self;
+
+    fn mul(self, rhs: Self) -> Self {
+        Self(((self.0 as u128 * rhs.0 as u128) % P as u128) as u64)
+    }
+}
+
+impl<const P: u64> Neg for Gf<P> {
+    type Output = Self;
+
+    fn neg(self) -> Self {
+        Self((P - self.0) % P)
+    }
+}
+
+impl<const P: u64> Div for Gf<P> {
+    type Output = Self;
+
+    /// Panics when dividing by zero, like integer division.
+    #[allow(clippy::suspicious_arithmetic_impl)]
+    fn div(self, rhs: Self) -> Self {
+        self * rhs.inverse().expect("division by zero in GF(P)")
+    }
+}
+
+impl<const P: u64> Zero for Gf<P> {
+    fn zero() -> Self {
+        Self(0)
+    }
+
+    fn is_zero(&self) -> bool {
+        self.0 == 0
+    }
+}
+
+impl<const P: u64> One for Gf<P> {
+    fn one() -> Self {
+        Self(1 % P)
+    }
+}
+
+/// A polynomial written as `unit * product of factors^multiplicity`, with monic factors.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Factorization<const P: u64> {
+    pub unit: Gf<P>,
+    pub factors: Vec<(Polynomial<Gf<P>>, usize)>,
+}
+
+impl<const P: u64> Polynomial<Gf<P>> {
+    /// Factors into monic irreducibles by trial division with every monic polynomial of increasing
+    /// degree. Each candidate that divides is irreducible, since its own factors were already
+    /// divided out. This is exponential in the degree and meant for small exercises.
+    ///
+    /// Returns `None` for the zero polynomial, or once the candidates of a degree are too many to
+    /// count in a `u64`.
+    pub fn factor(&self) -> Option<Factorization<P>> {
+        self.factor_cancellable(&CancellationToken::new()).unwrap()
+    }
+
+    /// Like `factor`, but aborts with `Err(Cancelled)` once `token` is cancelled.
+    pub fn factor_cancellable(
+        &self,
+        token: &CancellationToken,
+    ) -> Result<Option<Factorization<P>>, Cancelled> {
+        if self.degree().is_none() {
+            return Ok(None);
+        }
+
+        let unit = self.leading_coefficient();
+        let mut remaining = self.monic();
+        let mut factors = Vec::new();
+
+        let mut degree = 1;
+        while remaining.degree().is_some_and(|d| d >= 2 * degree) {
+            let Some(candidates) = monic_polynomials::<P>(self.var(), degree) else {
+                return Ok(None);
+            };
+            for candidate in candidates {
+                token.check()?;
+                let mut multiplicity = 0;
+                while let Some((quotient, remainder)) = remaining.div_rem(&candidate) {
+                    if !remainder.is_zero() {
+                        break;
+                    }
+                    remaining = quotient;
+                    multiplicity += 1;
+                }
+                if multiplicity > 0 {
+                    factors.push((candidate, multiplicity));
+                }
+            }
+            degree += 1;
+        }
+
+        if remaining.degree().is_some_and(|d| d > 0) {
+            // Whatever is left has no factor of at most half its degree, so it is irreducible.
+            factors.push((remaining, 1));
+        }
+
+        Ok(Some(Factorization { unit, factors }))
+    }
+
+    /// Whether the polynomial has no non-trivial factorization.
+    pub fn is_irreducible(&self) -> bool {
+        self.factor()
+            .is_some_and(|factorization| matches!(factorization.factors.as_slice(), [(_, 1)]))
+    }
+}
+
+/// Every monic polynomial of exactly `degree` over `GF(P)`, or `None` if there are more than
+/// `u64::MAX`.
+fn monic_polynomials<const P: u64>(
+    var: &str,
+    degree: usize,
+) -> Option<impl Iterator<Item = Polynomial<Gf<P>>> + '_> {
+    let count = P.checked_pow(u32::try_from(degree).ok()?)?;
+
+    Some((0..count).map(move |mut index| {
+        let mut coefficients = Vec::with_capacity(degree + 1);
+        for _ in 0..degree {
+            coefficients.push(Gf(index % P));
+            index /= P;
+        }
+        coefficients.push(Gf::one());
+        Polynomial::new(var, coefficients)
+    }))
+}
@@ -0,0 +1,399 @@
+use std::str::FromStr;
+
+use super::constants::Constant;
+use super::display::{ATOM, POWER, PRODUCT, SUM, UNARY};
+use super::escape::escape_latex;
+use super::functions::Function;
+use super::latex::GREEK;
+use super::terms::{Fraction, Number, ParseNumberError, Term};
+use super::values::{absolute, is_minus_one, is_negative, negated, reciprocal};
+
+/// Locale conventions for reading and writing numbers.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct NumberFormat {
+    /// Separates groups of three digits in the integer part, if grouping is used.
+    pub group_separator: Option<char>,
+    pub decimal_separator: char,
+}
+
+impl NumberFormat {
+    /// No grouping and a `.` decimal point, as used by `Display` and `FromStr`.
+    pub const PLAIN: Self = Self {
+        group_separator: None,
+        decimal_separator: '.',
+    };
+
+    /// `1,000,000.5`
+    pub const ENGLISH: Self = Self {
+        group_separator: Some(','),
+        decimal_separator: '.',
+    };
+
+    /// `1.000.000,5`
+    pub const GERMAN: Self = Self {
+        group_separator: Some('.'),
+        decimal_separator: ',',
+    };
+}
+
+impl Default for NumberFormat {
+    fn default() -> Self {
+        Self::PLAIN
+    }
+}
+
+impl Number {
+    /// Parses a number written in `format`. Underscores between digits are always accepted.
+    ///
+    /// Group separators must split the integer part into well-formed groups of three, so in the
+    /// English format `1,5` is rejected rather than silently read as fifteen.
+    pub fn parse_with(s: &str, format: &NumberFormat) -> Result<Number, ParseNumberError> {
+        let chars: Vec<char> = s.chars().collect();
+        let mut normalized = String::with_capacity(s.len());
+        let mut fractional = false;
+        let mut i = 0;
+
+        while i < chars.len() {
+            let c = chars[i];
+            let is_digit_run =
+                |c: char| c.is_ascii_digit() || c == '_' || Some(c) == format.group_separator;
+
+            if c.is_ascii_digit() {
+                let start = i;
+                while i < chars.len() && is_digit_run(chars[i]) {
+                    i += 1;
+                }
+                let run: String = chars[start..i].iter().collect();
+                normalized.push_str(&digits(&run, format.group_separator, fractional)?);
+                continue;
+            }
+
+            if c == '_' {
+                return Err(ParseNumberError);
+            } else if c == format.decimal_separator {
+                fractional = true;
+                normalized.push('.');
+            } else {
+                if matches!(c, '/' | '+' | '-' | 'e' | 'E') {
+                    fractional = false;
+                }
+                normalized.push(c);
+            }
+            i += 1;
+        }
+
+        Number::from_str(&normalized)
+    }
+
+    /// Writes the number in `format`, grouping the digits of integer parts. Floats in exponent
+    /// notation are left as they are.
+    pub fn format_with(&self, format: &NumberFormat) -> String {
+        match self {
+            Number::Rational(fraction) => {
+                let numer = group(&fraction.numer().to_string(), format.group_separator);
+                if *fraction.denom() == 1 {
+                    numer
+                } else {
+                    let denom = group(&fraction.denom().to_string(), format.group_separator);
+                    format!("{numer}/{denom}")
+                }
+            }
+            Number::Irrational(value) => {
+                let text = format!("{value:?}");
+                if !value.is_finite() || text.contains('e') {
+                    return text;
+                }
+
+                match text.split_once('.') {
+                    Some((integer, fraction)) => format!(
+                        "{}{}{fraction}",
+                        group(integer, format.group_separator),
+                        format.decimal_separator
+                    ),
+                    None => group(&text, format.group_separator),
+                }
+            }
+        }
+    }
+}
+
+/// Strips separators from a run of digits after checking they are well placed.
+fn digits(
+    run: &str,
+    separator: Option<char>,
+    fractional: bool,
+) -> Result<String, ParseNumberError> {
+    let chars: Vec<char> = run.chars().collect();
+    for (i, c) in chars.iter().enumerate() {
+        let between_digits = i > 0
+            && chars[i - 1].is_ascii_digit()
+            && chars.get(i + 1).is_some_and(char::is_ascii_digit);
+        if *c == '_' && !between_digits {
+            return Err(ParseNumberError);
+        }
+    }
+
+    let run = run.replace('_', "");
+    let Some(separator) = separator.filter(|separator| run.contains(*separator)) else {
+        return Ok(run);
+    };
+    if fractional {
+        return Err(ParseNumberError);
+    }
+
+    let mut groups = run.split(separator);
+    let first = groups.next().unwrap_or_default();
+    let well_formed = (1..=3).contains(&first.len()) && groups.all(|group| group.len() == 3);
+    if well_formed {
+        Ok(run.replace(separator, ""))
+    } else {
+        Err(ParseNumberError)
+    }
+}
+
+/// Inserts `separator` between groups of three digits, keeping a leading sign.
+fn group(integer: &str, separator: Option<char>) -> String {
+    let Some(separator) = separator else {
+        return integer.to_string();
+    };
+
+    let (sign, digits) = match integer.strip_prefix('-') {
+        Some(digits) => ("-", digits),
+        None => ("", integer),
+    };
+
+    let mut grouped = String::from(sign);
+    for (i, digit) in digits.chars().enumerate() {
+        if i > 0 && (digits.len() - i).is_multiple_of(3) {
+            grouped.push(separator);
+        }
+        grouped.push(digit);
+    }
+    grouped
+}
+
+impl Term {
+    /// LaTeX math-mode markup for the term, like `\frac{x^{2}}{2} + \sqrt[3]{y}`. Parentheses are
+    /// added only where the notation needs them.
+    pub fn to_latex(&self) -> String {
+        let mut out = String::new();
+        latex(self, &mut out);
+        out
+    }
+}
+
+/// Like `display::precedence`, except that fractions are set apart by `\frac` and only need
+/// parentheses as the base of a power.
+pub(super) fn latex_precedence(term: &Term) -> u8 {
+    match term {
+        Term::Value(value) if is_negative(value) => UNARY,
+        Term::Value(Number::Rational(value)) if !value.is_integer() => POWER,
+        Term::Addition(..) | Term::Subtraction(..) => SUM,
+        Term::Multiplication(..) if negated(term).is_some() => UNARY,
+        Term::Multiplication(lhs, rhs) if reciprocal(rhs).is_none() || is_minus_one(lhs) => PRODUCT,
+        Term::Multiplication(..) | Term::Division(..) => POWER,
+        Term::Exponentation(..) => POWER,
+        _ => ATOM,
+    }
+}
+
+fn latex_operand(term: &Term, min: u8, out: &mut String) {
+    if latex_precedence(term) >= min {
+        latex(term, out);
+    } else {
+        out.push_str("\\left(");
+        latex(term, out);
+        out.push_str("\\right)");
+    }
+}
+
+fn latex_elements(elements: &[Term], out: &mut String) {
+    for (i, element) in elements.iter().enumerate() {
+        if i > 0 {
+            out.push_str(", ");
+        }
+        latex(element, out);
+    }
+}
+
+fn latex_fraction(numerator: &Term, denominator: &Term, out: &mut String) {
+    out.push_str("\\frac{");
+    latex(numerator, out);
+    out.push_str("}{");
+    latex(denominator, out);
+    out.push('}');
+}
+
+fn latex_number(value: &Number, out: &mut String) {
+    match value {
+        Number::Rational(value) if !value.is_integer() => {
+            if is_negative(&Number::Rational(*value)) {
+                out.push('-');
+            }
+            out.push_str(&format!(
+                "\\frac{{{}}}{{{}}}",
+                value.numer().abs(),
+                value.denom()
+            ));
+        }
+        value => out.push_str(&value.to_string()),
+    }
+}
+
+/// Single letters as they are, Greek letters as their commands, longer names upright, and
+/// anything after the first `_` as a subscript, so `x_1` becomes `x_{1}`. Names with characters
+/// other than letters, digits and that `_` are escaped and set upright as a whole, and names that
+/// would read back as a constant or function, like `e` or `sin`, are set as `\mathit{e}`.
+fn latex_variable(name: &str, out: &mut String) {
+    let (base, subscript) = match name.split_once('_') {
+        Some((base, subscript)) if !base.is_empty() && !subscript.is_empty() => {
+            (base, Some(subscript))
+        }
+        _ => (name, None),
+    };
+
+    let plain = |text: &str| text.chars().all(char::is_alphanumeric);
+    if !plain(base) || !subscript.is_none_or(plain) {
+        out.push_str(&format!("\\mathrm{{{}}}", escape_latex(name)));
+        return;
+    }
+
+    if (base == "e" && subscript.is_none()) || Function::from_name(base).is_some() {
+        out.push_str(&format!("\\mathit{{{base}}}"));
+    } else if base.chars().count() == 1 {
+        out.push_str(base);
+    } else if GREEK.contains(&base) {
+        out.push('\\');
+        out.push_str(base);
+    } else {
+        out.push_str(&format!("\\mathrm{{{base}}}"));
+    }
+    if let Some(subscript) = subscript {
+        out.push_str(&format!("_{{{subscript}}}"));
+    }
+}
+
+/// Whether `coefficient` can be written directly in front of `factor`, as in `2x`.
+pub(super) fn juxtaposes(coefficient: &Term, factor: &Term) -> bool {
+    let integer = matches!(coefficient, Term::Value(Number::Rational(value)) if value.is_integer());
+    let letter = |term: &Term| matches!(term, Term::Variable(_) | Term::Constant(_));
+
+    integer
+        && match factor {
+            Term::Exponentation(base, _) => letter(base),
+            Term::Function(..) | Term::RootExtraction(..) => true,
+            factor => letter(factor),
+        }
+}
+
+fn latex(term: &Term, out: &mut String) {
+    match term {
+        Term::Value(value) => latex_number(value, out),
+        Term::Variable(name) => latex_variable(name, out),
+        Term::Constant(constant) => out.push_str(match constant {
+            Constant::Pi => "\\pi",
+            Constant::E => "e",
+            Constant::Phi => "\\varphi",
+        }),
+        Term::Addition(lhs, rhs) => {
+            latex_operand(lhs, SUM, out);
+            match &**rhs {
+                Term::Value(value) if is_negative(value) => {
+                    out.push_str(" - ");
+                    latex_number(&absolute(value), out);
+                }
+                rhs => match negated(rhs) {
+                    Some(rhs) => {
+                        out.push_str(" - ");
+                        latex_operand(rhs, PRODUCT, out);
+                    }
+                    None => {
+                        out.push_str(" + ");
+                        latex_operand(rhs, PRODUCT, out);
+                    }
+                },
+            }
+        }
+        Term::Subtraction(lhs, rhs) => {
+            latex_operand(lhs, SUM, out);
+            out.push_str(" - ");
+            latex_operand(rhs, PRODUCT, out);
+        }
+        Term::Multiplication(lhs, rhs) => {
+            if is_minus_one(lhs) {
+                out.push('-');
+                return latex_operand(rhs, UNARY, out);
+            }
+            if let Some(divisor) = reciprocal(rhs) {
+                return latex_fraction(lhs, divisor, out);
+            }
+
+            latex_operand(lhs, PRODUCT, out);
+            if !juxtaposes(lhs, rhs) {
+                out.push_str(" \\cdot ");
+            }
+            latex_operand(rhs, UNARY, out);
+        }
+        Term::Division(lhs, rhs) => latex_fraction(lhs, rhs, out),
+        Term::Exponentation(base, power) => {
+            latex_operand(base, ATOM, out);
+            out.push_str("^{");
+            latex(power, out);
+            out.push('}');
+        }
+        Term::RootExtraction(radicand, degree) => {
+            if **degree == Term::Value(Number::Rational(Fraction::from(2))) {
+                out.push_str("\\sqrt{");
+            } else {
+                out.push_str("\\sqrt[");
+                latex(degree, out);
+                out.push_str("]{");
+            }
+            latex(radicand, out);
+            out.push('}');
+        }
+        Term::Function(function, argument) => {
+            let (open, close) = match function {
+                Function::Abs => ("\\left|", "\\right|"),
+                Function::Floor => ("\\left\\lfloor ", " \\right\\rfloor"),
+                Function::Ceil => ("\\left\\lceil ", " \\right\\rceil"),
+                // LaTeX has no commands for these
+                function if function.is_discrete() => {
+                    out.push_str("\\operatorname{");
+                    out.push_str(function.name());
+                    out.push('}');
+                    ("\\left(", "\\right)")
+                }
+                function => {
+                    out.push('\\');
+                    out.push_str(function.name());
+                    ("\\left(", "\\right)")
+                }
+            };
+            out.push_str(open);
+            latex_elements(function.arguments(argument), out);
+            out.push_str(close);
+        }
+        Term::Vector(items) => {
+            out.push_str("\\left[");
+            latex_elements(items, out);
+            out.push_str("\\right]");
+        }
+        Term::List(items) => {
+            out.push_str("\\left(");
+            latex_elements(items, out);
+            out.push_str("\\right)");
+        }
+        Term::Set(items) => {
+            out.push_str("\\left\\{");
+            latex_elements(items, out);
+            out.push_str("\\right\\}");
+        }
+        Term::Index(collection, index) => {
+            latex_operand(collection, ATOM, out);
+            out.push_str("_{");
+            latex(index, out);
+            out.push('}');
+        }
+    }
+}
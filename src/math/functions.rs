@@ -0,0 +1,376 @@
+use std::slice;
+
+use fraction::{Signed, ToPrimitive, Zero};
+
+use super::bits::BitError;
+use super::combinatorics;
+use super::nt::{self, Sequence};
+use super::terms::{EvalError, Fraction, Number, Term};
+use super::verify::Samples;
+
+/// A function of a fixed number of arguments. Functions of one argument apply to it directly,
+/// and functions of several to a `Term::List` of them, like `Function(Binomial, List([n, k]))`.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "serde", serde(rename_all = "lowercase"))]
+pub enum Function {
+    Sin,
+    Cos,
+    Tan,
+    /// The natural logarithm.
+    Ln,
+    Exp,
+    Abs,
+    Floor,
+    Ceil,
+    /// The `n`th prime, starting with `prime(1) = 2`. `Overflow` past `nt::MAX_PRIME_INDEX`.
+    Prime,
+    /// The `n`th Fibonacci number, starting with `fibonacci(0) = 0`.
+    Fibonacci,
+    /// The `n`th triangular number `n(n + 1)/2`.
+    Triangular,
+    /// `nCr(n, k)`, the ways to choose `k` of `n` things.
+    Binomial,
+    /// `nPr(n, k)`, the ways to arrange `k` of `n` things.
+    Permutations,
+    /// `bitand(a, b)`, like `Number::bit_and`.
+    BitAnd,
+    /// `bitor(a, b)`, like `Number::bit_or`.
+    BitOr,
+    /// `xor(a, b)`, like `Number::bit_xor`.
+    BitXor,
+    /// `shl(a, k)`, like `Number::shift_left`.
+    ShiftLeft,
+    /// `shr(a, k)`, like `Number::shift_right`.
+    ShiftRight,
+    /// `popcount(a)`, like `Number::popcount`.
+    Popcount,
+    /// The `n`th harmonic number, like `combinatorics::harmonic`.
+    Harmonic,
+    /// The `n`th Bernoulli number, like `combinatorics::bernoulli`.
+    Bernoulli,
+    /// `stirling1(n, k)`, like `combinatorics::stirling_first`.
+    StirlingFirst,
+    /// `stirling2(n, k)`, like `combinatorics::stirling_second`.
+    StirlingSecond,
+    /// `rand(seed, i)`, a reproducible pseudo-random rational in `[0, 1)`: sample `i` of the
+    /// generator seeded with `seed`.
+    Random,
+}
+
+impl Function {
+    pub const ALL: [Function; 24] = [
+        Function::Sin,
+        Function::Cos,
+        Function::Tan,
+        Function::Ln,
+        Function::Exp,
+        Function::Abs,
+        Function::Floor,
+        Function::Ceil,
+        Function::Prime,
+        Function::Fibonacci,
+        Function::Triangular,
+        Function::Binomial,
+        Function::Permutations,
+        Function::BitAnd,
+        Function::BitOr,
+        Function::BitXor,
+        Function::ShiftLeft,
+        Function::ShiftRight,
+        Function::Popcount,
+        Function::Harmonic,
+        Function::Bernoulli,
+        Function::StirlingFirst,
+        Function::StirlingSecond,
+        Function::Random,
+    ];
+
+    /// The name the parser and `Display` use, like `sin`.
+    pub fn name(self) -> &'static str {
+        match self {
+            Function::Sin => "sin",
+            Function::Cos => "cos",
+            Function::Tan => "tan",
+            Function::Ln => "ln",
+            Function::Exp => "exp",
+            Function::Abs => "abs",
+            Function::Floor => "floor",
+            Function::Ceil => "ceil",
+            Function::Prime => "prime",
+            Function::Fibonacci => "fibonacci",
+            Function::Triangular => "triangular",
+            Function::Binomial => "nCr",
+            Function::Permutations => "nPr",
+            Function::BitAnd => "bitand",
+            Function::BitOr => "bitor",
+            Function::BitXor => "xor",
+            Function::ShiftLeft => "shl",
+            Function::ShiftRight => "shr",
+            Function::Popcount => "popcount",
+            Function::Harmonic => "harmonic",
+            Function::Bernoulli => "bernoulli",
+            Function::StirlingFirst => "stirling1",
+            Function::StirlingSecond => "stirling2",
+            Function::Random => "rand",
+        }
+    }
+
+    /// The names of the parameters, for completions and documentation, like `n, k`.
+    pub fn parameters(self) -> &'static str {
+        match self {
+            Function::Binomial
+            | Function::Permutations
+            | Function::StirlingFirst
+            | Function::StirlingSecond => "n, k",
+            Function::BitAnd | Function::BitOr | Function::BitXor => "a, b",
+            Function::ShiftLeft | Function::ShiftRight => "a, k",
+            Function::Random => "seed, i",
+            Function::Prime
+            | Function::Fibonacci
+            | Function::Triangular
+            | Function::Harmonic
+            | Function::Bernoulli => "n",
+            Function::Popcount => "a",
+            _ => "x",
+        }
+    }
+
+    /// How many arguments the function takes.
+    pub fn arity(self) -> usize {
+        match self {
+            Function::Binomial
+            | Function::Permutations
+            | Function::BitAnd
+            | Function::BitOr
+            | Function::BitXor
+            | Function::ShiftLeft
+            | Function::ShiftRight
+            | Function::StirlingFirst
+            | Function::StirlingSecond
+            | Function::Random => 2,
+            _ => 1,
+        }
+    }
+
+    /// The arguments of this function applied to `argument`: the elements of the list holding
+    /// them for a function of several arguments, and `argument` itself otherwise.
+    pub fn arguments(self, argument: &Term) -> &[Term] {
+        match argument {
+            Term::List(elements) if self.arity() > 1 => elements,
+            argument => slice::from_ref(argument),
+        }
+    }
+
+    /// The term applying this function to `arguments`, or `None` if there are not `arity` of
+    /// them.
+    pub fn term(self, mut arguments: Vec<Term>) -> Option<Term> {
+        if arguments.len() != self.arity() {
+            return None;
+        }
+        let argument = match self.arity() {
+            1 => arguments.pop().unwrap(),
+            _ => Term::List(arguments),
+        };
+        Some(Term::Function(self, Box::new(argument)))
+    }
+
+    /// Whether the function is only defined at integers, like the sequences, `nCr` and the bit
+    /// operations. Its values are exact, and it has no derivative.
+    pub(crate) fn is_discrete(self) -> bool {
+        !matches!(
+            self,
+            Function::Sin
+                | Function::Cos
+                | Function::Tan
+                | Function::Ln
+                | Function::Exp
+                | Function::Abs
+                | Function::Floor
+                | Function::Ceil
+        )
+    }
+
+    pub fn from_name(name: &str) -> Option<Function> {
+        Function::ALL
+            .into_iter()
+            .find(|function| function.name() == name)
+    }
+
+    /// The exact value at `x`, if it is rational. `abs`, `floor` and `ceil` are exact for every
+    /// rational, the discrete functions at every valid argument, and the others only at points
+    /// like `ln(1) = 0` or `cos(0) = 1`.
+    pub fn apply_exact(self, x: Fraction) -> Option<Fraction> {
+        self.evaluate_exact(&[x])
+    }
+
+    /// Like `apply_exact`, for the arguments of a function of any arity.
+    pub fn evaluate_exact(self, arguments: &[Fraction]) -> Option<Fraction> {
+        let (zero, one) = (Fraction::zero(), Fraction::from(1));
+        if self.is_discrete() {
+            let arguments: Vec<Number> = arguments.iter().copied().map(Number::Rational).collect();
+            return match self.discrete(&arguments) {
+                Ok(Number::Rational(value)) => Some(value),
+                _ => None,
+            };
+        }
+        let &[x] = arguments else {
+            return None;
+        };
+        match self {
+            Function::Abs => Some(x.abs()),
+            Function::Floor => Some(x.floor()),
+            Function::Ceil => Some(x.ceil()),
+            Function::Sin | Function::Tan if x == zero => Some(zero),
+            Function::Cos | Function::Exp if x == zero => Some(one),
+            Function::Ln if x == one => Some(zero),
+            _ => None,
+        }
+    }
+
+    pub fn apply_f64(self, x: f64) -> f64 {
+        self.evaluate_f64(&[x])
+    }
+
+    /// Like `apply_f64`, for the arguments of a function of any arity. NaN where the function is
+    /// undefined.
+    pub fn evaluate_f64(self, arguments: &[f64]) -> f64 {
+        if self.is_discrete() {
+            let arguments: Vec<Number> =
+                arguments.iter().copied().map(Number::Irrational).collect();
+            return self.discrete(&arguments).map_or(f64::NAN, f64::from);
+        }
+        let &[x] = arguments else {
+            return f64::NAN;
+        };
+        match self {
+            Function::Sin => x.sin(),
+            Function::Cos => x.cos(),
+            Function::Tan => x.tan(),
+            Function::Ln => x.ln(),
+            Function::Exp => x.exp(),
+            Function::Abs => x.abs(),
+            Function::Floor => x.floor(),
+            Function::Ceil => x.ceil(),
+            _ => unreachable!("{self:?} is discrete"),
+        }
+    }
+
+    /// The value at `x`, exact where `apply_exact` is and in floating point otherwise.
+    /// Logarithms of non-positive numbers are `InvalidDomain`.
+    /// Discrete functions are `InvalidDomain` outside their domain, such as at non-integers or
+    /// below the first index of a sequence, and `Overflow` where their value does not fit in a
+    /// `Fraction`.
+    pub fn apply(self, x: Number) -> Result<Number, EvalError> {
+        self.evaluate(&[x])
+    }
+
+    /// Like `apply`, for the arguments of a function of any arity. `InvalidDomain` if there are
+    /// not `arity` of them.
+    pub fn evaluate(self, arguments: &[Number]) -> Result<Number, EvalError> {
+        if arguments.len() != self.arity() {
+            return Err(EvalError::InvalidDomain);
+        }
+        if self.is_discrete() {
+            return self.discrete(arguments);
+        }
+        let x = arguments[0];
+        if self == Function::Ln && f64::from(x) <= 0.0 {
+            return Err(EvalError::InvalidDomain);
+        }
+        if let Number::Rational(x) = x {
+            if let Some(value) = self.apply_exact(x) {
+                return Ok(Number::Rational(value));
+            }
+        }
+        Ok(Number::Irrational(self.apply_f64(x.into())))
+    }
+
+    /// Whether `evaluate` can compute a value at `arguments` without leaving exact arithmetic.
+    pub(crate) fn is_exact_at(self, arguments: &[Number]) -> bool {
+        let rationals: Option<Vec<Fraction>> = arguments
+            .iter()
+            .map(|argument| match argument {
+                Number::Rational(argument) => Some(*argument),
+                Number::Irrational(_) => None,
+            })
+            .collect();
+        rationals.is_some_and(|rationals| self.evaluate_exact(&rationals).is_some())
+    }
+
+    /// The value of a discrete function, whose arguments may be integers in either
+    /// representation.
+    fn discrete(self, arguments: &[Number]) -> Result<Number, EvalError> {
+        let integer = |value: i64| Number::Rational(Fraction::from(value));
+        let natural = |index: usize| natural(arguments[index]).ok_or(EvalError::InvalidDomain);
+        let small = |index: usize| u32::try_from(natural(index)?).map_err(|_| EvalError::Overflow);
+        let bits = |result: Result<Number, BitError>| {
+            result.map_err(|error| match error {
+                BitError::Overflow => EvalError::Overflow,
+                BitError::NotAnInteger(_) | BitError::Negative(_) => EvalError::InvalidDomain,
+            })
+        };
+        let argument = |index: usize| arguments[index].canonical();
+
+        let value = match self {
+            Function::Prime | Function::Fibonacci | Function::Triangular => {
+                let index = natural(0)?;
+                return match self.sequence_at(index) {
+                    Some(value) => Ok(value),
+                    None if index >= self.first_index() => Err(EvalError::Overflow),
+                    None => Err(EvalError::InvalidDomain),
+                };
+            }
+            Function::Binomial => combinatorics::binomial(natural(0)?, natural(1)?).map(integer),
+            Function::Permutations => {
+                combinatorics::permutations(natural(0)?, natural(1)?).map(integer)
+            }
+            Function::BitAnd => return bits(argument(0).bit_and(argument(1))),
+            Function::BitOr => return bits(argument(0).bit_or(argument(1))),
+            Function::BitXor => return bits(argument(0).bit_xor(argument(1))),
+            Function::ShiftLeft => return bits(argument(0).shift_left(argument(1))),
+            Function::ShiftRight => return bits(argument(0).shift_right(argument(1))),
+            Function::Popcount => return bits(argument(0).popcount()),
+            Function::Harmonic => combinatorics::harmonic(small(0)?).map(Number::Rational),
+            Function::Bernoulli => combinatorics::bernoulli(small(0)?).map(Number::Rational),
+            Function::StirlingFirst => {
+                combinatorics::stirling_first(small(0)?, small(1)?).map(integer)
+            }
+            Function::StirlingSecond => {
+                combinatorics::stirling_second(small(0)?, small(1)?).map(integer)
+            }
+            Function::Random => {
+                let sample = Samples::stream(natural(0)?, natural(1)?).next() >> 32;
+                Some(Number::Rational(Fraction::new(sample as i64, 1 << 32)))
+            }
+            _ => unreachable!("{self:?} is not discrete"),
+        };
+        value.ok_or(EvalError::Overflow)
+    }
+
+    fn first_index(self) -> u64 {
+        match self {
+            Function::Prime => nt::Primes::FIRST,
+            _ => 0,
+        }
+    }
+
+    /// The element of the sequence at `index`, for `prime`, `fibonacci` and `triangular`.
+    fn sequence_at(self, index: u64) -> Option<Number> {
+        match self {
+            Function::Prime => nt::primes().at(index),
+            Function::Fibonacci => nt::fibonacci().at(index),
+            Function::Triangular => nt::triangular().at(index),
+            _ => None,
+        }
+    }
+}
+
+/// `x` as a natural number, if it is one.
+fn natural(x: Number) -> Option<u64> {
+    match x {
+        Number::Rational(x) if x.is_integer() => x.to_integer().to_u64(),
+        Number::Irrational(x) if x.fract() == 0.0 => x.to_u64(),
+        _ => None,
+    }
+}
@@ -0,0 +1,265 @@
+use std::collections::BTreeMap;
+use std::ops::RangeInclusive;
+use std::thread;
+
+use super::matrix::Matrix;
+use super::numeric::{self, DomainError};
+use super::terms::Term;
+
+/// Values of a two-variable term on a regular grid. Row `i` holds the samples at `ys[i]` and
+/// column `j` those at `xs[j]`.
+#[derive(Clone, Debug)]
+pub struct Grid {
+    pub xs: Vec<f64>,
+    pub ys: Vec<f64>,
+    pub values: Matrix<Result<f64, DomainError>>,
+}
+
+/// A grid edge: the horizontal one from `(row, col)` to `(row, col + 1)`, or the vertical one from
+/// `(row, col)` to `(row + 1, col)`.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord)]
+struct Edge {
+    row: usize,
+    col: usize,
+    horizontal: bool,
+}
+
+impl Grid {
+    /// The polylines along which the sampled values cross `value`, by marching squares. Closed
+    /// curves end with their first point. Cells with an undefined corner are skipped.
+    pub fn level_set(&self, value: f64) -> Vec<Vec<(f64, f64)>> {
+        let mut points = BTreeMap::new();
+        let mut adjacency: BTreeMap<Edge, Vec<Edge>> = BTreeMap::new();
+
+        let mut connect = |a: Edge, b: Edge| {
+            adjacency.entry(a).or_default().push(b);
+            adjacency.entry(b).or_default().push(a);
+        };
+
+        for row in 0..self.values.rows().saturating_sub(1) {
+            for col in 0..self.values.cols().saturating_sub(1) {
+                let corner = |r, c| self.values.get(r, c).as_ref().ok().copied();
+                let (Some(v00), Some(v01), Some(v10), Some(v11)) = (
+                    corner(row, col),
+                    corner(row, col + 1),
+                    corner(row + 1, col),
+                    corner(row + 1, col + 1),
+                ) else {
+                    continue;
+                };
+
+                let (bottom, top, left, right) = (
+                    Edge {
+                        row,
+                        col,
+                        horizontal: true,
+                    },
+                    Edge {
+                        row: row + 1,
+                        col,
+                        horizontal: true,
+                    },
+                    Edge {
+                        row,
+                        col,
+                        horizontal: false,
+                    },
+                    Edge {
+                        row,
+                        col: col + 1,
+                        horizontal: false,
+                    },
+                );
+
+                let mut crossings = Vec::new();
+                for (edge, a, b) in [
+                    (bottom, v00, v01),
+                    (top, v10, v11),
+                    (left, v00, v10),
+                    (right, v01, v11),
+                ] {
+                    if (a > value) != (b > value) {
+                        points.insert(edge, self.crossing(edge, a, b, value));
+                        crossings.push(edge);
+                    }
+                }
+
+                match crossings.as_slice() {
+                    [a, b] => connect(*a, *b),
+                    [_, _, _, _] => {
+                        // A saddle: the average at the center decides which corners are cut off.
+                        let center = (v00 + v01 + v10 + v11) / 4.0;
+                        if (center > value) == (v00 > value) {
+                            connect(bottom, right);
+                            connect(left, top);
+                        } else {
+                            connect(bottom, left);
+                            connect(top, right);
+                        }
+                    }
+                    _ => {}
+                }
+            }
+        }
+
+        let mut polylines = Vec::new();
+        loop {
+            // Start open curves at an end so they are not split in two.
+            let start = adjacency
+                .iter()
+                .find(|(_, neighbours)| neighbours.len() == 1)
+                .or_else(|| {
+                    adjacency
+                        .iter()
+                        .find(|(_, neighbours)| !neighbours.is_empty())
+                })
+                .map(|(edge, _)| *edge);
+            let Some(start) = start else {
+                break;
+            };
+
+            let mut polyline = vec![points[&start]];
+            let mut current = start;
+            while let Some(next) = adjacency.get_mut(&current).and_then(Vec::pop) {
+                let back = adjacency.get_mut(&next).unwrap();
+                if let Some(position) = back.iter().position(|edge| *edge == current) {
+                    back.swap_remove(position);
+                }
+                polyline.push(points[&next]);
+                current = next;
+            }
+            adjacency.retain(|_, neighbours| !neighbours.is_empty());
+
+            polylines.push(polyline);
+        }
+
+        polylines
+    }
+
+    fn crossing(&self, edge: Edge, a: f64, b: f64, value: f64) -> (f64, f64) {
+        let t = (value - a) / (b - a);
+        let (x, y) = (self.xs[edge.col], self.ys[edge.row]);
+        if edge.horizontal {
+            (x + t * (self.xs[edge.col + 1] - x), y)
+        } else {
+            (x, y + t * (self.ys[edge.row + 1] - y))
+        }
+    }
+}
+
+impl Term {
+    /// The curves where the term equals `value` over the sampled region, see `Grid::level_set`.
+    pub fn level_set(
+        &self,
+        x_var: &str,
+        y_var: &str,
+        value: f64,
+        x_range: RangeInclusive<f64>,
+        y_range: RangeInclusive<f64>,
+        resolution: usize,
+    ) -> Vec<Vec<(f64, f64)>> {
+        self.sample_grid(x_var, y_var, x_range, y_range, resolution)
+            .level_set(value)
+    }
+
+    /// Samples the term at `resolution` evenly spaced points along each axis, endpoints included.
+    /// Cells where the term has no finite value hold the reason instead.
+    ///
+    /// The term is compiled once with `Term::compile` and each cell evaluated with
+    /// `CompiledExpr::eval`. Terms that do not compile, like those indexing by a variable, are
+    /// evaluated cell by cell with `numeric::try_evaluate` instead.
+    pub fn sample_grid(
+        &self,
+        x_var: &str,
+        y_var: &str,
+        x_range: RangeInclusive<f64>,
+        y_range: RangeInclusive<f64>,
+        resolution: usize,
+    ) -> Grid {
+        let (xs, ys) = (axis(&x_range, resolution), axis(&y_range, resolution));
+        let sample = self.sampler(x_var, y_var);
+        let entries = ys
+            .iter()
+            .flat_map(|y| sample_row(&sample, &xs, *y))
+            .collect();
+
+        Grid {
+            values: Matrix::new(ys.len(), xs.len(), entries).unwrap(),
+            xs,
+            ys,
+        }
+    }
+
+    /// Like `sample_grid`, but splits the rows between `threads` scoped threads.
+    pub fn sample_grid_parallel(
+        &self,
+        x_var: &str,
+        y_var: &str,
+        x_range: RangeInclusive<f64>,
+        y_range: RangeInclusive<f64>,
+        resolution: usize,
+        threads: usize,
+    ) -> Grid {
+        let (xs, ys) = (axis(&x_range, resolution), axis(&y_range, resolution));
+        let chunk = ys.len().div_ceil(threads.max(1)).max(1);
+        let sample = self.sampler(x_var, y_var);
+
+        let entries = thread::scope(|scope| {
+            let workers: Vec<_> = ys
+                .chunks(chunk)
+                .map(|rows| {
+                    let (xs, sample) = (&xs, &sample);
+                    scope.spawn(move || {
+                        rows.iter()
+                            .flat_map(|y| sample_row(sample, xs, *y))
+                            .collect::<Vec<_>>()
+                    })
+                })
+                .collect();
+
+            workers
+                .into_iter()
+                .flat_map(|worker| worker.join().unwrap())
+                .collect()
+        });
+
+        Grid {
+            values: Matrix::new(ys.len(), xs.len(), entries).unwrap(),
+            xs,
+            ys,
+        }
+    }
+
+    /// The value at `(x, y)` as `sample_grid` computes it.
+    fn sampler<'a>(
+        &'a self,
+        x_var: &'a str,
+        y_var: &'a str,
+    ) -> impl Fn(f64, f64) -> Result<f64, DomainError> + Sync + 'a {
+        let compiled = self.compile(&[x_var, y_var]);
+        move |x, y| match &compiled {
+            Ok(compiled) => Some(compiled.eval(&[x, y]))
+                .filter(|value| value.is_finite())
+                .ok_or(DomainError::NotFinite),
+            Err(_) => numeric::try_evaluate(self, &[(x_var, x), (y_var, y)]),
+        }
+    }
+}
+
+fn sample_row(
+    sample: &impl Fn(f64, f64) -> Result<f64, DomainError>,
+    xs: &[f64],
+    y: f64,
+) -> Vec<Result<f64, DomainError>> {
+    xs.iter().map(|x| sample(*x, y)).collect()
+}
+
+fn axis(range: &RangeInclusive<f64>, resolution: usize) -> Vec<f64> {
+    let (start, end) = (*range.start(), *range.end());
+    if resolution < 2 {
+        return vec![start; resolution];
+    }
+
+    let step = (end - start) / (resolution - 1) as f64;
+    (0..resolution).map(|i| start + step * i as f64).collect()
+}
@@ -0,0 +1,92 @@
+use std::ops::Range;
+
+use super::parser::{self, ParseError, Unit};
+use super::path::TermPath;
+use super::terms::Term;
+
+/// How `Document::edit` brought its term up to date.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum Reparse {
+    /// Only the bracketed primary now at this byte range of the source, like `(x + 1)` or
+    /// `sin(x)`, was parsed again. Everything around it was kept.
+    Region(Range<usize>),
+    /// The whole source was parsed again.
+    Full,
+}
+
+/// A source being edited, such as the contents of an input field, kept parsed as it changes.
+///
+/// An edit between the brackets of a parenthesized term, call, vector, set or list only reparses
+/// the innermost such primary around it, as long as that still parses on its own. Other edits,
+/// and edits to a source that does not parse, reparse everything. Either way `term` is what
+/// `Term::parse` returns for the current source.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Document {
+    source: String,
+    parsed: Result<(Term, Vec<Unit>), ParseError>,
+}
+
+impl Document {
+    pub fn new(source: impl Into<String>) -> Self {
+        let source = source.into();
+        let parsed = parser::parse_units(&source);
+        Document { source, parsed }
+    }
+
+    pub fn source(&self) -> &str {
+        &self.source
+    }
+
+    pub fn term(&self) -> Result<&Term, &ParseError> {
+        self.parsed.as_ref().map(|(term, _)| term)
+    }
+
+    /// Replaces `range` of the source with `text` and reparses what changed.
+    ///
+    /// Panics like `String::replace_range` if `range` is out of bounds or does not lie on `char`
+    /// boundaries.
+    pub fn edit(&mut self, range: Range<usize>, text: &str) -> Reparse {
+        self.source.replace_range(range.clone(), text);
+        match self.reparse_unit(range, text.len()) {
+            Some(region) => Reparse::Region(region),
+            None => {
+                self.parsed = parser::parse_units(&self.source);
+                Reparse::Full
+            }
+        }
+    }
+
+    /// Reparses the innermost unit around the edit of `range` to `inserted` bytes and splices it
+    /// into the term, or returns `None` if there is none or it no longer parses on its own.
+    fn reparse_unit(&mut self, range: Range<usize>, inserted: usize) -> Option<Range<usize>> {
+        let (term, units) = self.parsed.as_mut().ok()?;
+        let unit = units
+            .iter()
+            .filter(|unit| unit.open < range.start && range.end < unit.end)
+            .max_by_key(|unit| unit.start)?;
+        let (start, old_end, path) = (unit.start, unit.end, unit.path.clone());
+        // Every position from the end of the edit on moves by the change in length
+        let shift = |position: usize| position - range.len() + inserted;
+        let end = shift(old_end);
+
+        let (subterm, nested) = parser::parse_unit(&self.source[start..end], start).ok()?;
+        *term.subterm_mut(&path)? = subterm;
+
+        // Units are nested or disjoint, so this drops the reparsed unit and those inside it
+        units.retain(|unit| unit.start < start || unit.end > old_end);
+        for unit in units.iter_mut() {
+            if unit.start >= old_end {
+                unit.start = shift(unit.start);
+                unit.open = shift(unit.open);
+            }
+            if unit.end > old_end {
+                unit.end = shift(unit.end);
+            }
+        }
+        units.extend(nested.into_iter().map(|unit| Unit {
+            path: TermPath([&path.0[..], &unit.path.0[..]].concat()),
+            ..unit
+        }));
+        Some(start..end)
+    }
+}
@@ -0,0 +1,268 @@
+use std::cmp::Ordering;
+use std::ops::{Add, Mul, Neg, Sub};
+
+use super::terms::{Fraction, Number};
+
+/// Which ends of a range contain their endpoint.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Closedness {
+    Open,
+    Closed,
+    /// `(lo, hi]`
+    OpenClosed,
+    /// `[lo, hi)`
+    ClosedOpen,
+}
+
+impl Closedness {
+    pub fn from_ends(lo_closed: bool, hi_closed: bool) -> Self {
+        match (lo_closed, hi_closed) {
+            (false, false) => Closedness::Open,
+            (true, true) => Closedness::Closed,
+            (false, true) => Closedness::OpenClosed,
+            (true, false) => Closedness::ClosedOpen,
+        }
+    }
+
+    pub fn lo_closed(self) -> bool {
+        matches!(self, Closedness::Closed | Closedness::ClosedOpen)
+    }
+
+    pub fn hi_closed(self) -> bool {
+        matches!(self, Closedness::Closed | Closedness::OpenClosed)
+    }
+}
+
+/// An interval of the real line. Unbounded ends use infinite `Irrational` endpoints.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Range {
+    pub lo: Number,
+    pub hi: Number,
+    pub closedness: Closedness,
+}
+
+fn compare(a: &Number, b: &Number) -> Ordering {
+    match (a, b) {
+        (Number::Rational(a), Number::Rational(b)) => a.cmp(b),
+        _ => f64::from(*a).total_cmp(&f64::from(*b)),
+    }
+}
+
+fn negate(value: Number) -> Number {
+    value * Number::Rational(Fraction::from(-1))
+}
+
+/// A single endpoint, used to pick bounds out of candidate sets.
+#[derive(Clone, Copy)]
+struct Endpoint {
+    value: Number,
+    closed: bool,
+}
+
+impl Endpoint {
+    /// The smaller endpoint; on ties the result is closed if either one is.
+    fn min(self, other: Self) -> Self {
+        match compare(&self.value, &other.value) {
+            Ordering::Less => self,
+            Ordering::Greater => other,
+            Ordering::Equal => Self {
+                closed: self.closed || other.closed,
+                ..self
+            },
+        }
+    }
+
+    fn max(self, other: Self) -> Self {
+        match compare(&self.value, &other.value) {
+            Ordering::Greater => self,
+            Ordering::Less => other,
+            Ordering::Equal => Self {
+                closed: self.closed || other.closed,
+                ..self
+            },
+        }
+    }
+}
+
+impl Range {
+    pub fn new(lo: Number, hi: Number, closedness: Closedness) -> Self {
+        Self { lo, hi, closedness }
+    }
+
+    pub fn closed(lo: Number, hi: Number) -> Self {
+        Self::new(lo, hi, Closedness::Closed)
+    }
+
+    pub fn open(lo: Number, hi: Number) -> Self {
+        Self::new(lo, hi, Closedness::Open)
+    }
+
+    pub fn real_line() -> Self {
+        Self::open(
+            Number::Irrational(f64::NEG_INFINITY),
+            Number::Irrational(f64::INFINITY),
+        )
+    }
+
+    fn from_endpoints(lo: Endpoint, hi: Endpoint) -> Self {
+        Self::new(
+            lo.value,
+            hi.value,
+            Closedness::from_ends(lo.closed, hi.closed),
+        )
+    }
+
+    fn lower(&self) -> Endpoint {
+        Endpoint {
+            value: self.lo,
+            closed: self.closedness.lo_closed(),
+        }
+    }
+
+    fn upper(&self) -> Endpoint {
+        Endpoint {
+            value: self.hi,
+            closed: self.closedness.hi_closed(),
+        }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        match compare(&self.lo, &self.hi) {
+            Ordering::Less => false,
+            Ordering::Equal => self.closedness != Closedness::Closed,
+            Ordering::Greater => true,
+        }
+    }
+
+    pub fn contains(&self, value: Number) -> bool {
+        let above = match compare(&value, &self.lo) {
+            Ordering::Greater => true,
+            Ordering::Equal => self.closedness.lo_closed(),
+            Ordering::Less => false,
+        };
+        let below = match compare(&value, &self.hi) {
+            Ordering::Less => true,
+            Ordering::Equal => self.closedness.hi_closed(),
+            Ordering::Greater => false,
+        };
+
+        above && below
+    }
+
+    pub fn intersection(&self, other: &Range) -> Option<Range> {
+        // The larger lower bound is open if either bound at that value is open.
+        let lo = match compare(&self.lo, &other.lo) {
+            Ordering::Greater => self.lower(),
+            Ordering::Less => other.lower(),
+            Ordering::Equal => Endpoint {
+                closed: self.closedness.lo_closed() && other.closedness.lo_closed(),
+                ..self.lower()
+            },
+        };
+        let hi = match compare(&self.hi, &other.hi) {
+            Ordering::Less => self.upper(),
+            Ordering::Greater => other.upper(),
+            Ordering::Equal => Endpoint {
+                closed: self.closedness.hi_closed() && other.closedness.hi_closed(),
+                ..self.upper()
+            },
+        };
+
+        let range = Range::from_endpoints(lo, hi);
+        (!range.is_empty()).then_some(range)
+    }
+
+    /// The union as a list of disjoint ranges in ascending order.
+    pub fn union(&self, other: &Range) -> Vec<Range> {
+        let (first, second) = match compare(&self.lo, &other.lo) {
+            Ordering::Greater => (other, self),
+            _ => (self, other),
+        };
+
+        let touching = match compare(&first.hi, &second.lo) {
+            Ordering::Greater => true,
+            Ordering::Equal => first.closedness.hi_closed() || second.closedness.lo_closed(),
+            Ordering::Less => false,
+        };
+
+        if touching {
+            vec![Range::from_endpoints(
+                first.lower().min(second.lower()),
+                first.upper().max(second.upper()),
+            )]
+        } else {
+            vec![*first, *second]
+        }
+    }
+}
+
+impl Add for Range {
+    type Output = Range;
+
+    fn add(self, rhs: Range) -> Range {
+        let (lo, hi) = (self.lower(), self.upper());
+        let (other_lo, other_hi) = (rhs.lower(), rhs.upper());
+
+        Range::from_endpoints(
+            Endpoint {
+                value: lo.value + other_lo.value,
+                closed: lo.closed && other_lo.closed,
+            },
+            Endpoint {
+                value: hi.value + other_hi.value,
+                closed: hi.closed && other_hi.closed,
+            },
+        )
+    }
+}
+
+impl Neg for Range {
+    type Output = Range;
+
+    fn neg(self) -> Range {
+        Range::new(
+            negate(self.hi),
+            negate(self.lo),
+            Closedness::from_ends(self.closedness.hi_closed(), self.closedness.lo_closed()),
+        )
+    }
+}
+
+impl Sub for Range {
+    type Output = Range;
+
+    fn sub(self, rhs: Range) -> Range {
+        self + -rhs
+    }
+}
+
+impl Mul for Range {
+    type Output = Range;
+
+    fn mul(self, rhs: Range) -> Range {
+        let candidates = [
+            (self.lower(), rhs.lower()),
+            (self.lower(), rhs.upper()),
+            (self.upper(), rhs.lower()),
+            (self.upper(), rhs.upper()),
+        ]
+        .map(|(a, b)| {
+            // Zero times an unbounded end is zero, and a closed zero end makes the product zero
+            // whichever value the other range takes
+            let (a_zero, b_zero) = (f64::from(a.value) == 0.0, f64::from(b.value) == 0.0);
+            Endpoint {
+                value: if a_zero || b_zero {
+                    Number::Rational(Fraction::from(0))
+                } else {
+                    a.value * b.value
+                },
+                closed: a.closed && (b.closed || a_zero) || b.closed && b_zero,
+            }
+        });
+
+        let lo = candidates.into_iter().reduce(Endpoint::min).unwrap();
+        let hi = candidates.into_iter().reduce(Endpoint::max).unwrap();
+
+        Range::from_endpoints(lo, hi)
+    }
+}
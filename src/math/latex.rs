@@ -0,0 +1,504 @@
+use super::constants::Constant;
+use super::functions::Function;
+use super::parser::{negate, number, ParseError, ParseErrorKind};
+use super::terms::{Fraction, Number, Term};
+
+/// Lowercase and uppercase Greek letters that have a command of their own. `\pi` and `\phi` are
+/// read as constants instead.
+pub(super) const GREEK: [&str; 32] = [
+    "alpha",
+    "beta",
+    "gamma",
+    "delta",
+    "epsilon",
+    "varepsilon",
+    "zeta",
+    "eta",
+    "theta",
+    "vartheta",
+    "iota",
+    "kappa",
+    "lambda",
+    "mu",
+    "nu",
+    "xi",
+    "rho",
+    "sigma",
+    "tau",
+    "upsilon",
+    "chi",
+    "psi",
+    "omega",
+    "Gamma",
+    "Delta",
+    "Theta",
+    "Lambda",
+    "Xi",
+    "Sigma",
+    "Phi",
+    "Psi",
+    "Omega",
+];
+
+#[derive(Clone, Debug, PartialEq)]
+enum Token {
+    Number(Number),
+    Letter(char),
+    /// A command without its backslash, like `frac`. The escaped braces `\{` and `\}` are the
+    /// commands `{` and `}`.
+    Command(Box<str>),
+    Symbol(char),
+}
+
+fn tokenize(input: &str) -> Result<Vec<(Token, usize)>, ParseError> {
+    let mut tokens = Vec::new();
+    let mut chars = input.char_indices().peekable();
+
+    while let Some(&(position, c)) = chars.peek() {
+        if c.is_whitespace() {
+            chars.next();
+        } else if c.is_ascii_digit() || c == '.' {
+            let mut end = position;
+            while let Some(&(i, c)) = chars.peek() {
+                if !(c.is_ascii_digit() || c == '.') {
+                    break;
+                }
+                end = i + c.len_utf8();
+                chars.next();
+            }
+            tokens.push((
+                Token::Number(number(&input[position..end], position)?),
+                position,
+            ));
+        } else if c.is_alphabetic() {
+            tokens.push((Token::Letter(c), position));
+            chars.next();
+        } else if c == '\\' {
+            chars.next();
+            let start = position + 1;
+            let mut end = start;
+            while let Some(&(i, c)) = chars.peek() {
+                if !c.is_ascii_alphabetic() {
+                    break;
+                }
+                end = i + 1;
+                chars.next();
+            }
+
+            let name = if end > start {
+                &input[start..end]
+            } else {
+                match chars.next() {
+                    Some((i, c)) => &input[i..i + c.len_utf8()],
+                    None => {
+                        return Err(ParseError {
+                            kind: ParseErrorKind::UnexpectedCharacter('\\'),
+                            position,
+                        })
+                    }
+                }
+            };
+
+            match name {
+                // Sizing and spacing only affect the layout
+                "left" | "right" => {
+                    chars.next_if(|&(_, c)| c == '.');
+                }
+                "," | ";" | ":" | "!" | " " | "quad" | "qquad" => {}
+                name => tokens.push((Token::Command(name.into()), position)),
+            }
+        } else if "+-*/^_()[]{}|,".contains(c) {
+            tokens.push((Token::Symbol(c), position));
+            chars.next();
+        } else {
+            return Err(ParseError {
+                kind: ParseErrorKind::UnexpectedCharacter(c),
+                position,
+            });
+        }
+    }
+
+    Ok(tokens)
+}
+
+struct Parser {
+    tokens: Vec<(Token, usize)>,
+    index: usize,
+    end: usize,
+}
+
+impl Parser {
+    fn peek(&self) -> Option<&Token> {
+        self.tokens.get(self.index).map(|(token, _)| token)
+    }
+
+    fn position(&self) -> usize {
+        self.tokens
+            .get(self.index)
+            .map_or(self.end, |(_, position)| *position)
+    }
+
+    fn error(&self, kind: ParseErrorKind) -> ParseError {
+        ParseError {
+            kind,
+            position: self.position(),
+        }
+    }
+
+    fn unexpected(&self) -> ParseError {
+        match self.peek() {
+            Some(_) => self.error(ParseErrorKind::UnexpectedToken),
+            None => self.error(ParseErrorKind::UnexpectedEnd),
+        }
+    }
+
+    fn advance(&mut self) -> Option<Token> {
+        let token = self.peek().cloned()?;
+        self.index += 1;
+        Some(token)
+    }
+
+    fn eat(&mut self, symbol: char) -> bool {
+        self.eat_token(&Token::Symbol(symbol))
+    }
+
+    fn eat_command(&mut self, name: &str) -> bool {
+        if matches!(self.peek(), Some(Token::Command(command)) if &**command == name) {
+            self.index += 1;
+            true
+        } else {
+            false
+        }
+    }
+
+    fn eat_token(&mut self, token: &Token) -> bool {
+        if self.peek() == Some(token) {
+            self.index += 1;
+            true
+        } else {
+            false
+        }
+    }
+
+    fn close(&mut self, close: &Token, open: char, opened_at: usize) -> Result<(), ParseError> {
+        if self.eat_token(close) {
+            Ok(())
+        } else if self.peek().is_none() {
+            Err(ParseError {
+                kind: ParseErrorKind::Unclosed(open),
+                position: opened_at,
+            })
+        } else {
+            Err(self.unexpected())
+        }
+    }
+
+    /// `sum := product (('+' | '-') product)*`
+    fn sum(&mut self) -> Result<Term, ParseError> {
+        let mut lhs = self.product()?;
+        loop {
+            if self.eat('+') {
+                lhs = Term::Addition(Box::new(lhs), Box::new(self.product()?));
+            } else if self.eat('-') {
+                lhs = Term::Subtraction(Box::new(lhs), Box::new(self.product()?));
+            } else {
+                return Ok(lhs);
+            }
+        }
+    }
+
+    /// `product := unary (('\cdot' | '\times' | '*' | '/') unary | power)*`, where a factor
+    /// written directly after another, as in `2x`, is multiplied.
+    fn product(&mut self) -> Result<Term, ParseError> {
+        let mut lhs = self.unary()?;
+        loop {
+            if self.eat('*') || self.eat_command("cdot") || self.eat_command("times") {
+                lhs = Term::Multiplication(Box::new(lhs), Box::new(self.unary()?));
+            } else if self.eat('/') || self.eat_command("div") {
+                lhs = Term::Division(Box::new(lhs), Box::new(self.unary()?));
+            } else if self.starts_factor() {
+                lhs = Term::Multiplication(Box::new(lhs), Box::new(self.power()?));
+            } else {
+                return Ok(lhs);
+            }
+        }
+    }
+
+    fn starts_factor(&self) -> bool {
+        match self.peek() {
+            Some(Token::Number(_) | Token::Letter(_)) => true,
+            Some(Token::Symbol(c)) => "([{".contains(*c),
+            Some(Token::Command(name)) => {
+                !matches!(&**name, "cdot" | "times" | "div" | "rfloor" | "rceil" | "}")
+            }
+            None => false,
+        }
+    }
+
+    /// `unary := ('-' | '+') unary | power`
+    fn unary(&mut self) -> Result<Term, ParseError> {
+        if self.eat('-') {
+            Ok(negate(self.unary()?))
+        } else if self.eat('+') {
+            self.unary()
+        } else {
+            self.power()
+        }
+    }
+
+    /// `power := postfix ('^' argument)?`
+    fn power(&mut self) -> Result<Term, ParseError> {
+        let base = self.postfix()?;
+        if self.eat('^') {
+            Ok(Term::Exponentation(
+                Box::new(base),
+                Box::new(self.argument()?),
+            ))
+        } else {
+            Ok(base)
+        }
+    }
+
+    /// `postfix := primary ('_' argument)*`, indexing collections as in `v_{0}`.
+    fn postfix(&mut self) -> Result<Term, ParseError> {
+        let mut term = self.primary()?;
+        while self.eat('_') {
+            term = Term::Index(Box::new(term), Box::new(self.argument()?));
+        }
+        Ok(term)
+    }
+
+    /// A braced group or a single number, letter or symbol command, as after `^` or `\frac`.
+    fn argument(&mut self) -> Result<Term, ParseError> {
+        let opened_at = self.position();
+        if self.eat('{') {
+            let term = self.sum()?;
+            self.close(&Token::Symbol('}'), '{', opened_at)?;
+            return Ok(term);
+        }
+
+        match self.peek() {
+            Some(Token::Number(_) | Token::Letter(_) | Token::Command(_)) => self.primary(),
+            _ => Err(self.unexpected()),
+        }
+    }
+
+    /// The raw letters and digits of a braced group, as in `\mathrm{abc}`.
+    fn text(&mut self) -> Result<String, ParseError> {
+        let opened_at = self.position();
+        if !self.eat('{') {
+            return Err(self.unexpected());
+        }
+
+        let mut text = String::new();
+        loop {
+            match self.advance() {
+                Some(Token::Letter(c)) => text.push(c),
+                Some(Token::Number(value)) => text.push_str(&value.to_string()),
+                Some(Token::Symbol('}')) if !text.is_empty() => return Ok(text),
+                Some(_) => {
+                    self.index -= 1;
+                    return Err(self.unexpected());
+                }
+                None => {
+                    return Err(ParseError {
+                        kind: ParseErrorKind::Unclosed('{'),
+                        position: opened_at,
+                    })
+                }
+            }
+        }
+    }
+
+    /// A variable named `name`, with a subscript like `x_{1}` becoming part of the name.
+    fn variable(&mut self, name: String) -> Result<Term, ParseError> {
+        let start = self.index;
+        if !self.eat('_') {
+            return Ok(Term::Variable(name.into()));
+        }
+
+        let subscript = match self.peek() {
+            Some(Token::Symbol('{')) => self.text().ok(),
+            Some(Token::Letter(c)) => Some(c.to_string()),
+            Some(Token::Number(Number::Rational(value))) if value.is_integer() => {
+                Some(value.to_string())
+            }
+            _ => None,
+        };
+        match subscript {
+            Some(subscript) => {
+                if !matches!(self.tokens[self.index - 1].0, Token::Symbol('}')) {
+                    self.index += 1;
+                }
+                Ok(Term::Variable(format!("{name}_{subscript}").into()))
+            }
+            None => {
+                // Not part of the name, so `postfix` reads it as an index
+                self.index = start;
+                Ok(Term::Variable(name.into()))
+            }
+        }
+    }
+
+    /// The argument of a function written `\sin x`, `\sin(x)` or `\sin^2 x`. An argument in
+    /// parentheses ends there, so `\sin(x)^2` is the square of `\sin(x)`, while `\sin x^2` is
+    /// the sine of `x^2`.
+    fn apply(&mut self, function: Function) -> Result<Term, ParseError> {
+        let exponent = if self.eat('^') {
+            Some(self.argument()?)
+        } else {
+            None
+        };
+        let argument = if self.peek() == Some(&Token::Symbol('(')) {
+            self.primary()?
+        } else {
+            self.power()?
+        };
+        let term = Term::Function(function, Box::new(argument));
+        Ok(match exponent {
+            Some(exponent) => Term::Exponentation(Box::new(term), Box::new(exponent)),
+            None => term,
+        })
+    }
+
+    /// Comma-separated sums up to and including `close`.
+    fn elements(
+        &mut self,
+        close: &Token,
+        open: char,
+        opened_at: usize,
+    ) -> Result<Vec<Term>, ParseError> {
+        let mut elements = Vec::new();
+        loop {
+            if self.eat_token(close) {
+                return Ok(elements);
+            }
+            elements.push(self.sum()?);
+            if !self.eat(',') {
+                self.close(close, open, opened_at)?;
+                return Ok(elements);
+            }
+        }
+    }
+
+    fn primary(&mut self) -> Result<Term, ParseError> {
+        let opened_at = self.position();
+        let token = self.advance().ok_or_else(|| self.unexpected())?;
+
+        match token {
+            Token::Number(value) => Ok(Term::Value(value)),
+            Token::Letter('e') if self.peek() != Some(&Token::Symbol('_')) => {
+                Ok(Term::Constant(Constant::E))
+            }
+            Token::Letter(c) => self.variable(c.to_string()),
+            Token::Symbol('(') => {
+                let mut elements = self.elements(&Token::Symbol(')'), '(', opened_at)?;
+                if elements.len() == 1 {
+                    Ok(elements.pop().unwrap())
+                } else {
+                    Ok(Term::List(elements))
+                }
+            }
+            Token::Symbol('[') => Ok(Term::Vector(self.elements(
+                &Token::Symbol(']'),
+                '[',
+                opened_at,
+            )?)),
+            Token::Symbol('{') => {
+                let term = self.sum()?;
+                self.close(&Token::Symbol('}'), '{', opened_at)?;
+                Ok(term)
+            }
+            Token::Symbol('|') => {
+                let term = self.sum()?;
+                self.close(&Token::Symbol('|'), '|', opened_at)?;
+                Ok(Term::Function(Function::Abs, Box::new(term)))
+            }
+            Token::Symbol(_) => {
+                self.index -= 1;
+                Err(self.unexpected())
+            }
+            Token::Command(name) => self.command(&name, opened_at),
+        }
+    }
+
+    fn command(&mut self, name: &str, opened_at: usize) -> Result<Term, ParseError> {
+        match name {
+            "frac" | "dfrac" | "tfrac" => {
+                let numerator = self.argument()?;
+                let denominator = self.argument()?;
+                Ok(Term::Division(Box::new(numerator), Box::new(denominator)))
+            }
+            "sqrt" => {
+                let degree = if self.eat('[') {
+                    let degree = self.sum()?;
+                    self.close(&Token::Symbol(']'), '[', opened_at)?;
+                    degree
+                } else {
+                    Term::Value(Number::Rational(Fraction::from(2)))
+                };
+                let radicand = self.argument()?;
+                Ok(Term::RootExtraction(Box::new(radicand), Box::new(degree)))
+            }
+            "lfloor" | "lceil" => {
+                let (function, open, close) = match name {
+                    "lfloor" => (Function::Floor, '⌊', "rfloor"),
+                    _ => (Function::Ceil, '⌈', "rceil"),
+                };
+                let term = self.sum()?;
+                self.close(&Token::Command(close.into()), open, opened_at)?;
+                Ok(Term::Function(function, Box::new(term)))
+            }
+            "{" => Ok(Term::Set(self.elements(
+                &Token::Command("}".into()),
+                '{',
+                opened_at,
+            )?)),
+            // Always a variable, for names that would otherwise read as constants or functions
+            "mathit" => {
+                let name = self.text()?;
+                self.variable(name)
+            }
+            "mathrm" | "operatorname" | "text" => {
+                let name = self.text()?;
+                match Function::from_name(&name) {
+                    Some(function) => self.apply(function),
+                    None => self.variable(name),
+                }
+            }
+            "pi" => Ok(Term::Constant(Constant::Pi)),
+            "phi" | "varphi" => Ok(Term::Constant(Constant::Phi)),
+            name if GREEK.contains(&name) => self.variable(name.to_string()),
+            name => match Function::from_name(name) {
+                Some(function) => self.apply(function),
+                None => Err(ParseError {
+                    kind: ParseErrorKind::UnknownCommand(name.into()),
+                    position: opened_at,
+                }),
+            },
+        }
+    }
+}
+
+impl Term {
+    /// Parses LaTeX math-mode markup such as `\frac{x^2}{2} + 3\sqrt[3]{y}` or
+    /// `2\pi r \cdot \sin\left(\theta\right)`, so that `to_latex` output reads back.
+    ///
+    /// Single letters are variables (and `e` is Euler's number), factors written next to each
+    /// other are multiplied, `x_1`, `\mathrm{speed}` and `\mathit{e}` are variables of those
+    /// names, and
+    /// `\left`, `\right` and spacing commands are ignored.
+    pub fn from_latex(input: &str) -> Result<Term, ParseError> {
+        let mut parser = Parser {
+            tokens: tokenize(input)?,
+            index: 0,
+            end: input.len(),
+        };
+
+        let term = parser.sum()?;
+        if parser.peek().is_some() {
+            return Err(parser.unexpected());
+        }
+        Ok(term)
+    }
+}
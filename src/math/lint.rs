@@ -0,0 +1,182 @@
+use super::correction::Tokens;
+use super::numeric::{self, DomainError};
+use super::path::TermPath;
+use super::terms::Term;
+use super::values::is_minus_one;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum LintKind {
+    /// A division by, or negative power of, a subterm that is zero.
+    DivisionByZero,
+    /// A division by, or negative power of, a subterm that depends on variables and may be zero.
+    PossibleDivisionByZero,
+    /// `a - a` or `a / a`, which could be written as a constant.
+    SelfCancellation,
+    /// `0^0`, which is ambiguous.
+    ZeroToZero,
+    /// An even root of a negative constant, which has no real value.
+    EvenRootOfNegative,
+    /// A division by, or negative power of, a subterm without variables whose value is infinite
+    /// or NaN.
+    NonFiniteValue,
+    /// Parentheses that can be left out without changing the term, like those in `2*(x)`. Only
+    /// reported by `lint_source`.
+    RedundantParentheses,
+    /// Operands written next to each other, like `2x`, which do not parse. Only reported by
+    /// `lint_source`; `suggest_corrections` inserts the missing `*`.
+    ImplicitMultiplication,
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Lint {
+    pub at: TermPath,
+    pub kind: LintKind,
+}
+
+/// A warning about how an input is written, at a byte offset into it.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct SourceLint {
+    pub at: usize,
+    pub kind: LintKind,
+}
+
+/// Warnings about how `input` is written, in order of position: `RedundantParentheses` at each
+/// opening parenthesis that can be left out, if `input` parses, and `ImplicitMultiplication` at
+/// each operand that directly follows another. All the redundant parentheses can be left out at
+/// once, so of `((x))` only the inner pair is reported. Use `Term::lint` for mistakes in the
+/// term itself.
+pub fn lint_source(input: &str) -> Vec<SourceLint> {
+    let Some(tokens) = Tokens::new(input) else {
+        return Vec::new();
+    };
+    let mut lints = Vec::new();
+
+    if let Ok(term) = Term::parse(input) {
+        // The input with the redundant parentheses found so far left out
+        let mut stripped = input.to_string();
+        let mut open = Vec::new();
+        for i in 0..tokens.len() {
+            match tokens.symbol(i) {
+                Some('(' | '[' | '{') => open.push(i),
+                Some(')' | ']' | '}') => {
+                    let Some(start) = open.pop() else { break };
+                    if tokens.symbol(start) != Some('(') {
+                        continue;
+                    }
+                    // Blanking them out keeps the neighbouring tokens apart
+                    let (lhs, rhs) = (tokens.start(start), tokens.start(i));
+                    let mut without = stripped.clone();
+                    without.replace_range(lhs..lhs + 1, " ");
+                    without.replace_range(rhs..rhs + 1, " ");
+                    if Term::parse(&without).is_ok_and(|without| without == term) {
+                        stripped = without;
+                        lints.push(SourceLint {
+                            at: lhs,
+                            kind: LintKind::RedundantParentheses,
+                        });
+                    }
+                }
+                _ => {}
+            }
+        }
+    }
+
+    for i in 1..tokens.len() {
+        if tokens.implicit_product(i - 1, i) {
+            lints.push(SourceLint {
+                at: tokens.start(i),
+                kind: LintKind::ImplicitMultiplication,
+            });
+        }
+    }
+
+    lints.sort_by_key(|lint| lint.at);
+    lints
+}
+
+impl Term {
+    /// Warnings about likely mistakes, in depth-first order.
+    pub fn lint(&self) -> Vec<Lint> {
+        let mut lints = Vec::new();
+        self.lint_at(TermPath::root(), &mut lints);
+        lints
+    }
+
+    fn lint_at(&self, path: TermPath, lints: &mut Vec<Lint>) {
+        let mut warn = |kind| {
+            lints.push(Lint {
+                at: path.clone(),
+                kind,
+            })
+        };
+
+        match self {
+            Term::Addition(lhs, rhs) if negation_of(lhs, rhs) || negation_of(rhs, lhs) => {
+                warn(LintKind::SelfCancellation)
+            }
+            Term::Multiplication(lhs, rhs)
+                if reciprocal_of(lhs, rhs) || reciprocal_of(rhs, lhs) =>
+            {
+                warn(LintKind::SelfCancellation)
+            }
+            Term::Subtraction(lhs, rhs) if lhs == rhs => warn(LintKind::SelfCancellation),
+            Term::Division(lhs, rhs) if lhs == rhs => warn(LintKind::SelfCancellation),
+            Term::Division(_, rhs) => match constant(rhs) {
+                Some(0.0) => warn(LintKind::DivisionByZero),
+                Some(_) => {}
+                None if non_finite(rhs) => warn(LintKind::NonFiniteValue),
+                None => warn(LintKind::PossibleDivisionByZero),
+            },
+            Term::Exponentation(base, power) => match (constant(base), constant(power)) {
+                (Some(base), Some(power)) if base == 0.0 && power == 0.0 => {
+                    warn(LintKind::ZeroToZero)
+                }
+                (Some(base), Some(power)) if base == 0.0 && power < 0.0 => {
+                    warn(LintKind::DivisionByZero)
+                }
+                (None, Some(power)) if power < 0.0 && non_finite(base) => {
+                    warn(LintKind::NonFiniteValue)
+                }
+                (None, Some(power)) if power < 0.0 => warn(LintKind::PossibleDivisionByZero),
+                _ => {}
+            },
+            Term::RootExtraction(radicand, degree) => {
+                if let (Some(radicand), Some(degree)) = (constant(radicand), constant(degree)) {
+                    if radicand < 0.0 && degree.fract() == 0.0 && degree % 2.0 == 0.0 {
+                        warn(LintKind::EvenRootOfNegative)
+                    }
+                }
+            }
+            _ => {}
+        }
+
+        for (index, child) in self.children().into_iter().enumerate() {
+            child.lint_at(path.child(index), lints);
+        }
+    }
+}
+
+/// The value of a subterm without free variables.
+fn constant(term: &Term) -> Option<f64> {
+    numeric::try_evaluate(term, &[]).ok()
+}
+
+/// Whether `term` has no free variables but evaluates to infinity or NaN.
+fn non_finite(term: &Term) -> bool {
+    numeric::try_evaluate(term, &[]) == Err(DomainError::NotFinite)
+}
+
+/// Whether `negated` is `-1 * term` or `term * -1`.
+fn negation_of(term: &Term, negated: &Term) -> bool {
+    match negated {
+        Term::Multiplication(lhs, rhs) => {
+            (is_minus_one(lhs) && **rhs == *term) || (is_minus_one(rhs) && **lhs == *term)
+        }
+        _ => false,
+    }
+}
+
+/// Whether `reciprocal` is `term^-1`.
+fn reciprocal_of(term: &Term, reciprocal: &Term) -> bool {
+    matches!(reciprocal, Term::Exponentation(base, power) if is_minus_one(power) && **base == *term)
+}
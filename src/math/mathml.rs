@@ -0,0 +1,203 @@
+use super::display::{ATOM, PRODUCT, SUM, UNARY};
+use super::escape::escape_xml;
+use super::format::{juxtaposes, latex_precedence};
+use super::functions::Function;
+use super::terms::{Fraction, Number, Term};
+use super::values::{absolute, is_minus_one, is_negative, negated, reciprocal};
+
+impl Term {
+    /// Presentation MathML for the term, as a `<math>` element that can be embedded in HTML.
+    /// The layout follows `to_latex`, with `<mfrac>` for fractions, `<msup>` for powers and
+    /// `<msqrt>` and `<mroot>` for roots.
+    pub fn to_mathml(&self) -> String {
+        let mut out = String::from("<math xmlns=\"http://www.w3.org/1998/Math/MathML\">");
+        mathml(self, &mut out);
+        out.push_str("</math>");
+        out
+    }
+}
+
+fn operator(symbol: &str, out: &mut String) {
+    out.push_str("<mo>");
+    out.push_str(symbol);
+    out.push_str("</mo>");
+}
+
+/// `term` as a single element, so it can be a child of `<mfrac>`, `<msup>` and the like.
+fn row(term: &Term, out: &mut String) {
+    out.push_str("<mrow>");
+    mathml(term, out);
+    out.push_str("</mrow>");
+}
+
+fn fenced(open: &str, close: &str, out: &mut String, inner: impl FnOnce(&mut String)) {
+    out.push_str("<mrow>");
+    operator(open, out);
+    inner(out);
+    operator(close, out);
+    out.push_str("</mrow>");
+}
+
+fn operand(term: &Term, min: u8, out: &mut String) {
+    if latex_precedence(term) >= min {
+        mathml(term, out);
+    } else {
+        fenced("(", ")", out, |out| mathml(term, out));
+    }
+}
+
+fn elements(elements: &[Term], out: &mut String) {
+    for (i, element) in elements.iter().enumerate() {
+        if i > 0 {
+            operator(",", out);
+        }
+        mathml(element, out);
+    }
+}
+
+fn fraction(numerator: &Term, denominator: &Term, out: &mut String) {
+    out.push_str("<mfrac>");
+    row(numerator, out);
+    row(denominator, out);
+    out.push_str("</mfrac>");
+}
+
+fn number(value: &Number, out: &mut String) {
+    if is_negative(value) {
+        out.push_str("<mrow>");
+        operator("&#x2212;", out);
+        number(&absolute(value), out);
+        out.push_str("</mrow>");
+        return;
+    }
+    match value {
+        Number::Rational(value) if !value.is_integer() => out.push_str(&format!(
+            "<mfrac><mn>{}</mn><mn>{}</mn></mfrac>",
+            value.numer(),
+            value.denom()
+        )),
+        value => out.push_str(&format!("<mn>{value}</mn>")),
+    }
+}
+
+/// Names are escaped, and anything after the first `_` becomes a subscript as in `to_latex`.
+fn variable(name: &str, out: &mut String) {
+    match name.split_once('_') {
+        Some((base, subscript)) if !base.is_empty() && !subscript.is_empty() => {
+            out.push_str(&format!(
+                "<msub><mi>{}</mi><mi>{}</mi></msub>",
+                escape_xml(base),
+                escape_xml(subscript)
+            ));
+        }
+        _ => out.push_str(&format!("<mi>{}</mi>", escape_xml(name))),
+    }
+}
+
+fn mathml(term: &Term, out: &mut String) {
+    match term {
+        Term::Value(value) => number(value, out),
+        Term::Variable(name) => variable(name, out),
+        Term::Constant(constant) => out.push_str(&format!("<mi>{}</mi>", constant.symbol())),
+        Term::Addition(lhs, rhs) => {
+            out.push_str("<mrow>");
+            operand(lhs, SUM, out);
+            match &**rhs {
+                Term::Value(value) if is_negative(value) => {
+                    operator("&#x2212;", out);
+                    number(&absolute(value), out);
+                }
+                rhs => match negated(rhs) {
+                    Some(rhs) => {
+                        operator("&#x2212;", out);
+                        operand(rhs, PRODUCT, out);
+                    }
+                    None => {
+                        operator("+", out);
+                        operand(rhs, PRODUCT, out);
+                    }
+                },
+            }
+            out.push_str("</mrow>");
+        }
+        Term::Subtraction(lhs, rhs) => {
+            out.push_str("<mrow>");
+            operand(lhs, SUM, out);
+            operator("&#x2212;", out);
+            operand(rhs, PRODUCT, out);
+            out.push_str("</mrow>");
+        }
+        Term::Multiplication(lhs, rhs) => {
+            if let Some(divisor) = reciprocal(rhs).filter(|_| !is_minus_one(lhs)) {
+                return fraction(lhs, divisor, out);
+            }
+            out.push_str("<mrow>");
+            if is_minus_one(lhs) {
+                operator("&#x2212;", out);
+            } else {
+                operand(lhs, PRODUCT, out);
+                // Invisible times for `2x`, a dot otherwise
+                operator(
+                    if juxtaposes(lhs, rhs) {
+                        "&#x2062;"
+                    } else {
+                        "&#x22C5;"
+                    },
+                    out,
+                );
+            }
+            operand(rhs, UNARY, out);
+            out.push_str("</mrow>");
+        }
+        Term::Division(lhs, rhs) => fraction(lhs, rhs, out),
+        Term::Exponentation(base, power) => {
+            out.push_str("<msup>");
+            out.push_str("<mrow>");
+            operand(base, ATOM, out);
+            out.push_str("</mrow>");
+            row(power, out);
+            out.push_str("</msup>");
+        }
+        Term::RootExtraction(radicand, degree) => {
+            if **degree == Term::Value(Number::Rational(Fraction::from(2))) {
+                out.push_str("<msqrt>");
+                mathml(radicand, out);
+                out.push_str("</msqrt>");
+            } else {
+                out.push_str("<mroot>");
+                row(radicand, out);
+                row(degree, out);
+                out.push_str("</mroot>");
+            }
+        }
+        Term::Function(function, argument) => {
+            let (open, close) = match function {
+                Function::Abs => ("|", "|"),
+                Function::Floor => ("&#x230A;", "&#x230B;"),
+                Function::Ceil => ("&#x2308;", "&#x2309;"),
+                function => {
+                    out.push_str("<mrow>");
+                    out.push_str(&format!("<mi>{}</mi>", function.name()));
+                    // Function application
+                    operator("&#x2061;", out);
+                    fenced("(", ")", out, |out| {
+                        elements(function.arguments(argument), out)
+                    });
+                    out.push_str("</mrow>");
+                    return;
+                }
+            };
+            fenced(open, close, out, |out| mathml(argument, out));
+        }
+        Term::Vector(items) => fenced("[", "]", out, |out| elements(items, out)),
+        Term::List(items) => fenced("(", ")", out, |out| elements(items, out)),
+        Term::Set(items) => fenced("{", "}", out, |out| elements(items, out)),
+        Term::Index(collection, index) => {
+            out.push_str("<msub><mrow>");
+            operand(collection, ATOM, out);
+            out.push_str("</mrow>");
+            row(index, out);
+            out.push_str("</msub>");
+        }
+    }
+}
@@ -0,0 +1,435 @@
+use std::ops::{Add, Mul};
+
+use fraction::{One, ToPrimitive, Zero};
+
+use super::budget::Budget;
+use super::cancel::{CancellationToken, Cancelled};
+use super::polynomial::Polynomial;
+use super::terms::{Fraction, Number, Term};
+use super::values::{as_rational, rational};
+
+const QR_ITERATIONS: usize = 500;
+
+/// A dense row-major matrix.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Matrix<T> {
+    rows: usize,
+    cols: usize,
+    entries: Vec<T>,
+}
+
+impl<T> Matrix<T> {
+    pub fn new(rows: usize, cols: usize, entries: Vec<T>) -> Option<Self> {
+        (entries.len() == rows * cols).then_some(Self {
+            rows,
+            cols,
+            entries,
+        })
+    }
+
+    pub fn from_rows(rows: Vec<Vec<T>>) -> Option<Self> {
+        let cols = rows.first().map_or(0, Vec::len);
+        if rows.iter().any(|row| row.len() != cols) {
+            return None;
+        }
+
+        let count = rows.len();
+        Self::new(count, cols, rows.into_iter().flatten().collect())
+    }
+
+    pub fn rows(&self) -> usize {
+        self.rows
+    }
+
+    pub fn cols(&self) -> usize {
+        self.cols
+    }
+
+    pub fn is_square(&self) -> bool {
+        self.rows == self.cols
+    }
+
+    pub fn get(&self, row: usize, col: usize) -> &T {
+        &self.entries[row * self.cols + col]
+    }
+
+    pub fn entries(&self) -> &[T] {
+        &self.entries
+    }
+}
+
+impl<T: Clone> Matrix<T> {
+    /// The matrix with `row` and `col` removed.
+    pub fn minor(&self, row: usize, col: usize) -> Self {
+        let entries = (0..self.rows)
+            .filter(|&r| r != row)
+            .flat_map(|r| {
+                (0..self.cols)
+                    .filter(move |&c| c != col)
+                    .map(move |c| self.get(r, c).clone())
+            })
+            .collect();
+
+        Self {
+            rows: self.rows - 1,
+            cols: self.cols - 1,
+            entries,
+        }
+    }
+
+    pub fn transpose(&self) -> Self {
+        let entries = (0..self.cols)
+            .flat_map(|c| (0..self.rows).map(move |r| self.get(r, c).clone()))
+            .collect();
+
+        Self {
+            rows: self.cols,
+            cols: self.rows,
+            entries,
+        }
+    }
+}
+
+impl<T: Copy + Zero + One> Matrix<T> {
+    pub fn identity(size: usize) -> Self {
+        let entries = (0..size * size)
+            .map(|i| {
+                if i / size == i % size {
+                    T::one()
+                } else {
+                    T::zero()
+                }
+            })
+            .collect();
+
+        Self {
+            rows: size,
+            cols: size,
+            entries,
+        }
+    }
+
+    pub fn trace(&self) -> T {
+        (0..self.rows.min(self.cols)).fold(T::zero(), |acc, i| acc + *self.get(i, i))
+    }
+}
+
+impl<T: Copy + Zero + Add<Output = T> + Mul<Output = T>> Matrix<T> {
+    pub fn multiply(&self, rhs: &Self) -> Option<Self> {
+        if self.cols != rhs.rows {
+            return None;
+        }
+
+        let entries = (0..self.rows)
+            .flat_map(|row| (0..rhs.cols).map(move |col| (row, col)))
+            .map(|(row, col)| {
+                (0..self.cols).fold(T::zero(), |acc, k| {
+                    acc + *self.get(row, k) * *rhs.get(k, col)
+                })
+            })
+            .collect();
+
+        Matrix::new(self.rows, rhs.cols, entries)
+    }
+}
+
+impl Matrix<Fraction> {
+    /// The characteristic polynomial `det(var * I - A)`, computed with the Faddeev-LeVerrier
+    /// recurrence.
+    pub fn characteristic_polynomial(&self, var: &str) -> Option<Polynomial> {
+        if !self.is_square() {
+            return None;
+        }
+
+        let n = self.rows;
+        let mut coefficients = vec![Fraction::zero(); n + 1];
+        coefficients[n] = Fraction::one();
+
+        let identity = Matrix::identity(n);
+        let mut m = Matrix::new(n, n, vec![Fraction::zero(); n * n])?;
+
+        for k in 1..=n {
+            let scaled = identity.scale(coefficients[n - k + 1]);
+            m = self.multiply(&m)?.sum(&scaled)?;
+            coefficients[n - k] = -self.multiply(&m)?.trace() / Fraction::from(k as i64);
+        }
+
+        Some(Polynomial::new(var, coefficients))
+    }
+
+    fn scale(&self, factor: Fraction) -> Self {
+        Self {
+            rows: self.rows,
+            cols: self.cols,
+            entries: self.entries.iter().map(|entry| entry * factor).collect(),
+        }
+    }
+
+    fn sum(&self, other: &Self) -> Option<Self> {
+        if self.rows != other.rows || self.cols != other.cols {
+            return None;
+        }
+
+        Matrix::new(
+            self.rows,
+            self.cols,
+            self.entries
+                .iter()
+                .zip(&other.entries)
+                .map(|(a, b)| a + b)
+                .collect(),
+        )
+    }
+
+    /// The real eigenvalues, repeated according to their algebraic multiplicity.
+    ///
+    /// Matrices up to 3x3 are solved exactly through their characteristic polynomial, with
+    /// irrational roots reported as `Number::Irrational`. Larger matrices, and cubics without a
+    /// rational root, fall back to the QR algorithm. Complex eigenvalues are omitted.
+    ///
+    /// Returns `None` if the QR algorithm does not split the matrix into blocks of at most 2x2.
+    pub fn eigenvalues(&self) -> Option<Vec<Number>> {
+        if !self.is_square() {
+            return None;
+        }
+
+        if self.rows > 3 {
+            return self.numeric_eigenvalues();
+        }
+
+        let polynomial = self.characteristic_polynomial("x")?;
+        match polynomial.real_roots() {
+            Some(roots) => Some(roots),
+            None => self.numeric_eigenvalues(),
+        }
+    }
+
+    fn numeric_eigenvalues(&self) -> Option<Vec<Number>> {
+        let matrix = Matrix {
+            rows: self.rows,
+            cols: self.cols,
+            entries: self
+                .entries
+                .iter()
+                .map(|entry| entry.to_f64().unwrap())
+                .collect(),
+        };
+
+        let (schur, _) = matrix
+            .iterate_qr(QR_ITERATIONS, &CancellationToken::new())
+            .unwrap();
+        Some(
+            schur
+                .block_eigenvalues()?
+                .into_iter()
+                .map(Number::Irrational)
+                .collect(),
+        )
+    }
+}
+
+impl Matrix<f64> {
+    /// Approximates the eigenvalues with the unshifted QR algorithm.
+    ///
+    /// Assumes the eigenvalues are real; complex pairs do not converge.
+    pub fn eigenvalues(&self, max_iterations: usize) -> Vec<f64> {
+        self.eigenvalues_cancellable(max_iterations, &CancellationToken::new())
+            .unwrap()
+    }
+
+    /// Like `eigenvalues`, but checks `token` between QR iterations.
+    pub fn eigenvalues_cancellable(
+        &self,
+        max_iterations: usize,
+        token: &CancellationToken,
+    ) -> Result<Vec<f64>, Cancelled> {
+        self.run_qr(max_iterations, token)
+            .map(|(eigenvalues, _)| eigenvalues)
+    }
+
+    /// Like `eigenvalues`, also reporting how many iterations were used and whether the
+    /// iteration converged before reaching `max_iterations`.
+    pub fn eigenvalues_with_budget(&self, max_iterations: usize) -> (Vec<f64>, Budget) {
+        self.run_qr(max_iterations, &CancellationToken::new())
+            .unwrap()
+    }
+
+    fn run_qr(
+        &self,
+        max_iterations: usize,
+        token: &CancellationToken,
+    ) -> Result<(Vec<f64>, Budget), Cancelled> {
+        let (a, budget) = self.iterate_qr(max_iterations, token)?;
+        Ok(((0..self.rows).map(|i| *a.get(i, i)).collect(), budget))
+    }
+
+    fn iterate_qr(
+        &self,
+        max_iterations: usize,
+        token: &CancellationToken,
+    ) -> Result<(Matrix<f64>, Budget), Cancelled> {
+        let n = self.rows;
+        let mut a = self.clone();
+        let mut budget = Budget {
+            used: 0,
+            limit: max_iterations,
+            converged: false,
+        };
+
+        let is_triangular =
+            |a: &Matrix<f64>| (1..n).all(|row| (0..row).all(|col| a.get(row, col).abs() < 1e-12));
+
+        while budget.used < max_iterations {
+            token.check()?;
+            if is_triangular(&a) {
+                break;
+            }
+
+            let (q, r) = a.qr();
+            a = r.multiply(&q).unwrap();
+            budget.used += 1;
+        }
+        budget.converged = is_triangular(&a);
+
+        Ok((a, budget))
+    }
+
+    /// The real eigenvalues of a matrix the QR algorithm has brought to block upper triangular
+    /// form, solving each 2x2 block on the diagonal that has not split and leaving out its
+    /// complex pairs. Returns `None` if a larger block remains.
+    fn block_eigenvalues(&self) -> Option<Vec<f64>> {
+        let n = self.rows;
+        let split = |row: usize| row >= n || self.get(row, row - 1).abs() < 1e-12;
+
+        let mut eigenvalues = Vec::new();
+        let mut i = 0;
+        while i < n {
+            if split(i + 1) {
+                eigenvalues.push(*self.get(i, i));
+                i += 1;
+                continue;
+            }
+            if !split(i + 2) {
+                return None;
+            }
+
+            let (a, b, c, d) = (
+                self.get(i, i),
+                self.get(i, i + 1),
+                self.get(i + 1, i),
+                self.get(i + 1, i + 1),
+            );
+            let mean = (a + d) / 2.0;
+            let discriminant = ((a - d) / 2.0).powi(2) + b * c;
+            if discriminant >= 0.0 {
+                let root = discriminant.sqrt();
+                eigenvalues.extend([mean - root, mean + root]);
+            }
+            i += 2;
+        }
+
+        Some(eigenvalues)
+    }
+
+    /// The QR decomposition of a square matrix by modified Gram-Schmidt.
+    fn qr(&self) -> (Matrix<f64>, Matrix<f64>) {
+        let n = self.rows;
+        let mut q = self.clone();
+        let mut r = Matrix::new(n, n, vec![0.0; n * n]).unwrap();
+
+        for col in 0..n {
+            for prev in 0..col {
+                let dot: f64 = (0..n).map(|row| q.get(row, prev) * q.get(row, col)).sum();
+                r.entries[prev * n + col] = dot;
+                for row in 0..n {
+                    q.entries[row * n + col] -= dot * q.get(row, prev);
+                }
+            }
+
+            let norm = (0..n)
+                .map(|row| q.get(row, col).powi(2))
+                .sum::<f64>()
+                .sqrt();
+            r.entries[col * n + col] = norm;
+            if norm != 0.0 {
+                for row in 0..n {
+                    q.entries[row * n + col] /= norm;
+                }
+            }
+        }
+
+        (q, r)
+    }
+}
+
+fn add(lhs: Term, rhs: Term) -> Term {
+    match (as_rational(&lhs), as_rational(&rhs)) {
+        (Some(a), Some(b)) => rational(a + b),
+        (Some(a), _) if a.is_zero() => rhs,
+        (_, Some(b)) if b.is_zero() => lhs,
+        _ => Term::Addition(Box::new(lhs), Box::new(rhs)),
+    }
+}
+
+fn mul(lhs: Term, rhs: Term) -> Term {
+    match (as_rational(&lhs), as_rational(&rhs)) {
+        (Some(a), Some(b)) => rational(a * b),
+        (Some(a), _) | (_, Some(a)) if a.is_zero() => rational(Fraction::zero()),
+        (Some(a), _) if a.is_one() => rhs,
+        (_, Some(b)) if b.is_one() => lhs,
+        _ => Term::Multiplication(Box::new(lhs), Box::new(rhs)),
+    }
+}
+
+fn reciprocal(term: Term) -> Term {
+    match as_rational(&term) {
+        Some(a) => rational(a.recip()),
+        None => Term::Exponentation(Box::new(term), Box::new(rational(Fraction::from(-1)))),
+    }
+}
+
+impl Matrix<Term> {
+    /// The determinant by cofactor expansion along the first row, folding constants as it goes.
+    pub fn determinant(&self) -> Option<Term> {
+        if !self.is_square() {
+            return None;
+        }
+
+        Some(match self.rows {
+            0 => rational(Fraction::one()),
+            1 => self.get(0, 0).clone(),
+            _ => (0..self.cols)
+                .map(|col| mul(self.get(0, col).clone(), self.cofactor(0, col)))
+                .reduce(add)
+                .unwrap(),
+        })
+    }
+
+    pub fn cofactor(&self, row: usize, col: usize) -> Term {
+        let minor = self.minor(row, col).determinant().unwrap();
+
+        if (row + col).is_multiple_of(2) {
+            minor
+        } else {
+            mul(rational(Fraction::from(-1)), minor)
+        }
+    }
+
+    /// The inverse as the adjugate scaled by the reciprocal determinant.
+    ///
+    /// Returns `None` for non-square matrices and matrices whose determinant folds to zero.
+    pub fn inverse(&self) -> Option<Matrix<Term>> {
+        let determinant = self.determinant()?;
+        if as_rational(&determinant).is_some_and(|d| d.is_zero()) {
+            return None;
+        }
+
+        let scale = reciprocal(determinant);
+        let entries = (0..self.rows)
+            .flat_map(|row| (0..self.cols).map(move |col| (row, col)))
+            .map(|(row, col)| mul(self.cofactor(col, row), scale.clone()))
+            .collect();
+
+        Matrix::new(self.rows, self.cols, entries)
+    }
+}
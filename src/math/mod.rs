@@ -1,2 +1,65 @@
-
-pub mod terms;
\ No newline at end of file
+pub mod analysis;
+pub mod approximate;
+pub mod arena;
+pub mod big;
+pub mod bits;
+pub mod budget;
+pub mod builder;
+pub mod cancel;
+pub mod canonical;
+#[cfg(feature = "arrow")]
+pub mod columnar;
+pub mod combinatorics;
+pub mod compile;
+pub mod completion;
+pub mod constants;
+pub mod correction;
+pub mod curves;
+pub mod derivative;
+pub mod diagnostics;
+#[cfg(feature = "differential")]
+pub mod differential;
+pub mod display;
+pub mod equations;
+pub mod escape;
+pub mod evaluator;
+pub mod exact;
+pub mod explain;
+pub mod export;
+pub mod fingerprint;
+pub mod finite_field;
+pub mod format;
+pub mod functions;
+pub mod grid;
+pub mod incremental;
+pub mod interval;
+pub mod latex;
+pub mod lint;
+pub mod mathml;
+pub mod matrix;
+pub mod nt;
+pub mod numeric;
+pub mod parser;
+pub mod partial;
+pub mod path;
+#[cfg(feature = "serde")]
+pub mod persist;
+#[cfg(feature = "plotting")]
+pub mod plot;
+pub mod polynomial;
+pub mod pool;
+pub mod proportion;
+pub mod rational_function;
+pub mod relative;
+pub mod rename;
+#[cfg(feature = "server")]
+pub mod server;
+pub mod sexpr;
+pub mod shared;
+pub mod simplify;
+pub mod surface;
+pub mod template;
+pub mod terms;
+pub mod transforms;
+mod values;
+pub mod verify;
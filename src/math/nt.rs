@@ -0,0 +1,154 @@
+use super::terms::{Fraction, Number};
+
+/// A sequence of exact integers that can be iterated from its first element or indexed directly.
+/// Indices follow the usual numbering of the sequence, which starts at `FIRST`, so
+/// `sequence.nth(k)` and `sequence.at(FIRST + k)` are the same element.
+pub trait Sequence: Iterator<Item = Number> {
+    /// The index of the first element.
+    const FIRST: u64;
+
+    /// The element at `index`, or `None` if `index` is before `FIRST` or the element does not fit
+    /// in a `Fraction`. Does not depend on how far the iterator has advanced.
+    fn at(&self, index: u64) -> Option<Number>;
+}
+
+fn integer(value: i64) -> Number {
+    Number::Rational(Fraction::from(value))
+}
+
+/// The prime numbers 2, 3, 5, 7, ...
+pub fn primes() -> Primes {
+    Primes::default()
+}
+
+/// The Fibonacci numbers 0, 1, 1, 2, 3, 5, ..., ending before the first one that overflows.
+pub fn fibonacci() -> Fibonacci {
+    Fibonacci {
+        current: Some(0),
+        following: Some(1),
+    }
+}
+
+/// The triangular numbers 0, 1, 3, 6, 10, ..., ending before the first one that overflows.
+pub fn triangular() -> Triangular {
+    Triangular { index: 0 }
+}
+
+#[derive(Clone, Debug, Default)]
+pub struct Primes {
+    found: Vec<i64>,
+}
+
+impl Iterator for Primes {
+    type Item = Number;
+
+    fn next(&mut self) -> Option<Number> {
+        let mut candidate = match self.found.last() {
+            None => 2,
+            Some(2) => 3,
+            Some(last) => last + 2,
+        };
+        // Trial division by the primes found so far, which include every prime up to the root
+        while self
+            .found
+            .iter()
+            .take_while(|&&prime| prime * prime <= candidate)
+            .any(|prime| candidate % prime == 0)
+        {
+            candidate += 2;
+        }
+        self.found.push(candidate);
+        Some(integer(candidate))
+    }
+}
+
+/// The largest index `Primes::at` computes, so that a single call takes bounded time and memory.
+/// The prime at this index is 1299709.
+pub const MAX_PRIME_INDEX: u64 = 100_000;
+
+impl Sequence for Primes {
+    const FIRST: u64 = 1;
+
+    /// The `index`th prime, so `at(1)` is 2, or `None` past `MAX_PRIME_INDEX`.
+    fn at(&self, index: u64) -> Option<Number> {
+        if !(Self::FIRST..=MAX_PRIME_INDEX).contains(&index) {
+            return None;
+        }
+        // The `n`th prime is below n (ln n + ln ln n) for n >= 6
+        let n = index.max(6) as f64;
+        let bound = (n * (n.ln() + n.ln().ln())) as usize + 1;
+
+        let mut composite = vec![false; bound];
+        let mut count = 0;
+        for candidate in 2..bound {
+            if composite[candidate] {
+                continue;
+            }
+            count += 1;
+            if count == index {
+                return Some(integer(candidate as i64));
+            }
+            for multiple in (candidate * candidate..bound).step_by(candidate) {
+                composite[multiple] = true;
+            }
+        }
+        unreachable!("the bound is above the {index}th prime")
+    }
+}
+
+#[derive(Clone, Debug)]
+pub struct Fibonacci {
+    current: Option<i64>,
+    following: Option<i64>,
+}
+
+impl Iterator for Fibonacci {
+    type Item = Number;
+
+    fn next(&mut self) -> Option<Number> {
+        let current = self.current?;
+        self.current = self.following;
+        self.following = self
+            .following
+            .and_then(|following| following.checked_add(current));
+        Some(integer(current))
+    }
+}
+
+impl Sequence for Fibonacci {
+    const FIRST: u64 = 0;
+
+    fn at(&self, index: u64) -> Option<Number> {
+        fibonacci().nth(usize::try_from(index).ok()?)
+    }
+}
+
+#[derive(Clone, Debug)]
+pub struct Triangular {
+    index: u64,
+}
+
+impl Iterator for Triangular {
+    type Item = Number;
+
+    fn next(&mut self) -> Option<Number> {
+        let value = self.at(self.index)?;
+        self.index += 1;
+        Some(value)
+    }
+}
+
+impl Sequence for Triangular {
+    const FIRST: u64 = 0;
+
+    fn at(&self, index: u64) -> Option<Number> {
+        let index = i64::try_from(index).ok()?;
+        // One of `index` and `index + 1` is even, so halving it first avoids overflowing early
+        let (even, other) = if index % 2 == 0 {
+            (index, index.checked_add(1)?)
+        } else {
+            (index.checked_add(1)?, index)
+        };
+        (even / 2).checked_mul(other).map(integer)
+    }
+}
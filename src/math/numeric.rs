@@ -0,0 +1,359 @@
+use std::f64::consts::PI;
+
+use super::cancel::{CancellationToken, Cancelled};
+use super::functions::Function;
+use super::terms::{Number, Term};
+
+const DEFAULT_STEPS: usize = 1000;
+const CANCELLATION_INTERVAL: usize = 64;
+
+/// Why a term has no floating point value.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum DomainError {
+    UnboundVariable,
+    /// The result, or an intermediate result, is infinite or NaN.
+    NotFinite,
+    NotAScalar,
+    InvalidIndex,
+    /// An operation that `evaluate_deterministic` cannot compute identically on every platform.
+    Nondeterministic,
+}
+
+/// Evaluates `term` in floating point with `var` bound to `x`.
+///
+/// Returns `None` if the term contains any other variable or the result is not finite.
+pub fn evaluate(term: &Term, var: &str, x: f64) -> Option<f64> {
+    try_evaluate(term, &[(var, x)]).ok()
+}
+
+/// Evaluates `term` in floating point with every variable in `bindings` bound to its value.
+pub fn try_evaluate(term: &Term, bindings: &[(&str, f64)]) -> Result<f64, DomainError> {
+    evaluate_f64(term, bindings, Mode::FINITE)
+}
+
+/// Like `try_evaluate`, but with plain IEEE 754 semantics: infinities and NaN propagate as they
+/// would in any other `f64` engine instead of being reported as `NotFinite`.
+pub fn evaluate_ieee(term: &Term, bindings: &[(&str, f64)]) -> Result<f64, DomainError> {
+    evaluate_f64(term, bindings, Mode::IEEE)
+}
+
+/// Like `try_evaluate`, but with results that are bit-for-bit identical on every platform, such
+/// as x86-64, ARM and wasm32.
+///
+/// IEEE 754 requires `+`, `-`, `*`, `/` and square roots to be correctly rounded, and Rust
+/// evaluates them without extended precision or fused multiply-adds, so they agree everywhere.
+/// Integer powers are computed by repeated squaring from those operations, and `abs`, `floor`
+/// and `ceil` are exact. Transcendental functions and non-integer powers come from the
+/// platform's math library, which may round differently, so they fail with `Nondeterministic`.
+pub fn evaluate_deterministic(term: &Term, bindings: &[(&str, f64)]) -> Result<f64, DomainError> {
+    evaluate_f64(term, bindings, Mode::DETERMINISTIC)
+}
+
+#[derive(Clone, Copy)]
+struct Mode {
+    finite: bool,
+    deterministic: bool,
+}
+
+impl Mode {
+    const FINITE: Mode = Mode {
+        finite: true,
+        deterministic: false,
+    };
+    const IEEE: Mode = Mode {
+        finite: false,
+        deterministic: false,
+    };
+    const DETERMINISTIC: Mode = Mode {
+        finite: true,
+        deterministic: true,
+    };
+}
+
+/// `base^power` for an integer-valued `power`, by repeated squaring.
+fn integer_powf(base: f64, power: f64) -> f64 {
+    let (mut result, mut square, mut remaining) = (1.0, base, power.abs());
+    while remaining > 0.0 {
+        if remaining % 2.0 == 1.0 {
+            result *= square;
+        }
+        remaining = (remaining / 2.0).floor();
+        if remaining > 0.0 {
+            square *= square;
+        }
+    }
+
+    if power < 0.0 {
+        1.0 / result
+    } else {
+        result
+    }
+}
+
+fn evaluate_f64(term: &Term, bindings: &[(&str, f64)], mode: Mode) -> Result<f64, DomainError> {
+    let eval = |term| evaluate_f64(term, bindings, mode);
+
+    let value = match term {
+        Term::Value(v) => f64::from(*v),
+        Term::Constant(constant) => constant.value(),
+        Term::Variable(name) => bindings
+            .iter()
+            .find(|(var, _)| *var == &**name)
+            .map(|(_, x)| *x)
+            .ok_or(DomainError::UnboundVariable)?,
+        Term::Addition(lhs, rhs) => eval(lhs)? + eval(rhs)?,
+        Term::Subtraction(lhs, rhs) => eval(lhs)? - eval(rhs)?,
+        Term::Multiplication(lhs, rhs) => eval(lhs)? * eval(rhs)?,
+        Term::Division(lhs, rhs) => eval(lhs)? / eval(rhs)?,
+        Term::Exponentation(base, power) if mode.deterministic => {
+            let (base, power) = (eval(base)?, eval(power)?);
+            if power.fract() != 0.0 || !power.is_finite() {
+                return Err(DomainError::Nondeterministic);
+            }
+            integer_powf(base, power)
+        }
+        Term::Exponentation(base, power) => eval(base)?.powf(eval(power)?),
+        Term::RootExtraction(radicand, degree) if mode.deterministic => {
+            let (radicand, degree) = (eval(radicand)?, eval(degree)?);
+            if degree != 2.0 {
+                return Err(DomainError::Nondeterministic);
+            }
+            radicand.sqrt()
+        }
+        Term::RootExtraction(radicand, degree) => eval(radicand)?.powf(eval(degree)?.recip()),
+        Term::Function(function, argument) => {
+            let exact = function.is_discrete()
+                || matches!(function, Function::Abs | Function::Floor | Function::Ceil);
+            if mode.deterministic && !exact {
+                return Err(DomainError::Nondeterministic);
+            }
+            let arguments = (function.arguments(argument).iter())
+                .map(eval)
+                .collect::<Result<Vec<_>, _>>()?;
+            function.evaluate_f64(&arguments)
+        }
+        Term::Index(collection, index) => match &**collection {
+            Term::Vector(elements) | Term::List(elements) => {
+                let position = eval(index)?;
+                if position.fract() != 0.0 || position < 0.0 {
+                    return Err(DomainError::InvalidIndex);
+                }
+                let element = elements
+                    .get(position as usize)
+                    .ok_or(DomainError::InvalidIndex)?;
+                eval(element)?
+            }
+            _ => return Err(DomainError::NotAScalar),
+        },
+        Term::Vector(_) | Term::List(_) | Term::Set(_) => return Err(DomainError::NotAScalar),
+    };
+
+    if mode.finite && !value.is_finite() {
+        Err(DomainError::NotFinite)
+    } else {
+        Ok(value)
+    }
+}
+
+/// How `Term::evaluate_with` computes a value.
+///
+/// The two modes differ only for rational literals and non-finite results:
+/// - `0.1 + 0.2` is exactly `3/10` when exact, but `0.30000000000000004` in IEEE mode, since
+///   every literal is first rounded to the nearest `f64`.
+/// - Irrational results like `sqrt(2)` stay `f64` in both modes, and so does everything computed
+///   from them. `sqrt(2)^2` is `Irrational(2.0000000000000004)` either way.
+/// - Non-finite results like `1/0` or `0/0` are `None` when exact, but propagate as infinities
+///   and NaN in IEEE mode.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Default)]
+pub enum Semantics {
+    /// Rational arithmetic wherever possible, as in `get_value`.
+    #[default]
+    ExactPreferred,
+    /// Everything in `f64`, bit-compatible with other float-based engines.
+    Ieee,
+}
+
+impl Term {
+    /// Evaluates the term, which must not have free variables, with the given `Semantics`.
+    /// `Semantics::ExactPreferred` is `get_value`. This picks the arithmetic, whereas
+    /// `evaluate_in` binds variables and always prefers exact arithmetic.
+    pub fn evaluate_with(&self, semantics: Semantics) -> Option<Number> {
+        match semantics {
+            Semantics::ExactPreferred => self.get_value(),
+            Semantics::Ieee => evaluate_ieee(self, &[]).ok().map(Number::Irrational),
+        }
+    }
+}
+
+/// Evaluates `term` row by row over equally long columns of variable values, such as the value
+/// buffers of dataframe columns. Returns `None` if the columns differ in length.
+pub fn evaluate_columns(
+    term: &Term,
+    columns: &[(&str, &[f64])],
+) -> Option<Vec<Result<f64, DomainError>>> {
+    let rows = columns.first().map_or(0, |(_, column)| column.len());
+    if columns.iter().any(|(_, column)| column.len() != rows) {
+        return None;
+    }
+
+    let mut bindings: Vec<(&str, f64)> = columns.iter().map(|(var, _)| (*var, 0.0)).collect();
+    Some(
+        (0..rows)
+            .map(|row| {
+                for (binding, (_, column)) in bindings.iter_mut().zip(columns) {
+                    binding.1 = column[row];
+                }
+                try_evaluate(term, &bindings)
+            })
+            .collect(),
+    )
+}
+
+/// Integrates `term` over `[a, b]` with respect to `var` using the composite Simpson rule.
+pub fn integrate(term: &Term, var: &str, a: f64, b: f64) -> Option<f64> {
+    integrate_cancellable(term, var, a, b, &CancellationToken::new()).unwrap()
+}
+
+/// Like `integrate`, but aborts with `Err(Cancelled)` once `token` is cancelled.
+pub fn integrate_cancellable(
+    term: &Term,
+    var: &str,
+    a: f64,
+    b: f64,
+    token: &CancellationToken,
+) -> Result<Option<f64>, Cancelled> {
+    integrate_with(|x| evaluate(term, var, x), a, b, DEFAULT_STEPS, token)
+}
+
+fn integrate_with(
+    f: impl Fn(f64) -> Option<f64>,
+    a: f64,
+    b: f64,
+    steps: usize,
+    token: &CancellationToken,
+) -> Result<Option<f64>, Cancelled> {
+    let steps = steps + steps % 2;
+    let h = (b - a) / steps as f64;
+
+    let (Some(start), Some(end)) = (f(a), f(b)) else {
+        return Ok(None);
+    };
+
+    let mut sum = start + end;
+    for i in 1..steps {
+        if i % CANCELLATION_INTERVAL == 0 {
+            token.check()?;
+        }
+
+        let weight = if i % 2 == 1 { 4.0 } else { 2.0 };
+        match f(a + i as f64 * h) {
+            Some(value) => sum += weight * value,
+            None => return Ok(None),
+        }
+    }
+
+    Ok(Some(sum * h / 3.0))
+}
+
+/// A truncated Fourier series `a_0 / 2 + sum_k (a_k cos(k w x) + b_k sin(k w x))` with
+/// `w = 2 pi / period`.
+#[derive(Clone, Debug, PartialEq)]
+pub struct FourierSeries {
+    pub period: f64,
+    pub a0: f64,
+    pub a: Vec<f64>,
+    pub b: Vec<f64>,
+}
+
+impl FourierSeries {
+    pub fn evaluate(&self, x: f64) -> f64 {
+        let w = 2.0 * PI / self.period;
+
+        self.a
+            .iter()
+            .zip(&self.b)
+            .enumerate()
+            .fold(self.a0 / 2.0, |acc, (k, (a, b))| {
+                let angle = (k + 1) as f64 * w * x;
+                acc + a * angle.cos() + b * angle.sin()
+            })
+    }
+
+    /// The series as a term in `var`.
+    pub fn to_term(&self, var: &str) -> Term {
+        let w = 2.0 * PI / self.period;
+        let value = |x: f64| Box::new(Term::Value(Number::Irrational(x)));
+        let wave = |function, amplitude: f64, k: usize| {
+            let angle = Term::Multiplication(
+                value((k + 1) as f64 * w),
+                Box::new(Term::Variable(var.into())),
+            );
+            Term::Multiplication(
+                value(amplitude),
+                Box::new(Term::Function(function, Box::new(angle))),
+            )
+        };
+
+        self.a
+            .iter()
+            .zip(&self.b)
+            .enumerate()
+            .fold(*value(self.a0 / 2.0), |acc, (k, (a, b))| {
+                let acc = Term::Addition(Box::new(acc), Box::new(wave(Function::Cos, *a, k)));
+                Term::Addition(Box::new(acc), Box::new(wave(Function::Sin, *b, k)))
+            })
+    }
+}
+
+/// Computes the first `n` Fourier coefficients of `term` over one period centered on zero.
+pub fn fourier_coefficients(
+    term: &Term,
+    var: &str,
+    period: f64,
+    n: usize,
+) -> Option<FourierSeries> {
+    fourier_coefficients_cancellable(term, var, period, n, &CancellationToken::new()).unwrap()
+}
+
+/// Like `fourier_coefficients`, but aborts with `Err(Cancelled)` once `token` is cancelled.
+pub fn fourier_coefficients_cancellable(
+    term: &Term,
+    var: &str,
+    period: f64,
+    n: usize,
+    token: &CancellationToken,
+) -> Result<Option<FourierSeries>, Cancelled> {
+    let (start, end) = (-period / 2.0, period / 2.0);
+    let w = 2.0 * PI / period;
+    let scale = 2.0 / period;
+
+    let Some(a0) = integrate_cancellable(term, var, start, end, token)? else {
+        return Ok(None);
+    };
+
+    let mut a = Vec::with_capacity(n);
+    let mut b = Vec::with_capacity(n);
+
+    for k in 1..=n {
+        let k = k as f64;
+        let cos = |x: f64| Some(evaluate(term, var, x)? * (k * w * x).cos());
+        let sin = |x: f64| Some(evaluate(term, var, x)? * (k * w * x).sin());
+
+        let (Some(a_k), Some(b_k)) = (
+            integrate_with(cos, start, end, DEFAULT_STEPS, token)?,
+            integrate_with(sin, start, end, DEFAULT_STEPS, token)?,
+        ) else {
+            return Ok(None);
+        };
+
+        a.push(scale * a_k);
+        b.push(scale * b_k);
+    }
+
+    Ok(Some(FourierSeries {
+        period,
+        a0: scale * a0,
+        a,
+        b,
+    }))
+}
@@ -0,0 +1,658 @@
+use std::fmt::{self, Display, Formatter};
+use std::str::FromStr;
+
+use fraction::{CheckedAdd, CheckedDiv, CheckedMul};
+
+use super::completion;
+use super::constants::Constant;
+use super::functions::Function;
+use super::path::TermPath;
+use super::terms::{Fraction, Number, Term};
+
+#[derive(Clone, Debug, PartialEq)]
+pub enum ParseErrorKind {
+    UnexpectedCharacter(char),
+    UnexpectedToken,
+    UnexpectedEnd,
+    /// An opening bracket without its closing counterpart.
+    Unclosed(char),
+    InvalidNumber,
+    /// A call to a name that is not a function, with the functions named most like it.
+    UnknownFunction {
+        name: Box<str>,
+        suggestions: Vec<Box<str>>,
+    },
+    /// A LaTeX command that `Term::from_latex` does not know, without its backslash.
+    UnknownCommand(Box<str>),
+    /// A built-in function called with the wrong number of arguments.
+    WrongArity {
+        function: Box<str>,
+        expected: usize,
+    },
+}
+
+/// A parse failure at byte offset `position` of the input.
+#[derive(Clone, Debug, PartialEq)]
+pub struct ParseError {
+    pub kind: ParseErrorKind,
+    pub position: usize,
+}
+
+impl Display for ParseError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        match &self.kind {
+            ParseErrorKind::UnexpectedCharacter(c) => write!(f, "unexpected character `{c}`")?,
+            ParseErrorKind::UnexpectedToken => write!(f, "unexpected token")?,
+            ParseErrorKind::UnexpectedEnd => write!(f, "unexpected end of input")?,
+            ParseErrorKind::Unclosed(c) => write!(f, "unclosed `{c}`")?,
+            ParseErrorKind::InvalidNumber => write!(f, "invalid number")?,
+            ParseErrorKind::UnknownFunction { name, suggestions } => {
+                write!(f, "unknown function `{name}`")?;
+                write_suggestions(f, suggestions)?
+            }
+            ParseErrorKind::UnknownCommand(name) => write!(f, "unknown command `\\{name}`")?,
+            ParseErrorKind::WrongArity { function, expected } => {
+                write!(f, "`{function}` takes {expected} argument(s)")?
+            }
+        }
+        write!(f, " at position {}", self.position)
+    }
+}
+
+impl std::error::Error for ParseError {}
+
+/// Appends a "did you mean" hint naming the `suggestions`, if there are any.
+pub(super) fn write_suggestions(f: &mut Formatter<'_>, suggestions: &[Box<str>]) -> fmt::Result {
+    let Some((last, rest)) = suggestions.split_last() else {
+        return Ok(());
+    };
+    write!(f, " (did you mean ")?;
+    for (i, suggestion) in rest.iter().enumerate() {
+        let separator = if i + 1 < rest.len() { ", " } else { " or " };
+        write!(f, "`{suggestion}`{separator}")?;
+    }
+    write!(f, "`{last}`?)")
+}
+
+/// The `UnknownFunction` error for `name`.
+pub(super) fn unknown_function(name: &str) -> ParseErrorKind {
+    ParseErrorKind::UnknownFunction {
+        name: name.into(),
+        suggestions: completion::similar_names(name, completion::function_names()),
+    }
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub(super) enum Token {
+    Number(Number),
+    Identifier(Box<str>),
+    /// A name in backticks, like `` `e` ``, which is always a variable.
+    Quoted(Box<str>),
+    Symbol(char),
+}
+
+pub(super) fn tokenize(input: &str) -> Result<Vec<(Token, usize)>, ParseError> {
+    let mut tokens = Vec::new();
+    let mut chars = input.char_indices().peekable();
+
+    while let Some(&(position, c)) = chars.peek() {
+        if c.is_whitespace() {
+            chars.next();
+        } else if c.is_ascii_digit() || c == '.' {
+            let mut end = position;
+            while let Some(&(i, c)) = chars.peek() {
+                if !(c.is_ascii_digit() || c == '.' || c == '_') {
+                    break;
+                }
+                end = i + c.len_utf8();
+                chars.next();
+            }
+            let exponent = exponent_len(&input[end..]);
+            end += exponent;
+            while chars.next_if(|&(i, _)| i < end).is_some() {}
+            tokens.push((
+                Token::Number(number(&input[position..end], position)?),
+                position,
+            ));
+        } else if c.is_alphabetic() || c == '_' {
+            let mut end = position;
+            while let Some(&(i, c)) = chars.peek() {
+                if !(c.is_alphanumeric() || c == '_') {
+                    break;
+                }
+                end = i + c.len_utf8();
+                chars.next();
+            }
+            tokens.push((Token::Identifier(input[position..end].into()), position));
+        } else if c == '`' {
+            chars.next();
+            let start = position + 1;
+            let Some(length) = input[start..].find('`') else {
+                return Err(ParseError {
+                    kind: ParseErrorKind::Unclosed('`'),
+                    position,
+                });
+            };
+            if length == 0 {
+                return Err(ParseError {
+                    kind: ParseErrorKind::UnexpectedCharacter('`'),
+                    position: start,
+                });
+            }
+            let end = start + length;
+            while chars.next_if(|&(i, _)| i <= end).is_some() {}
+            tokens.push((Token::Quoted(input[start..end].into()), position));
+        } else if "+-*/^()[]{},".contains(c) {
+            tokens.push((Token::Symbol(c), position));
+            chars.next();
+        } else {
+            return Err(ParseError {
+                kind: ParseErrorKind::UnexpectedCharacter(c),
+                position,
+            });
+        }
+    }
+
+    Ok(tokens)
+}
+
+/// The length of the exponent like `e-7` at the start of `rest`, or 0 if there is none.
+pub(super) fn exponent_len(rest: &str) -> usize {
+    let Some(after) = rest.strip_prefix(['e', 'E']) else {
+        return 0;
+    };
+    let unsigned = after.strip_prefix(['+', '-']).unwrap_or(after);
+    match unsigned
+        .find(|c: char| !c.is_ascii_digit())
+        .unwrap_or(unsigned.len())
+    {
+        0 => 0,
+        digits => rest.len() - unsigned.len() + digits,
+    }
+}
+
+/// Reads a decimal literal, optionally with an exponent as in `1.5e-7`, as an exact rational,
+/// falling back to a float if it does not fit.
+pub(super) fn number(literal: &str, position: usize) -> Result<Number, ParseError> {
+    let invalid = ParseError {
+        kind: ParseErrorKind::InvalidNumber,
+        position,
+    };
+
+    let digits = literal.replace('_', "");
+    let (mantissa, exponent) = match digits.split_once(['e', 'E']) {
+        Some((mantissa, exponent)) => (mantissa, exponent.parse::<i32>().ok()),
+        None => (digits.as_str(), Some(0)),
+    };
+    let (integer, fraction) = mantissa.split_once('.').unwrap_or((mantissa, ""));
+    if (integer.is_empty() && fraction.is_empty()) || fraction.contains('.') {
+        return Err(invalid);
+    }
+
+    let exact = integer
+        .chars()
+        .chain(fraction.chars())
+        .try_fold(Fraction::from(0), |value, digit| {
+            value
+                .checked_mul(&Fraction::from(10))?
+                .checked_add(&Fraction::from(digit.to_digit(10)? as i64))
+        })
+        .and_then(|value| {
+            let shift = exponent? - i32::try_from(fraction.len()).ok()?;
+            let scale = Fraction::from(10i64.checked_pow(shift.unsigned_abs())?);
+            if shift < 0 {
+                value.checked_div(&scale)
+            } else {
+                value.checked_mul(&scale)
+            }
+        });
+
+    match exact {
+        Some(value) => Ok(Number::Rational(value)),
+        None => f64::from_str(&digits)
+            .ok()
+            .filter(|value| value.is_finite())
+            .map(Number::Irrational)
+            .ok_or(invalid),
+    }
+}
+
+/// A bracketed primary such as `(x + 1)`, `sin(x)` or `[1, 2]`. Its term appears unchanged at
+/// `path` in the parsed term, so the unit can be reparsed on its own when only its contents change.
+#[derive(Clone, Debug, PartialEq)]
+pub(super) struct Unit {
+    /// The byte position of its first token.
+    pub start: usize,
+    /// The byte position of its opening bracket.
+    pub open: usize,
+    /// The byte position just after its closing bracket.
+    pub end: usize,
+    pub path: TermPath,
+}
+
+struct Parser {
+    tokens: Vec<(Token, usize)>,
+    index: usize,
+    end: usize,
+    /// Whether to record `units`, whose paths are built in reverse, from the unit up to the root.
+    track: bool,
+    units: Vec<Unit>,
+}
+
+/// Parses like `Term::parse`, also returning the units of the term.
+pub(super) fn parse_units(input: &str) -> Result<(Term, Vec<Unit>), ParseError> {
+    Parser::new(input, 0, true)?.complete(Parser::sum)
+}
+
+/// Parses the source of a single unit found at byte `offset` of a larger input. Positions are in
+/// that input, and the paths of nested units start at the unit.
+pub(super) fn parse_unit(source: &str, offset: usize) -> Result<(Term, Vec<Unit>), ParseError> {
+    Parser::new(source, offset, true)?.complete(Parser::primary)
+}
+
+impl Parser {
+    fn new(input: &str, offset: usize, track: bool) -> Result<Self, ParseError> {
+        let shift = |mut error: ParseError| {
+            error.position += offset;
+            error
+        };
+        let mut tokens = tokenize(input).map_err(shift)?;
+        for (_, position) in &mut tokens {
+            *position += offset;
+        }
+        Ok(Parser {
+            tokens,
+            index: 0,
+            end: offset + input.len(),
+            track,
+            units: Vec::new(),
+        })
+    }
+
+    /// Parses with `rule`, which has to consume every token.
+    fn complete(
+        mut self,
+        rule: fn(&mut Parser) -> Result<Term, ParseError>,
+    ) -> Result<(Term, Vec<Unit>), ParseError> {
+        let term = rule(&mut self)?;
+        if self.peek().is_some() {
+            return Err(self.unexpected());
+        }
+        for unit in &mut self.units {
+            unit.path.0.reverse();
+        }
+        Ok((term, self.units))
+    }
+
+    /// Records the primary from `start` to the last token as a unit.
+    fn unit(&mut self, start: usize, open: usize) {
+        if self.track {
+            let end = self.tokens[self.index - 1].1 + 1;
+            self.units.push(Unit {
+                start,
+                open,
+                end,
+                path: TermPath::root(),
+            });
+        }
+    }
+
+    /// Moves the units recorded since `first` into child `index` of the term being built.
+    fn nest(&mut self, first: usize, index: usize) {
+        for unit in &mut self.units[first..] {
+            unit.path.0.push(index);
+        }
+    }
+
+    /// `make(lhs, rhs)`, where the units recorded from `first` to `middle` are in `lhs` and those
+    /// after it in `rhs`.
+    fn binary(
+        &mut self,
+        make: fn(Box<Term>, Box<Term>) -> Term,
+        (lhs, rhs): (Term, Term),
+        first: usize,
+        middle: usize,
+    ) -> Term {
+        self.nest(middle, 1);
+        for unit in &mut self.units[first..middle] {
+            unit.path.0.push(0);
+        }
+        make(Box::new(lhs), Box::new(rhs))
+    }
+
+    fn peek(&self) -> Option<&Token> {
+        self.tokens.get(self.index).map(|(token, _)| token)
+    }
+
+    fn position(&self) -> usize {
+        self.tokens
+            .get(self.index)
+            .map_or(self.end, |(_, position)| *position)
+    }
+
+    fn error(&self, kind: ParseErrorKind) -> ParseError {
+        ParseError {
+            kind,
+            position: self.position(),
+        }
+    }
+
+    fn unexpected(&self) -> ParseError {
+        match self.peek() {
+            Some(_) => self.error(ParseErrorKind::UnexpectedToken),
+            None => self.error(ParseErrorKind::UnexpectedEnd),
+        }
+    }
+
+    fn eat(&mut self, symbol: char) -> bool {
+        if self.peek() == Some(&Token::Symbol(symbol)) {
+            self.index += 1;
+            true
+        } else {
+            false
+        }
+    }
+
+    fn close(&mut self, open: char, close: char, opened_at: usize) -> Result<(), ParseError> {
+        if self.eat(close) {
+            Ok(())
+        } else if self.peek().is_none() {
+            Err(ParseError {
+                kind: ParseErrorKind::Unclosed(open),
+                position: opened_at,
+            })
+        } else {
+            Err(self.unexpected())
+        }
+    }
+
+    /// `sum := product (('+' | '-') product)*`
+    fn sum(&mut self) -> Result<Term, ParseError> {
+        let first = self.units.len();
+        let mut lhs = self.product()?;
+        loop {
+            let make: fn(_, _) -> _ = if self.eat('+') {
+                Term::Addition
+            } else if self.eat('-') {
+                Term::Subtraction
+            } else {
+                return Ok(lhs);
+            };
+            let middle = self.units.len();
+            let rhs = self.product()?;
+            lhs = self.binary(make, (lhs, rhs), first, middle);
+        }
+    }
+
+    /// `product := unary (('*' | '/') unary)*`
+    fn product(&mut self) -> Result<Term, ParseError> {
+        let first = self.units.len();
+        let mut lhs = self.unary()?;
+        loop {
+            let make: fn(_, _) -> _ = if self.eat('*') {
+                Term::Multiplication
+            } else if self.eat('/') {
+                Term::Division
+            } else {
+                return Ok(lhs);
+            };
+            let middle = self.units.len();
+            let rhs = self.unary()?;
+            lhs = self.binary(make, (lhs, rhs), first, middle);
+        }
+    }
+
+    /// `unary := ('-' | '+') unary | power`, so `-x^2` is `-(x^2)`.
+    fn unary(&mut self) -> Result<Term, ParseError> {
+        if self.eat('-') {
+            let first = self.units.len();
+            let operand = self.unary()?;
+            // `negate` folds values, so a unit that is the whole operand cannot be swapped out
+            // without negating again, and the operand itself becomes child 1
+            let mut units = self.units.split_off(first);
+            units.retain(|unit| !unit.path.is_root());
+            self.units.append(&mut units);
+            self.nest(first, 1);
+            Ok(negate(operand))
+        } else if self.eat('+') {
+            self.unary()
+        } else {
+            self.power()
+        }
+    }
+
+    /// `power := postfix ('^' unary)?`, which makes `^` right-associative.
+    fn power(&mut self) -> Result<Term, ParseError> {
+        let first = self.units.len();
+        let base = self.postfix()?;
+        if self.eat('^') {
+            let middle = self.units.len();
+            let power = self.unary()?;
+            Ok(self.binary(Term::Exponentation, (base, power), first, middle))
+        } else {
+            Ok(base)
+        }
+    }
+
+    /// `postfix := primary ('[' sum ']')*`
+    fn postfix(&mut self) -> Result<Term, ParseError> {
+        let first = self.units.len();
+        let mut term = self.primary()?;
+        loop {
+            let opened_at = self.position();
+            if !self.eat('[') {
+                return Ok(term);
+            }
+            let middle = self.units.len();
+            let index = self.sum()?;
+            self.close('[', ']', opened_at)?;
+            term = self.binary(Term::Index, (term, index), first, middle);
+        }
+    }
+
+    fn primary(&mut self) -> Result<Term, ParseError> {
+        let first = self.units.len();
+        let opened_at = self.position();
+        let token = self.peek().cloned().ok_or_else(|| self.unexpected())?;
+        self.index += 1;
+
+        match token {
+            Token::Number(value) => {
+                let notation = match self.peek() {
+                    Some(Token::Identifier(name)) => selection_notation(name),
+                    _ => None,
+                };
+                match notation {
+                    Some((name, k)) => {
+                        self.index += 1;
+                        call(name.into(), vec![Term::Value(value), k], opened_at)
+                    }
+                    None => Ok(Term::Value(value)),
+                }
+            }
+            Token::Identifier(name) => {
+                if self.peek() == Some(&Token::Symbol('(')) {
+                    let paren = self.position();
+                    self.index += 1;
+                    let arguments = self.elements('(', ')', paren)?;
+                    let term = call(name, arguments, opened_at)?;
+                    // Functions of several arguments hold them in a list
+                    if matches!(&term, Term::Function(function, _) if function.arity() > 1) {
+                        self.nest(first, 0);
+                    }
+                    self.unit(opened_at, paren);
+                    Ok(term)
+                } else if let Some(constant) = Constant::from_name(&name) {
+                    Ok(Term::Constant(constant))
+                } else {
+                    Ok(Term::Variable(name))
+                }
+            }
+            Token::Quoted(name) => Ok(Term::Variable(name)),
+            Token::Symbol('(') => {
+                let term = if self.eat(')') {
+                    Term::List(Vec::new())
+                } else {
+                    let head = self.sum()?;
+                    if self.eat(',') {
+                        self.nest(first, 0);
+                        Term::List(self.elements_after(vec![head], '(', ')', opened_at)?)
+                    } else {
+                        self.close('(', ')', opened_at)?;
+                        head
+                    }
+                };
+                self.unit(opened_at, opened_at);
+                Ok(term)
+            }
+            Token::Symbol(open @ ('[' | '{')) => {
+                let (close, make): (_, fn(_) -> _) = match open {
+                    '[' => (']', Term::Vector),
+                    _ => ('}', Term::Set),
+                };
+                let term = make(self.elements(open, close, opened_at)?);
+                self.unit(opened_at, opened_at);
+                Ok(term)
+            }
+            Token::Symbol(_) => {
+                self.index -= 1;
+                Err(self.unexpected())
+            }
+        }
+    }
+
+    /// Comma-separated sums up to and including `close`, allowing a trailing comma.
+    fn elements(
+        &mut self,
+        open: char,
+        close: char,
+        opened_at: usize,
+    ) -> Result<Vec<Term>, ParseError> {
+        self.elements_after(Vec::new(), open, close, opened_at)
+    }
+
+    /// Like `elements`, continuing after the ones already in `elements`.
+    fn elements_after(
+        &mut self,
+        mut elements: Vec<Term>,
+        open: char,
+        close: char,
+        opened_at: usize,
+    ) -> Result<Vec<Term>, ParseError> {
+        loop {
+            if self.eat(close) {
+                return Ok(elements);
+            }
+            let first = self.units.len();
+            elements.push(self.sum()?);
+            self.nest(first, elements.len() - 1);
+            if !self.eat(',') {
+                self.close(open, close, opened_at)?;
+                return Ok(elements);
+            }
+        }
+    }
+}
+
+/// The calls that `call` builds itself rather than through `Function`, with their parameters.
+/// `P` and `C` are further aliases of `nPr` and `nCr`.
+pub(super) const BUILTIN_CALLS: [(&str, &str); 2] = [("sqrt", "x"), ("root", "x, n")];
+
+pub(super) fn call(
+    name: Box<str>,
+    mut arguments: Vec<Term>,
+    position: usize,
+) -> Result<Term, ParseError> {
+    let arity = |expected| ParseError {
+        kind: ParseErrorKind::WrongArity {
+            function: name.clone(),
+            expected,
+        },
+        position,
+    };
+
+    match &*name {
+        "sqrt" => {
+            let [radicand]: [Term; 1] = arguments.try_into().map_err(|_| arity(1))?;
+            Ok(Term::RootExtraction(
+                Box::new(radicand),
+                Box::new(Term::Value(Number::Rational(Fraction::from(2)))),
+            ))
+        }
+        "root" => {
+            if arguments.len() != 2 {
+                return Err(arity(2));
+            }
+            let degree = arguments.pop().unwrap();
+            let radicand = arguments.pop().unwrap();
+            Ok(Term::RootExtraction(Box::new(radicand), Box::new(degree)))
+        }
+        name => {
+            let function = match name {
+                "P" => Some(Function::Permutations),
+                "C" => Some(Function::Binomial),
+                name => Function::from_name(name),
+            };
+            match function {
+                Some(function) => function
+                    .term(arguments)
+                    .ok_or_else(|| arity(function.arity())),
+                None => Err(ParseError {
+                    kind: unknown_function(name),
+                    position,
+                }),
+            }
+        }
+    }
+}
+
+pub(super) fn negate(term: Term) -> Term {
+    match term {
+        Term::Value(Number::Rational(value)) => Term::Value(Number::Rational(-value)),
+        Term::Value(Number::Irrational(value)) => Term::Value(Number::Irrational(-value)),
+        term => Term::Multiplication(
+            Box::new(Term::Value(Number::Rational(Fraction::from(-1)))),
+            Box::new(term),
+        ),
+    }
+}
+
+impl Term {
+    /// Parses infix notation such as `2 * (x + 3)^2`.
+    ///
+    /// `^` binds tightest and is right-associative, and unary minus binds looser than `^`, with
+    /// `-a` becoming `(-1) * a`. `[a, b]` is a vector,
+    /// `{a, b}` a set, and `(a, b)` a list (`(a,)` for one element). `v[i]` indexes, and
+    /// `sqrt(x)` and `root(x, n)` build roots, and `sin(x)`, `nCr(n, k)` and the other `Function`
+    /// names apply functions, with `P` and `C` short for `nPr` and `nCr`. `pi` (or `π`), `e` and
+    /// `phi` (or `φ`) are constants, and names in backticks like `` `e` `` are variables whatever
+    /// they are called. Decimal literals like `0.25` or `1e-7` are exact rationals where they fit
+    /// in a `Fraction`.
+    pub fn parse(input: &str) -> Result<Term, ParseError> {
+        Parser::new(input, 0, false)?
+            .complete(Parser::sum)
+            .map(|(term, _)| term)
+    }
+}
+
+impl FromStr for Term {
+    type Err = ParseError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        Term::parse(s)
+    }
+}
+
+/// The `5P2` and `10C3` notations for `nPr(5, 2)` and `nCr(10, 3)`, written directly after the
+/// number.
+pub(super) fn selection_notation(name: &str) -> Option<(&'static str, Term)> {
+    let (function, k) = match name.split_at_checked(1)? {
+        ("P", k) => ("nPr", k),
+        ("C", k) => ("nCr", k),
+        _ => return None,
+    };
+    if k.is_empty() || !k.chars().all(|c| c.is_ascii_digit()) {
+        return None;
+    }
+    Some((function, Term::Value(number(k, 0).ok()?)))
+}
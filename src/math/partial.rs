@@ -0,0 +1,95 @@
+use super::exact::ExactnessReason;
+use super::terms::{EvalContext, Number, Term};
+
+/// The result of evaluating with only some variables bound.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Partial {
+    /// The term with every evaluable subterm replaced by its value.
+    pub term: Term,
+    /// The variables still free in `term`, in order of first appearance.
+    pub unbound: Vec<Box<str>>,
+}
+
+impl Term {
+    /// Substitutes the variables bound in `context` and folds every subterm that then has a
+    /// value, leaving the rest symbolic. Subterms without a value, such as a division by zero,
+    /// are kept as they are.
+    pub fn evaluate_partial(&self, context: &EvalContext) -> Partial {
+        let term = fold(self, context);
+        let mut unbound: Vec<Box<str>> = Vec::new();
+        for name in term.iter_variables() {
+            if !unbound.iter().any(|seen| &**seen == name) {
+                unbound.push(name.into());
+            }
+        }
+        Partial { term, unbound }
+    }
+}
+
+fn fold(term: &Term, context: &EvalContext) -> Term {
+    let boxed = |term: &Term| Box::new(fold(term, context));
+    let each = |terms: &[Term]| terms.iter().map(|term| fold(term, context)).collect();
+
+    let folded = match term {
+        Term::Value(_) | Term::Constant(_) => return term.clone(),
+        Term::Variable(name) => {
+            return match context.get(name) {
+                Some(value) => Term::Value(value),
+                None => term.clone(),
+            }
+        }
+        Term::Addition(lhs, rhs) => Term::Addition(boxed(lhs), boxed(rhs)),
+        Term::Subtraction(lhs, rhs) => Term::Subtraction(boxed(lhs), boxed(rhs)),
+        Term::Multiplication(lhs, rhs) => Term::Multiplication(boxed(lhs), boxed(rhs)),
+        Term::Division(lhs, rhs) => Term::Division(boxed(lhs), boxed(rhs)),
+        Term::Exponentation(base, power) => Term::Exponentation(boxed(base), boxed(power)),
+        Term::RootExtraction(radicand, degree) => {
+            Term::RootExtraction(boxed(radicand), boxed(degree))
+        }
+        // Calls the context does not allow are kept, so evaluating the result still rejects them
+        Term::Function(function, argument) if !context.allows(*function) => {
+            return Term::Function(*function, boxed(argument))
+        }
+        Term::Function(function, argument) => Term::Function(*function, boxed(argument)),
+        Term::Vector(elements) => return Term::Vector(each(elements)),
+        Term::List(elements) => return Term::List(each(elements)),
+        Term::Set(elements) => return Term::Set(each(elements)),
+        Term::Index(collection, index) => Term::Index(boxed(collection), boxed(index)),
+    };
+
+    let constant = folded
+        .children()
+        .iter()
+        .all(|child| matches!(child, Term::Value(_)) || folded_collection(child));
+    if !constant {
+        return folded;
+    }
+
+    match value(&folded) {
+        Some(value) => Term::Value(value),
+        None => folded,
+    }
+}
+
+fn folded_collection(term: &Term) -> bool {
+    match term {
+        Term::Vector(elements) | Term::List(elements) => elements
+            .iter()
+            .all(|element| matches!(element, Term::Value(_))),
+        _ => false,
+    }
+}
+
+/// Prefers exact evaluation, so subterms that cannot be evaluated (or would overflow) are detected
+/// before `get_value` gets to them.
+fn value(term: &Term) -> Option<Number> {
+    match term.evaluate_exact() {
+        Ok(value) => Some(Number::Rational(value)),
+        Err(violation) => match violation.reason {
+            ExactnessReason::IrrationalPower
+            | ExactnessReason::IrrationalLiteral
+            | ExactnessReason::Transcendental => term.get_value(),
+            _ => None,
+        },
+    }
+}
@@ -0,0 +1,220 @@
+use std::collections::BTreeSet;
+use std::fmt::{self, Display, Formatter};
+
+use fraction::ToPrimitive;
+
+use super::terms::{EvalContext, EvalError, Number, Term};
+
+/// The location of a subterm, as the sequence of child indices leading to it from the root.
+///
+/// Binary nodes number their operands `0` and `1` in declaration order
+/// (`lhs`/`rhs`, `base`/`power`, `radicand`/`degree`, `collection`/`index`), and collection
+/// literals number their elements.
+#[derive(Clone, Debug, Default, PartialEq, Eq, Hash)]
+pub struct TermPath(pub Vec<usize>);
+
+impl TermPath {
+    pub fn root() -> Self {
+        Self::default()
+    }
+
+    pub fn child(&self, index: usize) -> Self {
+        let mut path = self.0.clone();
+        path.push(index);
+        Self(path)
+    }
+
+    pub fn is_root(&self) -> bool {
+        self.0.is_empty()
+    }
+}
+
+/// An evaluation error together with the subterm that caused it.
+#[derive(Clone, Debug, PartialEq)]
+pub struct LocatedEvalError {
+    pub error: EvalError,
+    pub at: TermPath,
+    /// The offending subterm, rendered with `Display`.
+    pub subterm: String,
+}
+
+impl Display for LocatedEvalError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        write!(f, "{} in `{}`", self.error, self.subterm)
+    }
+}
+
+impl std::error::Error for LocatedEvalError {}
+
+impl Term {
+    /// Like `evaluate_in`, but an error points at the innermost subterm that fails although all
+    /// of its operands evaluate, such as the `x/0` in `1 + x/0` or an unbound variable.
+    pub fn evaluate_located(&self, context: &EvalContext) -> Result<Number, LocatedEvalError> {
+        self.evaluate_in(context).map_err(|error| {
+            let (at, subterm) = self.locate_failure(context, TermPath::root());
+            LocatedEvalError {
+                error,
+                at,
+                subterm: subterm.to_string(),
+            }
+        })
+    }
+
+    /// Descends from this failing term into the first failing operand, as long as there is one.
+    fn locate_failure(&self, context: &EvalContext, path: TermPath) -> (TermPath, &Term) {
+        let fails = |term: &Term| term.evaluate_in(context).is_err();
+
+        let next = match self {
+            // The collection itself is not a number, so follow the element instead
+            Term::Index(collection, index) if !fails(index) => {
+                let position = match index.evaluate_in(context) {
+                    Ok(Number::Rational(position)) if position.is_integer() => position.to_usize(),
+                    _ => None,
+                };
+                match (&**collection, position) {
+                    (Term::Vector(elements) | Term::List(elements), Some(position)) => elements
+                        .get(position)
+                        .filter(|element| fails(element))
+                        .map(|element| (path.child(0).child(position), element)),
+                    _ => None,
+                }
+            }
+            Term::Vector(_) | Term::List(_) | Term::Set(_) => None,
+            _ => self
+                .children()
+                .into_iter()
+                .enumerate()
+                .find(|(_, child)| fails(child))
+                .map(|(i, child)| (path.child(i), child)),
+        };
+
+        match next {
+            Some((path, child)) => child.locate_failure(context, path),
+            None => (path, self),
+        }
+    }
+
+    /// The direct children of this term, in `TermPath` order.
+    pub fn children(&self) -> Vec<&Term> {
+        match self {
+            Term::Value(_) | Term::Variable(_) | Term::Constant(_) => Vec::new(),
+            Term::Addition(lhs, rhs)
+            | Term::Subtraction(lhs, rhs)
+            | Term::Multiplication(lhs, rhs)
+            | Term::Division(lhs, rhs)
+            | Term::Exponentation(lhs, rhs)
+            | Term::RootExtraction(lhs, rhs)
+            | Term::Index(lhs, rhs) => vec![lhs, rhs],
+            Term::Function(_, argument) => vec![argument],
+            Term::Vector(elements) | Term::List(elements) | Term::Set(elements) => {
+                elements.iter().collect()
+            }
+        }
+    }
+
+    pub fn subterm(&self, path: &TermPath) -> Option<&Term> {
+        path.0
+            .iter()
+            .try_fold(self, |term, &index| term.children().get(index).copied())
+    }
+
+    pub fn subterm_mut(&mut self, path: &TermPath) -> Option<&mut Term> {
+        path.0.iter().try_fold(self, |term, &index| match term {
+            Term::Value(_) | Term::Variable(_) | Term::Constant(_) => None,
+            Term::Addition(lhs, rhs)
+            | Term::Subtraction(lhs, rhs)
+            | Term::Multiplication(lhs, rhs)
+            | Term::Division(lhs, rhs)
+            | Term::Exponentation(lhs, rhs)
+            | Term::RootExtraction(lhs, rhs)
+            | Term::Index(lhs, rhs) => match index {
+                0 => Some(&mut **lhs),
+                1 => Some(&mut **rhs),
+                _ => None,
+            },
+            Term::Function(_, argument) => (index == 0).then_some(&mut **argument),
+            Term::Vector(elements) | Term::List(elements) | Term::Set(elements) => {
+                elements.get_mut(index)
+            }
+        })
+    }
+
+    /// This term and all of its subterms in pre-order, parents before their children.
+    pub fn iter_subterms(&self) -> Subterms<'_> {
+        Subterms { stack: vec![self] }
+    }
+
+    /// The values, variables and constants in this term, left to right.
+    pub fn iter_leaves(&self) -> impl Iterator<Item = &Term> {
+        self.iter_subterms()
+            .filter(|term| matches!(term, Term::Value(_) | Term::Variable(_) | Term::Constant(_)))
+    }
+
+    /// Rebuilds the term bottom-up, passing every subterm to `f` after its children have been
+    /// replaced. The first error stops the traversal and is returned.
+    pub fn try_map<E>(&self, mut f: impl FnMut(Term) -> Result<Term, E>) -> Result<Term, E> {
+        self.try_map_with(&mut f)
+    }
+
+    fn try_map_with<E>(&self, f: &mut impl FnMut(Term) -> Result<Term, E>) -> Result<Term, E> {
+        let mut boxed = |term: &Term| term.try_map_with(&mut *f).map(Box::new);
+
+        let term = match self {
+            Term::Value(_) | Term::Variable(_) | Term::Constant(_) => self.clone(),
+            Term::Addition(lhs, rhs) => Term::Addition(boxed(lhs)?, boxed(rhs)?),
+            Term::Subtraction(lhs, rhs) => Term::Subtraction(boxed(lhs)?, boxed(rhs)?),
+            Term::Multiplication(lhs, rhs) => Term::Multiplication(boxed(lhs)?, boxed(rhs)?),
+            Term::Division(lhs, rhs) => Term::Division(boxed(lhs)?, boxed(rhs)?),
+            Term::Exponentation(base, power) => Term::Exponentation(boxed(base)?, boxed(power)?),
+            Term::RootExtraction(radicand, degree) => {
+                Term::RootExtraction(boxed(radicand)?, boxed(degree)?)
+            }
+            Term::Function(function, argument) => Term::Function(*function, boxed(argument)?),
+            Term::Index(collection, index) => Term::Index(boxed(collection)?, boxed(index)?),
+            Term::Vector(elements) => Term::Vector(try_each(elements, f)?),
+            Term::List(elements) => Term::List(try_each(elements, f)?),
+            Term::Set(elements) => Term::Set(try_each(elements, f)?),
+        };
+        f(term)
+    }
+
+    /// The distinct variable names in this term, in sorted order.
+    pub fn variables(&self) -> BTreeSet<&str> {
+        self.iter_variables().collect()
+    }
+
+    /// The name of every variable occurrence, left to right and including repeats.
+    pub fn iter_variables(&self) -> impl Iterator<Item = &str> {
+        self.iter_subterms().filter_map(|term| match term {
+            Term::Variable(name) => Some(&**name),
+            _ => None,
+        })
+    }
+}
+
+fn try_each<E>(
+    terms: &[Term],
+    f: &mut impl FnMut(Term) -> Result<Term, E>,
+) -> Result<Vec<Term>, E> {
+    terms
+        .iter()
+        .map(|term| term.try_map_with(&mut *f))
+        .collect()
+}
+
+/// The iterator returned by `Term::iter_subterms`. It keeps an explicit stack, so deep terms
+/// cannot overflow the call stack.
+#[derive(Clone, Debug)]
+pub struct Subterms<'a> {
+    stack: Vec<&'a Term>,
+}
+
+impl<'a> Iterator for Subterms<'a> {
+    type Item = &'a Term;
+
+    fn next(&mut self) -> Option<&'a Term> {
+        let term = self.stack.pop()?;
+        self.stack.extend(term.children().into_iter().rev());
+        Some(term)
+    }
+}
@@ -0,0 +1,191 @@
+use std::collections::BTreeMap;
+use std::fmt::{self, Display, Formatter};
+
+use serde::de::{self, Deserializer};
+use serde::{Deserialize, Serialize, Serializer};
+use serde_json::{json, Value};
+
+use super::arena::{ArenaNode, TermArena};
+use super::terms::{Number, Term};
+
+/// The version of the JSON format written by `Term::to_json`. It increases whenever a change to
+/// `Term` changes how stored terms look, together with a migration from the previous version.
+///
+/// Version 2 stores terms as flat lists of nodes instead of nested objects.
+pub const FORMAT_VERSION: u32 = 2;
+
+/// Numbers are stored as their `Display` output, which parses back to the same number with
+/// exactly the same bits, including infinities and NaN.
+impl Serialize for Number {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.collect_str(self)
+    }
+}
+
+impl<'de> Deserialize<'de> for Number {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let text = String::deserialize(deserializer)?;
+        text.parse()
+            .map_err(|_| de::Error::invalid_value(de::Unexpected::Str(&text), &"a number"))
+    }
+}
+
+/// Terms are stored as the nodes of a `TermArena`, children before their parents and the root
+/// last, like `[{"variable":"x"},{"value":"1"},{"addition":[0,1]}]` for `x + 1`. Neither side
+/// recurses per level, so arbitrarily deep terms round-trip.
+impl Serialize for Term {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        let mut arena = TermArena::new();
+        arena.insert(self);
+        serializer.collect_seq(arena.nodes())
+    }
+}
+
+impl<'de> Deserialize<'de> for Term {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let nodes = Vec::<ArenaNode>::deserialize(deserializer)?;
+        let mut arena = TermArena::with_capacity(nodes.len());
+        let mut root = None;
+        for (index, node) in nodes.into_iter().enumerate() {
+            let id = arena.try_push(node).ok_or_else(|| {
+                de::Error::custom(format!("node {index} refers to a node after it"))
+            })?;
+            root = Some(id);
+        }
+        let root = root.ok_or_else(|| de::Error::invalid_length(0, &"at least one node"))?;
+        Ok(arena.to_term(root))
+    }
+}
+
+#[derive(Serialize, Deserialize)]
+struct Stored<T> {
+    version: u32,
+    term: T,
+}
+
+/// Why a stored term could not be loaded.
+#[derive(Debug)]
+pub enum LoadError {
+    Json(serde_json::Error),
+    /// Written by a newer version of this crate.
+    UnsupportedVersion(u32),
+    /// No migration is registered from this version to the next.
+    MissingMigration(u32),
+}
+
+impl Display for LoadError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        match self {
+            LoadError::Json(error) => write!(f, "invalid stored term: {error}"),
+            LoadError::UnsupportedVersion(version) => {
+                write!(f, "format version {version} is newer than {FORMAT_VERSION}")
+            }
+            LoadError::MissingMigration(version) => {
+                write!(f, "no migration from format version {version}")
+            }
+        }
+    }
+}
+
+impl std::error::Error for LoadError {}
+
+impl From<serde_json::Error> for LoadError {
+    fn from(error: serde_json::Error) -> Self {
+        LoadError::Json(error)
+    }
+}
+
+/// Functions that rewrite the JSON of a stored term from one format version to the next, so
+/// terms stored by older versions keep loading. Each migration gets the `term` field of the
+/// stored JSON. The migrations between this crate's own versions are registered from the start.
+#[derive(Clone, Debug)]
+pub struct Migrations {
+    steps: BTreeMap<u32, fn(Value) -> Value>,
+}
+
+impl Default for Migrations {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Migrations {
+    pub fn new() -> Self {
+        Self {
+            steps: BTreeMap::from([(1, flatten as fn(Value) -> Value)]),
+        }
+    }
+
+    /// Registers the migration from format version `from` to `from + 1`.
+    pub fn register(mut self, from: u32, migration: fn(Value) -> Value) -> Self {
+        self.steps.insert(from, migration);
+        self
+    }
+}
+
+impl Term {
+    /// The term as JSON, tagged with `FORMAT_VERSION`.
+    pub fn to_json(&self) -> String {
+        serde_json::to_string(&Stored {
+            version: FORMAT_VERSION,
+            term: self,
+        })
+        .expect("terms always serialize")
+    }
+
+    /// Loads a term written by `to_json` with the current `FORMAT_VERSION`.
+    pub fn from_json(json: &str) -> Result<Term, LoadError> {
+        Term::from_json_versioned(json, &Migrations::new())
+    }
+
+    /// Loads a term written by `to_json` with any format version up to `FORMAT_VERSION`,
+    /// running the registered migrations from its version onwards first.
+    pub fn from_json_versioned(json: &str, migrations: &Migrations) -> Result<Term, LoadError> {
+        let Stored { version, mut term } = serde_json::from_str::<Stored<Value>>(json)?;
+        if version > FORMAT_VERSION {
+            return Err(LoadError::UnsupportedVersion(version));
+        }
+        for from in version..FORMAT_VERSION {
+            let migration = migrations
+                .steps
+                .get(&from)
+                .ok_or(LoadError::MissingMigration(from))?;
+            term = migration(term);
+        }
+        Ok(serde_json::from_value(term)?)
+    }
+}
+
+/// Migrates a term from version 1, where every node nested its children, to the flat nodes of
+/// version 2. Malformed nodes are kept as they are, for deserialization to reject.
+fn flatten(term: Value) -> Value {
+    let (mut pending, mut ids, mut nodes) = (vec![(&term, false)], Vec::new(), Vec::new());
+    while let Some((node, children_done)) = pending.pop() {
+        let Some((tag, content)) = node.as_object().and_then(|object| object.iter().next()) else {
+            nodes.push(node.clone());
+            ids.push(json!(nodes.len() - 1));
+            continue;
+        };
+        let children: Vec<&Value> = match (tag.as_str(), content) {
+            ("value" | "variable" | "constant", _) => Vec::new(),
+            ("function", Value::Array(operands)) => operands.get(1).into_iter().collect(),
+            (_, Value::Array(operands)) => operands.iter().collect(),
+            _ => Vec::new(),
+        };
+        if !children_done && !children.is_empty() {
+            pending.push((node, true));
+            pending.extend(children.into_iter().rev().map(|child| (child, false)));
+            continue;
+        }
+
+        let operands = ids.split_off(ids.len() - children.len());
+        let content = match tag.as_str() {
+            _ if children.is_empty() => content.clone(),
+            "function" => json!([content[0], operands[0]]),
+            _ => Value::Array(operands),
+        };
+        nodes.push(json!({ tag: content }));
+        ids.push(json!(nodes.len() - 1));
+    }
+    Value::Array(nodes)
+}
@@ -0,0 +1,91 @@
+use std::io::Write;
+
+use super::grid::Grid;
+
+/// Colormaps as evenly spaced stops, interpolated linearly.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Default)]
+pub enum Colormap {
+    Grayscale,
+    #[default]
+    Viridis,
+    Inferno,
+    /// Blue below the middle of the range, red above it; for signed data.
+    Diverging,
+}
+
+impl Colormap {
+    fn stops(self) -> &'static [[u8; 3]] {
+        match self {
+            Colormap::Grayscale => &[[0, 0, 0], [255, 255, 255]],
+            Colormap::Viridis => &[
+                [68, 1, 84],
+                [59, 82, 139],
+                [33, 145, 140],
+                [94, 201, 98],
+                [253, 231, 37],
+            ],
+            Colormap::Inferno => &[
+                [0, 0, 4],
+                [87, 16, 110],
+                [188, 55, 84],
+                [249, 142, 9],
+                [252, 255, 164],
+            ],
+            Colormap::Diverging => &[[59, 76, 192], [221, 221, 221], [180, 4, 38]],
+        }
+    }
+
+    /// The color at `t` in `[0, 1]`; values outside are clamped.
+    pub fn color(self, t: f64) -> [u8; 3] {
+        let stops = self.stops();
+        let position = t.clamp(0.0, 1.0) * (stops.len() - 1) as f64;
+        let index = (position as usize).min(stops.len() - 2);
+        let fraction = position - index as f64;
+
+        let (from, to) = (stops[index], stops[index + 1]);
+        [0, 1, 2].map(|channel| {
+            let (a, b) = (from[channel] as f64, to[channel] as f64);
+            (a + (b - a) * fraction).round() as u8
+        })
+    }
+}
+
+impl Grid {
+    /// The smallest and largest defined sample, or `None` if there are none.
+    pub fn value_range(&self) -> Option<(f64, f64)> {
+        self.values
+            .entries()
+            .iter()
+            .filter_map(|value| value.as_ref().ok())
+            .fold(None, |range, value| match range {
+                None => Some((*value, *value)),
+                Some((lo, hi)) => Some((value.min(lo), value.max(hi))),
+            })
+    }
+
+    /// Renders the grid as an RGBA PNG with one pixel per sample and the largest `y` at the top.
+    /// Colors are scaled to `value_range`; undefined samples are transparent.
+    pub fn write_png(&self, out: impl Write, colormap: Colormap) -> Result<(), png::EncodingError> {
+        let (rows, cols) = (self.values.rows(), self.values.cols());
+        let (lo, hi) = self.value_range().unwrap_or((0.0, 0.0));
+        let span = if hi > lo { hi - lo } else { 1.0 };
+
+        let mut pixels = Vec::with_capacity(rows * cols * 4);
+        for row in (0..rows).rev() {
+            for col in 0..cols {
+                match self.values.get(row, col) {
+                    Ok(value) => {
+                        let [r, g, b] = colormap.color((value - lo) / span);
+                        pixels.extend_from_slice(&[r, g, b, 255]);
+                    }
+                    Err(_) => pixels.extend_from_slice(&[0, 0, 0, 0]),
+                }
+            }
+        }
+
+        let mut encoder = png::Encoder::new(out, cols as u32, rows as u32);
+        encoder.set_color(png::ColorType::Rgba);
+        encoder.set_depth(png::BitDepth::Eight);
+        encoder.write_header()?.write_image_data(&pixels)
+    }
+}
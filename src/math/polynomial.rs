@@ -0,0 +1,486 @@
+use std::fmt::Debug;
+use std::ops::{Add, Div, Mul, Neg, Sub};
+
+use fraction::{CheckedAdd, CheckedDiv, CheckedMul, CheckedSub, Integer, One, ToPrimitive, Zero};
+
+use super::terms::{Fraction, Number, Term};
+
+/// Coefficients a polynomial can be built over.
+pub trait Ring:
+    Copy
+    + Debug
+    + PartialEq
+    + Zero
+    + One
+    + Add<Output = Self>
+    + Sub<Output = Self>
+    + Mul<Output = Self>
+    + Neg<Output = Self>
+{
+}
+
+impl<T> Ring for T where
+    T: Copy
+        + Debug
+        + PartialEq
+        + Zero
+        + One
+        + Add<Output = T>
+        + Sub<Output = T>
+        + Mul<Output = T>
+        + Neg<Output = T>
+{
+}
+
+/// Rings in which every non-zero element can be divided by.
+pub trait Field: Ring + Div<Output = Self> {}
+
+impl<T: Ring + Div<Output = T>> Field for T {}
+
+/// A polynomial in a single variable with its coefficients stored lowest degree first.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Polynomial<C = Fraction> {
+    var: Box<str>,
+    coefficients: Vec<C>,
+}
+
+impl<C: Ring> Polynomial<C> {
+    pub fn new(var: &str, coefficients: Vec<C>) -> Self {
+        let mut polynomial = Self {
+            var: var.into(),
+            coefficients,
+        };
+        polynomial.trim();
+        polynomial
+    }
+
+    pub fn zero(var: &str) -> Self {
+        Self::new(var, Vec::new())
+    }
+
+    /// The monomial `coefficient * var^power`.
+    pub fn monomial(var: &str, coefficient: C, power: usize) -> Self {
+        let mut coefficients = vec![C::zero(); power + 1];
+        coefficients[power] = coefficient;
+        Self::new(var, coefficients)
+    }
+
+    fn trim(&mut self) {
+        while self.coefficients.last().is_some_and(C::is_zero) {
+            self.coefficients.pop();
+        }
+    }
+
+    pub fn var(&self) -> &str {
+        &self.var
+    }
+
+    pub fn coefficients(&self) -> &[C] {
+        &self.coefficients
+    }
+
+    pub fn coefficient(&self, power: usize) -> C {
+        self.coefficients
+            .get(power)
+            .copied()
+            .unwrap_or_else(C::zero)
+    }
+
+    pub fn leading_coefficient(&self) -> C {
+        self.coefficients.last().copied().unwrap_or_else(C::zero)
+    }
+
+    /// The degree of the polynomial, or `None` for the zero polynomial.
+    pub fn degree(&self) -> Option<usize> {
+        self.coefficients.len().checked_sub(1)
+    }
+
+    pub fn is_zero(&self) -> bool {
+        self.coefficients.is_empty()
+    }
+
+    /// The value at `x`. Fraction coefficients can overflow here; see `checked_evaluate`.
+    pub fn evaluate(&self, x: C) -> C {
+        self.coefficients
+            .iter()
+            .rev()
+            .fold(C::zero(), |acc, coefficient| acc * x + *coefficient)
+    }
+
+    pub fn add(&self, other: &Self) -> Self {
+        let length = self.coefficients.len().max(other.coefficients.len());
+        let coefficients = (0..length)
+            .map(|i| self.coefficient(i) + other.coefficient(i))
+            .collect();
+
+        Self::new(&self.var, coefficients)
+    }
+
+    pub fn sub(&self, other: &Self) -> Self {
+        self.add(&other.scale(-C::one()))
+    }
+
+    pub fn mul(&self, other: &Self) -> Self {
+        if self.is_zero() || other.is_zero() {
+            return Self::zero(&self.var);
+        }
+
+        let mut coefficients =
+            vec![C::zero(); self.coefficients.len() + other.coefficients.len() - 1];
+        for (i, a) in self.coefficients.iter().enumerate() {
+            for (j, b) in other.coefficients.iter().enumerate() {
+                coefficients[i + j] = coefficients[i + j] + *a * *b;
+            }
+        }
+
+        Self::new(&self.var, coefficients)
+    }
+
+    pub fn scale(&self, factor: C) -> Self {
+        Self::new(
+            &self.var,
+            self.coefficients.iter().map(|c| *c * factor).collect(),
+        )
+    }
+
+    /// Divides by `(var - root)` using synthetic division, discarding the remainder.
+    pub fn deflate(&self, root: C) -> Self {
+        let mut quotient = vec![C::zero(); self.coefficients.len().saturating_sub(1)];
+        let mut carry = C::zero();
+
+        for (power, coefficient) in self.coefficients.iter().enumerate().rev() {
+            carry = carry * root + *coefficient;
+            if power > 0 {
+                quotient[power - 1] = carry;
+            }
+        }
+
+        Self::new(&self.var, quotient)
+    }
+}
+
+impl<C: Field> Polynomial<C> {
+    /// Polynomial long division, returning the quotient and remainder.
+    ///
+    /// Returns `None` when dividing by the zero polynomial.
+    pub fn div_rem(&self, divisor: &Self) -> Option<(Self, Self)> {
+        let divisor_degree = divisor.degree()?;
+        let leading = divisor.leading_coefficient();
+
+        let mut remainder = self.clone();
+        let mut quotient = vec![C::zero(); self.coefficients.len().saturating_sub(divisor_degree)];
+
+        while let Some(degree) = remainder
+            .degree()
+            .filter(|degree| *degree >= divisor_degree)
+        {
+            let factor = remainder.leading_coefficient() / leading;
+            let shift = degree - divisor_degree;
+            quotient[shift] = factor;
+
+            for (i, c) in divisor.coefficients.iter().enumerate() {
+                remainder.coefficients[i + shift] = remainder.coefficients[i + shift] - factor * *c;
+            }
+            remainder.coefficients.truncate(degree);
+            remainder.trim();
+        }
+
+        Some((Self::new(&self.var, quotient), remainder))
+    }
+
+    /// The polynomial scaled to have leading coefficient one.
+    pub fn monic(&self) -> Self {
+        if self.is_zero() {
+            return self.clone();
+        }
+        self.scale(C::one() / self.leading_coefficient())
+    }
+
+    /// The monic greatest common divisor.
+    pub fn gcd(&self, other: &Self) -> Self {
+        let (mut a, mut b) = (self.clone(), other.clone());
+        while !b.is_zero() {
+            let (_, remainder) = a.div_rem(&b).unwrap();
+            a = b;
+            b = remainder;
+        }
+        a.monic()
+    }
+
+    /// The formal derivative.
+    pub fn derivative(&self) -> Self {
+        let coefficients = self
+            .coefficients
+            .iter()
+            .enumerate()
+            .skip(1)
+            .map(|(power, c)| (0..power).fold(C::zero(), |acc, _| acc + *c))
+            .collect();
+
+        Self::new(&self.var, coefficients)
+    }
+}
+
+impl Polynomial {
+    pub fn to_term(&self) -> Term {
+        let mut monomials = self
+            .coefficients
+            .iter()
+            .enumerate()
+            .filter(|(_, coefficient)| !coefficient.is_zero())
+            .map(|(power, coefficient)| self.monomial_term(*coefficient, power));
+
+        let first = match monomials.next() {
+            Some(first) => first,
+            None => return Term::Value(Number::Rational(Fraction::zero())),
+        };
+
+        monomials.fold(first, |acc, monomial| {
+            Term::Addition(Box::new(acc), Box::new(monomial))
+        })
+    }
+
+    fn monomial_term(&self, coefficient: Fraction, power: usize) -> Term {
+        let variable = match power {
+            0 => return Term::Value(Number::Rational(coefficient)),
+            1 => Term::Variable(self.var.clone()),
+            _ => Term::Exponentation(
+                Box::new(Term::Variable(self.var.clone())),
+                Box::new(Term::Value(Number::Rational(Fraction::from(power as i64)))),
+            ),
+        };
+
+        if coefficient == Fraction::from(1) {
+            variable
+        } else {
+            Term::Multiplication(
+                Box::new(Term::Value(Number::Rational(coefficient))),
+                Box::new(variable),
+            )
+        }
+    }
+
+    /// The value at `x`, or `None` if it overflows.
+    pub fn checked_evaluate(&self, x: Fraction) -> Option<Fraction> {
+        self.coefficients
+            .iter()
+            .rev()
+            .try_fold(Fraction::zero(), |acc, coefficient| {
+                acc.checked_mul(&x)?.checked_add(coefficient)
+            })
+    }
+
+    /// The antiderivative with zero constant term.
+    pub fn antiderivative(&self) -> Self {
+        self.checked_antiderivative()
+            .expect("antiderivative coefficient overflowed")
+    }
+
+    fn checked_antiderivative(&self) -> Option<Self> {
+        let coefficients = std::iter::once(Some(Fraction::zero()))
+            .chain(
+                self.coefficients
+                    .iter()
+                    .enumerate()
+                    .map(|(power, c)| c.checked_div(&Fraction::from(power as i64 + 1))),
+            )
+            .collect::<Option<_>>()?;
+
+        Some(Self::new(&self.var, coefficients))
+    }
+
+    /// The exact integral over `[a, b]`, or `None` if it overflows.
+    pub fn definite_integral(&self, a: Fraction, b: Fraction) -> Option<Fraction> {
+        let antiderivative = self.checked_antiderivative()?;
+        antiderivative
+            .checked_evaluate(b)?
+            .checked_sub(&antiderivative.checked_evaluate(a)?)
+    }
+
+    /// All rational roots, repeated according to their multiplicity.
+    pub fn rational_roots(&self) -> Vec<Fraction> {
+        let mut roots = Vec::new();
+        let mut remaining = self.clone();
+
+        while remaining.degree().is_some_and(|degree| degree > 0) {
+            if remaining.coefficient(0).is_zero() {
+                roots.push(Fraction::zero());
+                remaining = remaining.deflate(Fraction::zero());
+                continue;
+            }
+
+            match remaining.rational_root() {
+                Some(root) => {
+                    roots.push(root);
+                    remaining = remaining.deflate(root);
+                }
+                None => break,
+            }
+        }
+
+        roots
+    }
+
+    /// All real roots with multiplicity: the rational ones exactly, and those of a remaining
+    /// irreducible quadratic as floats. Returns `None` if a factor of higher degree remains.
+    pub fn real_roots(&self) -> Option<Vec<Number>> {
+        let roots = self.rational_roots();
+
+        let mut remaining = self.clone();
+        for root in &roots {
+            remaining = remaining.deflate(*root);
+        }
+
+        let mut roots: Vec<Number> = roots.into_iter().map(Number::Rational).collect();
+
+        match remaining.degree() {
+            Some(2) => {
+                let (c, b, a) = (
+                    remaining.coefficient(0),
+                    remaining.coefficient(1),
+                    remaining.coefficient(2),
+                );
+                let discriminant = (b * b - Fraction::from(4) * a * c).to_f64()?;
+
+                if discriminant >= 0.0 {
+                    let (b, a) = (b.to_f64()?, a.to_f64()?);
+                    let root = discriminant.sqrt();
+                    roots.push(Number::Irrational((-b - root) / (2.0 * a)));
+                    roots.push(Number::Irrational((-b + root) / (2.0 * a)));
+                }
+            }
+            Some(degree) if degree > 2 => return None,
+            _ => {}
+        }
+
+        Some(roots)
+    }
+
+    fn rational_root(&self) -> Option<Fraction> {
+        // Scale to integer coefficients and apply the rational root theorem.
+        let lcm = self
+            .coefficients
+            .iter()
+            .fold(1i64, |lcm, c| lcm.lcm(c.denom()));
+        let integers: Vec<i64> = self
+            .coefficients
+            .iter()
+            .map(|c| (c * Fraction::from(lcm)).to_integer())
+            .collect();
+
+        let constant = integers[0];
+        let leading = *integers.last()?;
+
+        for p in divisors(constant) {
+            for q in divisors(leading) {
+                for candidate in [Fraction::new(p, q), Fraction::new(-p, q)] {
+                    if self
+                        .checked_evaluate(candidate)
+                        .is_some_and(|y| y.is_zero())
+                    {
+                        return Some(candidate);
+                    }
+                }
+            }
+        }
+
+        None
+    }
+
+    /// Computes the `[m/n]` Padé approximant of the function whose Taylor coefficients (around 0)
+    /// are the coefficients of this polynomial, returning the numerator and denominator.
+    ///
+    /// Returns `None` if the linear system for the denominator is singular.
+    pub fn pade_parts(&self, m: usize, n: usize) -> Option<(Polynomial, Polynomial)> {
+        let c = |index: isize| {
+            if index < 0 {
+                Fraction::zero()
+            } else {
+                self.coefficient(index as usize)
+            }
+        };
+
+        // Denominator coefficients q_1..q_n satisfy sum_{j=1..n} q_j c_{m+k-j} = -c_{m+k}
+        // for k = 1..n, with q_0 = 1.
+        let mut system: Vec<Vec<Fraction>> = (1..=n)
+            .map(|k| {
+                let mut row: Vec<Fraction> =
+                    (1..=n).map(|j| c((m + k) as isize - j as isize)).collect();
+                row.push(-c((m + k) as isize));
+                row
+            })
+            .collect();
+
+        let solution = solve_linear_system(&mut system)?;
+
+        let mut denominator = vec![Fraction::from(1)];
+        denominator.extend(solution);
+
+        let numerator = (0..=m)
+            .map(|i| {
+                (0..=i.min(n))
+                    .map(|j| denominator[j] * c(i as isize - j as isize))
+                    .fold(Fraction::zero(), |acc, x| acc + x)
+            })
+            .collect();
+
+        Some((
+            Polynomial::new(&self.var, numerator),
+            Polynomial::new(&self.var, denominator),
+        ))
+    }
+
+    /// Computes the `[m/n]` Padé approximant as a term of the form `P * Q^-1`.
+    pub fn pade(&self, m: usize, n: usize) -> Option<Term> {
+        let (numerator, denominator) = self.pade_parts(m, n)?;
+
+        if denominator.degree() == Some(0) && denominator.coefficient(0) == Fraction::from(1) {
+            return Some(numerator.to_term());
+        }
+
+        Some(Term::Multiplication(
+            Box::new(numerator.to_term()),
+            Box::new(Term::Exponentation(
+                Box::new(denominator.to_term()),
+                Box::new(Term::Value(Number::Rational(Fraction::from(-1)))),
+            )),
+        ))
+    }
+}
+
+/// The positive divisors of `n` that fit in an `i64`, found in pairs up to its square root.
+fn divisors(n: i64) -> impl Iterator<Item = i64> {
+    let n = n.unsigned_abs();
+    (1..)
+        .take_while(move |d| d * d <= n)
+        .filter(move |d| n.is_multiple_of(*d))
+        .flat_map(move |d| [d, n / d])
+        .filter_map(|d| i64::try_from(d).ok())
+}
+
+/// Solves an augmented `n x (n + 1)` system by Gauss-Jordan elimination.
+fn solve_linear_system(system: &mut [Vec<Fraction>]) -> Option<Vec<Fraction>> {
+    let n = system.len();
+
+    for column in 0..n {
+        let pivot = (column..n).find(|&row| !system[row][column].is_zero())?;
+        system.swap(column, pivot);
+
+        let pivot_value = system[column][column];
+        for value in system[column].iter_mut() {
+            *value /= pivot_value;
+        }
+
+        let pivot_row = system[column].clone();
+        for (index, row) in system.iter_mut().enumerate() {
+            if index != column && !row[column].is_zero() {
+                let factor = row[column];
+                for (value, pivot) in row.iter_mut().zip(&pivot_row).skip(column) {
+                    *value -= factor * pivot;
+                }
+            }
+        }
+    }
+
+    Some(system.iter().map(|row| row[n]).collect())
+}
@@ -0,0 +1,198 @@
+use std::collections::HashMap;
+
+use fraction::One;
+
+use super::functions::Function;
+use super::terms::{EvalContext, EvalError, Fraction, Number, Term};
+
+/// An expression inserted into an `ExprPool`. Handles index the results of `evaluate_all` in the
+/// order the expressions were inserted.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub struct ExprHandle(usize);
+
+impl ExprHandle {
+    pub fn index(self) -> usize {
+        self.0
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+enum Operation {
+    Add,
+    Sub,
+    Mul,
+    Div,
+    Pow,
+    Root,
+}
+
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
+enum Node {
+    Value(Number),
+    Variable(Box<str>),
+    Binary(Operation, usize, usize),
+    Function(Function, usize),
+    /// A collection, an index or a function of several arguments, evaluated as a whole by
+    /// `Term::evaluate_in`.
+    Opaque(usize),
+}
+
+/// Many related expressions evaluated together. Each expression is canonicalized when it is
+/// inserted, subexpressions shared between expressions are stored and evaluated once, and
+/// subexpressions without variables are evaluated ahead of time.
+///
+/// Results match `Term::evaluate_in`, except that no fallback diagnostics are reported.
+#[derive(Clone, Debug, Default)]
+pub struct ExprPool {
+    /// Every node comes after its children.
+    nodes: Vec<Node>,
+    interned: HashMap<Node, usize>,
+    opaque: Vec<Term>,
+    opaque_interned: HashMap<u128, usize>,
+    roots: Vec<usize>,
+}
+
+impl ExprPool {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn insert(&mut self, term: &Term) -> ExprHandle {
+        let root = self.intern(&term.canonicalize());
+        self.roots.push(root);
+        ExprHandle(self.roots.len() - 1)
+    }
+
+    /// The number of expressions inserted so far.
+    pub fn len(&self) -> usize {
+        self.roots.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.roots.is_empty()
+    }
+
+    /// The number of distinct subexpressions stored, across all expressions.
+    pub fn node_count(&self) -> usize {
+        self.nodes.len()
+    }
+
+    /// The values of all expressions, indexed by `ExprHandle::index`.
+    pub fn evaluate_all(&self, context: &EvalContext) -> Vec<Result<Number, EvalError>> {
+        let mut values: Vec<Result<Number, EvalError>> = Vec::with_capacity(self.nodes.len());
+        for node in &self.nodes {
+            let value = self.evaluate_node(node, &values, context);
+            values.push(value);
+        }
+        self.roots
+            .iter()
+            .map(|&root| values[root].clone())
+            .collect()
+    }
+
+    pub fn evaluate(&self, handle: ExprHandle, context: &EvalContext) -> Result<Number, EvalError> {
+        let mut values = Vec::with_capacity(self.roots[handle.0] + 1);
+        for node in &self.nodes[..=self.roots[handle.0]] {
+            let value = self.evaluate_node(node, &values, context);
+            values.push(value);
+        }
+        values.pop().unwrap()
+    }
+
+    fn evaluate_node(
+        &self,
+        node: &Node,
+        values: &[Result<Number, EvalError>],
+        context: &EvalContext,
+    ) -> Result<Number, EvalError> {
+        let value = |index: usize| values[index].clone();
+
+        match node {
+            Node::Value(value) => Ok(*value),
+            Node::Variable(name) => context.get(name).ok_or_else(|| context.unbound(name)),
+            Node::Binary(operation, lhs, rhs) => apply(*operation, value(*lhs)?, value(*rhs)?),
+            Node::Function(function, _) if !context.allows(*function) => {
+                Err(EvalError::RestrictedFunction(*function))
+            }
+            Node::Function(function, argument) => function.apply(value(*argument)?),
+            Node::Opaque(index) => self.opaque[*index].evaluate_in(context),
+        }
+    }
+
+    fn intern(&mut self, term: &Term) -> usize {
+        let binary = |pool: &mut Self, operation, lhs: &Term, rhs: &Term| {
+            let (lhs, rhs) = (pool.intern(lhs), pool.intern(rhs));
+            pool.fold(Node::Binary(operation, lhs, rhs))
+        };
+
+        let node = match term {
+            Term::Value(value) => Node::Value(*value),
+            Term::Constant(constant) => Node::Value(Number::Irrational(constant.value())),
+            Term::Variable(name) => Node::Variable(name.clone()),
+            Term::Addition(lhs, rhs) => binary(self, Operation::Add, lhs, rhs),
+            Term::Subtraction(lhs, rhs) => binary(self, Operation::Sub, lhs, rhs),
+            Term::Multiplication(lhs, rhs) => binary(self, Operation::Mul, lhs, rhs),
+            Term::Division(lhs, rhs) => binary(self, Operation::Div, lhs, rhs),
+            Term::Exponentation(base, power) => binary(self, Operation::Pow, base, power),
+            Term::RootExtraction(radicand, degree) => {
+                binary(self, Operation::Root, radicand, degree)
+            }
+            Term::Function(function, argument) if function.arity() == 1 => {
+                let argument = self.intern(argument);
+                self.fold(Node::Function(*function, argument))
+            }
+            Term::Function(..)
+            | Term::Vector(_)
+            | Term::List(_)
+            | Term::Set(_)
+            | Term::Index(..) => {
+                let next = self.opaque.len();
+                let index = *self
+                    .opaque_interned
+                    .entry(term.fingerprint())
+                    .or_insert(next);
+                if index == next {
+                    self.opaque.push(term.clone());
+                }
+                Node::Opaque(index)
+            }
+        };
+
+        let next = self.nodes.len();
+        let index = *self.interned.entry(node.clone()).or_insert(next);
+        if index == next {
+            self.nodes.push(node);
+        }
+        index
+    }
+
+    /// `node` replaced by its value if all its operands are known and evaluating it succeeds.
+    fn fold(&self, node: Node) -> Node {
+        let known = |index: usize| match self.nodes[index] {
+            Node::Value(value) => Some(value),
+            _ => None,
+        };
+        let value = match node {
+            Node::Binary(operation, lhs, rhs) => known(lhs)
+                .zip(known(rhs))
+                .and_then(|(lhs, rhs)| apply(operation, lhs, rhs).ok()),
+            // Calls are left to evaluation, where the context decides whether they are allowed
+            _ => None,
+        };
+        value.map_or(node, Node::Value)
+    }
+}
+
+fn apply(operation: Operation, lhs: Number, rhs: Number) -> Result<Number, EvalError> {
+    match operation {
+        Operation::Add => lhs.checked_add(rhs),
+        Operation::Sub => lhs.checked_sub(rhs),
+        Operation::Mul => lhs.checked_mul(rhs),
+        Operation::Div => lhs.checked_div(rhs),
+        Operation::Pow => lhs.checked_pow(&rhs),
+        Operation::Root => {
+            let power = Number::Rational(Fraction::one()).checked_div(rhs)?;
+            lhs.checked_pow(&power)
+        }
+    }
+}
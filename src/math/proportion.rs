@@ -0,0 +1,150 @@
+use std::fmt::{self, Display, Formatter};
+
+use fraction::{CheckedDiv, CheckedMul, Zero};
+
+use super::exact::ExactnessViolation;
+use super::parser::{ParseError, ParseErrorKind};
+use super::terms::{Fraction, Term};
+
+/// A proportion `a : b = c : d`.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Proportion {
+    pub a: Term,
+    pub b: Term,
+    pub c: Term,
+    pub d: Term,
+}
+
+impl Display for Proportion {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        write!(f, "{} : {} = {} : {}", self.a, self.b, self.c, self.d)
+    }
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub enum ProportionError {
+    /// The number of terms that are a single variable, when there is not exactly one.
+    Unknowns(usize),
+    /// A known term without an exact value.
+    Inexact(ExactnessViolation),
+    /// The unknown is only determined by dividing by zero, like in `0 : 2 = 3 : x`.
+    DivisionByZero,
+    Overflow,
+}
+
+impl Display for ProportionError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        match self {
+            ProportionError::Unknowns(count) => {
+                write!(f, "expected exactly one unknown, found {count}")
+            }
+            ProportionError::Inexact(violation) => {
+                write!(
+                    f,
+                    "a known term has no exact value ({:?})",
+                    violation.reason
+                )
+            }
+            ProportionError::DivisionByZero => write!(f, "solving would divide by zero"),
+            ProportionError::Overflow => write!(f, "exact result too large"),
+        }
+    }
+}
+
+impl std::error::Error for ProportionError {}
+
+impl Proportion {
+    pub fn new(a: Term, b: Term, c: Term, d: Term) -> Self {
+        Self { a, b, c, d }
+    }
+
+    /// Parses `a : b = c : d`, where each of the four terms is read by `Term::parse`.
+    pub fn parse(input: &str) -> Result<Proportion, ParseError> {
+        let mut separators = input.char_indices().filter(|(_, c)| matches!(c, ':' | '='));
+        let mut bounds = vec![0];
+        for expected in [':', '=', ':'] {
+            match separators.next() {
+                Some((position, c)) if c == expected => bounds.extend([position, position + 1]),
+                Some((position, c)) => {
+                    return Err(ParseError {
+                        kind: ParseErrorKind::UnexpectedCharacter(c),
+                        position,
+                    })
+                }
+                None => {
+                    return Err(ParseError {
+                        kind: ParseErrorKind::UnexpectedEnd,
+                        position: input.len(),
+                    })
+                }
+            }
+        }
+        if let Some((position, c)) = separators.next() {
+            return Err(ParseError {
+                kind: ParseErrorKind::UnexpectedCharacter(c),
+                position,
+            });
+        }
+        bounds.push(input.len());
+
+        let parse = |index: usize| {
+            let (start, end) = (bounds[2 * index], bounds[2 * index + 1]);
+            Term::parse(&input[start..end]).map_err(|error| ParseError {
+                position: error.position + start,
+                ..error
+            })
+        };
+        Ok(Proportion::new(parse(0)?, parse(1)?, parse(2)?, parse(3)?))
+    }
+
+    /// Solves for the one term that is a single variable, as for `solve_proportion`.
+    pub fn solve(&self) -> Result<(Box<str>, Fraction), ProportionError> {
+        solve_proportion(&self.a, &self.b, &self.c, &self.d)
+    }
+}
+
+/// Solves `a : b = c : x` exactly by cross-multiplying. The unknown may be any of the four terms,
+/// as long as it is the only one that is a single variable; the others must have exact values.
+/// Returns the name of the unknown and its value.
+pub fn solve_proportion(
+    a: &Term,
+    b: &Term,
+    c: &Term,
+    x: &Term,
+) -> Result<(Box<str>, Fraction), ProportionError> {
+    let terms = [a, b, c, x];
+    let unknowns: Vec<_> = (0..4)
+        .filter(|&index| matches!(terms[index], Term::Variable(_)))
+        .collect();
+    let &[unknown] = unknowns.as_slice() else {
+        return Err(ProportionError::Unknowns(unknowns.len()));
+    };
+    let Term::Variable(name) = terms[unknown] else {
+        unreachable!("unknowns are variables");
+    };
+
+    let mut values = [Fraction::zero(); 4];
+    for index in (0..4).filter(|&index| index != unknown) {
+        values[index] = terms[index]
+            .evaluate_exact()
+            .map_err(ProportionError::Inexact)?;
+    }
+
+    // `a * x = b * c`, so the unknown is the product of its two neighbours divided by the term
+    // opposite to it
+    let (neighbours, opposite) = match unknown {
+        0 => ((1, 2), 3),
+        1 => ((0, 3), 2),
+        2 => ((0, 3), 1),
+        _ => ((1, 2), 0),
+    };
+    if values[opposite].is_zero() {
+        return Err(ProportionError::DivisionByZero);
+    }
+    let value = values[neighbours.0]
+        .checked_mul(&values[neighbours.1])
+        .and_then(|product| product.checked_div(&values[opposite]))
+        .ok_or(ProportionError::Overflow)?;
+
+    Ok((name.clone(), value))
+}
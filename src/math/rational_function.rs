@@ -0,0 +1,217 @@
+use fraction::{CheckedDiv, One, Zero};
+
+use super::polynomial::Polynomial;
+use super::terms::{Fraction, Number, Term};
+
+/// A line the graph of a rational function approaches.
+#[derive(Clone, Debug, PartialEq)]
+pub enum Asymptote {
+    /// `var = x` at a pole.
+    Vertical(Number),
+    /// `y = c` as `var` tends to infinity.
+    Horizontal(Fraction),
+    /// `y = slope * var + intercept` as `var` tends to infinity.
+    Oblique {
+        slope: Fraction,
+        intercept: Fraction,
+    },
+}
+
+/// A quotient of two polynomials in lowest terms, with a monic denominator.
+#[derive(Clone, Debug, PartialEq)]
+pub struct RationalFunction {
+    num: Polynomial,
+    den: Polynomial,
+}
+
+impl RationalFunction {
+    /// Builds `num / den` in lowest terms. Returns `None` if `den` is zero.
+    pub fn new(num: Polynomial, den: Polynomial) -> Option<Self> {
+        if den.is_zero() {
+            return None;
+        }
+
+        let divisor = num.gcd(&den);
+        let (num, _) = num.div_rem(&divisor)?;
+        let (den, _) = den.div_rem(&divisor)?;
+
+        let scale = den.leading_coefficient().recip();
+        Some(Self {
+            num: num.scale(scale),
+            den: den.scale(scale),
+        })
+    }
+
+    pub fn from_polynomial(polynomial: Polynomial) -> Self {
+        let one = Polynomial::new(polynomial.var(), vec![Fraction::one()]);
+        Self {
+            num: polynomial,
+            den: one,
+        }
+    }
+
+    pub fn numerator(&self) -> &Polynomial {
+        &self.num
+    }
+
+    pub fn denominator(&self) -> &Polynomial {
+        &self.den
+    }
+
+    pub fn var(&self) -> &str {
+        self.num.var()
+    }
+
+    /// The numerator, if the denominator is constant.
+    pub fn to_polynomial(&self) -> Option<Polynomial> {
+        (self.den.degree() == Some(0)).then(|| self.num.clone())
+    }
+
+    pub fn add(&self, other: &Self) -> Self {
+        Self::new(
+            self.num.mul(&other.den).add(&other.num.mul(&self.den)),
+            self.den.mul(&other.den),
+        )
+        .unwrap()
+    }
+
+    pub fn sub(&self, other: &Self) -> Self {
+        Self::new(
+            self.num.mul(&other.den).sub(&other.num.mul(&self.den)),
+            self.den.mul(&other.den),
+        )
+        .unwrap()
+    }
+
+    pub fn mul(&self, other: &Self) -> Self {
+        Self::new(self.num.mul(&other.num), self.den.mul(&other.den)).unwrap()
+    }
+
+    /// Returns `None` when dividing by the zero function.
+    pub fn div(&self, other: &Self) -> Option<Self> {
+        Self::new(self.num.mul(&other.den), self.den.mul(&other.num))
+    }
+
+    pub fn recip(&self) -> Option<Self> {
+        Self::new(self.den.clone(), self.num.clone())
+    }
+
+    pub fn pow(&self, exponent: i64) -> Option<Self> {
+        let base = if exponent < 0 {
+            self.recip()?
+        } else {
+            self.clone()
+        };
+
+        let one = Self::from_polynomial(Polynomial::new(self.var(), vec![Fraction::one()]));
+        Some((0..exponent.unsigned_abs()).fold(one, |acc, _| acc.mul(&base)))
+    }
+
+    /// Evaluates at `x`, returning `None` at a pole or if the value overflows.
+    pub fn evaluate(&self, x: Fraction) -> Option<Fraction> {
+        let den = self.den.checked_evaluate(x)?;
+        if den.is_zero() {
+            return None;
+        }
+        self.num.checked_evaluate(x)?.checked_div(&den)
+    }
+
+    /// The distinct real poles, in ascending order. Common factors have already been cancelled, so
+    /// removable singularities are not included. Returns `None` if the denominator has an
+    /// irreducible factor of degree three or more.
+    pub fn poles(&self) -> Option<Vec<Number>> {
+        let mut poles = self.den.real_roots()?;
+        poles.sort_by(|a, b| f64::from(*a).total_cmp(&f64::from(*b)));
+        poles.dedup();
+        Some(poles)
+    }
+
+    /// Vertical asymptotes at the poles, followed by the horizontal or oblique asymptote if the
+    /// numerator's degree exceeds the denominator's by at most one.
+    pub fn asymptotes(&self) -> Option<Vec<Asymptote>> {
+        let mut asymptotes: Vec<Asymptote> =
+            self.poles()?.into_iter().map(Asymptote::Vertical).collect();
+
+        let (num, den) = (
+            self.num.degree().unwrap_or(0),
+            self.den.degree().unwrap_or(0),
+        );
+
+        if num < den {
+            asymptotes.push(Asymptote::Horizontal(Fraction::zero()));
+        } else if num == den {
+            asymptotes.push(Asymptote::Horizontal(
+                self.num.leading_coefficient() / self.den.leading_coefficient(),
+            ));
+        } else if num == den + 1 && den > 0 {
+            let (quotient, _) = self.num.div_rem(&self.den)?;
+            asymptotes.push(Asymptote::Oblique {
+                slope: quotient.coefficient(1),
+                intercept: quotient.coefficient(0),
+            });
+        }
+
+        Some(asymptotes)
+    }
+
+    pub fn to_term(&self) -> Term {
+        match self.to_polynomial() {
+            Some(polynomial) => polynomial.to_term(),
+            None => Term::Multiplication(
+                Box::new(self.num.to_term()),
+                Box::new(Term::Exponentation(
+                    Box::new(self.den.to_term()),
+                    Box::new(Term::Value(Number::Rational(Fraction::from(-1)))),
+                )),
+            ),
+        }
+    }
+
+    /// Converts a term built from rational constants, `var`, the four basic operations and integer
+    /// powers.
+    pub fn from_term(term: &Term, var: &str) -> Option<Self> {
+        let constant = |value: Fraction| Self::from_polynomial(Polynomial::new(var, vec![value]));
+
+        match term {
+            Term::Value(Number::Rational(value)) => Some(constant(*value)),
+            Term::Variable(name) if &**name == var => Some(Self::from_polynomial(
+                Polynomial::monomial(var, Fraction::one(), 1),
+            )),
+            Term::Addition(lhs, rhs) => {
+                Some(Self::from_term(lhs, var)?.add(&Self::from_term(rhs, var)?))
+            }
+            Term::Subtraction(lhs, rhs) => {
+                Some(Self::from_term(lhs, var)?.sub(&Self::from_term(rhs, var)?))
+            }
+            Term::Multiplication(lhs, rhs) => {
+                Some(Self::from_term(lhs, var)?.mul(&Self::from_term(rhs, var)?))
+            }
+            Term::Division(lhs, rhs) => Self::from_term(lhs, var)?.div(&Self::from_term(rhs, var)?),
+            Term::Exponentation(base, power) => {
+                let power = match **power {
+                    Term::Value(Number::Rational(power)) if power.is_integer() => {
+                        power.to_integer()
+                    }
+                    _ => return None,
+                };
+                Self::from_term(base, var)?.pow(power)
+            }
+            _ => None,
+        }
+    }
+}
+
+impl Term {
+    /// The asymptotes of this term as a rational function of `var`. Returns `None` if the term is
+    /// not a rational function or its poles cannot be found exactly.
+    pub fn asymptotes(&self, var: &str) -> Option<Vec<Asymptote>> {
+        RationalFunction::from_term(self, var)?.asymptotes()
+    }
+}
+
+impl Polynomial {
+    /// Converts a term that is a polynomial in `var` with rational coefficients.
+    pub fn from_term(term: &Term, var: &str) -> Option<Self> {
+        RationalFunction::from_term(term, var)?.to_polynomial()
+    }
+}
@@ -0,0 +1,68 @@
+use fraction::Signed;
+
+use super::terms::{EvalContext, EvalError, Fraction, Number, Term};
+
+impl Number {
+    /// `|self - expected| / |expected|`, exact when both numbers are rational. Fails with
+    /// `DivisionByZero` when `expected` is zero.
+    pub fn relative_error(self, expected: Number) -> Result<Number, EvalError> {
+        magnitude(self.checked_sub(expected)?).checked_div(magnitude(expected))
+    }
+
+    /// `relative_error` in percent, so `3/2` against `2` gives `25`.
+    pub fn percent_error(self, expected: Number) -> Result<Number, EvalError> {
+        self.relative_error(expected)?
+            .checked_mul(Number::Rational(Fraction::from(100)))
+    }
+
+    /// `|self - other| / max(|self|, |other|)`, which unlike `relative_error` does not depend on
+    /// the order of the numbers. Two zeros have a relative difference of zero.
+    pub fn relative_difference(self, other: Number) -> Result<Number, EvalError> {
+        let larger = magnitude(self).max(magnitude(other));
+        if is_zero(larger) {
+            return Ok(larger);
+        }
+        magnitude(self.checked_sub(other)?).checked_div(larger)
+    }
+}
+
+impl Term {
+    /// The `relative_error` of the value of this term against the value of `expected`.
+    pub fn relative_error(
+        &self,
+        expected: &Term,
+        context: &EvalContext,
+    ) -> Result<Number, EvalError> {
+        self.evaluate_in(context)?
+            .relative_error(expected.evaluate_in(context)?)
+    }
+
+    /// Whether the value of this term is within a relative `tolerance` of the value of
+    /// `expected`. An expected value of exactly zero is compared by the absolute difference
+    /// instead.
+    pub fn within_tolerance(
+        &self,
+        expected: &Term,
+        tolerance: Number,
+        context: &EvalContext,
+    ) -> Result<bool, EvalError> {
+        let (actual, expected) = (self.evaluate_in(context)?, expected.evaluate_in(context)?);
+        let error = if is_zero(expected) {
+            magnitude(actual)
+        } else {
+            actual.relative_error(expected)?
+        };
+        Ok(error <= tolerance)
+    }
+}
+
+fn magnitude(number: Number) -> Number {
+    match number {
+        Number::Rational(value) => Number::Rational(value.abs()),
+        Number::Irrational(value) => Number::Irrational(value.abs()),
+    }
+}
+
+fn is_zero(number: Number) -> bool {
+    f64::from(number) == 0.0
+}
@@ -0,0 +1,79 @@
+use std::collections::{BTreeSet, HashMap};
+
+use super::terms::Term;
+
+impl Term {
+    /// Replaces every occurrence of the variable `old` with `new`.
+    pub fn rename_variable(&self, old: &str, new: &str) -> Term {
+        self.rename_variables(&HashMap::from([(old, new)]))
+    }
+
+    /// Renames all variables in `renaming` at once, so `{x: y, y: x}` swaps `x` and `y` rather
+    /// than turning both into `x`.
+    pub fn rename_variables(&self, renaming: &HashMap<&str, &str>) -> Term {
+        self.map_variables(&|name| renaming.get(name).map(|new| Term::Variable((*new).into())))
+    }
+
+    /// Puts `prefix` in front of every variable name, so `x` becomes `model1::x`.
+    pub fn prefix_variables(&self, prefix: &str) -> Term {
+        self.map_variables(&|name| Some(Term::Variable(format!("{prefix}{name}").into())))
+    }
+
+    /// Combines this term with `other`, for example with `Term::Addition`, after renaming each
+    /// variable of `other` that also occurs here to the first free `name_1`, `name_2`, ... Returns
+    /// the combined term and the renaming applied to `other`.
+    pub fn compose(
+        &self,
+        other: &Term,
+        combine: impl FnOnce(Box<Term>, Box<Term>) -> Term,
+    ) -> (Term, HashMap<Box<str>, Box<str>>) {
+        let names =
+            |term: &Term| -> BTreeSet<Box<str>> { term.iter_variables().map(Into::into).collect() };
+        let (mut taken, theirs) = (names(self), names(other));
+
+        let mut renaming = HashMap::new();
+        for name in &theirs {
+            if !taken.contains(name) {
+                continue;
+            }
+            let fresh: Box<str> = (1..)
+                .map(|i| format!("{name}_{i}").into())
+                .find(|fresh| !taken.contains(fresh) && !theirs.contains(fresh))
+                .unwrap();
+            taken.insert(fresh.clone());
+            renaming.insert(name.clone(), fresh);
+        }
+
+        let other = other.map_variables(&|name| {
+            renaming
+                .get(name)
+                .map(|fresh| Term::Variable(fresh.clone()))
+        });
+        (combine(Box::new(self.clone()), Box::new(other)), renaming)
+    }
+
+    /// Replaces each variable for which `f` returns a term in a single pass, so replacements are
+    /// never themselves replaced.
+    pub(crate) fn map_variables(&self, f: &impl Fn(&str) -> Option<Term>) -> Term {
+        let boxed = |term: &Term| Box::new(term.map_variables(f));
+        let each = |terms: &[Term]| terms.iter().map(|term| term.map_variables(f)).collect();
+
+        match self {
+            Term::Variable(name) => f(name).unwrap_or_else(|| self.clone()),
+            Term::Value(_) | Term::Constant(_) => self.clone(),
+            Term::Addition(lhs, rhs) => Term::Addition(boxed(lhs), boxed(rhs)),
+            Term::Subtraction(lhs, rhs) => Term::Subtraction(boxed(lhs), boxed(rhs)),
+            Term::Multiplication(lhs, rhs) => Term::Multiplication(boxed(lhs), boxed(rhs)),
+            Term::Division(lhs, rhs) => Term::Division(boxed(lhs), boxed(rhs)),
+            Term::Exponentation(base, power) => Term::Exponentation(boxed(base), boxed(power)),
+            Term::RootExtraction(radicand, degree) => {
+                Term::RootExtraction(boxed(radicand), boxed(degree))
+            }
+            Term::Function(function, argument) => Term::Function(*function, boxed(argument)),
+            Term::Vector(elements) => Term::Vector(each(elements)),
+            Term::List(elements) => Term::List(each(elements)),
+            Term::Set(elements) => Term::Set(each(elements)),
+            Term::Index(collection, index) => Term::Index(boxed(collection), boxed(index)),
+        }
+    }
+}
@@ -0,0 +1,389 @@
+use std::collections::HashMap;
+use std::io::{self, Read, Write};
+use std::net::{TcpListener, TcpStream};
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::mpsc::{self, RecvTimeoutError};
+use std::sync::Arc;
+use std::thread;
+use std::time::{Duration, Instant};
+
+use serde::Deserialize;
+use serde_json::{json, Value};
+
+use super::cancel::{CancellationToken, Cancelled};
+use super::terms::{EvalContext, Number, Term};
+
+/// The largest request head, the request line and headers, that is read.
+pub const MAX_HEAD: usize = 8 * 1024;
+/// The largest request body that is read.
+pub const MAX_BODY: usize = 64 * 1024;
+/// The longest `input` that is parsed.
+pub const MAX_INPUT: usize = 16 * 1024;
+/// The largest term, in nodes, that is worked on.
+pub const MAX_NODES: usize = 10_000;
+/// The most subterms `/simplify` and `/derive` visit before giving up.
+pub const MAX_STEPS: usize = 100_000;
+/// How many connections are served at the same time before new ones are turned away.
+pub const MAX_CONNECTIONS: usize = 64;
+
+/// How long `respond` works on a request before answering that it timed out. Simplifying and
+/// differentiating are cancelled at this deadline. Evaluating needs none, because every
+/// function bounds its arguments, like `prime` by `nt::MAX_PRIME_INDEX`.
+pub const COMPUTE_TIMEOUT: Duration = Duration::from_secs(5);
+
+/// How long a client has to send its whole request, and to receive the response.
+const TIMEOUT: Duration = Duration::from_secs(10);
+/// Parsing and most operations on terms recurse, so inputs nested as deeply as `MAX_INPUT` allows
+/// need more than the default stack.
+const STACK_SIZE: usize = 64 * 1024 * 1024;
+
+/// The status code and JSON body of a response.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Response {
+    pub status: u16,
+    pub body: Value,
+}
+
+impl Response {
+    fn ok(body: Value) -> Self {
+        Response { status: 200, body }
+    }
+
+    /// An error body like `{"error": {"kind": "parse", "message": "...", "position": 3}}`.
+    fn error(status: u16, kind: &str, message: impl ToString) -> Self {
+        Response {
+            status,
+            body: json!({ "error": { "kind": kind, "message": message.to_string() } }),
+        }
+    }
+
+    fn with_position(mut self, position: usize) -> Self {
+        self.body["error"]["position"] = position.into();
+        self
+    }
+}
+
+/// The body of every request. The term is given either as text in `input` or in `term` as the
+/// list of nodes `Term` serializes to.
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+struct Request {
+    input: Option<String>,
+    term: Option<Term>,
+    #[serde(default)]
+    bindings: HashMap<String, Number>,
+    var: Option<String>,
+}
+
+impl Request {
+    fn term(self) -> Result<Term, Response> {
+        let term = match (self.input, self.term) {
+            (Some(input), None) => {
+                if input.len() > MAX_INPUT {
+                    return Err(Response::error(
+                        413,
+                        "too_large",
+                        format!("inputs may have at most {MAX_INPUT} bytes"),
+                    ));
+                }
+                Term::parse(&input).map_err(|error| {
+                    Response::error(400, "parse", &error).with_position(error.position)
+                })?
+            }
+            (None, Some(term)) => term,
+            _ => {
+                return Err(Response::error(
+                    400,
+                    "bad_request",
+                    "expected exactly one of `input` and `term`",
+                ))
+            }
+        };
+        if term.iter_subterms().count() > MAX_NODES {
+            return Err(Response::error(
+                413,
+                "too_large",
+                format!("terms may have at most {MAX_NODES} nodes"),
+            ));
+        }
+        Ok(term)
+    }
+}
+
+/// Answers a request to one of the endpoints, all of which take a JSON body by `POST`:
+///
+/// - `/parse` reads `input` and returns `{"term": ...}`.
+/// - `/evaluate` returns `{"value": "..."}`, with variables bound by `bindings` like
+///   `{"x": "1/2"}`.
+/// - `/simplify` returns `{"term": ..., "budget": ...}`, where the budget like
+///   `{"used": 5, "limit": 100000, "converged": true}` tells whether the whole term was
+///   simplified within `MAX_STEPS`.
+/// - `/derive` differentiates with respect to `var` and returns `{"term": ...}`.
+///
+/// Requests that take longer than `COMPUTE_TIMEOUT` are answered with a `timeout` error.
+pub fn respond(method: &str, path: &str, body: &[u8]) -> Response {
+    let token = CancellationToken::new();
+    let (done, finished) = mpsc::channel::<()>();
+    let deadline = token.clone();
+    thread::spawn(move || {
+        // Dropping `done` wakes this early once the response is ready
+        if finished.recv_timeout(COMPUTE_TIMEOUT) == Err(RecvTimeoutError::Timeout) {
+            deadline.cancel();
+        }
+    });
+    let response = respond_cancellable(method, path, body, &token);
+    drop(done);
+    response
+}
+
+/// Like `respond`, answering with a `timeout` error once `token` is cancelled.
+pub(crate) fn respond_cancellable(
+    method: &str,
+    path: &str,
+    body: &[u8],
+    token: &CancellationToken,
+) -> Response {
+    if !matches!(path, "/parse" | "/evaluate" | "/simplify" | "/derive") {
+        return Response::error(404, "not_found", format!("no endpoint at `{path}`"));
+    }
+    if method != "POST" {
+        return Response::error(405, "method_not_allowed", "endpoints only accept POST");
+    }
+    let request: Request = match serde_json::from_slice(body) {
+        Ok(request) => request,
+        Err(error) => return Response::error(400, "invalid_json", error),
+    };
+
+    let result = match path {
+        "/parse" if request.term.is_some() => Err(Response::error(
+            400,
+            "bad_request",
+            "`/parse` takes `input`, not `term`",
+        )),
+        "/parse" => request.term().map(|term| json!({ "term": term })),
+        "/evaluate" => evaluate(request),
+        "/simplify" => simplify(request, token),
+        _ => derive(request, token),
+    };
+    result.map_or_else(|error| error, Response::ok)
+}
+
+fn evaluate(request: Request) -> Result<Value, Response> {
+    let mut context = EvalContext::new();
+    for (name, value) in &request.bindings {
+        context.set(name, *value);
+    }
+    let value = request
+        .term()?
+        .evaluate_in(&context)
+        .map_err(|error| Response::error(422, "eval", error))?;
+    Ok(json!({ "value": value }))
+}
+
+fn timed_out(_: Cancelled) -> Response {
+    Response::error(
+        503,
+        "timeout",
+        format!("requests may take at most {COMPUTE_TIMEOUT:?}"),
+    )
+}
+
+fn simplify(request: Request, token: &CancellationToken) -> Result<Value, Response> {
+    let (term, budget) = request
+        .term()?
+        .simplify_metered(MAX_STEPS, token)
+        .map_err(timed_out)?;
+    Ok(json!({ "term": term, "budget": budget }))
+}
+
+fn derive(request: Request, token: &CancellationToken) -> Result<Value, Response> {
+    let Some(var) = request.var.clone() else {
+        return Err(Response::error(400, "bad_request", "`/derive` needs `var`"));
+    };
+    let (derivative, budget) = request
+        .term()?
+        .derivative_metered(&var, MAX_STEPS, token)
+        .map_err(timed_out)?;
+    let derivative = derivative.ok_or_else(|| {
+        if budget.exhausted() {
+            Response::error(
+                422,
+                "budget_exhausted",
+                format!("differentiating takes more than {MAX_STEPS} steps"),
+            )
+        } else {
+            Response::error(
+                422,
+                "not_differentiable",
+                format!("cannot differentiate with respect to `{var}`"),
+            )
+        }
+    })?;
+    Ok(json!({ "term": derivative }))
+}
+
+/// Serves the endpoints of `respond` over HTTP/1.1, one thread per connection. Every response
+/// closes its connection.
+///
+/// The HTTP handling is a small subset written on `std::net` rather than a framework like axum:
+/// the endpoints need nothing beyond one `Content-Length` body per connection, and this keeps
+/// the `server` feature free of an async runtime and the dependencies that come with it. Clients
+/// have `TIMEOUT` for their whole request, however slowly they send it.
+pub fn serve(listener: TcpListener) -> io::Result<()> {
+    let open = Arc::new(AtomicUsize::new(0));
+    for stream in listener.incoming() {
+        // Failing to accept one connection leaves the others unaffected
+        let Ok(mut stream) = stream else {
+            continue;
+        };
+        if open.fetch_add(1, Ordering::SeqCst) >= MAX_CONNECTIONS {
+            open.fetch_sub(1, Ordering::SeqCst);
+            let busy = Response::error(503, "busy", "too many connections");
+            let _ = write_response(&mut stream, &busy);
+            continue;
+        }
+        let open = Arc::clone(&open);
+        thread::Builder::new()
+            .stack_size(STACK_SIZE)
+            .spawn(move || {
+                // A client that hangs up early is not an error of the server
+                let _ = handle_connection(stream);
+                open.fetch_sub(1, Ordering::SeqCst);
+            })?;
+    }
+    Ok(())
+}
+
+fn handle_connection(mut stream: TcpStream) -> io::Result<()> {
+    stream.set_write_timeout(Some(TIMEOUT))?;
+    let response = match read_request(&mut stream, Instant::now() + TIMEOUT)? {
+        Ok(request) => respond(&request.method, &request.path, &request.body),
+        Err(response) => response,
+    };
+    write_response(&mut stream, &response)
+}
+
+/// The parts of an HTTP request that `respond` looks at.
+struct HttpRequest {
+    method: String,
+    path: String,
+    body: Vec<u8>,
+}
+
+/// The request on `stream`, or the response rejecting it. Fails with `TimedOut` if the request
+/// is not complete by `deadline`.
+fn read_request(
+    stream: &mut TcpStream,
+    deadline: Instant,
+) -> io::Result<Result<HttpRequest, Response>> {
+    let mut buffer = Vec::new();
+    let mut chunk = [0; 4096];
+    let head_end = loop {
+        if let Some(end) = buffer.windows(4).position(|window| window == b"\r\n\r\n") {
+            break end;
+        }
+        if buffer.len() > MAX_HEAD {
+            return Ok(Err(Response::error(
+                431,
+                "too_large",
+                "request head is too large",
+            )));
+        }
+        match read_until(stream, &mut chunk, deadline)? {
+            0 => {
+                return Ok(Err(Response::error(
+                    400,
+                    "bad_request",
+                    "incomplete request",
+                )))
+            }
+            read => buffer.extend_from_slice(&chunk[..read]),
+        }
+    };
+
+    let bad_request = |message| Ok(Err(Response::error(400, "bad_request", message)));
+    let Ok(head) = std::str::from_utf8(&buffer[..head_end]) else {
+        return bad_request("request head is not UTF-8");
+    };
+    let mut lines = head.split("\r\n");
+    let mut request_line = lines.next().unwrap_or_default().split(' ');
+    let (Some(method), Some(target), Some(_version)) = (
+        request_line.next(),
+        request_line.next(),
+        request_line.next(),
+    ) else {
+        return bad_request("malformed request line");
+    };
+    let (method, path) = (
+        method.to_string(),
+        target.split('?').next().unwrap().to_string(),
+    );
+
+    let mut length = 0;
+    for line in lines {
+        let Some((name, value)) = line.split_once(':') else {
+            return bad_request("malformed header");
+        };
+        if name.eq_ignore_ascii_case("transfer-encoding") {
+            return bad_request("bodies must have a Content-Length");
+        }
+        if name.eq_ignore_ascii_case("content-length") {
+            let Ok(value) = value.trim().parse::<usize>() else {
+                return bad_request("invalid Content-Length");
+            };
+            length = value;
+        }
+    }
+    if length > MAX_BODY {
+        return Ok(Err(Response::error(
+            413,
+            "too_large",
+            format!("bodies may have at most {MAX_BODY} bytes"),
+        )));
+    }
+
+    let mut body = buffer.split_off(head_end + 4);
+    body.truncate(length);
+    let mut filled = body.len();
+    body.resize(length, 0);
+    while filled < length {
+        match read_until(stream, &mut body[filled..], deadline)? {
+            0 => return Err(io::ErrorKind::UnexpectedEof.into()),
+            read => filled += read,
+        }
+    }
+    Ok(Ok(HttpRequest { method, path, body }))
+}
+
+/// Like `stream.read(buffer)`, but fails with `TimedOut` once `deadline` has passed instead of
+/// only when a single read takes too long.
+fn read_until(stream: &mut TcpStream, buffer: &mut [u8], deadline: Instant) -> io::Result<usize> {
+    let left = deadline.saturating_duration_since(Instant::now());
+    if left.is_zero() {
+        return Err(io::ErrorKind::TimedOut.into());
+    }
+    stream.set_read_timeout(Some(left))?;
+    stream.read(buffer)
+}
+
+fn write_response(stream: &mut TcpStream, response: &Response) -> io::Result<()> {
+    let reason = match response.status {
+        200 => "OK",
+        400 => "Bad Request",
+        404 => "Not Found",
+        405 => "Method Not Allowed",
+        413 => "Content Too Large",
+        422 => "Unprocessable Content",
+        431 => "Request Header Fields Too Large",
+        _ => "Service Unavailable",
+    };
+    let body = response.body.to_string();
+    write!(
+        stream,
+        "HTTP/1.1 {} {reason}\r\nContent-Type: application/json\r\nContent-Length: {}\r\n\
+         Connection: close\r\n\r\n{body}",
+        response.status,
+        body.len()
+    )?;
+    stream.flush()
+}
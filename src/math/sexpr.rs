@@ -0,0 +1,258 @@
+use super::constants::Constant;
+use super::functions::Function;
+use super::parser::{self, ParseError, ParseErrorKind};
+use super::terms::{Number, Term};
+
+impl Term {
+    /// The term as an S-expression like `(+ (* 2 x) (^ x 2))`, which `from_sexpr` reads back to
+    /// the same term.
+    ///
+    /// Operations are `+`, `-`, `*`, `/`, `^` and `(root radicand degree)`, functions and constants
+    /// use their names, and collections are `(vector ...)`, `(list ...)`, `(set ...)` and
+    /// `(index collection position)`. Variables whose names could be read as something else are
+    /// written as `(var "name")`.
+    pub fn to_sexpr(&self) -> String {
+        let mut out = String::new();
+        write_sexpr(self, &mut out);
+        out
+    }
+
+    /// Reads the output of `to_sexpr`. `+` and `*` also accept more than two operands, which
+    /// are combined from left to right.
+    pub fn from_sexpr(input: &str) -> Result<Term, ParseError> {
+        let mut reader = Reader { input, position: 0 };
+        let term = reader.term()?;
+        reader.skip_whitespace();
+        if reader.position < input.len() {
+            return Err(reader.error(ParseErrorKind::UnexpectedToken));
+        }
+        Ok(term)
+    }
+}
+
+fn write_sexpr(term: &Term, out: &mut String) {
+    let mut node = |head: &str, children: &[&Term]| {
+        out.push('(');
+        out.push_str(head);
+        for child in children {
+            out.push(' ');
+            write_sexpr(child, out);
+        }
+        out.push(')');
+    };
+
+    match term {
+        Term::Value(value) => out.push_str(&value.to_string()),
+        Term::Constant(constant) => out.push_str(constant.name()),
+        Term::Variable(name) if is_plain(name) => out.push_str(name),
+        Term::Variable(name) => {
+            out.push_str("(var \"");
+            for c in name.chars() {
+                if matches!(c, '"' | '\\') {
+                    out.push('\\');
+                }
+                out.push(c);
+            }
+            out.push_str("\")");
+        }
+        Term::Addition(lhs, rhs) => node("+", &[lhs, rhs]),
+        Term::Subtraction(lhs, rhs) => node("-", &[lhs, rhs]),
+        Term::Multiplication(lhs, rhs) => node("*", &[lhs, rhs]),
+        Term::Division(lhs, rhs) => node("/", &[lhs, rhs]),
+        Term::Exponentation(base, power) => node("^", &[base, power]),
+        Term::RootExtraction(radicand, degree) => node("root", &[radicand, degree]),
+        Term::Function(function, argument) => node(
+            function.name(),
+            &function.arguments(argument).iter().collect::<Vec<_>>(),
+        ),
+        Term::Vector(elements) => node("vector", &elements.iter().collect::<Vec<_>>()),
+        Term::List(elements) => node("list", &elements.iter().collect::<Vec<_>>()),
+        Term::Set(elements) => node("set", &elements.iter().collect::<Vec<_>>()),
+        Term::Index(collection, index) => node("index", &[collection, index]),
+    }
+}
+
+/// Whether a variable name reads back as that variable when written as a bare atom.
+fn is_plain(name: &str) -> bool {
+    !name.starts_with(|c: char| c.is_ascii_digit() || c == '.')
+        && !name.is_empty()
+        && !name
+            .chars()
+            .any(|c| c.is_whitespace() || "()\"".contains(c))
+        && name.parse::<Number>().is_err()
+        && Constant::from_name(name).is_none()
+}
+
+struct Reader<'a> {
+    input: &'a str,
+    position: usize,
+}
+
+impl Reader<'_> {
+    fn error(&self, kind: ParseErrorKind) -> ParseError {
+        ParseError {
+            kind,
+            position: self.position,
+        }
+    }
+
+    fn rest(&self) -> &str {
+        &self.input[self.position..]
+    }
+
+    fn skip_whitespace(&mut self) {
+        let rest = self.rest();
+        self.position += rest.len() - rest.trim_start().len();
+    }
+
+    fn eat(&mut self, c: char) -> bool {
+        self.skip_whitespace();
+        let found = self.rest().starts_with(c);
+        if found {
+            self.position += c.len_utf8();
+        }
+        found
+    }
+
+    /// A run of characters up to whitespace, a parenthesis or a quote.
+    fn atom(&mut self) -> Result<&str, ParseError> {
+        self.skip_whitespace();
+        let rest = &self.input[self.position..];
+        let len = rest
+            .find(|c: char| c.is_whitespace() || "()\"".contains(c))
+            .unwrap_or(rest.len());
+        match len {
+            0 => match rest.chars().next() {
+                Some(c) => Err(self.error(ParseErrorKind::UnexpectedCharacter(c))),
+                None => Err(self.error(ParseErrorKind::UnexpectedEnd)),
+            },
+            _ => {
+                self.position += len;
+                Ok(&rest[..len])
+            }
+        }
+    }
+
+    fn string(&mut self) -> Result<String, ParseError> {
+        let opened_at = self.position;
+        if !self.eat('"') {
+            return Err(self.error(ParseErrorKind::UnexpectedToken));
+        }
+        let mut value = String::new();
+        let mut chars = self.rest().char_indices();
+        while let Some((offset, c)) = chars.next() {
+            let c = match c {
+                '"' => {
+                    self.position += offset + 1;
+                    return Ok(value);
+                }
+                '\\' => match chars.next() {
+                    Some((_, escaped)) => escaped,
+                    None => break,
+                },
+                c => c,
+            };
+            value.push(c);
+        }
+        Err(ParseError {
+            kind: ParseErrorKind::Unclosed('"'),
+            position: opened_at,
+        })
+    }
+
+    fn term(&mut self) -> Result<Term, ParseError> {
+        let opened_at = self.position;
+        if !self.eat('(') {
+            let start = self.position;
+            let atom = self.atom()?;
+            return Ok(if let Ok(value) = atom.parse() {
+                Term::Value(value)
+            } else if let Some(constant) = Constant::from_name(atom) {
+                Term::Constant(constant)
+            } else if atom.starts_with(|c: char| c.is_ascii_digit() || c == '.') {
+                return Err(ParseError {
+                    kind: ParseErrorKind::InvalidNumber,
+                    position: start,
+                });
+            } else {
+                Term::Variable(atom.into())
+            });
+        }
+
+        self.skip_whitespace();
+        let head_at = self.position;
+        let head: Box<str> = self.atom()?.into();
+        if &*head == "var" {
+            let name = self.string()?;
+            self.close(opened_at)?;
+            return Ok(Term::Variable(name.into()));
+        }
+
+        let mut operands = Vec::new();
+        while !self.eat(')') {
+            if self.rest().is_empty() {
+                return Err(ParseError {
+                    kind: ParseErrorKind::Unclosed('('),
+                    position: opened_at,
+                });
+            }
+            operands.push(self.term()?);
+        }
+
+        let arity = |expected| ParseError {
+            kind: ParseErrorKind::WrongArity {
+                function: head.clone(),
+                expected,
+            },
+            position: head_at,
+        };
+        let binary = |combine: fn(Box<Term>, Box<Term>) -> Term, operands: Vec<Term>| {
+            let [lhs, rhs]: [Term; 2] = operands.try_into().map_err(|_| arity(2))?;
+            Ok(combine(Box::new(lhs), Box::new(rhs)))
+        };
+        let chain = |combine: fn(Box<Term>, Box<Term>) -> Term, operands: Vec<Term>| {
+            if operands.len() < 2 {
+                return Err(arity(2));
+            }
+            Ok(operands
+                .into_iter()
+                .reduce(|acc, operand| combine(Box::new(acc), Box::new(operand)))
+                .unwrap())
+        };
+
+        match &*head {
+            "+" => chain(Term::Addition, operands),
+            "*" => chain(Term::Multiplication, operands),
+            "-" => binary(Term::Subtraction, operands),
+            "/" => binary(Term::Division, operands),
+            "^" => binary(Term::Exponentation, operands),
+            "root" => binary(Term::RootExtraction, operands),
+            "index" => binary(Term::Index, operands),
+            "vector" => Ok(Term::Vector(operands)),
+            "list" => Ok(Term::List(operands)),
+            "set" => Ok(Term::Set(operands)),
+            name => match Function::from_name(name) {
+                Some(function) => function
+                    .term(operands)
+                    .ok_or_else(|| arity(function.arity())),
+                None => Err(ParseError {
+                    kind: parser::unknown_function(&head),
+                    position: head_at,
+                }),
+            },
+        }
+    }
+
+    fn close(&mut self, opened_at: usize) -> Result<(), ParseError> {
+        if self.eat(')') {
+            Ok(())
+        } else if self.rest().trim_start().is_empty() {
+            Err(ParseError {
+                kind: ParseErrorKind::Unclosed('('),
+                position: opened_at,
+            })
+        } else {
+            Err(self.error(ParseErrorKind::UnexpectedToken))
+        }
+    }
+}
@@ -0,0 +1,440 @@
+use std::collections::HashMap;
+use std::fmt::{self, Display, Formatter};
+use std::hash::{Hash, Hasher};
+use std::rc::Rc;
+
+use fraction::{CheckedAdd, CheckedMul, CheckedSub, One, Zero};
+
+use super::constants::Constant;
+use super::functions::Function;
+use super::terms::{Fraction, Number, Term};
+
+/// A term built by an `Interner`, which stores every distinct subterm once. Shared terms compare
+/// and hash by address, which matches structural equality for terms of the same interner.
+#[derive(Clone, Debug)]
+pub struct SharedTerm(Rc<SharedNode>);
+
+impl PartialEq for SharedTerm {
+    fn eq(&self, other: &Self) -> bool {
+        Rc::ptr_eq(&self.0, &other.0)
+    }
+}
+
+impl Eq for SharedTerm {}
+
+impl Hash for SharedTerm {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        Rc::as_ptr(&self.0).hash(state);
+    }
+}
+
+/// A node of a `SharedTerm`, like a `Term` with shared children.
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
+pub enum SharedNode {
+    Value(Number),
+    Variable(Box<str>),
+    Constant(Constant),
+    Addition(SharedTerm, SharedTerm),
+    Subtraction(SharedTerm, SharedTerm),
+    Multiplication(SharedTerm, SharedTerm),
+    Division(SharedTerm, SharedTerm),
+    Exponentation(SharedTerm, SharedTerm),
+    RootExtraction(SharedTerm, SharedTerm),
+    Function(Function, SharedTerm),
+    Vector(Vec<SharedTerm>),
+    List(Vec<SharedTerm>),
+    Set(Vec<SharedTerm>),
+    Index(SharedTerm, SharedTerm),
+}
+
+impl SharedTerm {
+    pub fn node(&self) -> &SharedNode {
+        &self.0
+    }
+
+    /// The term as a boxed `Term`, with shared subterms copied wherever they are used.
+    pub fn to_term(&self) -> Term {
+        let boxed = |term: &SharedTerm| Box::new(term.to_term());
+        let each = |terms: &[SharedTerm]| terms.iter().map(SharedTerm::to_term).collect();
+
+        match self.node() {
+            SharedNode::Value(value) => Term::Value(*value),
+            SharedNode::Variable(name) => Term::Variable(name.clone()),
+            SharedNode::Constant(constant) => Term::Constant(*constant),
+            SharedNode::Addition(lhs, rhs) => Term::Addition(boxed(lhs), boxed(rhs)),
+            SharedNode::Subtraction(lhs, rhs) => Term::Subtraction(boxed(lhs), boxed(rhs)),
+            SharedNode::Multiplication(lhs, rhs) => Term::Multiplication(boxed(lhs), boxed(rhs)),
+            SharedNode::Division(lhs, rhs) => Term::Division(boxed(lhs), boxed(rhs)),
+            SharedNode::Exponentation(lhs, rhs) => Term::Exponentation(boxed(lhs), boxed(rhs)),
+            SharedNode::RootExtraction(lhs, rhs) => Term::RootExtraction(boxed(lhs), boxed(rhs)),
+            SharedNode::Function(function, argument) => Term::Function(*function, boxed(argument)),
+            SharedNode::Vector(elements) => Term::Vector(each(elements)),
+            SharedNode::List(elements) => Term::List(each(elements)),
+            SharedNode::Set(elements) => Term::Set(each(elements)),
+            SharedNode::Index(lhs, rhs) => Term::Index(boxed(lhs), boxed(rhs)),
+        }
+    }
+
+    fn rational(&self) -> Option<Fraction> {
+        match self.node() {
+            SharedNode::Value(Number::Rational(value)) => Some(*value),
+            _ => None,
+        }
+    }
+
+    fn is(&self, value: impl Fn(&Fraction) -> bool) -> bool {
+        self.rational().is_some_and(|term| value(&term))
+    }
+}
+
+impl Display for SharedTerm {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        self.to_term().fmt(f)
+    }
+}
+
+/// A table of the subterms built so far, so that identical subterms are created once and then
+/// shared. Keeps every term it built alive until it is dropped.
+#[derive(Clone, Debug, Default)]
+pub struct Interner {
+    table: HashMap<SharedNode, SharedTerm>,
+}
+
+impl Interner {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// The number of distinct subterms built so far.
+    pub fn len(&self) -> usize {
+        self.table.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.table.is_empty()
+    }
+
+    /// The shared term for `node`, which is only created if it does not exist yet.
+    pub fn share(&mut self, node: SharedNode) -> SharedTerm {
+        if let Some(term) = self.table.get(&node) {
+            return term.clone();
+        }
+        let term = SharedTerm(Rc::new(node.clone()));
+        self.table.insert(node, term.clone());
+        term
+    }
+
+    pub fn intern(&mut self, term: &Term) -> SharedTerm {
+        let node = match term {
+            Term::Value(value) => SharedNode::Value(*value),
+            Term::Variable(name) => SharedNode::Variable(name.clone()),
+            Term::Constant(constant) => SharedNode::Constant(*constant),
+            Term::Addition(lhs, rhs) => SharedNode::Addition(self.intern(lhs), self.intern(rhs)),
+            Term::Subtraction(lhs, rhs) => {
+                SharedNode::Subtraction(self.intern(lhs), self.intern(rhs))
+            }
+            Term::Multiplication(lhs, rhs) => {
+                SharedNode::Multiplication(self.intern(lhs), self.intern(rhs))
+            }
+            Term::Division(lhs, rhs) => SharedNode::Division(self.intern(lhs), self.intern(rhs)),
+            Term::Exponentation(lhs, rhs) => {
+                SharedNode::Exponentation(self.intern(lhs), self.intern(rhs))
+            }
+            Term::RootExtraction(lhs, rhs) => {
+                SharedNode::RootExtraction(self.intern(lhs), self.intern(rhs))
+            }
+            Term::Function(function, argument) => {
+                SharedNode::Function(*function, self.intern(argument))
+            }
+            Term::Vector(elements) => SharedNode::Vector(self.intern_each(elements)),
+            Term::List(elements) => SharedNode::List(self.intern_each(elements)),
+            Term::Set(elements) => SharedNode::Set(self.intern_each(elements)),
+            Term::Index(lhs, rhs) => SharedNode::Index(self.intern(lhs), self.intern(rhs)),
+        };
+        self.share(node)
+    }
+
+    fn intern_each(&mut self, terms: &[Term]) -> Vec<SharedTerm> {
+        terms.iter().map(|term| self.intern(term)).collect()
+    }
+
+    /// `Term::derivative` on shared terms, which gives the same result. Every distinct subterm is
+    /// differentiated once, and the result shares structure with `term` and with itself.
+    pub fn derivative(&mut self, term: &SharedTerm, var: &str) -> Option<SharedTerm> {
+        Differentiation {
+            interner: self,
+            var,
+            derivatives: HashMap::new(),
+            dependencies: HashMap::new(),
+        }
+        .derivative(term)
+    }
+
+    fn constant(&mut self, value: i64) -> SharedTerm {
+        self.share(SharedNode::Value(Number::Rational(Fraction::from(value))))
+    }
+
+    /// The rational constant `op(lhs, rhs)`, unless it overflows.
+    fn fold(
+        &mut self,
+        lhs: &SharedTerm,
+        rhs: &SharedTerm,
+        op: fn(&Fraction, &Fraction) -> Option<Fraction>,
+    ) -> Option<SharedTerm> {
+        let value = op(&lhs.rational()?, &rhs.rational()?)?;
+        Some(self.share(SharedNode::Value(Number::Rational(value))))
+    }
+
+    fn sum(&mut self, lhs: SharedTerm, rhs: SharedTerm) -> SharedTerm {
+        if let Some(value) = self.fold(&lhs, &rhs, CheckedAdd::checked_add) {
+            return value;
+        }
+        if lhs.is(Fraction::is_zero) {
+            rhs
+        } else if rhs.is(Fraction::is_zero) {
+            lhs
+        } else {
+            self.share(SharedNode::Addition(lhs, rhs))
+        }
+    }
+
+    fn difference(&mut self, lhs: SharedTerm, rhs: SharedTerm) -> SharedTerm {
+        if let Some(value) = self.fold(&lhs, &rhs, CheckedSub::checked_sub) {
+            return value;
+        }
+        if rhs.is(Fraction::is_zero) {
+            lhs
+        } else if lhs.is(Fraction::is_zero) {
+            let minus_one = self.constant(-1);
+            self.product(minus_one, rhs)
+        } else {
+            self.share(SharedNode::Subtraction(lhs, rhs))
+        }
+    }
+
+    fn product(&mut self, lhs: SharedTerm, rhs: SharedTerm) -> SharedTerm {
+        if let Some(value) = self.fold(&lhs, &rhs, CheckedMul::checked_mul) {
+            return value;
+        }
+        if lhs.is(Fraction::is_zero) || rhs.is(Fraction::is_zero) {
+            self.constant(0)
+        } else if lhs.is(Fraction::is_one) {
+            rhs
+        } else if rhs.is(Fraction::is_one) {
+            lhs
+        } else {
+            self.share(SharedNode::Multiplication(lhs, rhs))
+        }
+    }
+
+    fn quotient(&mut self, lhs: SharedTerm, rhs: SharedTerm) -> SharedTerm {
+        if lhs.is(Fraction::is_zero) {
+            self.constant(0)
+        } else if rhs.is(Fraction::is_one) {
+            lhs
+        } else {
+            self.share(SharedNode::Division(lhs, rhs))
+        }
+    }
+
+    fn power(&mut self, base: SharedTerm, exponent: SharedTerm) -> SharedTerm {
+        if exponent.is(Fraction::is_zero) {
+            self.constant(1)
+        } else if exponent.is(Fraction::is_one) {
+            base
+        } else {
+            self.share(SharedNode::Exponentation(base, exponent))
+        }
+    }
+
+    fn apply(&mut self, function: Function, argument: SharedTerm) -> SharedTerm {
+        self.share(SharedNode::Function(function, argument))
+    }
+}
+
+/// The state of one `Interner::derivative`, memoized per subterm.
+struct Differentiation<'a> {
+    interner: &'a mut Interner,
+    var: &'a str,
+    derivatives: HashMap<SharedTerm, Option<SharedTerm>>,
+    dependencies: HashMap<SharedTerm, bool>,
+}
+
+impl Differentiation<'_> {
+    fn depends_on(&mut self, term: &SharedTerm) -> bool {
+        if let Some(&depends) = self.dependencies.get(term) {
+            return depends;
+        }
+        let depends = match term.node() {
+            SharedNode::Value(_) | SharedNode::Constant(_) => false,
+            SharedNode::Variable(name) => &**name == self.var,
+            SharedNode::Addition(lhs, rhs)
+            | SharedNode::Subtraction(lhs, rhs)
+            | SharedNode::Multiplication(lhs, rhs)
+            | SharedNode::Division(lhs, rhs)
+            | SharedNode::Exponentation(lhs, rhs)
+            | SharedNode::RootExtraction(lhs, rhs)
+            | SharedNode::Index(lhs, rhs) => self.depends_on(lhs) || self.depends_on(rhs),
+            SharedNode::Function(_, argument) => self.depends_on(argument),
+            SharedNode::Vector(elements)
+            | SharedNode::List(elements)
+            | SharedNode::Set(elements) => elements.iter().any(|element| self.depends_on(element)),
+        };
+        self.dependencies.insert(term.clone(), depends);
+        depends
+    }
+
+    fn derivative(&mut self, term: &SharedTerm) -> Option<SharedTerm> {
+        if let Some(derivative) = self.derivatives.get(term) {
+            return derivative.clone();
+        }
+        let derivative = self.differentiate(term);
+        self.derivatives.insert(term.clone(), derivative.clone());
+        derivative
+    }
+
+    /// One step of `Term::derivative`, with the same rules in the same order.
+    fn differentiate(&mut self, term: &SharedTerm) -> Option<SharedTerm> {
+        let is_collection = matches!(
+            term.node(),
+            SharedNode::Vector(_) | SharedNode::List(_) | SharedNode::Set(_)
+        );
+        if !self.depends_on(term) && !is_collection {
+            return Some(self.interner.constant(0));
+        }
+
+        Some(match term.node() {
+            SharedNode::Value(_) | SharedNode::Constant(_) => self.interner.constant(0),
+            SharedNode::Variable(name) => {
+                let value = if &**name == self.var { 1 } else { 0 };
+                self.interner.constant(value)
+            }
+            SharedNode::Addition(lhs, rhs) => {
+                let (dl, dr) = (self.derivative(lhs)?, self.derivative(rhs)?);
+                self.interner.sum(dl, dr)
+            }
+            SharedNode::Subtraction(lhs, rhs) => {
+                let (dl, dr) = (self.derivative(lhs)?, self.derivative(rhs)?);
+                self.interner.difference(dl, dr)
+            }
+            SharedNode::Multiplication(lhs, rhs) => {
+                let (dl, dr) = (self.derivative(lhs)?, self.derivative(rhs)?);
+                let (left, right) = (
+                    self.interner.product(dl, rhs.clone()),
+                    self.interner.product(lhs.clone(), dr),
+                );
+                self.interner.sum(left, right)
+            }
+            SharedNode::Division(lhs, rhs) => {
+                let (dl, dr) = (self.derivative(lhs)?, self.derivative(rhs)?);
+                let (left, right) = (
+                    self.interner.product(dl, rhs.clone()),
+                    self.interner.product(lhs.clone(), dr),
+                );
+                let numerator = self.interner.difference(left, right);
+                let two = self.interner.constant(2);
+                let denominator = self.interner.power(rhs.clone(), two);
+                self.interner.quotient(numerator, denominator)
+            }
+            SharedNode::Exponentation(base, exponent) if !self.depends_on(exponent) => {
+                // n * b^(n - 1) * b'
+                let one = self.interner.constant(1);
+                let reduced = self.interner.difference(exponent.clone(), one);
+                let power = self.interner.power(base.clone(), reduced);
+                let scaled = self.interner.product(exponent.clone(), power);
+                let db = self.derivative(base)?;
+                self.interner.product(scaled, db)
+            }
+            SharedNode::Exponentation(base, exponent) if !self.depends_on(base) => {
+                // c^g * ln(c) * g'
+                let ln = self.interner.apply(Function::Ln, base.clone());
+                let scaled = self.interner.product(term.clone(), ln);
+                let de = self.derivative(exponent)?;
+                self.interner.product(scaled, de)
+            }
+            SharedNode::Exponentation(base, exponent) => {
+                // f^g * (g' * ln(f) + g * f' / f)
+                let de = self.derivative(exponent)?;
+                let ln = self.interner.apply(Function::Ln, base.clone());
+                let left = self.interner.product(de, ln);
+                let db = self.derivative(base)?;
+                let scaled = self.interner.product(exponent.clone(), db);
+                let right = self.interner.quotient(scaled, base.clone());
+                let factor = self.interner.sum(left, right);
+                self.interner.product(term.clone(), factor)
+            }
+            SharedNode::RootExtraction(radicand, degree) if !self.depends_on(degree) => {
+                // a' / (n * root(a, n)^(n - 1))
+                let da = self.derivative(radicand)?;
+                let one = self.interner.constant(1);
+                let reduced = self.interner.difference(degree.clone(), one);
+                let power = self.interner.power(term.clone(), reduced);
+                let denominator = self.interner.product(degree.clone(), power);
+                self.interner.quotient(da, denominator)
+            }
+            SharedNode::RootExtraction(radicand, degree) => {
+                let one = self.interner.constant(1);
+                let reciprocal = self
+                    .interner
+                    .share(SharedNode::Division(one, degree.clone()));
+                let power = self
+                    .interner
+                    .share(SharedNode::Exponentation(radicand.clone(), reciprocal));
+                self.derivative(&power)?
+            }
+            // Only reached when the argument depends on `var`
+            SharedNode::Function(function, _) if function.is_discrete() => return None,
+            SharedNode::Function(function, argument) => {
+                let u = argument.clone();
+                let du = self.derivative(argument)?;
+                let interner = &mut *self.interner;
+                match function {
+                    Function::Sin => {
+                        let cos = interner.apply(Function::Cos, u);
+                        interner.product(cos, du)
+                    }
+                    Function::Cos => {
+                        let (minus_one, sin) =
+                            (interner.constant(-1), interner.apply(Function::Sin, u));
+                        let negated = interner.product(minus_one, sin);
+                        interner.product(negated, du)
+                    }
+                    Function::Tan => {
+                        let (cos, two) = (interner.apply(Function::Cos, u), interner.constant(2));
+                        let squared = interner.power(cos, two);
+                        interner.quotient(du, squared)
+                    }
+                    Function::Ln => interner.quotient(du, u),
+                    Function::Exp => interner.product(term.clone(), du),
+                    Function::Abs => {
+                        let scaled = interner.product(du, u);
+                        interner.quotient(scaled, term.clone())
+                    }
+                    Function::Floor | Function::Ceil => interner.constant(0),
+                    _ => unreachable!("discrete functions are handled above"),
+                }
+            }
+            SharedNode::Vector(elements) => {
+                let elements = self.each(elements)?;
+                self.interner.share(SharedNode::Vector(elements))
+            }
+            SharedNode::List(elements) => {
+                let elements = self.each(elements)?;
+                self.interner.share(SharedNode::List(elements))
+            }
+            SharedNode::Set(elements) => {
+                let elements = self.each(elements)?;
+                self.interner.share(SharedNode::Set(elements))
+            }
+            SharedNode::Index(collection, index) if !self.depends_on(index) => {
+                let dc = self.derivative(collection)?;
+                self.interner.share(SharedNode::Index(dc, index.clone()))
+            }
+            SharedNode::Index(..) => return None,
+        })
+    }
+
+    fn each(&mut self, terms: &[SharedTerm]) -> Option<Vec<SharedTerm>> {
+        terms.iter().map(|term| self.derivative(term)).collect()
+    }
+}
@@ -0,0 +1,263 @@
+use fraction::{CheckedAdd, CheckedDiv, CheckedMul, One, Signed, Zero};
+
+use super::budget::{Budget, Meter};
+use super::cancel::{CancellationToken, Cancelled};
+use super::functions::Function;
+use super::terms::{Fraction, Number, Term};
+use super::values::{as_rational, rational};
+
+impl Term {
+    /// A simpler equivalent term. Working bottom-up, this folds constant subterms with exact
+    /// values, removes identities such as `x + 0`, `x*1`, `x*0` and `x^1`, and flattens nested
+    /// sums and products, combining their numeric parts into a single constant or coefficient.
+    ///
+    /// Sums are rebuilt left to right with the constant last, as in `x + y - 3`, and products
+    /// with the coefficient first, as in `6*x*y` or `3*x/(2*y)`. Undefined terms stay undefined,
+    /// so `0*ln(0)` and `0/0` are left as they are.
+    pub fn simplify(&self) -> Term {
+        self.simplify_cancellable(&CancellationToken::new())
+            .unwrap()
+    }
+
+    /// Like `simplify`, but aborts with `Err(Cancelled)` once `token` is cancelled.
+    pub fn simplify_cancellable(&self, token: &CancellationToken) -> Result<Term, Cancelled> {
+        self.simplify_metered(usize::MAX, token)
+            .map(|(term, _)| term)
+    }
+
+    /// Like `simplify`, working on at most `limit` subterms and leaving the others as they are.
+    /// The budget has not converged if some were left.
+    pub fn simplify_with_budget(&self, limit: usize) -> (Term, Budget) {
+        self.simplify_metered(limit, &CancellationToken::new())
+            .unwrap()
+    }
+
+    pub(crate) fn simplify_metered(
+        &self,
+        limit: usize,
+        token: &CancellationToken,
+    ) -> Result<(Term, Budget), Cancelled> {
+        let meter = Meter::new(limit, token);
+        let term = self.simplify_within(&meter)?;
+        Ok((term, meter.budget()))
+    }
+
+    fn simplify_within(&self, meter: &Meter) -> Result<Term, Cancelled> {
+        if !meter.step()? {
+            return Ok(self.clone());
+        }
+        let boxed = |term: &Term| term.simplify_within(meter).map(Box::new);
+        let each = |terms: &[Term]| {
+            terms
+                .iter()
+                .map(|term| term.simplify_within(meter))
+                .collect::<Result<_, _>>()
+        };
+
+        let term = match self {
+            Term::Value(_) | Term::Variable(_) | Term::Constant(_) => return Ok(self.clone()),
+            Term::Addition(lhs, rhs) => Term::Addition(boxed(lhs)?, boxed(rhs)?),
+            Term::Subtraction(lhs, rhs) => Term::Subtraction(boxed(lhs)?, boxed(rhs)?),
+            Term::Multiplication(lhs, rhs) => Term::Multiplication(boxed(lhs)?, boxed(rhs)?),
+            Term::Division(lhs, rhs) => Term::Division(boxed(lhs)?, boxed(rhs)?),
+            Term::Exponentation(base, power) => Term::Exponentation(boxed(base)?, boxed(power)?),
+            Term::RootExtraction(radicand, degree) => {
+                Term::RootExtraction(boxed(radicand)?, boxed(degree)?)
+            }
+            Term::Function(function, argument) => Term::Function(*function, boxed(argument)?),
+            Term::Vector(elements) => return Ok(Term::Vector(each(elements)?)),
+            Term::List(elements) => return Ok(Term::List(each(elements)?)),
+            Term::Set(elements) => return Ok(Term::Set(each(elements)?)),
+            Term::Index(collection, index) => Term::Index(boxed(collection)?, boxed(index)?),
+        };
+
+        if let Ok(value) = term.evaluate_exact() {
+            return Ok(rational(value));
+        }
+
+        Ok(match term {
+            Term::Addition(..) | Term::Subtraction(..) => simplify_sum(term),
+            Term::Multiplication(..) | Term::Division(..) => simplify_product(term),
+            Term::Exponentation(ref base, ref power) => match as_rational(power) {
+                Some(power) if power.is_zero() && droppable(base) => rational(Fraction::one()),
+                Some(power) if power.is_one() => (**base).clone(),
+                _ if as_rational(base).is_some_and(|base| base.is_one()) && droppable(power) => {
+                    (**base).clone()
+                }
+                _ => term,
+            },
+            Term::RootExtraction(ref radicand, ref degree) => match as_rational(degree) {
+                Some(degree) if degree.is_one() => (**radicand).clone(),
+                _ => term,
+            },
+            term => term,
+        })
+    }
+}
+
+/// Whether `term` can be left out, like the `x` in `x*0`, without hiding that the whole term is
+/// undefined: either it has a value, or it is defined for every value of its variables, like
+/// sums, products and natural powers of variables, and `sin`, `cos`, `exp`, `abs`, `floor` and
+/// `ceil` of them.
+fn droppable(term: &Term) -> bool {
+    match term {
+        Term::Value(_) | Term::Variable(_) | Term::Constant(_) => true,
+        Term::Addition(lhs, rhs) | Term::Subtraction(lhs, rhs) | Term::Multiplication(lhs, rhs) => {
+            droppable(lhs) && droppable(rhs)
+        }
+        Term::Exponentation(base, power)
+            if as_rational(power)
+                .is_some_and(|power| power.is_integer() && !power.is_negative()) =>
+        {
+            droppable(base)
+        }
+        Term::Function(
+            Function::Sin
+            | Function::Cos
+            | Function::Exp
+            | Function::Abs
+            | Function::Floor
+            | Function::Ceil,
+            argument,
+        ) => droppable(argument),
+        term => term.evaluate().is_ok(),
+    }
+}
+
+/// The operands of a chain of sums and differences, each with whether it is subtracted.
+fn summands(term: Term, subtracted: bool, out: &mut Vec<(Term, bool)>) {
+    let flips = matches!(term, Term::Subtraction(..));
+    match term {
+        Term::Addition(..) | Term::Subtraction(..) => {
+            let (lhs, rhs) = term.into_operands().unwrap();
+            summands(lhs, subtracted, out);
+            summands(rhs, subtracted != flips, out);
+        }
+        term => out.push((term, subtracted)),
+    }
+}
+
+fn simplify_sum(term: Term) -> Term {
+    let mut operands = Vec::new();
+    summands(term, false, &mut operands);
+
+    let mut total = Fraction::zero();
+    let mut terms = Vec::new();
+    for (term, subtracted) in operands {
+        match as_rational(&term) {
+            Some(value) => {
+                let value = if subtracted { -value } else { value };
+                match total.checked_add(&value) {
+                    Some(sum) => total = sum,
+                    None => terms.push(rational(value)),
+                }
+            }
+            None if subtracted => terms.push(negate(term)),
+            None => terms.push(term),
+        }
+    }
+    if !total.is_zero() || terms.is_empty() {
+        terms.push(rational(total));
+    }
+
+    let mut terms = terms.into_iter();
+    let first = terms.next().unwrap();
+    terms.fold(first, |acc, term| match positive(&term) {
+        Some(term) => Term::Subtraction(Box::new(acc), Box::new(term)),
+        None => Term::Addition(Box::new(acc), Box::new(term)),
+    })
+}
+
+/// `-term`, with the sign folded into its coefficient.
+fn negate(term: Term) -> Term {
+    simplify_product(Term::Multiplication(
+        Box::new(rational(-Fraction::one())),
+        Box::new(term),
+    ))
+}
+
+/// The term without its sign, if its coefficient is negative.
+fn positive(term: &Term) -> Option<Term> {
+    match term {
+        Term::Value(Number::Rational(value)) if value.is_negative() => Some(rational(-value)),
+        Term::Multiplication(lhs, rhs) => match as_rational(lhs) {
+            Some(value) if value == -Fraction::one() => Some((**rhs).clone()),
+            Some(value) if value.is_negative() => Some(Term::Multiplication(
+                Box::new(rational(-value)),
+                rhs.clone(),
+            )),
+            Some(_) => None,
+            None => Some(Term::Multiplication(Box::new(positive(lhs)?), rhs.clone())),
+        },
+        Term::Division(lhs, rhs) => Some(Term::Division(Box::new(positive(lhs)?), rhs.clone())),
+        _ => None,
+    }
+}
+
+/// The operands of a chain of products and quotients, each with whether it divides.
+fn factors(term: Term, inverted: bool, out: &mut Vec<(Term, bool)>) {
+    let flips = matches!(term, Term::Division(..));
+    match term {
+        Term::Multiplication(..) | Term::Division(..) => {
+            let (lhs, rhs) = term.into_operands().unwrap();
+            factors(lhs, inverted, out);
+            factors(rhs, inverted != flips, out);
+        }
+        term => out.push((term, inverted)),
+    }
+}
+
+fn simplify_product(term: Term) -> Term {
+    let mut operands = Vec::new();
+    factors(term, false, &mut operands);
+
+    let mut coefficient = Fraction::one();
+    let (mut numerator, mut denominator) = (Vec::new(), Vec::new());
+    for (term, inverted) in operands {
+        let folded = as_rational(&term).and_then(|value| {
+            if !inverted {
+                coefficient.checked_mul(&value)
+            } else if value.is_zero() {
+                None
+            } else {
+                coefficient.checked_div(&value)
+            }
+        });
+        match folded {
+            Some(product) => coefficient = product,
+            None if inverted => denominator.push(term),
+            None => numerator.push(term),
+        }
+    }
+
+    // Zero times anything defined is zero, but `0/0` or `0*ln(0)` stay undefined
+    let divides_by_zero = denominator
+        .iter()
+        .any(|term| as_rational(term).is_some_and(|value| value.is_zero()));
+    if coefficient.is_zero()
+        && !divides_by_zero
+        && numerator.iter().chain(&denominator).all(droppable)
+    {
+        return rational(coefficient);
+    }
+
+    let (p, q) = (*coefficient.numer(), *coefficient.denom());
+    if q != 1 {
+        denominator.insert(0, rational(Fraction::from(q)));
+    }
+    if p != 1 || numerator.is_empty() {
+        numerator.insert(0, rational(Fraction::from(p)));
+    }
+    let numerator = product(numerator).unwrap();
+
+    match product(denominator) {
+        Some(denominator) => Term::Division(Box::new(numerator), Box::new(denominator)),
+        None => numerator,
+    }
+}
+
+fn product(factors: Vec<Term>) -> Option<Term> {
+    factors
+        .into_iter()
+        .reduce(|acc, factor| Term::Multiplication(Box::new(acc), Box::new(factor)))
+}
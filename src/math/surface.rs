@@ -0,0 +1,90 @@
+use std::io::{self, Write};
+use std::ops::RangeInclusive;
+
+use super::export::CsvWriter;
+use super::grid::Grid;
+use super::terms::Term;
+
+/// A triangle mesh of the surface `z = f(x, y)`.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Mesh {
+    pub vertices: Vec<[f64; 3]>,
+    /// Indices into `vertices`, counter-clockwise seen from above.
+    pub faces: Vec<[usize; 3]>,
+}
+
+impl Mesh {
+    /// Triangulates each grid cell, dropping triangles that touch an undefined sample.
+    pub fn from_grid(grid: &Grid) -> Self {
+        let (rows, cols) = (grid.values.rows(), grid.values.cols());
+        let mut vertices = Vec::new();
+        let mut indices = vec![None; rows * cols];
+
+        for row in 0..rows {
+            for col in 0..cols {
+                if let Ok(z) = grid.values.get(row, col) {
+                    indices[row * cols + col] = Some(vertices.len());
+                    vertices.push([grid.xs[col], grid.ys[row], *z]);
+                }
+            }
+        }
+
+        let mut faces = Vec::new();
+        for row in 0..rows.saturating_sub(1) {
+            for col in 0..cols.saturating_sub(1) {
+                let index = |r: usize, c: usize| indices[r * cols + c];
+                let (a, b, c, d) = (
+                    index(row, col),
+                    index(row, col + 1),
+                    index(row + 1, col + 1),
+                    index(row + 1, col),
+                );
+                for triangle in [[a, b, c], [a, c, d]] {
+                    if let [Some(a), Some(b), Some(c)] = triangle {
+                        faces.push([a, b, c]);
+                    }
+                }
+            }
+        }
+
+        Self { vertices, faces }
+    }
+
+    /// Writes the mesh as a Wavefront OBJ file.
+    pub fn write_obj(&self, out: &mut impl Write) -> io::Result<()> {
+        for [x, y, z] in &self.vertices {
+            writeln!(out, "v {x} {y} {z}")?;
+        }
+        for [a, b, c] in &self.faces {
+            writeln!(out, "f {} {} {}", a + 1, b + 1, c + 1)?;
+        }
+        Ok(())
+    }
+}
+
+impl Grid {
+    /// Writes the samples as `x,y,z` rows under a header, leaving `z` empty where undefined.
+    pub fn write_csv(&self, out: &mut impl Write) -> io::Result<()> {
+        let mut writer = CsvWriter::new(out, &["x", "y", "z"])?;
+        for (row, y) in self.ys.iter().enumerate() {
+            for (col, x) in self.xs.iter().enumerate() {
+                writer.write_row(&[Ok(*x), Ok(*y), *self.values.get(row, col)])?;
+            }
+        }
+        Ok(())
+    }
+}
+
+impl Term {
+    /// Samples `z = self` like `sample_grid` and triangulates the result.
+    pub fn sample_surface(
+        &self,
+        x_var: &str,
+        y_var: &str,
+        x_range: RangeInclusive<f64>,
+        y_range: RangeInclusive<f64>,
+        resolution: usize,
+    ) -> Mesh {
+        Mesh::from_grid(&self.sample_grid(x_var, y_var, x_range, y_range, resolution))
+    }
+}
@@ -0,0 +1,188 @@
+use std::collections::HashMap;
+use std::fmt::{self, Display, Formatter};
+
+use super::interval::Range;
+use super::terms::{Number, Term};
+
+/// The shape of term a placeholder accepts.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Kind {
+    Any,
+    /// Anything but a vector, list or set.
+    Scalar,
+    Vector,
+    List,
+    Set,
+}
+
+impl Kind {
+    fn accepts(self, term: &Term) -> bool {
+        match self {
+            Kind::Any => true,
+            Kind::Scalar => !matches!(term, Term::Vector(_) | Term::List(_) | Term::Set(_)),
+            Kind::Vector => matches!(term, Term::Vector(_)),
+            Kind::List => matches!(term, Term::List(_)),
+            Kind::Set => matches!(term, Term::Set(_)),
+        }
+    }
+}
+
+/// A condition an argument must satisfy. Apart from `Constant`, checking one requires the
+/// argument to have a value.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Assumption {
+    /// The argument has no free variables.
+    Constant,
+    Positive,
+    NonNegative,
+    NonZero,
+    Integer,
+    Within(Range),
+}
+
+impl Assumption {
+    /// `None` if the argument has no value to check against.
+    fn holds(&self, term: &Term) -> Option<bool> {
+        let value = || term.get_value();
+        let float = || value().map(f64::from);
+
+        match self {
+            Assumption::Constant => Some(term.has_value()),
+            Assumption::Positive => float().map(|value| value > 0.0),
+            Assumption::NonNegative => float().map(|value| value >= 0.0),
+            Assumption::NonZero => float().map(|value| value != 0.0),
+            Assumption::Integer => value().map(|value| match value {
+                Number::Rational(value) => value.is_integer(),
+                Number::Irrational(value) => value.fract() == 0.0,
+            }),
+            Assumption::Within(range) => value().map(|value| range.contains(value)),
+        }
+    }
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct Placeholder {
+    pub name: Box<str>,
+    pub kind: Kind,
+    pub assumptions: Vec<Assumption>,
+}
+
+impl Placeholder {
+    pub fn new(name: &str, kind: Kind) -> Self {
+        Self {
+            name: name.into(),
+            kind,
+            assumptions: Vec::new(),
+        }
+    }
+
+    pub fn assuming(mut self, assumption: Assumption) -> Self {
+        self.assumptions.push(assumption);
+        self
+    }
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub enum TemplateError {
+    Missing(Box<str>),
+    /// An argument was given for a name that is not a placeholder.
+    Unexpected(Box<str>),
+    WrongKind {
+        name: Box<str>,
+        expected: Kind,
+    },
+    Violated {
+        name: Box<str>,
+        assumption: Assumption,
+    },
+    /// The argument has no value, so the assumption cannot be checked.
+    Unverifiable {
+        name: Box<str>,
+        assumption: Assumption,
+    },
+}
+
+impl Display for TemplateError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        match self {
+            TemplateError::Missing(name) => write!(f, "no argument for placeholder `{name}`"),
+            TemplateError::Unexpected(name) => write!(f, "`{name}` is not a placeholder"),
+            TemplateError::WrongKind { name, expected } => {
+                write!(f, "argument for `{name}` is not of kind {expected:?}")
+            }
+            TemplateError::Violated { name, assumption } => {
+                write!(f, "argument for `{name}` violates {assumption:?}")
+            }
+            TemplateError::Unverifiable { name, assumption } => {
+                write!(
+                    f,
+                    "cannot check {assumption:?} for `{name}` without a value"
+                )
+            }
+        }
+    }
+}
+
+impl std::error::Error for TemplateError {}
+
+/// A term whose placeholder variables are replaced by validated arguments.
+#[derive(Clone, Debug)]
+pub struct Template {
+    pub body: Term,
+    pub placeholders: Vec<Placeholder>,
+}
+
+impl Template {
+    pub fn new(body: Term, placeholders: Vec<Placeholder>) -> Self {
+        Self { body, placeholders }
+    }
+
+    /// Checks every argument against its placeholder, then substitutes them all at once. Other
+    /// variables in the body are left alone.
+    pub fn instantiate(&self, arguments: &HashMap<&str, Term>) -> Result<Term, TemplateError> {
+        if let Some(name) = arguments
+            .keys()
+            .find(|name| !self.placeholders.iter().any(|p| &*p.name == **name))
+        {
+            return Err(TemplateError::Unexpected((*name).into()));
+        }
+
+        for placeholder in &self.placeholders {
+            let name = &placeholder.name;
+            let argument = arguments
+                .get(&**name)
+                .ok_or_else(|| TemplateError::Missing(name.clone()))?;
+
+            if !placeholder.kind.accepts(argument) {
+                return Err(TemplateError::WrongKind {
+                    name: name.clone(),
+                    expected: placeholder.kind,
+                });
+            }
+
+            for assumption in &placeholder.assumptions {
+                match assumption.holds(argument) {
+                    Some(true) => {}
+                    Some(false) => {
+                        return Err(TemplateError::Violated {
+                            name: name.clone(),
+                            assumption: *assumption,
+                        })
+                    }
+                    None => {
+                        return Err(TemplateError::Unverifiable {
+                            name: name.clone(),
+                            assumption: *assumption,
+                        })
+                    }
+                }
+            }
+        }
+
+        Ok(replace(&self.body, arguments))
+    }
+}
+
+fn replace(term: &Term, arguments: &HashMap<&str, Term>) -> Term {
+    term.map_variables(&|name| arguments.get(name).cloned())
+}
@@ -1,6 +1,23 @@
-use std::ops::{Add, Mul};
+use std::cmp::Ordering;
+use std::collections::HashMap;
+use std::fmt::{self, Display, Formatter};
+use std::hash::{Hash, Hasher};
+use std::mem;
+use std::ops::{Add, Mul, Sub};
+use std::str::FromStr;
 
-use fraction::{FromPrimitive, Ratio, ToPrimitive};
+use fraction::{
+    CheckedAdd, CheckedDiv, CheckedMul, CheckedSub, FromPrimitive, Ratio, ToPrimitive, Zero,
+};
+
+use super::big;
+use super::completion;
+use super::constants::Constant;
+use super::diagnostics::{self, FallbackReason};
+use super::exact;
+use super::format::NumberFormat;
+use super::functions::Function;
+use super::parser;
 
 pub type Fraction = Ratio<i64>;
 
@@ -11,27 +28,83 @@ pub enum Number {
 }
 
 impl Number {
-    fn pow_frac(frac1: &Fraction, frac2: &Fraction) -> Number {
-        if *frac2.denom() == 1i64 {
-            let power = i32::from_i64(*frac2.numer());
+    /// Raises to a power, falling back to `f64::powf` where `checked_pow` fails.
+    pub fn pow(&self, other: &Number) -> Number {
+        self.checked_pow(other)
+            .unwrap_or_else(|_| Number::Irrational(f64::powf((*self).into(), (*other).into())))
+    }
 
-            if let Some(p) = power {
-                return Self::Rational(frac1.pow(p));
-            } else {
-                panic!("{} is too powerful!", frac2.denom())
+    /// Raises to a power, exactly for rational bases and integer powers and for rational powers
+    /// with an exact root, like `(9/4)^(1/2) = 3/2`. Other powers are irrational. Negative bases
+    /// have real powers only for rational exponents with an odd denominator, like
+    /// `(-8)^(1/3) = -2`.
+    pub fn checked_pow(&self, other: &Number) -> Result<Number, EvalError> {
+        if let (Number::Rational(base), Number::Rational(power)) = (self, other) {
+            if power.is_integer() {
+                return integer_power(*base, power.to_integer()).map(Number::Rational);
             }
+            if let Ok(exact) = exact::exact_power(*base, *power) {
+                return Ok(Number::Rational(exact));
+            }
+        }
+
+        let (base, power) = (f64::from(*self), f64::from(*other));
+        let value = match other {
+            Number::Rational(exponent) if base < 0.0 && *exponent.denom() % 2 == 1 => {
+                let magnitude = (-base).powf(power);
+                if exponent.numer() % 2 == 0 {
+                    magnitude
+                } else {
+                    -magnitude
+                }
+            }
+            _ => base.powf(power),
+        };
+
+        if value.is_nan() && !base.is_nan() && !power.is_nan() {
+            Err(EvalError::InvalidDomain)
+        } else if base == 0.0 && power < 0.0 {
+            Err(EvalError::DivisionByZero)
         } else {
-            return f64::powf(frac1.to_f64().unwrap(), frac2.to_f64().unwrap()).into();
+            Ok(Number::Irrational(value))
         }
     }
 
-    pub fn pow(&self, other: &Number) -> Number {
-        match self {
-            Self::Rational(frac) => match other {
-                Self::Rational(other_frac) => Self::pow_frac(frac, other_frac),
-                Self::Irrational(value) => f64::powf(frac.to_f64().unwrap(), *value).into(),
-            },
-            Self::Irrational(value) => (*value).powf(Into::<f64>::into(*other)).into(),
+    /// `self / divisor`, failing when dividing by a rational zero.
+    pub fn checked_div(self, divisor: Number) -> Result<Number, EvalError> {
+        match divisor {
+            Number::Rational(divisor) if divisor.is_zero() => Err(EvalError::DivisionByZero),
+            divisor => self.checked_mul(divisor.inverse()),
+        }
+    }
+
+    pub(super) fn checked_add(self, rhs: Number) -> Result<Number, EvalError> {
+        match (self, rhs) {
+            (Number::Rational(lhs), Number::Rational(rhs)) => lhs
+                .checked_add(&rhs)
+                .map(Number::Rational)
+                .ok_or(EvalError::Overflow),
+            _ => Ok(self + rhs),
+        }
+    }
+
+    pub(super) fn checked_sub(self, rhs: Number) -> Result<Number, EvalError> {
+        match (self, rhs) {
+            (Number::Rational(lhs), Number::Rational(rhs)) => lhs
+                .checked_sub(&rhs)
+                .map(Number::Rational)
+                .ok_or(EvalError::Overflow),
+            _ => Ok(self - rhs),
+        }
+    }
+
+    pub(super) fn checked_mul(self, rhs: Number) -> Result<Number, EvalError> {
+        match (self, rhs) {
+            (Number::Rational(lhs), Number::Rational(rhs)) => lhs
+                .checked_mul(&rhs)
+                .map(Number::Rational)
+                .ok_or(EvalError::Overflow),
+            _ => Ok(self * rhs),
         }
     }
 
@@ -43,16 +116,122 @@ impl Number {
     }
 }
 
+/// `base^power` by repeated squaring, without overflowing.
+fn integer_power(base: Fraction, power: i64) -> Result<Fraction, EvalError> {
+    if power < 0 && base.is_zero() {
+        return Err(EvalError::DivisionByZero);
+    }
+
+    let (mut result, mut square, mut remaining) = (Fraction::from(1), base, power.unsigned_abs());
+    while remaining > 0 {
+        if remaining & 1 == 1 {
+            result = result.checked_mul(&square).ok_or(EvalError::Overflow)?;
+        }
+        remaining >>= 1;
+        if remaining > 0 {
+            square = square.checked_mul(&square).ok_or(EvalError::Overflow)?;
+        }
+    }
+
+    Ok(if power < 0 { result.recip() } else { result })
+}
+
+/// Converts a finite float to the fraction it denotes exactly, if that fraction fits in
+/// `Fraction`.
+fn exact_fraction(value: f64) -> Option<Fraction> {
+    if !value.is_finite() {
+        return None;
+    }
+    if value == 0.0 {
+        return Some(Fraction::from(0));
+    }
+
+    let bits = value.to_bits();
+    let biased_exponent = ((bits >> 52) & 0x7ff) as i64;
+    let fraction_bits = bits & 0x000f_ffff_ffff_ffff;
+
+    // value = mantissa * 2^exponent
+    let (mut mantissa, mut exponent) = if biased_exponent == 0 {
+        (fraction_bits, -1074)
+    } else {
+        (
+            fraction_bits | 0x0010_0000_0000_0000,
+            biased_exponent - 1075,
+        )
+    };
+
+    let shift = mantissa.trailing_zeros();
+    mantissa >>= shift;
+    exponent += shift as i64;
+
+    let mantissa = mantissa as i64 * if value < 0.0 { -1 } else { 1 };
+
+    if exponent >= 0 {
+        let factor = 1i64
+            .checked_shl(exponent as u32)
+            .filter(|_| exponent < 63)?;
+        Some(Fraction::from(mantissa.checked_mul(factor)?))
+    } else if exponent > -63 {
+        Some(Fraction::new_raw(mantissa, 1i64 << -exponent))
+    } else {
+        None
+    }
+}
+
+impl Number {
+    /// The canonical representative of this number: irrationals that are exactly representable
+    /// as a `Fraction` become rationals.
+    pub fn canonical(self) -> Self {
+        match self {
+            Self::Irrational(value) => exact_fraction(value).map_or(self, Self::Rational),
+            rational => rational,
+        }
+    }
+}
+
+/// Numbers compare by their canonical form, so `Irrational(0.5) == Rational(1/2)`.
 impl PartialEq for Number {
     fn eq(&self, other: &Self) -> bool {
-        match (self, other) {
-            (Self::Rational(l0), Self::Rational(r0)) => l0 == r0,
-            (Self::Irrational(l0), Self::Irrational(r0)) => l0 == r0,
-            _ => false,
+        self.cmp(other) == Ordering::Equal
+    }
+}
+
+impl Eq for Number {}
+
+impl Hash for Number {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        match self.canonical() {
+            Self::Rational(fraction) => {
+                state.write_u8(0);
+                fraction.hash(state);
+            }
+            Self::Irrational(value) => {
+                state.write_u8(1);
+                value.to_bits().hash(state);
+            }
+        }
+    }
+}
+
+/// A total order over canonical forms, for use as a map key. All rationals sort before all
+/// irrationals, so this is *not* numeric order across the two variants.
+impl Ord for Number {
+    fn cmp(&self, other: &Self) -> Ordering {
+        match (self.canonical(), other.canonical()) {
+            (Self::Rational(l0), Self::Rational(r0)) => l0.cmp(&r0),
+            (Self::Irrational(l0), Self::Irrational(r0)) => l0.total_cmp(&r0),
+            (Self::Rational(_), Self::Irrational(_)) => Ordering::Less,
+            (Self::Irrational(_), Self::Rational(_)) => Ordering::Greater,
         }
     }
 }
 
+impl PartialOrd for Number {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
 impl Mul for Number {
     type Output = Self;
 
@@ -60,9 +239,9 @@ impl Mul for Number {
         match self {
             Number::Rational(fraction) => match rhs {
                 Number::Rational(other) => Number::Rational(other * fraction),
-                Number::Irrational(value) => f64::mul(self.into(), value).into(),
+                Number::Irrational(value) => Number::Irrational(f64::mul(self.into(), value)),
             },
-            Number::Irrational(value) => Mul::<f64>::mul(value, rhs.into()).into(),
+            Number::Irrational(value) => Number::Irrational(Mul::<f64>::mul(value, rhs.into())),
         }
     }
 }
@@ -74,9 +253,201 @@ impl Add for Number {
         match self {
             Number::Rational(fraction) => match rhs {
                 Number::Rational(other) => Number::Rational(other + fraction),
-                Number::Irrational(value) => f64::add(self.into(), value).into(),
+                Number::Irrational(value) => Number::Irrational(f64::add(self.into(), value)),
+            },
+            Number::Irrational(value) => Number::Irrational(Add::<f64>::add(value, rhs.into())),
+        }
+    }
+}
+
+impl Sub for Number {
+    type Output = Self;
+
+    fn sub(self, rhs: Self) -> Self::Output {
+        match self {
+            Number::Rational(fraction) => match rhs {
+                Number::Rational(other) => Number::Rational(fraction - other),
+                Number::Irrational(value) => Number::Irrational(f64::sub(self.into(), value)),
             },
-            Number::Irrational(value) => Add::<f64>::add(value, rhs.into()).into(),
+            Number::Irrational(value) => Number::Irrational(Sub::<f64>::sub(value, rhs.into())),
+        }
+    }
+}
+
+/// Rationals print as `n` or `n/d`. Irrationals print as the shortest decimal that parses back
+/// to the same `f64`, and always contain a `.`, an exponent, `inf` or `NaN` so they re-parse as
+/// irrationals.
+impl Display for Number {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        match self {
+            Number::Rational(fraction) => write!(f, "{}", fraction),
+            Number::Irrational(value) => write!(f, "{:?}", value),
+        }
+    }
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct ParseNumberError;
+
+impl Display for ParseNumberError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        write!(f, "invalid number literal")
+    }
+}
+
+impl std::error::Error for ParseNumberError {}
+
+/// Why a term could not be evaluated to a number.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum EvalError {
+    /// A variable without a value, with the bound variables and constants named most like it.
+    UnboundVariable {
+        name: Box<str>,
+        suggestions: Vec<Box<str>>,
+    },
+    DivisionByZero,
+    /// An exact intermediate result that does not fit in a `Fraction`, in a term that cannot be
+    /// evaluated with arbitrary-precision rationals instead.
+    Overflow,
+    /// A result outside the real numbers, like an even root of a negative number.
+    InvalidDomain,
+    /// A collection where a single number was expected.
+    NotAScalar,
+    /// An index that is not a natural number or is out of bounds, or indexing something other
+    /// than a vector or list.
+    InvalidIndex,
+    /// A call to a function the context does not allow, see `EvalContext::restrict_functions`.
+    RestrictedFunction(Function),
+}
+
+impl Display for EvalError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        match self {
+            EvalError::UnboundVariable { name, suggestions } => {
+                write!(f, "variable `{name}` has no value")?;
+                parser::write_suggestions(f, suggestions)
+            }
+            EvalError::DivisionByZero => write!(f, "division by zero"),
+            EvalError::Overflow => write!(f, "exact result too large"),
+            EvalError::InvalidDomain => write!(f, "result is not a real number"),
+            EvalError::NotAScalar => write!(f, "expected a number, found a collection"),
+            EvalError::InvalidIndex => write!(f, "invalid index"),
+            EvalError::RestrictedFunction(function) => {
+                write!(f, "function `{}` is not allowed", function.name())
+            }
+        }
+    }
+}
+
+impl std::error::Error for EvalError {}
+
+/// Variable bindings for `Term::evaluate_in`, so the same term can be evaluated for many inputs
+/// without substituting into it.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct EvalContext {
+    bindings: HashMap<Box<str>, Number>,
+    /// The functions terms may call, or `None` to allow all of them.
+    allowed: Option<Vec<Function>>,
+}
+
+impl EvalContext {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn with(mut self, name: &str, value: Number) -> Self {
+        self.set(name, value);
+        self
+    }
+
+    /// Binds `name` to `value`, returning its previous value. Rebinding a name does not allocate.
+    pub fn set(&mut self, name: &str, value: Number) -> Option<Number> {
+        match self.bindings.get_mut(name) {
+            Some(bound) => Some(std::mem::replace(bound, value)),
+            None => self.bindings.insert(name.into(), value),
+        }
+    }
+
+    pub fn get(&self, name: &str) -> Option<Number> {
+        self.bindings.get(name).copied()
+    }
+
+    pub fn remove(&mut self, name: &str) -> Option<Number> {
+        self.bindings.remove(name)
+    }
+
+    /// Only allows terms to call the functions in `allowed`, for evaluating untrusted input.
+    /// Evaluating a term that calls any other function fails with
+    /// `EvalError::RestrictedFunction` instead of computing it.
+    pub fn restrict_functions(mut self, allowed: &[Function]) -> Self {
+        self.allowed = Some(allowed.to_vec());
+        self
+    }
+
+    pub fn allows(&self, function: Function) -> bool {
+        match &self.allowed {
+            Some(allowed) => allowed.contains(&function),
+            None => true,
+        }
+    }
+
+    /// `Err(RestrictedFunction)` for the first function in `term` that is not allowed.
+    pub(crate) fn check_functions(&self, term: &Term) -> Result<(), EvalError> {
+        if self.allowed.is_none() {
+            return Ok(());
+        }
+        for subterm in term.iter_subterms() {
+            if let Term::Function(function, _) = subterm {
+                if !self.allows(*function) {
+                    return Err(EvalError::RestrictedFunction(*function));
+                }
+            }
+        }
+        Ok(())
+    }
+
+    /// The `UnboundVariable` error for `name`, suggesting the bound variables and the constants
+    /// with similar names.
+    pub fn unbound(&self, name: &str) -> EvalError {
+        let mut names: Vec<&str> = self.bindings.keys().map(|name| &**name).collect();
+        for constant in Constant::ALL {
+            names.push(constant.name());
+        }
+        EvalError::UnboundVariable {
+            name: name.into(),
+            suggestions: completion::similar_names(name, names),
+        }
+    }
+
+    /// The bound names and their values, in no particular order.
+    pub fn iter(&self) -> impl Iterator<Item = (&str, Number)> {
+        self.bindings.iter().map(|(name, value)| (&**name, *value))
+    }
+}
+
+/// Parses the output of `Display`: integers and `n/d` become rationals, anything else the float
+/// parser accepts becomes an irrational with exactly the parsed bits. Digits may be separated by
+/// underscores, as in `1_000_000`.
+impl FromStr for Number {
+    type Err = ParseNumberError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        if s.contains('_') {
+            return Number::parse_with(s, &NumberFormat::PLAIN);
+        }
+
+        let is_rational = !s.is_empty()
+            && s.chars()
+                .all(|c| c.is_ascii_digit() || c == '-' || c == '+' || c == '/');
+
+        if is_rational {
+            Fraction::from_str(s)
+                .map(Number::Rational)
+                .map_err(|_| ParseNumberError)
+        } else {
+            f64::from_str(s)
+                .map(Number::Irrational)
+                .map_err(|_| ParseNumberError)
         }
     }
 }
@@ -100,102 +471,731 @@ impl From<f64> for Number {
     }
 }
 
+/// A term tree. Cloning, comparing, hashing and dropping walk the tree with an explicit stack,
+/// so they work on arbitrarily deep terms. So does serializing, which stores the flat nodes of a
+/// `TermArena` instead of nesting.
+#[derive(Debug)]
 pub enum Term {
     Value(Number),
     Variable(Box<str>),
     Addition(Box<Term>, Box<Term>),
+    Subtraction(Box<Term>, Box<Term>),
     Multiplication(Box<Term>, Box<Term>),
+    Division(Box<Term>, Box<Term>),
     Exponentation(Box<Term>, Box<Term>),
     RootExtraction(Box<Term>, Box<Term>),
+    Function(Function, Box<Term>),
+    Constant(Constant),
+    Vector(Vec<Term>),
+    List(Vec<Term>),
+    Set(Vec<Term>),
+    Index(Box<Term>, Box<Term>),
 }
 
 use Term::*;
 
+impl Clone for Term {
+    fn clone(&self) -> Self {
+        enum Task<'a> {
+            Visit(&'a Term),
+            /// Rebuild the term from its operands, which were just cloned.
+            Build(&'a Term),
+        }
+
+        let mut tasks = vec![Task::Visit(self)];
+        let mut built = Vec::new();
+        while let Some(task) = tasks.pop() {
+            match task {
+                Task::Visit(term) => match term {
+                    Value(value) => built.push(Value(*value)),
+                    Variable(name) => built.push(Variable(name.clone())),
+                    Constant(constant) => built.push(Constant(*constant)),
+                    Addition(lhs, rhs)
+                    | Subtraction(lhs, rhs)
+                    | Multiplication(lhs, rhs)
+                    | Division(lhs, rhs)
+                    | Exponentation(lhs, rhs)
+                    | RootExtraction(lhs, rhs)
+                    | Index(lhs, rhs) => {
+                        tasks.extend([Task::Build(term), Task::Visit(rhs), Task::Visit(lhs)])
+                    }
+                    Function(_, argument) => {
+                        tasks.extend([Task::Build(term), Task::Visit(argument)])
+                    }
+                    Vector(elements) | List(elements) | Set(elements) => {
+                        tasks.push(Task::Build(term));
+                        tasks.extend(elements.iter().rev().map(Task::Visit));
+                    }
+                },
+                Task::Build(term) => {
+                    let mut operand = || Box::new(built.pop().unwrap());
+                    let term = match term {
+                        Function(function, _) => Function(*function, operand()),
+                        Vector(elements) => Vector(built.split_off(built.len() - elements.len())),
+                        List(elements) => List(built.split_off(built.len() - elements.len())),
+                        Set(elements) => Set(built.split_off(built.len() - elements.len())),
+                        binary => {
+                            let (rhs, lhs) = (operand(), operand());
+                            match binary {
+                                Addition(..) => Addition(lhs, rhs),
+                                Subtraction(..) => Subtraction(lhs, rhs),
+                                Multiplication(..) => Multiplication(lhs, rhs),
+                                Division(..) => Division(lhs, rhs),
+                                Exponentation(..) => Exponentation(lhs, rhs),
+                                RootExtraction(..) => RootExtraction(lhs, rhs),
+                                Index(..) => Index(lhs, rhs),
+                                _ => unreachable!("leaves are cloned when visited"),
+                            }
+                        }
+                    };
+                    built.push(term);
+                }
+            }
+        }
+
+        built.pop().unwrap()
+    }
+}
+
+impl PartialEq for Term {
+    fn eq(&self, other: &Self) -> bool {
+        let mut pending = vec![(self, other)];
+        while let Some(pair) = pending.pop() {
+            match pair {
+                (Value(lhs), Value(rhs)) if lhs == rhs => {}
+                (Variable(lhs), Variable(rhs)) if lhs == rhs => {}
+                (Constant(lhs), Constant(rhs)) if lhs == rhs => {}
+                (Addition(l0, l1), Addition(r0, r1))
+                | (Subtraction(l0, l1), Subtraction(r0, r1))
+                | (Multiplication(l0, l1), Multiplication(r0, r1))
+                | (Division(l0, l1), Division(r0, r1))
+                | (Exponentation(l0, l1), Exponentation(r0, r1))
+                | (RootExtraction(l0, l1), RootExtraction(r0, r1))
+                | (Index(l0, l1), Index(r0, r1)) => pending.extend([(&**l0, &**r0), (l1, r1)]),
+                (Function(lhs, l0), Function(rhs, r0)) if lhs == rhs => pending.push((l0, r0)),
+                (Vector(lhs), Vector(rhs)) | (List(lhs), List(rhs)) | (Set(lhs), Set(rhs))
+                    if lhs.len() == rhs.len() =>
+                {
+                    pending.extend(lhs.iter().zip(rhs))
+                }
+                _ => return false,
+            }
+        }
+        true
+    }
+}
+
+impl Eq for Term {}
+
+/// Hashes the subterms in pre-order, with the number of elements of every collection, so equal
+/// terms hash equally.
+impl Hash for Term {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        for term in self.iter_subterms() {
+            mem::discriminant(term).hash(state);
+            match term {
+                Value(value) => value.hash(state),
+                Variable(name) => name.hash(state),
+                Constant(constant) => constant.hash(state),
+                Function(function, _) => function.hash(state),
+                Vector(elements) | List(elements) | Set(elements) => elements.len().hash(state),
+                _ => {}
+            }
+        }
+    }
+}
+
+impl Drop for Term {
+    fn drop(&mut self) {
+        let mut pending = Vec::new();
+        self.detach_operands(&mut pending);
+        while let Some(mut term) = pending.pop() {
+            term.detach_operands(&mut pending);
+        }
+    }
+}
+
 impl Term {
-    pub fn has_value(&self) -> bool {
+    /// The two operands of a binary term such as `Addition` or `Index`, moved out of it, or the
+    /// term itself if it isn't binary.
+    pub(super) fn into_operands(mut self) -> Result<(Term, Term), Term> {
+        match &mut self {
+            Addition(lhs, rhs)
+            | Subtraction(lhs, rhs)
+            | Multiplication(lhs, rhs)
+            | Division(lhs, rhs)
+            | Exponentation(lhs, rhs)
+            | RootExtraction(lhs, rhs)
+            | Index(lhs, rhs) => {
+                let leaf = || Value(Number::Rational(Fraction::zero()));
+                Ok((mem::replace(lhs, leaf()), mem::replace(rhs, leaf())))
+            }
+            _ => Err(self),
+        }
+    }
+
+    /// Moves the operands that have operands of their own into `pending`, leaving leaves in
+    /// their place, so that dropping this term doesn't recurse.
+    fn detach_operands(&mut self, pending: &mut Vec<Term>) {
+        let mut detach = |operand: &mut Term| {
+            if !matches!(operand, Value(_) | Variable(_) | Constant(_)) {
+                pending.push(mem::replace(
+                    operand,
+                    Value(Number::Rational(Fraction::zero())),
+                ));
+            }
+        };
+
         match self {
-            Value(_) => true,
-            Variable(_) => false,
-            Addition(lhs, rhs) => lhs.has_value() && rhs.has_value(),
-            Multiplication(lhs, rhs) => lhs.has_value() && rhs.has_value(),
-            Exponentation(base, power) => base.has_value() && power.has_value(),
-            RootExtraction(radicand, degree) => radicand.has_value() && degree.has_value(),
+            Value(_) | Variable(_) | Constant(_) => {}
+            Addition(lhs, rhs)
+            | Subtraction(lhs, rhs)
+            | Multiplication(lhs, rhs)
+            | Division(lhs, rhs)
+            | Exponentation(lhs, rhs)
+            | RootExtraction(lhs, rhs)
+            | Index(lhs, rhs) => {
+                detach(lhs);
+                detach(rhs);
+            }
+            Function(_, argument) => detach(argument),
+            Vector(elements) | List(elements) | Set(elements) => pending.append(elements),
         }
     }
+}
+
+/// How deep `Term::evaluate_rational` may recurse before leaving the term to the iterative
+/// evaluator.
+const RECURSION_LIMIT: usize = 512;
+
+/// Why `Term::evaluate_rational` stopped.
+enum Failure {
+    Error(EvalError),
+    /// The term leaves the fractions, so the iterative evaluator has to take over.
+    Inexact,
+}
+
+impl From<EvalError> for Failure {
+    fn from(error: EvalError) -> Self {
+        Failure::Error(error)
+    }
+}
+
+/// A step of the iterative `Term::evaluate_in`.
+enum Frame<'a> {
+    Visit(&'a Term),
+    /// Combine the values of the operands, which were just evaluated.
+    Apply(&'a Term),
+}
 
-    pub fn substitute(self, name: &str, value: Number) -> Self {
-        if let Variable(var) = &self {
-            if str::eq(&*var, name) {
-                Value(value)
-            } else {
-                self
+impl Term {
+    pub fn has_value(&self) -> bool {
+        let mut pending = vec![self];
+        while let Some(term) = pending.pop() {
+            match term {
+                Value(_) | Constant(_) => {}
+                Variable(_) => return false,
+                Addition(lhs, rhs)
+                | Subtraction(lhs, rhs)
+                | Multiplication(lhs, rhs)
+                | Division(lhs, rhs)
+                | Exponentation(lhs, rhs)
+                | RootExtraction(lhs, rhs) => pending.extend([&**lhs, &**rhs]),
+                Function(_, argument) => pending.push(argument),
+                // Vectors evaluate through `get_vector_value`, never to a single number.
+                Vector(_) | List(_) | Set(_) => return false,
+                Index(collection, index) => {
+                    if collection.get_collection_value().is_none() {
+                        return false;
+                    }
+                    pending.push(index);
+                }
             }
-        } else {
-            match self {
-                Addition(lhs, rhs) => {
-                    let (lhs, rhs) = (
-                        lhs.substitute(name, value.clone()),
-                        rhs.substitute(name, value),
-                    );
+        }
+        true
+    }
 
-                    let (lhs, rhs) = (Box::new(lhs), Box::new(rhs));
+    pub fn substitute(mut self, name: &str, value: Number) -> Self {
+        self.substitute_in_place(name, value);
+        self
+    }
 
-                    Addition(lhs, rhs)
+    /// `substitute` without consuming the term, so a formula can be reused.
+    pub fn substituted(&self, name: &str, value: Number) -> Term {
+        self.clone().substitute(name, value)
+    }
+
+    /// Replaces every occurrence of the variable `name` with `value`, in place.
+    pub fn substitute_in_place(&mut self, name: &str, value: Number) {
+        let mut pending = vec![self];
+        while let Some(term) = pending.pop() {
+            if matches!(term, Variable(var) if str::eq(var, name)) {
+                *term = Value(value);
+                continue;
+            }
+            match term {
+                Addition(lhs, rhs)
+                | Subtraction(lhs, rhs)
+                | Multiplication(lhs, rhs)
+                | Division(lhs, rhs)
+                | Exponentation(lhs, rhs)
+                | RootExtraction(lhs, rhs)
+                | Index(lhs, rhs) => pending.extend([&mut **lhs, &mut **rhs]),
+                Function(_, argument) => pending.push(argument),
+                Vector(elements) | List(elements) | Set(elements) => pending.extend(elements),
+                Value(_) | Variable(_) | Constant(_) => {}
+            }
+        }
+    }
+
+    /// Replaces every occurrence of the variable `name` with `replacement`, so substituting
+    /// `x := y + 1` into `x^2` gives `(y + 1)^2`. Occurrences of `name` inside `replacement` are
+    /// left alone.
+    pub fn substitute_term(&self, name: &str, replacement: &Term) -> Term {
+        self.map_variables(&|var| (var == name).then(|| replacement.clone()))
+    }
+
+    /// Replaces every variable in `replacements` in a single pass, so `{x: y, y: x}` swaps `x`
+    /// and `y` and variables inside a replacement are never replaced again.
+    pub fn substitute_all(&self, replacements: &HashMap<&str, Term>) -> Term {
+        self.map_variables(&|var| replacements.get(var).cloned())
+    }
+
+    /// `evaluate`, without the reason for failure.
+    pub fn get_value(&self) -> Option<Number> {
+        self.evaluate().ok()
+    }
+
+    /// Evaluates the term, exactly as long as every intermediate result is rational. Exact
+    /// results that don't fit in a `Fraction` are rounded to the nearest float, and intermediate
+    /// results may grow beyond one as long as the term stays exact; see `evaluate_big`.
+    pub fn evaluate(&self) -> Result<Number, EvalError> {
+        self.evaluate_in(&EvalContext::new())
+    }
+
+    /// Like `evaluate`, with the variables bound in `context` replaced by their values. This
+    /// binds variables and prefers exact arithmetic; to evaluate entirely in `f64` instead, see
+    /// `evaluate_with` and `Semantics::Ieee`.
+    ///
+    /// Terms built only from rational values and bindings, the four basic operations, integer
+    /// powers and `abs`, `floor` and `ceil` are evaluated on fractions directly. Anything else
+    /// is walked once with an explicit stack, so evaluation stops at the first failure and
+    /// arbitrarily deep terms cannot overflow the call stack. There is no scan beforehand: the
+    /// fast path hands over as soon as it meets a subterm it can't handle.
+    pub fn evaluate_in(&self, context: &EvalContext) -> Result<Number, EvalError> {
+        context.check_functions(self)?;
+        self.evaluate_with_stacks(context, &mut Vec::new(), &mut Vec::new())
+    }
+
+    /// Evaluates the term like `evaluate_in` once for every value of `var` in `inputs`, reusing
+    /// the context and the evaluation stacks from one input to the next.
+    pub fn evaluate_batch(&self, var: &str, inputs: &[Number]) -> Vec<Result<Number, EvalError>> {
+        let (mut context, mut frames, mut values) = (EvalContext::new(), Vec::new(), Vec::new());
+        inputs
+            .iter()
+            .map(|&input| {
+                context.set(var, input);
+                self.evaluate_with_stacks(&context, &mut frames, &mut values)
+            })
+            .collect()
+    }
+
+    fn evaluate_with_stacks<'a>(
+        &'a self,
+        context: &EvalContext,
+        frames: &mut Vec<Frame<'a>>,
+        values: &mut Vec<Number>,
+    ) -> Result<Number, EvalError> {
+        match self.evaluate_fractions(context, frames, values) {
+            // An exact term whose fractions outgrew `i64` is evaluated again without that limit
+            Err(EvalError::Overflow) => {
+                let value = self
+                    .evaluate_big_in(context)
+                    .map_err(|_| EvalError::Overflow)?;
+                let value = big::to_number(&value);
+                if let Number::Irrational(_) = value {
+                    diagnostics::report(FallbackReason::Overflow, || self.clone());
                 }
-                Multiplication(lhs, rhs) => {
-                    let (lhs, rhs) = (
-                        lhs.substitute(name, value.clone()),
-                        rhs.substitute(name, value),
-                    );
+                Ok(value)
+            }
+            result => result,
+        }
+    }
+
+    fn evaluate_fractions<'a>(
+        &'a self,
+        context: &EvalContext,
+        frames: &mut Vec<Frame<'a>>,
+        values: &mut Vec<Number>,
+    ) -> Result<Number, EvalError> {
+        match self.evaluate_rational(context, 0) {
+            Ok(value) => return Ok(Number::Rational(value)),
+            Err(Failure::Error(error)) => return Err(error),
+            Err(Failure::Inexact) => {}
+        }
 
-                    let (lhs, rhs) = (Box::new(lhs), Box::new(rhs));
+        // A failed evaluation leaves its stacks as they were at the failure
+        frames.clear();
+        values.clear();
+        frames.push(Frame::Visit(self));
+        while let Some(frame) = frames.pop() {
+            let term = match frame {
+                Frame::Visit(term) => term,
+                Frame::Apply(term) => {
+                    if let Some(value) = term.apply_operation(values, frames)? {
+                        values.push(value);
+                    }
+                    continue;
+                }
+            };
 
-                    Multiplication(lhs, rhs)
+            match term {
+                Value(v) => {
+                    if let Number::Irrational(_) = v {
+                        diagnostics::report(FallbackReason::IrrationalLiteral, || term.clone());
+                    }
+                    values.push(*v);
                 }
-                Exponentation(base, power) => {
-                    let (base, power) = (
-                        base.substitute(name, value.clone()),
-                        power.substitute(name, value),
-                    );
+                Constant(constant) => {
+                    diagnostics::report(FallbackReason::Constant, || term.clone());
+                    values.push(Number::Irrational(constant.value()));
+                }
+                Variable(name) => {
+                    values.push(context.get(name).ok_or_else(|| context.unbound(name))?)
+                }
+                Addition(lhs, rhs)
+                | Subtraction(lhs, rhs)
+                | Multiplication(lhs, rhs)
+                | Division(lhs, rhs)
+                | Exponentation(lhs, rhs)
+                | RootExtraction(lhs, rhs) => {
+                    frames.push(Frame::Apply(term));
+                    frames.push(Frame::Visit(rhs));
+                    frames.push(Frame::Visit(lhs));
+                }
+                Function(function, argument) => {
+                    frames.push(Frame::Apply(term));
+                    let arguments = function.arguments(argument);
+                    frames.extend(arguments.iter().rev().map(Frame::Visit));
+                }
+                Index(_, index) => {
+                    frames.push(Frame::Apply(term));
+                    frames.push(Frame::Visit(index));
+                }
+                Vector(_) | List(_) | Set(_) => return Err(EvalError::NotAScalar),
+            }
+        }
+
+        Ok(values.pop().expect("the root leaves one value"))
+    }
 
-                    let (base, power) = (Box::new(base), Box::new(power));
+    /// The fast path of `evaluate_in`, visiting operands in the same order as the iterative
+    /// evaluator so both stop at the same failure.
+    fn evaluate_rational(&self, context: &EvalContext, depth: usize) -> Result<Fraction, Failure> {
+        if depth > RECURSION_LIMIT {
+            return Err(Failure::Inexact);
+        }
+        let eval = |term: &Term| term.evaluate_rational(context, depth + 1);
+        let overflow = |value: Option<Fraction>| value.ok_or(Failure::Error(EvalError::Overflow));
 
-                    Exponentation(base, power)
+        match self {
+            Value(Number::Rational(value)) => Ok(*value),
+            Variable(name) => match context.get(name) {
+                Some(Number::Rational(value)) => Ok(value),
+                Some(Number::Irrational(_)) => Err(Failure::Inexact),
+                None => Err(context.unbound(name).into()),
+            },
+            Addition(lhs, rhs) => overflow(eval(lhs)?.checked_add(&eval(rhs)?)),
+            Subtraction(lhs, rhs) => overflow(eval(lhs)?.checked_sub(&eval(rhs)?)),
+            Multiplication(lhs, rhs) => overflow(eval(lhs)?.checked_mul(&eval(rhs)?)),
+            Division(lhs, rhs) => {
+                let (lhs, rhs) = (eval(lhs)?, eval(rhs)?);
+                if rhs.is_zero() {
+                    return Err(EvalError::DivisionByZero.into());
                 }
+                overflow(lhs.checked_div(&rhs))
+            }
+            Exponentation(base, power) => {
+                let (base, power) = (eval(base)?, eval(power)?);
+                if !power.is_integer() {
+                    return Err(Failure::Inexact);
+                }
+                Ok(integer_power(base, power.to_integer())?)
+            }
+            Function(function @ (Function::Abs | Function::Floor | Function::Ceil), argument) => {
+                Ok(function
+                    .apply_exact(eval(argument)?)
+                    .expect("abs, floor and ceil are exact"))
+            }
+            _ => Err(Failure::Inexact),
+        }
+    }
 
-                RootExtraction(radicand, degree) => {
-                    let (radicand, degree) = (
-                        radicand.substitute(name, value.clone()),
-                        degree.substitute(name, value),
-                    );
+    /// Combines the operand values on top of `values` for `evaluate_in`. An index instead
+    /// schedules its element to be evaluated in its place and returns `None`.
+    fn apply_operation<'a>(
+        &'a self,
+        values: &mut Vec<Number>,
+        frames: &mut Vec<Frame<'a>>,
+    ) -> Result<Option<Number>, EvalError> {
+        if let Function(function, argument) = self {
+            let start = values.len() - function.arguments(argument).len();
+            let arguments = &values[start..];
+            if !function.is_exact_at(arguments) {
+                diagnostics::report(FallbackReason::Transcendental, || self.clone());
+            }
+            let value = function.evaluate(arguments)?;
+            values.truncate(start);
+            return Ok(Some(value));
+        }
 
-                    let (radicand, degree) = (Box::new(radicand), Box::new(degree));
+        let mut pop = || values.pop().expect("operands are evaluated first");
 
-                    RootExtraction(radicand, degree)
+        Ok(Some(match self {
+            Index(collection, _) => {
+                let position = match pop() {
+                    Number::Rational(position) if position.is_integer() => {
+                        usize::try_from(position.to_integer()).ok()
+                    }
+                    _ => None,
+                };
+                let element = match &**collection {
+                    Vector(elements) | List(elements) => {
+                        position.and_then(|position| elements.get(position))
+                    }
+                    _ => None,
+                };
+                frames.push(Frame::Visit(element.ok_or(EvalError::InvalidIndex)?));
+                return Ok(None);
+            }
+            _ => {
+                let (rhs, lhs) = (pop(), pop());
+                match self {
+                    Addition(..) => lhs.checked_add(rhs)?,
+                    Subtraction(..) => lhs.checked_sub(rhs)?,
+                    Multiplication(..) => lhs.checked_mul(rhs)?,
+                    Division(..) => lhs.checked_div(rhs)?,
+                    Exponentation(..) => {
+                        self.report_inexact_power(&lhs, &rhs);
+                        lhs.checked_pow(&rhs)?
+                    }
+                    RootExtraction(..) => {
+                        let power = Number::Rational(Fraction::from(1)).checked_div(rhs)?;
+                        self.report_inexact_power(&lhs, &power);
+                        lhs.checked_pow(&power)?
+                    }
+                    _ => unreachable!("leaves and collections have no operation"),
                 }
+            }
+        }))
+    }
 
-                _ => self,
+    fn report_inexact_power(&self, base: &Number, power: &Number) {
+        if let (Number::Rational(_), Number::Rational(power)) = (base, power) {
+            if !power.is_integer() {
+                diagnostics::report(FallbackReason::NonIntegerPower, || self.clone());
             }
         }
     }
 
-    pub fn get_value(&self) -> Option<Number> {
-        if !self.has_value() {
-            None
-        } else {
-            Some(match self {
-                Value(v) => v.clone(),
-                Addition(lhs, rhs) => lhs.get_value().unwrap() + rhs.get_value().unwrap(),
-                Multiplication(lhs, rhs) => lhs.get_value().unwrap() + rhs.get_value().unwrap(),
-                Exponentation(base, power) => {
-                    base.get_value().unwrap().pow(&power.get_value().unwrap())
-                }
-                RootExtraction(radicand, degree) => radicand
-                    .get_value()
-                    .unwrap()
-                    .pow(&degree.get_value().unwrap().inverse()),
-                Variable(_) => panic!("How did we get here? Variables don't have values."),
-            })
+    pub fn get_vector_value(&self) -> Option<Vec<Number>> {
+        match self {
+            Vector(elements) => elements.iter().map(Term::get_value).collect(),
+            Addition(lhs, rhs) => {
+                let (lhs, rhs) = (lhs.get_vector_value()?, rhs.get_vector_value()?);
+                (lhs.len() == rhs.len())
+                    .then(|| lhs.into_iter().zip(rhs).map(|(a, b)| a + b).collect())
+            }
+            Subtraction(lhs, rhs) => {
+                let (lhs, rhs) = (lhs.get_vector_value()?, rhs.get_vector_value()?);
+                (lhs.len() == rhs.len())
+                    .then(|| lhs.into_iter().zip(rhs).map(|(a, b)| a - b).collect())
+            }
+            Multiplication(lhs, rhs) => {
+                let (scalar, vector) = match lhs.get_value() {
+                    Some(scalar) => (scalar, rhs.get_vector_value()?),
+                    None => (rhs.get_value()?, lhs.get_vector_value()?),
+                };
+                Some(vector.into_iter().map(|x| scalar * x).collect())
+            }
+            Division(lhs, rhs) => {
+                let (vector, divisor) = (lhs.get_vector_value()?, rhs.get_value()?);
+                vector
+                    .into_iter()
+                    .map(|x| x.checked_div(divisor).ok())
+                    .collect()
+            }
+            _ => None,
+        }
+    }
+
+    /// The values of a vector, list or set literal. Sets yield each distinct value once, in the
+    /// order it first appears.
+    pub fn get_collection_value(&self) -> Option<Vec<Number>> {
+        match self {
+            Vector(elements) | List(elements) => elements.iter().map(Term::get_value).collect(),
+            Set(elements) => {
+                let mut values: Vec<Number> = Vec::with_capacity(elements.len());
+                for element in elements {
+                    let value = element.get_value()?;
+                    if !values.contains(&value) {
+                        values.push(value);
+                    }
+                }
+                Some(values)
+            }
+            _ => None,
         }
     }
+
+    fn collection_elements(&self) -> Option<&[Term]> {
+        match self {
+            Vector(elements) | List(elements) | Set(elements) => Some(elements),
+            _ => None,
+        }
+    }
+
+    /// The number of elements of a collection literal. Sets must be evaluable so duplicates can
+    /// be discounted.
+    pub fn len(&self) -> Option<usize> {
+        match self {
+            Set(_) => Some(self.get_collection_value()?.len()),
+            _ => Some(self.collection_elements()?.len()),
+        }
+    }
+
+    pub fn is_empty(&self) -> Option<bool> {
+        Some(self.len()? == 0)
+    }
+
+    pub fn contains(&self, value: Number) -> Option<bool> {
+        Some(self.get_collection_value()?.contains(&value))
+    }
+
+    /// Evaluates `body` with `var` bound to each element, keeping the kind of collection. To
+    /// rewrite the structure of a term instead, see `try_map`.
+    pub fn map_elements(&self, var: &str, body: &Term) -> Option<Term> {
+        let elements = self
+            .get_collection_value()?
+            .into_iter()
+            .map(|value| Some(Value(body.clone().substitute(var, value).get_value()?)))
+            .collect::<Option<Vec<Term>>>()?;
+
+        Some(match self {
+            Vector(_) => Vector(elements),
+            List(_) => List(elements),
+            Set(_) => Set(elements),
+            _ => unreachable!(),
+        })
+    }
+
+    /// Keeps the elements whose value satisfies `predicate`, keeping the kind of collection.
+    pub fn filter(&self, predicate: impl Fn(&Number) -> bool) -> Option<Term> {
+        let elements = self
+            .get_collection_value()?
+            .into_iter()
+            .filter(predicate)
+            .map(Value)
+            .collect();
+
+        Some(match self {
+            Vector(_) => Vector(elements),
+            List(_) => List(elements),
+            Set(_) => Set(elements),
+            _ => unreachable!(),
+        })
+    }
+
+    /// The number of element values satisfying `predicate`.
+    pub fn count_if(&self, predicate: impl Fn(&Number) -> bool) -> Option<usize> {
+        Some(
+            self.get_collection_value()?
+                .iter()
+                .filter(|value| predicate(value))
+                .count(),
+        )
+    }
+
+    /// The sum of the element values satisfying `predicate`, zero if there are none.
+    pub fn sum_if(&self, predicate: impl Fn(&Number) -> bool) -> Option<Number> {
+        Some(
+            self.get_collection_value()?
+                .into_iter()
+                .filter(|value| predicate(value))
+                .fold(Number::Rational(Fraction::from(0)), |sum, value| {
+                    sum + value
+                }),
+        )
+    }
+
+    pub fn any(&self, predicate: impl Fn(&Number) -> bool) -> Option<bool> {
+        Some(self.get_collection_value()?.iter().any(predicate))
+    }
+
+    /// Whether every element value satisfies `predicate`; true for an empty collection.
+    pub fn all(&self, predicate: impl Fn(&Number) -> bool) -> Option<bool> {
+        Some(self.get_collection_value()?.iter().all(predicate))
+    }
+
+    fn vector_elements(&self) -> Option<&[Term]> {
+        match self {
+            Vector(elements) => Some(elements),
+            _ => None,
+        }
+    }
+
+    /// The dot product of two vector literals of equal length.
+    pub fn dot(&self, other: &Term) -> Option<Term> {
+        let (lhs, rhs) = (self.vector_elements()?, other.vector_elements()?);
+        if lhs.len() != rhs.len() {
+            return None;
+        }
+
+        lhs.iter()
+            .zip(rhs)
+            .map(|(a, b)| Multiplication(Box::new(a.clone()), Box::new(b.clone())))
+            .reduce(|acc, product| Addition(Box::new(acc), Box::new(product)))
+    }
+
+    /// The cross product of two three-dimensional vector literals.
+    pub fn cross(&self, other: &Term) -> Option<Term> {
+        let (a, b) = match (self.vector_elements()?, other.vector_elements()?) {
+            (a @ [_, _, _], b @ [_, _, _]) => (a, b),
+            _ => return None,
+        };
+
+        let component = |i: usize, j: usize| {
+            let product =
+                |x: &Term, y: &Term| Multiplication(Box::new(x.clone()), Box::new(y.clone()));
+            let negated = Multiplication(
+                Box::new(Value(Number::Rational(Fraction::from(-1)))),
+                Box::new(product(&a[j], &b[i])),
+            );
+            Addition(Box::new(product(&a[i], &b[j])), Box::new(negated))
+        };
+
+        Some(Vector(vec![
+            component(1, 2),
+            component(2, 0),
+            component(0, 1),
+        ]))
+    }
+
+    /// The Euclidean norm of a vector literal.
+    pub fn norm(&self) -> Option<Term> {
+        Some(RootExtraction(
+            Box::new(self.dot(self)?),
+            Box::new(Value(Number::Rational(Fraction::from(2)))),
+        ))
+    }
+
+    /// Multiplies every element of a vector literal by `factor`.
+    pub fn scale(&self, factor: &Term) -> Option<Term> {
+        Some(Vector(
+            self.vector_elements()?
+                .iter()
+                .map(|element| Multiplication(Box::new(factor.clone()), Box::new(element.clone())))
+                .collect(),
+        ))
+    }
 }
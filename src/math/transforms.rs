@@ -0,0 +1,320 @@
+use fraction::{CheckedMul, CheckedSub, One, Zero};
+
+use super::functions::Function;
+use super::polynomial::Polynomial;
+use super::terms::{Fraction, Term};
+use super::values::{as_rational, rational};
+
+/// A time-domain form with a known Laplace transform.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum StandardForm {
+    /// `t^n`; `n = 0` is the unit step.
+    Power(u32),
+    /// `e^(-a t)`
+    Exponential(Fraction),
+    /// `sin(w t)`
+    Sine(Fraction),
+    /// `cos(w t)`
+    Cosine(Fraction),
+}
+
+/// A linear combination of standard forms.
+pub type Combination = Vec<(Fraction, StandardForm)>;
+
+fn variable(name: &str) -> Term {
+    Term::Variable(name.into())
+}
+
+fn product(lhs: Term, rhs: Term) -> Term {
+    Term::Multiplication(Box::new(lhs), Box::new(rhs))
+}
+
+fn sum(lhs: Term, rhs: Term) -> Term {
+    Term::Addition(Box::new(lhs), Box::new(rhs))
+}
+
+fn power(base: Term, exponent: i64) -> Term {
+    Term::Exponentation(Box::new(base), Box::new(rational(Fraction::from(exponent))))
+}
+
+/// `n!`, or `None` if it overflows.
+fn factorial(n: u32) -> Option<Fraction> {
+    (1..=i64::from(n)).try_fold(Fraction::one(), |product, k| {
+        product.checked_mul(&Fraction::from(k))
+    })
+}
+
+/// `-fraction`, or `None` if it overflows.
+fn negate(fraction: Fraction) -> Option<Fraction> {
+    Fraction::zero().checked_sub(&fraction)
+}
+
+fn is_variable(term: &Term, name: &str) -> bool {
+    matches!(term, Term::Variable(var) if &**var == name)
+}
+
+fn exact_sqrt(fraction: Fraction) -> Option<Fraction> {
+    let root = |n: i64| {
+        if n < 0 {
+            return None;
+        }
+        let r = (n as f64).sqrt().round() as i64;
+        (r.checked_mul(r)? == n).then_some(r)
+    };
+
+    Some(Fraction::new(
+        root(*fraction.numer())?,
+        root(*fraction.denom())?,
+    ))
+}
+
+/// Every coefficient of `combination` times `factor`, or `None` if one overflows.
+fn scale(combination: Combination, factor: Fraction) -> Option<Combination> {
+    combination
+        .into_iter()
+        .map(|(coefficient, form)| Some((coefficient.checked_mul(&factor)?, form)))
+        .collect()
+}
+
+fn combine(
+    combination: Combination,
+    mut to_term: impl FnMut(StandardForm) -> Option<Term>,
+) -> Option<Term> {
+    let terms = combination
+        .into_iter()
+        .map(|(coefficient, form)| {
+            let term = to_term(form)?;
+            Some(if coefficient.is_one() {
+                term
+            } else {
+                product(rational(coefficient), term)
+            })
+        })
+        .collect::<Option<Vec<_>>>()?;
+    Some(
+        terms
+            .into_iter()
+            .reduce(sum)
+            .unwrap_or_else(|| rational(Fraction::zero())),
+    )
+}
+
+impl StandardForm {
+    /// The Laplace transform of this form in the variable `s`, or `None` if its coefficients
+    /// overflow, as for `t^n` with `n > 20`.
+    pub fn laplace(&self, s: &str) -> Option<Term> {
+        Some(match *self {
+            StandardForm::Power(n) => product(
+                rational(factorial(n)?),
+                power(variable(s), -i64::from(n) - 1),
+            ),
+            StandardForm::Exponential(a) => {
+                if a.is_zero() {
+                    power(variable(s), -1)
+                } else {
+                    power(sum(variable(s), rational(a)), -1)
+                }
+            }
+            StandardForm::Sine(w) => product(
+                rational(w),
+                power(sum(power(variable(s), 2), rational(w.checked_mul(&w)?)), -1),
+            ),
+            StandardForm::Cosine(w) => product(
+                variable(s),
+                power(sum(power(variable(s), 2), rational(w.checked_mul(&w)?)), -1),
+            ),
+        })
+    }
+
+    /// The form as a term in `t`, or `None` if its rate overflows when negated.
+    pub fn time_domain(&self, t: &str) -> Option<Term> {
+        let wave = |function, rate: Fraction| {
+            Term::Function(function, Box::new(product(rational(rate), variable(t))))
+        };
+
+        Some(match *self {
+            StandardForm::Power(0) => rational(Fraction::one()),
+            StandardForm::Power(1) => variable(t),
+            StandardForm::Power(n) => power(variable(t), i64::from(n)),
+            StandardForm::Exponential(a) => wave(Function::Exp, negate(a)?),
+            StandardForm::Sine(w) => wave(Function::Sin, w),
+            StandardForm::Cosine(w) => wave(Function::Cos, w),
+        })
+    }
+}
+
+/// The `k` in `k * t`.
+fn rate(term: &Term, t: &str) -> Option<Fraction> {
+    match Polynomial::from_term(term, t)?.coefficients() {
+        [k] if k.is_zero() => None,
+        [zero, k] if zero.is_zero() => Some(*k),
+        _ => None,
+    }
+}
+
+/// Recognizes `term` as a linear combination of standard forms in `t`.
+pub fn recognize(term: &Term, t: &str) -> Option<Combination> {
+    match term {
+        Term::Function(Function::Exp, argument) => Some(vec![(
+            Fraction::one(),
+            StandardForm::Exponential(negate(rate(argument, t)?)?),
+        )]),
+        Term::Function(Function::Sin, argument) => Some(vec![(
+            Fraction::one(),
+            StandardForm::Sine(rate(argument, t)?),
+        )]),
+        Term::Function(Function::Cos, argument) => Some(vec![(
+            Fraction::one(),
+            StandardForm::Cosine(rate(argument, t)?),
+        )]),
+        Term::Value(_) => Some(vec![(as_rational(term)?, StandardForm::Power(0))]),
+        Term::Variable(_) if is_variable(term, t) => {
+            Some(vec![(Fraction::one(), StandardForm::Power(1))])
+        }
+        Term::Exponentation(base, exponent) if is_variable(base, t) => {
+            let n = as_rational(exponent)?;
+            if n.is_integer() {
+                Some(vec![(
+                    Fraction::one(),
+                    StandardForm::Power(u32::try_from(*n.numer()).ok()?),
+                )])
+            } else {
+                None
+            }
+        }
+        Term::Addition(lhs, rhs) => {
+            let mut combination = recognize(lhs, t)?;
+            combination.extend(recognize(rhs, t)?);
+            Some(combination)
+        }
+        Term::Subtraction(lhs, rhs) => {
+            let mut combination = recognize(lhs, t)?;
+            combination.extend(scale(recognize(rhs, t)?, Fraction::from(-1))?);
+            Some(combination)
+        }
+        Term::Division(lhs, rhs) => {
+            let divisor = as_rational(rhs).filter(|divisor| !divisor.is_zero())?;
+            scale(recognize(lhs, t)?, divisor.recip())
+        }
+        Term::Multiplication(lhs, rhs) => {
+            let (lhs, rhs) = (recognize(lhs, t)?, recognize(rhs, t)?);
+
+            let mut combination = Combination::new();
+            for (c1, f1) in &lhs {
+                for (c2, f2) in &rhs {
+                    match (f1, f2) {
+                        (StandardForm::Power(n), StandardForm::Power(m)) => combination
+                            .push((c1.checked_mul(c2)?, StandardForm::Power(n.checked_add(*m)?))),
+                        (StandardForm::Power(0), form) | (form, StandardForm::Power(0)) => {
+                            combination.push((c1.checked_mul(c2)?, *form))
+                        }
+                        _ => return None,
+                    }
+                }
+            }
+            Some(combination)
+        }
+        _ => None,
+    }
+}
+
+/// Recognizes `term` as a linear combination of Laplace transforms of standard forms in `s`.
+pub fn recognize_laplace(term: &Term, s: &str) -> Option<Combination> {
+    match term {
+        Term::Addition(lhs, rhs) => {
+            let mut combination = recognize_laplace(lhs, s)?;
+            combination.extend(recognize_laplace(rhs, s)?);
+            Some(combination)
+        }
+        Term::Subtraction(lhs, rhs) => {
+            let mut combination = recognize_laplace(lhs, s)?;
+            combination.extend(scale(recognize_laplace(rhs, s)?, Fraction::from(-1))?);
+            Some(combination)
+        }
+        Term::Division(lhs, rhs) => {
+            let reciprocal =
+                Term::Exponentation(rhs.clone(), Box::new(rational(Fraction::from(-1))));
+            recognize_laplace(&product((**lhs).clone(), reciprocal), s)
+        }
+        Term::Multiplication(lhs, rhs) => {
+            if let Some(factor) = as_rational(lhs) {
+                return scale(recognize_laplace(rhs, s)?, factor);
+            }
+            if let Some(factor) = as_rational(rhs) {
+                return scale(recognize_laplace(lhs, s)?, factor);
+            }
+
+            // s * (s^2 + w^2)^-1
+            let (numerator, denominator) = if is_variable(lhs, s) {
+                (lhs, rhs)
+            } else {
+                (rhs, lhs)
+            };
+            if !is_variable(numerator, s) {
+                return None;
+            }
+            let w = oscillation(denominator, s)?;
+            Some(vec![(Fraction::one(), StandardForm::Cosine(w))])
+        }
+        Term::Exponentation(base, exponent) => {
+            let exponent = as_rational(exponent)?;
+            if !exponent.is_integer() || *exponent.numer() >= 0 {
+                return None;
+            }
+
+            if is_variable(base, s) {
+                let n = u32::try_from(exponent.numer().checked_neg()? - 1).ok()?;
+                return Some(vec![(factorial(n)?.recip(), StandardForm::Power(n))]);
+            }
+
+            if *exponent.numer() != -1 {
+                return None;
+            }
+
+            if let Term::Addition(lhs, rhs) = &**base {
+                if is_variable(lhs, s) {
+                    return Some(vec![(
+                        Fraction::one(),
+                        StandardForm::Exponential(as_rational(rhs)?),
+                    )]);
+                }
+            }
+
+            let w = oscillation(term, s)?;
+            Some(vec![(w.recip(), StandardForm::Sine(w))])
+        }
+        _ => None,
+    }
+}
+
+/// Matches `(s^2 + w^2)^-1` and returns `w`.
+fn oscillation(term: &Term, s: &str) -> Option<Fraction> {
+    let Term::Exponentation(base, exponent) = term else {
+        return None;
+    };
+    if as_rational(exponent)? != Fraction::from(-1) {
+        return None;
+    }
+    let Term::Addition(square, constant) = &**base else {
+        return None;
+    };
+    let Term::Exponentation(square_base, two) = &**square else {
+        return None;
+    };
+    if !is_variable(square_base, s) || as_rational(two)? != Fraction::from(2) {
+        return None;
+    }
+
+    exact_sqrt(as_rational(constant)?).filter(|w| !w.is_zero())
+}
+
+/// The Laplace transform of `term` with respect to `t`, as a term in `s`. `None` if `term` is not
+/// a combination of standard forms, or if the coefficients of its transform overflow.
+pub fn laplace(term: &Term, t: &str, s: &str) -> Option<Term> {
+    combine(recognize(term, t)?, |form| form.laplace(s))
+}
+
+/// The inverse Laplace transform of `term`, as a term in `t`.
+pub fn inverse_laplace(term: &Term, s: &str, t: &str) -> Option<Term> {
+    combine(recognize_laplace(term, s)?, |form| form.time_domain(t))
+}
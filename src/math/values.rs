@@ -0,0 +1,54 @@
+use fraction::Signed;
+
+use super::terms::{Fraction, Number, Term};
+
+/// The term for the exact `value`.
+pub(super) fn rational(value: Fraction) -> Term {
+    Term::Value(Number::Rational(value))
+}
+
+pub(super) fn integer(value: i64) -> Term {
+    rational(Fraction::from(value))
+}
+
+/// The value of `term` if it is an exact number.
+pub(super) fn as_rational(term: &Term) -> Option<Fraction> {
+    match term {
+        Term::Value(Number::Rational(value)) => Some(*value),
+        _ => None,
+    }
+}
+
+pub(super) fn is_minus_one(term: &Term) -> bool {
+    matches!(term, Term::Value(value) if *value == Number::Rational(Fraction::from(-1)))
+}
+
+/// The operand of `-1 * operand`.
+pub(super) fn negated(term: &Term) -> Option<&Term> {
+    match term {
+        Term::Multiplication(lhs, rhs) if is_minus_one(lhs) => Some(rhs),
+        _ => None,
+    }
+}
+
+/// The base of `base^-1`.
+pub(super) fn reciprocal(term: &Term) -> Option<&Term> {
+    match term {
+        Term::Exponentation(base, power) if is_minus_one(power) => Some(base),
+        _ => None,
+    }
+}
+
+pub(super) fn is_negative(value: &Number) -> bool {
+    match value {
+        Number::Rational(value) => value.is_negative(),
+        Number::Irrational(value) => *value < 0.0,
+    }
+}
+
+pub(super) fn absolute(value: &Number) -> Number {
+    match value {
+        Number::Rational(value) => Number::Rational(value.abs()),
+        Number::Irrational(value) => Number::Irrational(value.abs()),
+    }
+}
@@ -0,0 +1,115 @@
+use std::collections::BTreeSet;
+
+use super::exact::ExactnessReason;
+use super::terms::{Fraction, Number, Term};
+
+pub(super) const SEED: u64 = 0x9e37_79b9_7f4a_7c15;
+const TOLERANCE: f64 = 1e-9;
+
+/// An assignment under which two terms evaluated differently.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Counterexample {
+    pub assignment: Vec<(Box<str>, Fraction)>,
+    pub original: Option<Number>,
+    pub transformed: Option<Number>,
+}
+
+/// A small xorshift generator, so verification runs are reproducible.
+pub(super) struct Samples(pub(super) u64);
+
+impl Samples {
+    /// The generator for stream `index` of `seed`. Streams start from mixed states, so any of
+    /// them can be started without running through the ones before it.
+    pub(super) fn stream(seed: u64, index: u64) -> Samples {
+        // The splitmix64 finalizer, which spreads nearby inputs over the whole state
+        let mix = |mut z: u64| {
+            z = (z ^ (z >> 30)).wrapping_mul(0xbf58_476d_1ce4_e5b9);
+            z = (z ^ (z >> 27)).wrapping_mul(0x94d0_49bb_1331_11eb);
+            z ^ (z >> 31)
+        };
+        // Xorshift never leaves the zero state
+        Samples(mix(mix(seed.wrapping_add(SEED)) ^ index).max(1))
+    }
+
+    pub(super) fn next(&mut self) -> u64 {
+        self.0 ^= self.0 << 13;
+        self.0 ^= self.0 >> 7;
+        self.0 ^= self.0 << 17;
+        self.0
+    }
+
+    /// A rational with numerator in `-10..=10` and denominator in `1..=10`.
+    pub(super) fn rational(&mut self) -> Fraction {
+        let numer = (self.next() % 21) as i64 - 10;
+        let denom = (self.next() % 10) as i64 + 1;
+        Fraction::new(numer, denom)
+    }
+}
+
+fn evaluate(term: &Term) -> Option<Number> {
+    match term.evaluate_exact() {
+        Ok(value) => Some(Number::Rational(value)),
+        Err(violation) => match violation.reason {
+            ExactnessReason::IrrationalPower
+            | ExactnessReason::IrrationalLiteral
+            | ExactnessReason::Transcendental
+            | ExactnessReason::IrrationalConstant => term.get_value(),
+            _ => None,
+        },
+    }
+}
+
+fn agree(lhs: Option<Number>, rhs: Option<Number>) -> bool {
+    match (lhs, rhs) {
+        (None, None) => true,
+        (Some(Number::Rational(a)), Some(Number::Rational(b))) => a == b,
+        (Some(a), Some(b)) => {
+            let (a, b) = (f64::from(a), f64::from(b));
+            a == b || (a - b).abs() <= TOLERANCE * a.abs().max(b.abs()).max(1.0)
+        }
+        _ => false,
+    }
+}
+
+/// Checks that `transformed` evaluates like `original` on `samples` pseudo-random rational
+/// assignments of their free variables.
+///
+/// Exact results must match exactly; results that left exact arithmetic are compared with a small
+/// relative tolerance. An assignment where exactly one side fails to evaluate (for example a
+/// division by zero that a rewrite removed) counts as a mismatch.
+pub fn verify_transformation(
+    original: &Term,
+    transformed: &Term,
+    samples: usize,
+) -> Result<(), Counterexample> {
+    let variables: BTreeSet<&str> = original
+        .iter_variables()
+        .chain(transformed.iter_variables())
+        .collect();
+
+    let mut rng = Samples(SEED);
+
+    for _ in 0..samples {
+        let assignment: Vec<(Box<str>, Fraction)> = variables
+            .iter()
+            .map(|name| (Box::from(*name), rng.rational()))
+            .collect();
+
+        let bind = |term: &Term| {
+            assignment.iter().fold(term.clone(), |term, (name, value)| {
+                term.substitute(name, Number::Rational(*value))
+            })
+        };
+
+        let (lhs, rhs) = (evaluate(&bind(original)), evaluate(&bind(transformed)));
+        if !agree(lhs, rhs) {
+            return Err(Counterexample {
+                assignment,
+                original: lhs,
+                transformed: rhs,
+            });
+        }
+    }
+
+    Ok(())
+}